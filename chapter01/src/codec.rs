@@ -0,0 +1,194 @@
+#![allow(clippy::many_single_char_names,clippy::explicit_counter_loop)]
+use crate::interface::Pair;
+
+// a compact little-endian byte encoding for blocks written to disk
+pub trait Codec: Sized {
+    fn encode(&self, buf: &mut Vec<u8>);
+    fn decode(buf: &mut &[u8]) -> Option<Self>;
+}
+
+fn take<'a>(buf: &mut &'a [u8], n: usize) -> Option<&'a [u8]> {
+    if buf.len() < n {
+        return None;
+    }
+    let (head, tail) = buf.split_at(n);
+    *buf = tail;
+    Some(head)
+}
+
+macro_rules! int_codec {
+    ($($t:ty),*) => {
+        $(
+            impl Codec for $t {
+                fn encode(&self, buf: &mut Vec<u8>) {
+                    buf.extend_from_slice(&self.to_le_bytes());
+                }
+                fn decode(buf: &mut &[u8]) -> Option<Self> {
+                    let mut a = [0u8; std::mem::size_of::<$t>()];
+                    let n = a.len();
+                    a.copy_from_slice(take(buf, n)?);
+                    Some(<$t>::from_le_bytes(a))
+                }
+            }
+        )*
+    };
+}
+
+int_codec!(u8, u16, u32, u64, u128, i8, i16, i32, i64, i128);
+
+impl Codec for usize {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        (*self as u64).encode(buf)
+    }
+    fn decode(buf: &mut &[u8]) -> Option<Self> {
+        let x = u64::decode(buf)?;
+        if x > usize::MAX as u64 {
+            None
+        } else {
+            Some(x as usize)
+        }
+    }
+}
+
+impl Codec for isize {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        (*self as i64).encode(buf)
+    }
+    fn decode(buf: &mut &[u8]) -> Option<Self> {
+        let x = i64::decode(buf)?;
+        if x > isize::MAX as i64 || x < isize::MIN as i64 {
+            None
+        } else {
+            Some(x as isize)
+        }
+    }
+}
+
+impl Codec for bool {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        buf.push(*self as u8)
+    }
+    fn decode(buf: &mut &[u8]) -> Option<Self> {
+        match u8::decode(buf)? {
+            0 => Some(false),
+            1 => Some(true),
+            _ => None,
+        }
+    }
+}
+
+impl Codec for char {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        (*self as u32).encode(buf)
+    }
+    fn decode(buf: &mut &[u8]) -> Option<Self> {
+        std::char::from_u32(u32::decode(buf)?)
+    }
+}
+
+impl Codec for String {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        self.len().encode(buf);
+        buf.extend_from_slice(self.as_bytes());
+    }
+    fn decode(buf: &mut &[u8]) -> Option<Self> {
+        let n = usize::decode(buf)?;
+        String::from_utf8(take(buf, n)?.to_vec()).ok()
+    }
+}
+
+impl<T: Codec> Codec for Option<T> {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        match self {
+            Some(x) => {
+                buf.push(1);
+                x.encode(buf);
+            }
+            None => buf.push(0),
+        }
+    }
+    fn decode(buf: &mut &[u8]) -> Option<Self> {
+        match u8::decode(buf)? {
+            0 => Some(None),
+            1 => Some(Some(T::decode(buf)?)),
+            _ => None,
+        }
+    }
+}
+
+impl<T: Codec> Codec for Vec<T> {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        self.len().encode(buf);
+        for x in self.iter() {
+            x.encode(buf);
+        }
+    }
+    fn decode(buf: &mut &[u8]) -> Option<Self> {
+        let n = usize::decode(buf)?;
+        // every element takes at least one byte
+        if n > buf.len() {
+            return None;
+        }
+        let mut v = Vec::with_capacity(n);
+        for _ in 0..n {
+            v.push(T::decode(buf)?);
+        }
+        Some(v)
+    }
+}
+
+impl<A: Codec, B: Codec> Codec for (A, B) {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        self.0.encode(buf);
+        self.1.encode(buf);
+    }
+    fn decode(buf: &mut &[u8]) -> Option<Self> {
+        Some((A::decode(buf)?, B::decode(buf)?))
+    }
+}
+
+impl<K: Codec, V: Codec> Codec for Pair<K, V> {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        self.k.encode(buf);
+        self.v.encode(buf);
+    }
+    fn decode(buf: &mut &[u8]) -> Option<Self> {
+        Some(Pair {
+            k: K::decode(buf)?,
+            v: Option::<V>::decode(buf)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    fn roundtrip<T: Codec + PartialEq + std::fmt::Debug>(x: T) {
+        let mut buf = vec![];
+        x.encode(&mut buf);
+        let mut rest = &buf[..];
+        assert_eq!(T::decode(&mut rest), Some(x));
+        assert!(rest.is_empty());
+        for n in 0..buf.len() {
+            assert_eq!(T::decode(&mut &buf[..n]), None);
+        }
+    }
+    #[test]
+    fn test_codec() {
+        roundtrip(0x1234u16);
+        roundtrip(-5i32);
+        roundtrip(u64::MAX);
+        roundtrip(usize::MAX);
+        roundtrip(-1isize);
+        roundtrip(true);
+        roundtrip('λ');
+        roundtrip(String::from("open data structures"));
+        roundtrip(Some(7u8));
+        roundtrip(None::<u8>);
+        roundtrip(vec![(1i64, String::from("a")), (2, String::from("bc"))]);
+        roundtrip(Pair::new(3u32, String::from("x")));
+        assert_eq!(bool::decode(&mut &[2u8][..]), None);
+        assert_eq!(char::decode(&mut &[0u8, 0xd8, 0, 0][..]), None);
+        assert_eq!(Vec::<u8>::decode(&mut &[255u8; 8][..]), None);
+    }
+}
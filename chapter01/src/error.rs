@@ -0,0 +1,26 @@
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum Error {
+    OutOfBounds { index: usize, len: usize },
+    CapacityExceeded { capacity: usize },
+    AllocFailed { length: usize },
+    Unsorted { index: usize },
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::OutOfBounds { index, len } => {
+                write!(f, "index {} is out of bounds for length {}", index, len)
+            }
+            Error::CapacityExceeded { capacity } => {
+                write!(f, "capacity of {} elements exceeded", capacity)
+            }
+            Error::AllocFailed { length } => {
+                write!(f, "could not allocate an array of {} elements", length)
+            }
+            Error::Unsorted { index } => write!(f, "element {} is out of order", index),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
@@ -1,3 +1,6 @@
+use crate::error::Error;
+use std::ops::Range;
+
 pub trait Queue<T> {
     fn add(&mut self, x: T);
     fn remove(&mut self) -> Option<T>;
@@ -14,6 +17,59 @@ pub trait List<T: Clone> {
     fn set(&mut self, i: usize, x: T) -> Option<T>;
     fn add(&mut self, i: usize, x: T);
     fn remove(&mut self, i: usize) -> Option<T>;
+    fn try_add(&mut self, i: usize, x: T) -> Result<(), Error> {
+        let len = self.size();
+        if i > len {
+            return Err(Error::OutOfBounds { index: i, len });
+        }
+        self.add(i, x);
+        Ok(())
+    }
+    fn try_set(&mut self, i: usize, x: T) -> Result<T, Error> {
+        let len = self.size();
+        match i < len {
+            true => self.set(i, x).ok_or(Error::OutOfBounds { index: i, len }),
+            false => Err(Error::OutOfBounds { index: i, len }),
+        }
+    }
+    // moves every element of other to the end, leaving other empty
+    fn append(&mut self, other: &mut Self)
+    where
+        Self: Sized,
+    {
+        let xs = other.drain(0..other.size());
+        self.insert_slice(self.size(), &xs);
+    }
+    // the elements from i on, moved into a new list
+    fn split_off(&mut self, i: usize) -> Self
+    where
+        Self: Sized + Default,
+    {
+        let xs = self.drain(i..self.size());
+        let mut other = Self::default();
+        other.insert_slice(0, &xs);
+        other
+    }
+    fn drain(&mut self, r: Range<usize>) -> Vec<T> {
+        assert!(r.start <= r.end && r.end <= self.size());
+        let mut xs: Vec<T> = (r.start..r.end)
+            .rev()
+            .filter_map(|i| self.remove(i))
+            .collect();
+        xs.reverse();
+        xs
+    }
+    fn truncate(&mut self, len: usize) {
+        while self.size() > len {
+            self.remove(self.size() - 1);
+        }
+    }
+    fn insert_slice(&mut self, i: usize, xs: &[T]) {
+        assert!(i <= self.size());
+        for (k, x) in xs.iter().enumerate() {
+            self.add(i + k, x.clone());
+        }
+    }
 }
 
 pub trait USet<T: PartialEq + Clone> {
@@ -28,12 +84,230 @@ pub trait SSet<T: PartialOrd + Clone> {
     fn add(&mut self, x: T) -> bool;
     fn remove(&mut self, x: &T) -> Option<T>;
     fn find(&self, x: &T) -> Option<T>;
+    fn successor(&self, x: &T) -> Option<T>;
+    fn predecessor(&self, x: &T) -> Option<T>;
+    fn find_min(&self) -> Option<T>;
+    fn find_max(&self) -> Option<T>;
+    fn ceiling(&self, x: &T) -> Option<T> {
+        self.find(x)
+    }
+    fn floor(&self, x: &T) -> Option<T> {
+        match self.find(x) {
+            Some(y) if y == *x => Some(y),
+            _ => self.predecessor(x),
+        }
+    }
+    fn count_range(&self, r: std::ops::Range<T>) -> usize {
+        let mut c = 0;
+        let mut x = self.ceiling(&r.start);
+        while let Some(y) = x.filter(|y| *y < r.end) {
+            c += 1;
+            x = self.successor(&y);
+        }
+        c
+    }
 }
 
 pub trait Graph {
+    fn nvertices(&self) -> usize;
     fn add_edge(&mut self, i: usize, j: usize);
     fn remove_edge(&mut self, i: usize, j: usize);
     fn has_edge(&self, i: usize, j: usize) -> bool;
     fn out_edges(&self, i: usize) -> Vec<usize>;
     fn in_edges(&self, i: usize) -> Vec<usize>;
+    fn try_add_edge(&mut self, i: usize, j: usize) -> Result<(), Error> {
+        let len = self.nvertices();
+        if let Some(&index) = [i, j].iter().find(|&&k| k >= len) {
+            return Err(Error::OutOfBounds { index, len });
+        }
+        self.add_edge(i, j);
+        Ok(())
+    }
+}
+
+pub trait WeightedGraph<W> {
+    fn nvertices(&self) -> usize;
+    fn add_edge(&mut self, i: usize, j: usize, w: W);
+    fn remove_edge(&mut self, i: usize, j: usize);
+    fn has_edge(&self, i: usize, j: usize) -> bool;
+    fn weight(&self, i: usize, j: usize) -> Option<W>;
+    fn out_edges(&self, i: usize) -> Vec<(usize, W)>;
+    fn in_edges(&self, i: usize) -> Vec<(usize, W)>;
+    fn try_add_edge(&mut self, i: usize, j: usize, w: W) -> Result<(), Error> {
+        let len = self.nvertices();
+        if let Some(&index) = [i, j].iter().find(|&&k| k >= len) {
+            return Err(Error::OutOfBounds { index, len });
+        }
+        self.add_edge(i, j, w);
+        Ok(())
+    }
+}
+
+pub trait USetMap<K: PartialEq + Clone, V: Clone> {
+    // what get_mut hands out: &mut V where the map can lend it, a Lent otherwise
+    type ValueMut<'a>: std::ops::DerefMut<Target = V>
+    where
+        Self: 'a;
+    fn size(&self) -> usize;
+    fn insert(&mut self, k: K, v: V) -> Option<V>;
+    fn get(&self, k: &K) -> Option<V>;
+    fn get_mut(&mut self, k: &K) -> Option<Self::ValueMut<'_>>;
+    fn remove(&mut self, k: &K) -> Option<V>;
+    fn contains_key(&self, k: &K) -> bool {
+        self.get(k).is_some()
+    }
+    fn entry(&mut self, k: K) -> Entry<'_, K, V, Self>
+    where
+        Self: Sized,
+    {
+        Entry {
+            map: self,
+            k,
+            v: std::marker::PhantomData,
+        }
+    }
+}
+
+pub trait SSetMap<K: PartialOrd + Clone, V: Clone>: USetMap<K, V> {
+    fn successor(&self, k: &K) -> Option<(K, V)>;
+    fn predecessor(&self, k: &K) -> Option<(K, V)>;
+    fn find_min(&self) -> Option<(K, V)>;
+    fn find_max(&self) -> Option<(K, V)>;
+    fn ceiling(&self, k: &K) -> Option<(K, V)> {
+        match self.get(k) {
+            Some(v) => Some((k.clone(), v)),
+            None => self.successor(k),
+        }
+    }
+    fn floor(&self, k: &K) -> Option<(K, V)> {
+        match self.get(k) {
+            Some(v) => Some((k.clone(), v)),
+            None => self.predecessor(k),
+        }
+    }
+}
+
+// a value lent out of the node holding it, for maps whose nodes sit behind
+// a RefCell; drop puts it back into that same node
+pub struct Lent<'a, V> {
+    v: Option<V>,
+    back: Option<Box<dyn FnOnce(V) + 'a>>,
+}
+
+impl<'a, V> Lent<'a, V> {
+    pub fn new<F: FnOnce(V) + 'a>(v: V, back: F) -> Self {
+        Self {
+            v: Some(v),
+            back: Some(Box::new(back)),
+        }
+    }
+}
+
+impl<'a, V> std::ops::Deref for Lent<'a, V> {
+    type Target = V;
+    fn deref(&self) -> &V {
+        self.v.as_ref().unwrap()
+    }
+}
+
+impl<'a, V> std::ops::DerefMut for Lent<'a, V> {
+    fn deref_mut(&mut self) -> &mut V {
+        self.v.as_mut().unwrap()
+    }
+}
+
+impl<'a, V> Drop for Lent<'a, V> {
+    fn drop(&mut self) {
+        if let (Some(v), Some(back)) = (self.v.take(), self.back.take()) {
+            back(v);
+        }
+    }
+}
+
+pub struct Entry<'a, K, V, M>
+where
+    K: PartialEq + Clone,
+    V: Clone,
+    M: USetMap<K, V>,
+{
+    map: &'a mut M,
+    k: K,
+    v: std::marker::PhantomData<V>,
+}
+
+impl<'a, K, V, M> Entry<'a, K, V, M>
+where
+    K: PartialEq + Clone,
+    V: Clone,
+    M: USetMap<K, V>,
+{
+    pub fn key(&self) -> &K {
+        &self.k
+    }
+    pub fn or_insert(self, v: V) -> M::ValueMut<'a> {
+        self.or_insert_with(|| v)
+    }
+    pub fn or_insert_with<F: FnOnce() -> V>(self, f: F) -> M::ValueMut<'a> {
+        if !self.map.contains_key(&self.k) {
+            self.map.insert(self.k.clone(), f());
+        }
+        self.map.get_mut(&self.k).unwrap()
+    }
+    pub fn or_default(self) -> M::ValueMut<'a>
+    where
+        V: Default,
+    {
+        self.or_insert_with(Default::default)
+    }
+    pub fn and_modify<F: FnOnce(&mut V)>(self, f: F) -> Self {
+        if let Some(mut v) = self.map.get_mut(&self.k) {
+            f(&mut *v);
+        }
+        self
+    }
+}
+
+// a key-value pair compared and hashed by its key only,
+// so that the sets can be used to implement maps
+#[derive(Clone, Debug)]
+pub struct Pair<K, V> {
+    pub k: K,
+    pub v: Option<V>,
+}
+
+impl<K, V> Pair<K, V> {
+    pub fn new(k: K, v: V) -> Self {
+        Self { k, v: Some(v) }
+    }
+    pub fn probe(k: K) -> Self {
+        Self { k, v: None }
+    }
+    pub fn into_tuple(self) -> Option<(K, V)> {
+        let k = self.k;
+        self.v.map(|v| (k, v))
+    }
+}
+
+impl<K: Default, V> Default for Pair<K, V> {
+    fn default() -> Self {
+        Self::probe(Default::default())
+    }
+}
+
+impl<K: PartialEq, V> PartialEq for Pair<K, V> {
+    fn eq(&self, other: &Self) -> bool {
+        self.k == other.k
+    }
+}
+
+impl<K: PartialOrd, V> PartialOrd for Pair<K, V> {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        self.k.partial_cmp(&other.k)
+    }
+}
+
+impl<K: std::hash::Hash, V> std::hash::Hash for Pair<K, V> {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.k.hash(state);
+    }
 }
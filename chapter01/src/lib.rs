@@ -1 +1,8 @@
+pub mod codec;
+pub mod error;
 pub mod interface;
+pub mod rng;
+pub mod searchtree;
+pub mod snapshot;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
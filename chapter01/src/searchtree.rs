@@ -0,0 +1,64 @@
+use std::cell::Ref;
+use std::rc::Rc;
+
+// the node shape the binary search trees share, so the queries that only
+// walk down from the root can be written once
+pub trait SearchNode<T>: Sized {
+    fn x(&self) -> Ref<'_, T>;
+    fn left(&self) -> Option<Rc<Self>>;
+    fn right(&self) -> Option<Rc<Self>>;
+}
+
+pub fn successor<T, N>(r: &Option<Rc<N>>, x: &T) -> Option<T>
+where
+    T: PartialOrd + Clone,
+    N: SearchNode<T>,
+{
+    let mut w = r.clone();
+    let mut z = None;
+    while let Some(u) = w {
+        if *x < *u.x() {
+            w = u.left();
+            z = Some(u);
+        } else {
+            w = u.right();
+        }
+    }
+    z.map(|z| z.x().clone())
+}
+
+pub fn predecessor<T, N>(r: &Option<Rc<N>>, x: &T) -> Option<T>
+where
+    T: PartialOrd + Clone,
+    N: SearchNode<T>,
+{
+    let mut w = r.clone();
+    let mut z = None;
+    while let Some(u) = w {
+        if *x > *u.x() {
+            w = u.right();
+            z = Some(u);
+        } else {
+            w = u.left();
+        }
+    }
+    z.map(|z| z.x().clone())
+}
+
+pub fn find_min<T: Clone, N: SearchNode<T>>(r: &Option<Rc<N>>) -> Option<T> {
+    let mut w = r.clone()?;
+    while let Some(u) = w.left() {
+        w = u;
+    }
+    let x = w.x().clone();
+    Some(x)
+}
+
+pub fn find_max<T: Clone, N: SearchNode<T>>(r: &Option<Rc<N>>) -> Option<T> {
+    let mut w = r.clone()?;
+    while let Some(u) = w.right() {
+        w = u;
+    }
+    let x = w.x().clone();
+    Some(x)
+}
@@ -0,0 +1,151 @@
+use crate::codec::Codec;
+
+// a snapshot is MAGIC, the format version and the element count, followed by
+// the elements in increasing order, all in the codec's little-endian encoding
+const MAGIC: [u8; 4] = *b"ODSS";
+const VERSION: u16 = 1;
+const HEADER: usize = 14;
+
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum SnapshotError {
+    BadMagic,
+    UnsupportedVersion { version: u16 },
+    Truncated,
+    BadElement { index: usize },
+    Unsorted { index: usize },
+    TrailingBytes { len: usize },
+}
+
+impl std::fmt::Display for SnapshotError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SnapshotError::BadMagic => write!(f, "not a snapshot"),
+            SnapshotError::UnsupportedVersion { version } => {
+                write!(f, "snapshot format version {} is not supported", version)
+            }
+            SnapshotError::Truncated => write!(f, "snapshot is truncated"),
+            SnapshotError::BadElement { index } => {
+                write!(
+                    f,
+                    "element {} of the snapshot is truncated or malformed",
+                    index
+                )
+            }
+            SnapshotError::Unsorted { index } => {
+                write!(f, "element {} of the snapshot is out of order", index)
+            }
+            SnapshotError::TrailingBytes { len } => {
+                write!(f, "{} bytes follow the last element of the snapshot", len)
+            }
+        }
+    }
+}
+
+impl std::error::Error for SnapshotError {}
+
+pub fn dump<T: Codec, I: IntoIterator<Item = T>>(iter: I) -> Vec<u8> {
+    let mut buf = MAGIC.to_vec();
+    VERSION.encode(&mut buf);
+    0u64.encode(&mut buf);
+    let mut n = 0u64;
+    for x in iter {
+        x.encode(&mut buf);
+        n += 1;
+    }
+    buf[MAGIC.len() + 2..HEADER].copy_from_slice(&n.to_le_bytes());
+    buf
+}
+
+// the elements of a snapshot, which are strictly increasing
+pub fn load<T: Codec + PartialOrd>(mut buf: &[u8]) -> Result<Vec<T>, SnapshotError> {
+    if !buf.starts_with(&MAGIC) {
+        return Err(if MAGIC.starts_with(buf) {
+            SnapshotError::Truncated
+        } else {
+            SnapshotError::BadMagic
+        });
+    }
+    buf = &buf[MAGIC.len()..];
+    let version = u16::decode(&mut buf).ok_or(SnapshotError::Truncated)?;
+    if version != VERSION {
+        return Err(SnapshotError::UnsupportedVersion { version });
+    }
+    let n = u64::decode(&mut buf).ok_or(SnapshotError::Truncated)?;
+    // every element takes at least one byte
+    if n > buf.len() as u64 {
+        return Err(SnapshotError::Truncated);
+    }
+    let mut xs: Vec<T> = Vec::with_capacity(n as usize);
+    for index in 0..n as usize {
+        let x = T::decode(&mut buf).ok_or(SnapshotError::BadElement { index })?;
+        if xs.last().is_some_and(|last| *last >= x) {
+            return Err(SnapshotError::Unsorted { index });
+        }
+        xs.push(x);
+    }
+    if !buf.is_empty() {
+        return Err(SnapshotError::TrailingBytes { len: buf.len() });
+    }
+    Ok(xs)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[test]
+    fn test_snapshot() {
+        let buf = dump(vec![-3i32, 1, 4, 15]);
+        assert_eq!(&buf[..4], b"ODSS");
+        assert_eq!(buf.len(), HEADER + 4 * 4);
+        assert_eq!(load::<i32>(&buf), Ok(vec![-3, 1, 4, 15]));
+        assert_eq!(load::<i32>(&dump(Vec::<i32>::new())), Ok(vec![]));
+        let words = vec![String::from("data"), String::from("open")];
+        assert_eq!(load::<String>(&dump(words.clone())), Ok(words));
+
+        // a cut anywhere is an error, never a panic or a shorter set
+        for n in 0..buf.len() {
+            assert!(load::<i32>(&buf[..n]).is_err());
+        }
+        assert_eq!(load::<i32>(&buf[..3]), Err(SnapshotError::Truncated));
+        assert_eq!(load::<i32>(&buf[..16]), Err(SnapshotError::Truncated));
+        assert_eq!(
+            load::<i32>(&buf[..20]),
+            Err(SnapshotError::BadElement { index: 1 })
+        );
+        assert_eq!(
+            load::<i64>(&buf),
+            Err(SnapshotError::BadElement { index: 2 })
+        );
+        assert_eq!(load::<i32>(b"ODS!"), Err(SnapshotError::BadMagic));
+        // so is any flipped byte in the header
+        for i in 0..HEADER {
+            let mut bad = buf.clone();
+            bad[i] ^= 0x40;
+            assert!(load::<i32>(&bad).is_err());
+        }
+        let mut bad = buf.clone();
+        bad[4] = 2;
+        assert_eq!(
+            load::<i32>(&bad),
+            Err(SnapshotError::UnsupportedVersion { version: 2 })
+        );
+        let mut bad = buf.clone();
+        bad.push(0);
+        assert_eq!(
+            load::<i32>(&bad),
+            Err(SnapshotError::TrailingBytes { len: 1 })
+        );
+        assert_eq!(
+            load::<i32>(&dump(vec![1, 4, 4])),
+            Err(SnapshotError::Unsorted { index: 2 })
+        );
+        assert_eq!(
+            load::<i32>(&dump(vec![4, 1])),
+            Err(SnapshotError::Unsorted { index: 1 })
+        );
+        assert_eq!(
+            SnapshotError::Unsorted { index: 2 }.to_string(),
+            "element 2 of the snapshot is out of order"
+        );
+    }
+}
@@ -0,0 +1,107 @@
+use crate::interface::{SSetMap, USetMap};
+use rand::{thread_rng, Rng};
+use std::collections::{BTreeMap, HashMap};
+
+// the checks every map has to pass, each run on maps made by new
+pub fn check_usetmap<M: USetMap<i32, usize>, F: Fn() -> M>(new: F) {
+    let mut m = new();
+    assert_eq!(m.insert(1, 10), None);
+    assert_eq!(m.insert(1, 11), Some(10));
+    assert_eq!(m.get(&1), Some(11));
+    assert_eq!(m.get(&2), None);
+    *m.get_mut(&1).unwrap() += 1;
+    assert_eq!(m.get(&1), Some(12));
+    if let Some(mut v) = m.get_mut(&1) {
+        *v *= 2;
+        *v -= 12;
+    }
+    assert_eq!(m.get(&1), Some(12));
+    assert!(m.get_mut(&2).is_none());
+    *m.entry(2).or_insert(0) += 5;
+    *m.entry(2).or_insert(0) += 5;
+    m.entry(3).and_modify(|v| *v += 1).or_default();
+    m.entry(1).and_modify(|v| *v += 1).or_default();
+    assert_eq!(*m.entry(4).or_insert_with(|| 7), 7);
+    assert_eq!(m.entry(4).key(), &4);
+    assert_eq!(m.get(&1), Some(13));
+    assert_eq!(m.get(&2), Some(10));
+    assert_eq!(m.get(&3), Some(0));
+    assert_eq!(m.size(), 4);
+    assert_eq!(m.remove(&3), Some(0));
+    assert_eq!(m.remove(&3), None);
+    assert!(!m.contains_key(&3));
+    assert!(m.contains_key(&4));
+
+    let mut rng = thread_rng();
+    let n = 200;
+    let mut m = new();
+    let mut reference = HashMap::new();
+    for i in 0..5 * n {
+        let x = rng.gen_range(0, 5 * n as i32);
+        match rng.gen_range(0, 4) {
+            0 => assert_eq!(m.insert(x, i), reference.insert(x, i)),
+            1 => assert_eq!(m.remove(&x), reference.remove(&x)),
+            2 => {
+                if let Some(mut v) = m.get_mut(&x) {
+                    *v += i;
+                }
+                if let Some(v) = reference.get_mut(&x) {
+                    *v += i;
+                }
+            }
+            _ => {
+                *m.entry(x).or_default() += 1;
+                *reference.entry(x).or_default() += 1;
+            }
+        }
+        assert_eq!(m.size(), reference.len());
+        assert_eq!(m.get(&x), reference.get(&x).copied());
+    }
+    for (k, v) in reference {
+        assert_eq!(m.get(&k), Some(v));
+    }
+}
+
+// check_usetmap, then the order queries against a std BTreeMap
+pub fn check_ssetmap<M: SSetMap<i32, usize>, F: Fn() -> M>(new: F) {
+    check_usetmap(&new);
+    let mut rng = thread_rng();
+    let n = 200;
+    let mut m = new();
+    let mut reference = BTreeMap::new();
+    assert_eq!(m.find_min(), None);
+    assert_eq!(m.find_max(), None);
+    for i in 0..5 * n {
+        let x = rng.gen_range(0, 5 * n as i32);
+        match rng.gen_range(0, 3) {
+            0 => assert_eq!(m.insert(x, i), reference.insert(x, i)),
+            1 => assert_eq!(m.remove(&x), reference.remove(&x)),
+            _ => {
+                *m.entry(x).or_default() += 1;
+                *reference.entry(x).or_default() += 1;
+            }
+        }
+        assert_eq!(m.size(), reference.len());
+        assert_eq!(
+            m.successor(&x),
+            reference.range(x + 1..).next().map(|(k, v)| (*k, *v))
+        );
+        assert_eq!(
+            m.predecessor(&x),
+            reference.range(..x).next_back().map(|(k, v)| (*k, *v))
+        );
+        assert_eq!(
+            m.ceiling(&x),
+            reference.range(x..).next().map(|(k, v)| (*k, *v))
+        );
+        assert_eq!(
+            m.floor(&x),
+            reference.range(..=x).next_back().map(|(k, v)| (*k, *v))
+        );
+    }
+    assert_eq!(m.find_min(), reference.iter().next().map(|(k, v)| (*k, *v)));
+    assert_eq!(
+        m.find_max(),
+        reference.iter().next_back().map(|(k, v)| (*k, *v))
+    );
+}
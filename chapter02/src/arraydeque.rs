@@ -1,10 +1,65 @@
+use super::growth::Growth;
+use chapter01::error::Error;
 use chapter01::interface::List;
+use std::cmp::Ordering;
+use std::hash::{Hash, Hasher};
+use std::iter::FromIterator;
 
-#[derive(Clone, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[derive(Clone, Debug, Default)]
 pub struct Array<T> {
     a: Box<[Option<T>]>,
     j: usize,
     n: usize,
+    growth: Growth,
+}
+
+pub struct Iter<'a, T> {
+    a: &'a [Option<T>],
+    j: usize,
+    i: usize,
+    n: usize,
+}
+
+impl<'a, T> Iterator for Iter<'a, T> {
+    type Item = &'a T;
+    fn next(&mut self) -> Option<&'a T> {
+        if self.i < self.n {
+            let x = self.a[(self.j + self.i) % self.a.len()].as_ref();
+            self.i += 1;
+            x
+        } else {
+            None
+        }
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.n - self.i, Some(self.n - self.i))
+    }
+}
+
+impl<'a, T> DoubleEndedIterator for Iter<'a, T> {
+    fn next_back(&mut self) -> Option<&'a T> {
+        if self.i < self.n {
+            self.n -= 1;
+            self.a[(self.j + self.n) % self.a.len()].as_ref()
+        } else {
+            None
+        }
+    }
+}
+
+pub struct IntoIter<T>(std::iter::Flatten<std::vec::IntoIter<Option<T>>>);
+
+impl<T> Iterator for IntoIter<T> {
+    type Item = T;
+    fn next(&mut self) -> Option<T> {
+        self.0.next()
+    }
+}
+
+impl<T> DoubleEndedIterator for IntoIter<T> {
+    fn next_back(&mut self) -> Option<T> {
+        self.0.next_back()
+    }
 }
 
 impl<T> Array<T> {
@@ -14,6 +69,14 @@ impl<T> Array<T> {
     pub fn length(&self) -> usize {
         self.a.len()
     }
+    pub fn iter(&self) -> Iter<'_, T> {
+        Iter {
+            a: &self.a,
+            j: self.j,
+            i: 0,
+            n: self.n,
+        }
+    }
 
     pub fn new() -> Self {
         Self::with_length(1)
@@ -24,6 +87,14 @@ impl<T> Array<T> {
             a: Self::allocate_in_heap(length),
             j: 0,
             n: 0,
+            growth: Growth::default(),
+        }
+    }
+
+    pub fn with_growth(growth: Growth) -> Self {
+        Self {
+            growth,
+            ..Self::new()
         }
     }
 
@@ -34,14 +105,81 @@ impl<T> Array<T> {
             .into_boxed_slice()
     }
 
+    fn try_allocate_in_heap(size: usize) -> Result<Box<[Option<T>]>, Error> {
+        let mut a = Vec::new();
+        a.try_reserve_exact(size)
+            .map_err(|_| Error::AllocFailed { length: size })?;
+        a.extend(std::iter::repeat_with(|| None).take(size));
+        Ok(a.into_boxed_slice())
+    }
+
     pub fn resize(&mut self) {
-        let new_a = Self::allocate_in_heap(std::cmp::max(1, self.n * 2));
+        let new_a = Self::allocate_in_heap(self.growth.length(self.n));
+        self.move_to(new_a);
+    }
+
+    fn fit(&mut self) {
+        if self.length() > 3 * self.n && self.growth.length(self.n) < self.length() {
+            self.resize();
+        }
+    }
+
+    fn move_to(&mut self, new_a: Box<[Option<T>]>) {
         let mut old_a = std::mem::replace(&mut self.a, new_a);
         for k in 0..self.n {
             self.a[k] = old_a[(self.j + k) % old_a.len()].take();
         }
         self.j = 0;
     }
+
+    pub fn growth(&self) -> Growth {
+        self.growth
+    }
+
+    pub fn set_growth(&mut self, growth: Growth) {
+        self.growth = growth;
+    }
+
+    // the number of elements it holds before the next resize
+    pub fn capacity(&self) -> usize {
+        self.length().saturating_sub(1)
+    }
+
+    fn required(&self, additional: usize) -> Option<usize> {
+        self.n.checked_add(additional)?.checked_add(1)
+    }
+
+    // makes room for additional more elements, growing at least as much as a resize would
+    pub fn reserve(&mut self, additional: usize) {
+        let length = self.required(additional).expect("capacity overflow");
+        if length > self.length() {
+            let length = std::cmp::max(length, self.growth.length(self.length()));
+            self.move_to(Self::allocate_in_heap(length));
+        }
+    }
+
+    pub fn reserve_exact(&mut self, additional: usize) {
+        if let Err(e) = self.try_reserve(additional) {
+            panic!("{}", e);
+        }
+    }
+
+    pub fn shrink_to_fit(&mut self) {
+        if self.length() > self.n + 1 {
+            self.move_to(Self::allocate_in_heap(self.n + 1));
+        }
+    }
+
+    // makes room for additional more elements without a resize
+    pub fn try_reserve(&mut self, additional: usize) -> Result<(), Error> {
+        let length = self
+            .required(additional)
+            .ok_or(Error::AllocFailed { length: usize::MAX })?;
+        if length > self.length() {
+            self.move_to(Self::try_allocate_in_heap(length)?);
+        }
+        Ok(())
+    }
 }
 
 impl<T: Clone> List<T> for Array<T> {
@@ -101,18 +239,195 @@ impl<T: Clone> List<T> for Array<T> {
                 }
             }
             self.n -= 1;
-            if self.length() > 3 * self.n {
-                self.resize();
-            }
+            self.fit();
             x
         }
     }
+
+    // the bulk operations move every element at most once
+    fn append(&mut self, other: &mut Self) {
+        self.reserve(other.n);
+        for k in 0..other.n {
+            let x = other.a[(other.j + k) % other.length()].take();
+            let l = self.length();
+            self.a[(self.j + self.n + k) % l] = x;
+        }
+        self.n += other.n;
+        other.j = 0;
+        other.n = 0;
+    }
+
+    fn split_off(&mut self, i: usize) -> Self {
+        assert!(i <= self.n);
+        let k = self.n - i;
+        let mut other = Self::with_growth(self.growth);
+        other.reserve_exact(k);
+        for t in 0..k {
+            other.a[t] = self.a[(self.j + i + t) % self.length()].take();
+        }
+        other.n = k;
+        self.n = i;
+        self.fit();
+        other
+    }
+
+    fn drain(&mut self, r: std::ops::Range<usize>) -> Vec<T> {
+        assert!(r.start <= r.end && r.end <= self.n);
+        let (k, l, j) = (r.end - r.start, self.length(), self.j);
+        let xs = r
+            .clone()
+            .filter_map(|i| self.a[(j + i) % l].take())
+            .collect();
+        // close the gap from whichever side is shorter
+        if r.start < self.n - r.end {
+            for i in (0..r.start).rev() {
+                self.a[(j + i + k) % l] = self.a[(j + i) % l].take();
+            }
+            self.j = (j + k) % l;
+        } else {
+            for i in r.end..self.n {
+                self.a[(j + i - k) % l] = self.a[(j + i) % l].take();
+            }
+        }
+        self.n -= k;
+        self.fit();
+        xs
+    }
+
+    fn truncate(&mut self, len: usize) {
+        if len < self.n {
+            let l = self.length();
+            for i in len..self.n {
+                self.a[(self.j + i) % l].take();
+            }
+            self.n = len;
+            self.fit();
+        }
+    }
+
+    fn insert_slice(&mut self, i: usize, xs: &[T]) {
+        assert!(i <= self.n);
+        let (k, l, j) = (xs.len(), self.length(), self.j);
+        if self.n + k >= l {
+            let mut b = Self::allocate_in_heap(self.growth.length(self.n + k));
+            for t in 0..i {
+                b[t] = self.a[(j + t) % l].take();
+            }
+            for t in i..self.n {
+                b[t + k] = self.a[(j + t) % l].take();
+            }
+            self.a = b;
+            self.j = 0;
+        } else if i < self.n / 2 {
+            self.j = (j + l - k) % l;
+            for t in 0..i {
+                self.a[(self.j + t) % l] = self.a[(j + t) % l].take();
+            }
+        } else {
+            for t in (i..self.n).rev() {
+                self.a[(j + t + k) % l] = self.a[(j + t) % l].take();
+            }
+        }
+        let l = self.length();
+        for (t, x) in xs.iter().enumerate() {
+            self.a[(self.j + i + t) % l] = Some(x.clone());
+        }
+        self.n += k;
+    }
+}
+
+// compared element by element from the front; where the ring starts does not matter
+impl<T: PartialEq> PartialEq for Array<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.iter().eq(other.iter())
+    }
+}
+
+impl<T: Eq> Eq for Array<T> {}
+
+impl<T: PartialOrd> PartialOrd for Array<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        self.iter().partial_cmp(other.iter())
+    }
+}
+
+impl<T: Ord> Ord for Array<T> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.iter().cmp(other.iter())
+    }
+}
+
+impl<T: Hash> Hash for Array<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        state.write_usize(self.n);
+        self.iter().for_each(|x| x.hash(state));
+    }
+}
+
+impl<'a, T> IntoIterator for &'a Array<T> {
+    type Item = &'a T;
+    type IntoIter = Iter<'a, T>;
+    fn into_iter(self) -> Iter<'a, T> {
+        self.iter()
+    }
+}
+
+impl<T> IntoIterator for Array<T> {
+    type Item = T;
+    type IntoIter = IntoIter<T>;
+    fn into_iter(self) -> IntoIter<T> {
+        let mut a = self.a.into_vec();
+        a.rotate_left(self.j);
+        a.truncate(self.n);
+        IntoIter(a.into_iter().flatten())
+    }
+}
+
+impl<T: Clone> FromIterator<T> for Array<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut a = Self::new();
+        a.extend(iter);
+        a
+    }
+}
+
+impl<T: Clone> Extend<T> for Array<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for x in iter {
+            self.add(self.n, x);
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<T: serde::Serialize> serde::Serialize for Array<T> {
+    fn serialize<S: serde::Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        s.collect_seq(self)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, T: Clone + serde::Deserialize<'de>> serde::Deserialize<'de> for Array<T> {
+    fn deserialize<D: serde::Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+        serde::Deserialize::deserialize(d).map(|xs: Vec<T>| xs.into_iter().collect())
+    }
 }
 
 #[cfg(test)]
 mod test {
     use super::Array;
+    use crate::growth::Growth;
+    use chapter01::error::Error;
     use chapter01::interface::List;
+    use rand::{thread_rng, Rng};
+    use std::collections::hash_map::DefaultHasher;
+    use std::hash::{Hash, Hasher};
+    fn hash_of<T: Hash>(x: &T) -> u64 {
+        let mut s = DefaultHasher::new();
+        x.hash(&mut s);
+        s.finish()
+    }
+
     #[test]
     fn test_arraydeque() {
         let mut array_deque: Array<char> = Array::new();
@@ -140,5 +455,114 @@ mod test {
         }
         while array_deque.remove(0).is_some() {}
         println!("\nArrayDeque = {:?}\n", array_deque);
+        let mut array_deque: Array<i32> = (0..6).collect();
+        array_deque.add(0, -1);
+        array_deque.extend(6..8);
+        assert_eq!(
+            array_deque.iter().copied().collect::<Vec<_>>(),
+            (-1..8).collect::<Vec<_>>()
+        );
+        assert_eq!(
+            (&array_deque)
+                .into_iter()
+                .rev()
+                .copied()
+                .collect::<Vec<_>>(),
+            (-1..8).rev().collect::<Vec<_>>()
+        );
+        assert_eq!(
+            array_deque.into_iter().collect::<Vec<_>>(),
+            (-1..8).collect::<Vec<_>>()
+        );
+        let mut array_deque: Array<i32> = (0..6).collect();
+        array_deque.remove(0);
+        assert_eq!(array_deque.try_reserve(10), Ok(()));
+        let length = array_deque.length();
+        for i in 0..5 {
+            assert_eq!(array_deque.try_add(0, -i), Ok(()));
+            assert_eq!(array_deque.try_add(array_deque.size(), 6 + i), Ok(()));
+        }
+        assert_eq!(array_deque.length(), length);
+        assert_eq!(
+            array_deque.iter().copied().collect::<Vec<_>>(),
+            (-4..11).collect::<Vec<_>>()
+        );
+        assert_eq!(
+            array_deque.try_add(16, 0),
+            Err(Error::OutOfBounds { index: 16, len: 15 })
+        );
+        assert_eq!(array_deque.try_set(0, 4), Ok(-4));
+        let mut array_deque: Array<i32> = Array::with_growth(Growth::OneAndHalf);
+        array_deque.reserve_exact(8);
+        assert_eq!(array_deque.capacity(), 8);
+        for i in 0..8 {
+            array_deque.add(i / 2, i as i32);
+        }
+        assert_eq!(array_deque.capacity(), 8);
+        array_deque.reserve(1);
+        assert_eq!(array_deque.length(), 13);
+        array_deque.shrink_to_fit();
+        assert_eq!(array_deque.capacity(), 8);
+        assert_eq!(
+            array_deque.iter().copied().collect::<Vec<_>>(),
+            vec![1, 3, 5, 7, 6, 4, 2, 0]
+        );
+        // the same elements from a fresh deque, at another offset and capacity
+        let other: Array<i32> = vec![1, 3, 5, 7, 6, 4, 2, 0].into_iter().collect();
+        assert_eq!(array_deque, other);
+        assert_eq!(hash_of(&array_deque), hash_of(&other));
+        assert!(array_deque > (0..8).collect());
+        let mut rng = thread_rng();
+        let mut array_deque: Array<i32> = Array::new();
+        let mut vec: Vec<i32> = vec![];
+        for k in 0..2000 {
+            let n = vec.len();
+            let i = rng.gen_range(0, n + 1);
+            let m = rng.gen_range(0, 20);
+            match rng.gen_range(0, 5) {
+                0 => {
+                    let xs: Vec<i32> = (0..m).map(|x| 1000 * k + x).collect();
+                    array_deque.insert_slice(i, &xs);
+                    vec.splice(i..i, xs);
+                }
+                1 => {
+                    let j = rng.gen_range(i, n + 1);
+                    assert_eq!(array_deque.drain(i..j), vec.drain(i..j).collect::<Vec<_>>());
+                }
+                2 => {
+                    let mut rest = array_deque.split_off(i);
+                    let tail = vec.split_off(i);
+                    assert_eq!(rest.iter().copied().collect::<Vec<_>>(), tail);
+                    rest.add(0, -k);
+                    array_deque.append(&mut rest);
+                    assert_eq!(rest.size(), 0);
+                    vec.push(-k);
+                    vec.extend(tail);
+                }
+                3 => {
+                    let mut other: Array<i32> = (0..m).collect();
+                    other.remove(0);
+                    array_deque.append(&mut other);
+                    vec.extend(1..m);
+                }
+                _ => {
+                    let len = n - std::cmp::min(n, m as usize);
+                    array_deque.truncate(len);
+                    vec.truncate(len);
+                }
+            }
+            assert_eq!(array_deque.size(), vec.len());
+            assert!(array_deque.length() > vec.len());
+            assert_eq!(array_deque.iter().copied().collect::<Vec<_>>(), vec);
+        }
+        #[cfg(feature = "serde")]
+        {
+            let mut array_deque: Array<i32> = (0..5).collect();
+            array_deque.add(0, -1);
+            let json = serde_json::to_string(&array_deque).unwrap();
+            assert_eq!(json, "[-1,0,1,2,3,4]");
+            let copy: Array<i32> = serde_json::from_str(&json).unwrap();
+            assert!(copy.iter().eq(array_deque.iter()));
+        }
     }
 }
@@ -1,16 +1,79 @@
+use super::growth::Growth;
+use chapter01::error::Error;
 use chapter01::interface::Queue;
+use std::cmp::Ordering;
+use std::hash::{Hash, Hasher};
+use std::iter::FromIterator;
 
-#[derive(Clone, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[derive(Clone, Debug, Default)]
 pub struct Array<T> {
     a: Box<[Option<T>]>,
     j: usize,
     n: usize,
+    growth: Growth,
+}
+
+pub struct Iter<'a, T> {
+    a: &'a [Option<T>],
+    j: usize,
+    i: usize,
+    n: usize,
+}
+
+impl<'a, T> Iterator for Iter<'a, T> {
+    type Item = &'a T;
+    fn next(&mut self) -> Option<&'a T> {
+        if self.i < self.n {
+            let x = self.a[(self.j + self.i) % self.a.len()].as_ref();
+            self.i += 1;
+            x
+        } else {
+            None
+        }
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.n - self.i, Some(self.n - self.i))
+    }
+}
+
+impl<'a, T> DoubleEndedIterator for Iter<'a, T> {
+    fn next_back(&mut self) -> Option<&'a T> {
+        if self.i < self.n {
+            self.n -= 1;
+            self.a[(self.j + self.n) % self.a.len()].as_ref()
+        } else {
+            None
+        }
+    }
+}
+
+pub struct IntoIter<T>(std::iter::Flatten<std::vec::IntoIter<Option<T>>>);
+
+impl<T> Iterator for IntoIter<T> {
+    type Item = T;
+    fn next(&mut self) -> Option<T> {
+        self.0.next()
+    }
+}
+
+impl<T> DoubleEndedIterator for IntoIter<T> {
+    fn next_back(&mut self) -> Option<T> {
+        self.0.next_back()
+    }
 }
 
 impl<T> Array<T> {
     pub fn length(&self) -> usize {
         self.a.len()
     }
+    pub fn iter(&self) -> Iter<'_, T> {
+        Iter {
+            a: &self.a,
+            j: self.j,
+            i: 0,
+            n: self.n,
+        }
+    }
 
     pub fn new() -> Self {
         Self::with_length(1)
@@ -21,6 +84,14 @@ impl<T> Array<T> {
             a: Self::allocate_in_heap(capacity),
             j: 0,
             n: 0,
+            growth: Growth::default(),
+        }
+    }
+
+    pub fn with_growth(growth: Growth) -> Self {
+        Self {
+            growth,
+            ..Self::new()
         }
     }
 
@@ -31,14 +102,75 @@ impl<T> Array<T> {
             .into_boxed_slice()
     }
 
+    fn try_allocate_in_heap(size: usize) -> Result<Box<[Option<T>]>, Error> {
+        let mut a = Vec::new();
+        a.try_reserve_exact(size)
+            .map_err(|_| Error::AllocFailed { length: size })?;
+        a.extend(std::iter::repeat_with(|| None).take(size));
+        Ok(a.into_boxed_slice())
+    }
+
     fn resize(&mut self) {
-        let new_a = Self::allocate_in_heap(std::cmp::max(self.n * 2, 1));
+        let new_a = Self::allocate_in_heap(self.growth.length(self.n));
+        self.move_to(new_a);
+    }
+
+    fn move_to(&mut self, new_a: Box<[Option<T>]>) {
         let mut old_a = std::mem::replace(&mut self.a, new_a);
         for k in 0..self.n {
             self.a[k] = old_a[(self.j + k) % old_a.len()].take();
         }
         self.j = 0;
     }
+
+    pub fn growth(&self) -> Growth {
+        self.growth
+    }
+
+    pub fn set_growth(&mut self, growth: Growth) {
+        self.growth = growth;
+    }
+
+    // the number of elements it holds before the next resize
+    pub fn capacity(&self) -> usize {
+        self.length().saturating_sub(1)
+    }
+
+    fn required(&self, additional: usize) -> Option<usize> {
+        self.n.checked_add(additional)?.checked_add(1)
+    }
+
+    // makes room for additional more elements, growing at least as much as a resize would
+    pub fn reserve(&mut self, additional: usize) {
+        let length = self.required(additional).expect("capacity overflow");
+        if length > self.length() {
+            let length = std::cmp::max(length, self.growth.length(self.length()));
+            self.move_to(Self::allocate_in_heap(length));
+        }
+    }
+
+    pub fn reserve_exact(&mut self, additional: usize) {
+        if let Err(e) = self.try_reserve(additional) {
+            panic!("{}", e);
+        }
+    }
+
+    pub fn shrink_to_fit(&mut self) {
+        if self.length() > self.n + 1 {
+            self.move_to(Self::allocate_in_heap(self.n + 1));
+        }
+    }
+
+    // makes room for additional more elements without a resize
+    pub fn try_reserve(&mut self, additional: usize) -> Result<(), Error> {
+        let length = self
+            .required(additional)
+            .ok_or(Error::AllocFailed { length: usize::MAX })?;
+        if length > self.length() {
+            self.move_to(Self::try_allocate_in_heap(length)?);
+        }
+        Ok(())
+    }
 }
 
 impl<T> Queue<T> for Array<T> {
@@ -54,17 +186,89 @@ impl<T> Queue<T> for Array<T> {
         let x = self.a[self.j].take();
         self.j = (self.j + 1) % self.length();
         self.n -= 1;
-        if self.length() >= 3 * self.n {
+        if self.length() >= 3 * self.n && self.growth.length(self.n) < self.length() {
             self.resize();
         }
         x
     }
 }
 
+// queues compare in removal order, whatever their capacity or growth policy
+impl<T: PartialEq> PartialEq for Array<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.iter().eq(other.iter())
+    }
+}
+
+impl<T: Eq> Eq for Array<T> {}
+
+impl<T: PartialOrd> PartialOrd for Array<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        self.iter().partial_cmp(other.iter())
+    }
+}
+
+impl<T: Ord> Ord for Array<T> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.iter().cmp(other.iter())
+    }
+}
+
+impl<T: Hash> Hash for Array<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        state.write_usize(self.n);
+        self.iter().for_each(|x| x.hash(state));
+    }
+}
+
+impl<'a, T> IntoIterator for &'a Array<T> {
+    type Item = &'a T;
+    type IntoIter = Iter<'a, T>;
+    fn into_iter(self) -> Iter<'a, T> {
+        self.iter()
+    }
+}
+
+impl<T> IntoIterator for Array<T> {
+    type Item = T;
+    type IntoIter = IntoIter<T>;
+    fn into_iter(self) -> IntoIter<T> {
+        let mut a = self.a.into_vec();
+        a.rotate_left(self.j);
+        a.truncate(self.n);
+        IntoIter(a.into_iter().flatten())
+    }
+}
+
+impl<T> FromIterator<T> for Array<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut a = Self::new();
+        a.extend(iter);
+        a
+    }
+}
+
+impl<T> Extend<T> for Array<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for x in iter {
+            self.add(x);
+        }
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::Array;
+    use crate::growth::Growth;
     use chapter01::interface::Queue;
+    use std::collections::hash_map::DefaultHasher;
+    use std::hash::{Hash, Hasher};
+    fn hash_of<T: Hash>(x: &T) -> u64 {
+        let mut s = DefaultHasher::new();
+        x.hash(&mut s);
+        s.finish()
+    }
+
     #[test]
     fn test_arrayqueue() {
         let mut array_queue: Array<char> = Array::new();
@@ -82,5 +286,42 @@ mod test {
         array_queue.add('h');
         assert_eq!(array_queue.remove(), Some('b'));
         println!("\nArrayQueue = {:?}\n", array_queue);
+        assert_eq!(array_queue.iter().collect::<String>(), "cdefgh");
+        let mut array_queue: Array<char> = "xyz".chars().collect();
+        assert_eq!(array_queue.remove(), Some('x'));
+        array_queue.extend("uvw".chars());
+        assert_eq!(array_queue.into_iter().collect::<String>(), "yzuvw");
+        let mut array_queue: Array<char> = "abc".chars().collect();
+        array_queue.remove();
+        assert_eq!(array_queue.try_reserve(20), Ok(()));
+        let length = array_queue.length();
+        array_queue.extend("defghijklmnopqrstuvw".chars());
+        assert_eq!(array_queue.length(), length);
+        assert_eq!(
+            array_queue.iter().collect::<String>(),
+            "bcdefghijklmnopqrstuvw"
+        );
+        assert!(array_queue.try_reserve(usize::MAX).is_err());
+        let mut array_queue: Array<char> = Array::with_growth(Growth::Increment(4));
+        array_queue.extend("abcdefghij".chars());
+        assert_eq!(array_queue.length(), 13);
+        array_queue.reserve(10);
+        assert_eq!((array_queue.capacity(), array_queue.length()), (20, 21));
+        assert_eq!(array_queue.remove(), Some('a'));
+        array_queue.extend("klmnopqrstu".chars());
+        assert_eq!(array_queue.length(), 21);
+        array_queue.shrink_to_fit();
+        assert_eq!(array_queue.capacity(), array_queue.iter().count());
+        assert_eq!(
+            array_queue.iter().collect::<String>(),
+            "bcdefghijklmnopqrstu"
+        );
+        array_queue.reserve_exact(3);
+        assert_eq!(array_queue.capacity(), 23);
+        // a queue built in one go equals one grown, drained and shrunk
+        let other: Array<char> = "bcdefghijklmnopqrstu".chars().collect();
+        assert_eq!(array_queue, other);
+        assert_eq!(hash_of(&array_queue), hash_of(&other));
+        assert!(array_queue < "c".chars().collect());
     }
 }
@@ -1,9 +1,48 @@
+use super::growth::Growth;
+use chapter01::error::Error;
 use chapter01::interface::List;
+use std::cmp::Ordering;
+use std::hash::{Hash, Hasher};
+use std::iter::FromIterator;
 
-#[derive(Clone, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[derive(Clone, Debug, Default)]
 pub struct Array<T> {
     a: Box<[Option<T>]>,
     n: usize,
+    growth: Growth,
+}
+
+pub struct Iter<'a, T>(std::slice::Iter<'a, Option<T>>);
+
+impl<'a, T> Iterator for Iter<'a, T> {
+    type Item = &'a T;
+    fn next(&mut self) -> Option<&'a T> {
+        self.0.next()?.as_ref()
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+impl<'a, T> DoubleEndedIterator for Iter<'a, T> {
+    fn next_back(&mut self) -> Option<&'a T> {
+        self.0.next_back()?.as_ref()
+    }
+}
+
+pub struct IntoIter<T>(std::iter::Flatten<std::vec::IntoIter<Option<T>>>);
+
+impl<T> Iterator for IntoIter<T> {
+    type Item = T;
+    fn next(&mut self) -> Option<T> {
+        self.0.next()
+    }
+}
+
+impl<T> DoubleEndedIterator for IntoIter<T> {
+    fn next_back(&mut self) -> Option<T> {
+        self.0.next_back()
+    }
 }
 
 impl<T> Array<T> {
@@ -11,6 +50,10 @@ impl<T> Array<T> {
         self.a.len()
     }
 
+    pub fn iter(&self) -> Iter<'_, T> {
+        Iter(self.a[..self.n].iter())
+    }
+
     pub fn new() -> Self {
         Self::with_length(1)
     }
@@ -19,6 +62,14 @@ impl<T> Array<T> {
         Self {
             a: Self::allocate_in_heap(capacity),
             n: 0,
+            growth: Growth::default(),
+        }
+    }
+
+    pub fn with_growth(growth: Growth) -> Self {
+        Self {
+            growth,
+            ..Self::new()
         }
     }
 
@@ -29,13 +80,74 @@ impl<T> Array<T> {
             .into_boxed_slice()
     }
 
+    fn try_allocate_in_heap(size: usize) -> Result<Box<[Option<T>]>, Error> {
+        let mut a = Vec::new();
+        a.try_reserve_exact(size)
+            .map_err(|_| Error::AllocFailed { length: size })?;
+        a.extend(std::iter::repeat_with(|| None).take(size));
+        Ok(a.into_boxed_slice())
+    }
+
     fn resize(&mut self) {
-        let new_a = Self::allocate_in_heap(std::cmp::max(self.n * 2, 1));
+        let new_a = Self::allocate_in_heap(self.growth.length(self.n));
+        self.move_to(new_a);
+    }
+
+    fn move_to(&mut self, new_a: Box<[Option<T>]>) {
         let old_a = std::mem::replace(&mut self.a, new_a);
         for (i, elem) in old_a.into_vec().into_iter().enumerate().take(self.n) {
             self.a[i] = elem;
         }
     }
+
+    pub fn growth(&self) -> Growth {
+        self.growth
+    }
+
+    pub fn set_growth(&mut self, growth: Growth) {
+        self.growth = growth;
+    }
+
+    // the number of elements it holds before the next resize
+    pub fn capacity(&self) -> usize {
+        self.length().saturating_sub(1)
+    }
+
+    fn required(&self, additional: usize) -> Option<usize> {
+        self.n.checked_add(additional)?.checked_add(1)
+    }
+
+    // makes room for additional more elements, growing at least as much as a resize would
+    pub fn reserve(&mut self, additional: usize) {
+        let length = self.required(additional).expect("capacity overflow");
+        if length > self.length() {
+            let length = std::cmp::max(length, self.growth.length(self.length()));
+            self.move_to(Self::allocate_in_heap(length));
+        }
+    }
+
+    pub fn reserve_exact(&mut self, additional: usize) {
+        if let Err(e) = self.try_reserve(additional) {
+            panic!("{}", e);
+        }
+    }
+
+    pub fn shrink_to_fit(&mut self) {
+        if self.length() > self.n + 1 {
+            self.move_to(Self::allocate_in_heap(self.n + 1));
+        }
+    }
+
+    // makes room for additional more elements without a resize
+    pub fn try_reserve(&mut self, additional: usize) -> Result<(), Error> {
+        let length = self
+            .required(additional)
+            .ok_or(Error::AllocFailed { length: usize::MAX })?;
+        if length > self.length() {
+            self.move_to(Self::try_allocate_in_heap(length)?);
+        }
+        Ok(())
+    }
 }
 
 impl<T: PartialEq> Array<T> {
@@ -53,6 +165,9 @@ impl<T: Clone> Array<T> {
     pub fn take(&mut self, i: usize) -> Option<T> {
         self.a.get_mut(i)?.take()
     }
+    pub fn get_mut(&mut self, i: usize) -> Option<&mut T> {
+        self.a.get_mut(i)?.as_mut()
+    }
 }
 
 impl<T: Clone> List<T> for Array<T> {
@@ -88,7 +203,7 @@ impl<T: Clone> List<T> for Array<T> {
         if i < self.n {
             self.a[i..self.n].rotate_left(1);
             self.n -= 1;
-            if self.length() >= 3 * self.n {
+            if self.length() >= 3 * self.n && self.growth.length(self.n) < self.length() {
                 self.resize();
             }
         }
@@ -96,10 +211,96 @@ impl<T: Clone> List<T> for Array<T> {
     }
 }
 
+// equal contents are equal lists, whatever the capacity or growth policy
+impl<T: PartialEq> PartialEq for Array<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.iter().eq(other.iter())
+    }
+}
+
+impl<T: Eq> Eq for Array<T> {}
+
+impl<T: PartialOrd> PartialOrd for Array<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        self.iter().partial_cmp(other.iter())
+    }
+}
+
+impl<T: Ord> Ord for Array<T> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.iter().cmp(other.iter())
+    }
+}
+
+impl<T: Hash> Hash for Array<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        state.write_usize(self.n);
+        self.iter().for_each(|x| x.hash(state));
+    }
+}
+
+impl<'a, T> IntoIterator for &'a Array<T> {
+    type Item = &'a T;
+    type IntoIter = Iter<'a, T>;
+    fn into_iter(self) -> Iter<'a, T> {
+        self.iter()
+    }
+}
+
+impl<T> IntoIterator for Array<T> {
+    type Item = T;
+    type IntoIter = IntoIter<T>;
+    fn into_iter(self) -> IntoIter<T> {
+        let mut a = self.a.into_vec();
+        a.truncate(self.n);
+        IntoIter(a.into_iter().flatten())
+    }
+}
+
+impl<T: Clone> FromIterator<T> for Array<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut a = Self::new();
+        a.extend(iter);
+        a
+    }
+}
+
+impl<T: Clone> Extend<T> for Array<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for x in iter {
+            self.add(self.n, x);
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<T: serde::Serialize> serde::Serialize for Array<T> {
+    fn serialize<S: serde::Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        s.collect_seq(self)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, T: Clone + serde::Deserialize<'de>> serde::Deserialize<'de> for Array<T> {
+    fn deserialize<D: serde::Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+        serde::Deserialize::deserialize(d).map(|xs: Vec<T>| xs.into_iter().collect())
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::Array;
+    use crate::growth::Growth;
+    use chapter01::error::Error;
     use chapter01::interface::List;
+    use std::collections::hash_map::DefaultHasher;
+    use std::hash::{Hash, Hasher};
+
+    fn hash_of<T: Hash>(x: &T) -> u64 {
+        let mut s = DefaultHasher::new();
+        x.hash(&mut s);
+        s.finish()
+    }
 
     #[test]
     fn test_arraystack() {
@@ -134,5 +335,106 @@ mod test {
         }
         while array_stack.remove(0).is_some() {}
         println!("\nArrayStack = {:?}\n", array_stack);
+        let mut array_stack: Array<i32> = (0..5).collect();
+        array_stack.extend(5..8);
+        assert_eq!(
+            array_stack.iter().copied().collect::<Vec<_>>(),
+            (0..8).collect::<Vec<_>>()
+        );
+        assert_eq!((&array_stack).into_iter().next_back(), Some(&7));
+        assert_eq!(
+            array_stack.into_iter().collect::<Vec<_>>(),
+            (0..8).collect::<Vec<_>>()
+        );
+        let mut array_stack: Array<i32> = Array::new();
+        assert_eq!(
+            array_stack.try_add(1, 0),
+            Err(Error::OutOfBounds { index: 1, len: 0 })
+        );
+        assert_eq!(array_stack.try_reserve(100), Ok(()));
+        let length = array_stack.length();
+        assert!(length > 100);
+        for i in 0..100 {
+            assert_eq!(array_stack.try_add(i, i as i32), Ok(()));
+        }
+        assert_eq!(array_stack.length(), length);
+        assert_eq!(array_stack.try_set(99, -1), Ok(99));
+        assert_eq!(
+            array_stack.try_set(100, -1),
+            Err(Error::OutOfBounds {
+                index: 100,
+                len: 100
+            })
+        );
+        assert_eq!(array_stack.get(99), Some(-1));
+        assert_eq!(
+            array_stack.try_reserve(usize::MAX),
+            Err(Error::AllocFailed { length: usize::MAX })
+        );
+        assert_eq!(
+            array_stack
+                .try_reserve(usize::MAX / 2)
+                .unwrap_err()
+                .to_string(),
+            format!(
+                "could not allocate an array of {} elements",
+                usize::MAX / 2 + 101
+            )
+        );
+        assert_eq!(array_stack.size(), 100);
+        let mut array_stack: Array<i32> = Array::new();
+        array_stack.reserve(10);
+        assert_eq!((array_stack.capacity(), array_stack.length()), (10, 11));
+        array_stack.extend(0..10);
+        assert_eq!(array_stack.length(), 11);
+        array_stack.add(10, 10);
+        assert_eq!(array_stack.length(), 20);
+        array_stack.reserve_exact(5);
+        assert_eq!(array_stack.length(), 20);
+        array_stack.reserve(10);
+        assert_eq!(array_stack.length(), 40);
+        array_stack.shrink_to_fit();
+        assert_eq!((array_stack.capacity(), array_stack.length()), (11, 12));
+        assert_eq!(
+            array_stack.iter().copied().collect::<Vec<_>>(),
+            (0..11).collect::<Vec<_>>()
+        );
+        for (growth, length) in [
+            (Growth::Double, 18),
+            (Growth::OneAndHalf, 12),
+            (Growth::Increment(4), 13),
+        ]
+        .iter()
+        {
+            let mut array_stack: Array<i32> = Array::with_growth(*growth);
+            array_stack.extend(0..10);
+            assert_eq!(array_stack.length(), *length);
+            while array_stack.size() > 0 {
+                array_stack.remove(0);
+                assert!(array_stack.length() <= growth.length(3 * array_stack.size()));
+            }
+            assert_eq!(array_stack.growth(), *growth);
+        }
+        // a large increment does not make every remove reallocate
+        let mut array_stack: Array<i32> = Array::with_growth(Growth::Increment(100));
+        array_stack.extend(0..10);
+        let length = array_stack.length();
+        array_stack.remove(0);
+        assert_eq!(array_stack.length(), length);
+        // equality, order and hashing see only the elements
+        let other: Array<i32> = (1..10).collect();
+        assert_ne!(array_stack.length(), other.length());
+        assert_eq!(array_stack, other);
+        assert_eq!(hash_of(&array_stack), hash_of(&other));
+        assert!(array_stack < (2..3).collect());
+        #[cfg(feature = "serde")]
+        {
+            let array_stack: Array<i32> = (0..5).collect();
+            let json = serde_json::to_string(&array_stack).unwrap();
+            assert_eq!(json, "[0,1,2,3,4]");
+            let copy: Array<i32> = serde_json::from_str(&json).unwrap();
+            assert!(copy.iter().eq(array_stack.iter()));
+            assert!(serde_json::from_str::<Array<i32>>("[0,\"a\"]").is_err());
+        }
     }
 }
@@ -1,4 +1,6 @@
+use chapter01::error::Error;
 use chapter01::interface::List;
+use std::iter::FromIterator;
 
 #[derive(Clone, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
 pub struct Array<T> {
@@ -7,6 +9,55 @@ pub struct Array<T> {
     n: usize,
 }
 
+pub struct Iter<'a, T> {
+    a: &'a [Option<T>],
+    j: usize,
+    i: usize,
+    n: usize,
+}
+
+impl<'a, T> Iterator for Iter<'a, T> {
+    type Item = &'a T;
+    fn next(&mut self) -> Option<&'a T> {
+        if self.i < self.n {
+            let x = self.a[(self.j + self.i) % self.a.len()].as_ref();
+            self.i += 1;
+            x
+        } else {
+            None
+        }
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.n - self.i, Some(self.n - self.i))
+    }
+}
+
+impl<'a, T> DoubleEndedIterator for Iter<'a, T> {
+    fn next_back(&mut self) -> Option<&'a T> {
+        if self.i < self.n {
+            self.n -= 1;
+            self.a[(self.j + self.n) % self.a.len()].as_ref()
+        } else {
+            None
+        }
+    }
+}
+
+pub struct IntoIter<T>(std::iter::Flatten<std::vec::IntoIter<Option<T>>>);
+
+impl<T> Iterator for IntoIter<T> {
+    type Item = T;
+    fn next(&mut self) -> Option<T> {
+        self.0.next()
+    }
+}
+
+impl<T> DoubleEndedIterator for IntoIter<T> {
+    fn next_back(&mut self) -> Option<T> {
+        self.0.next_back()
+    }
+}
+
 impl<T> Array<T> {
     pub fn pos(&self) -> usize {
         self.j
@@ -14,6 +65,14 @@ impl<T> Array<T> {
     pub fn length(&self) -> usize {
         self.a.len()
     }
+    pub fn iter(&self) -> Iter<'_, T> {
+        Iter {
+            a: &self.a,
+            j: self.j,
+            i: 0,
+            n: self.n,
+        }
+    }
 
     pub fn new(b: usize) -> Self {
         Self {
@@ -23,6 +82,16 @@ impl<T> Array<T> {
         }
     }
 
+    // the array never grows, so this only checks that additional more elements fit
+    pub fn try_reserve(&self, additional: usize) -> Result<(), Error> {
+        match self.n.checked_add(additional) {
+            Some(m) if m <= self.length() => Ok(()),
+            _ => Err(Error::CapacityExceeded {
+                capacity: self.length(),
+            }),
+        }
+    }
+
     fn allocate_in_heap(size: usize) -> Box<[Option<T>]> {
         std::iter::repeat_with(Default::default)
             .take(size)
@@ -36,6 +105,22 @@ impl<T: Clone> List<T> for Array<T> {
         self.n
     }
 
+    fn try_add(&mut self, i: usize, x: T) -> Result<(), Error> {
+        if i > self.n {
+            return Err(Error::OutOfBounds {
+                index: i,
+                len: self.n,
+            });
+        }
+        if self.n == self.length() {
+            return Err(Error::CapacityExceeded {
+                capacity: self.length(),
+            });
+        }
+        self.add(i, x);
+        Ok(())
+    }
+
     fn get(&self, i: usize) -> Option<T> {
         self.a.get((self.j + i) % self.length())?.as_ref().cloned()
     }
@@ -90,9 +175,46 @@ impl<T: Clone> List<T> for Array<T> {
     }
 }
 
+impl<'a, T> IntoIterator for &'a Array<T> {
+    type Item = &'a T;
+    type IntoIter = Iter<'a, T>;
+    fn into_iter(self) -> Iter<'a, T> {
+        self.iter()
+    }
+}
+
+impl<T> IntoIterator for Array<T> {
+    type Item = T;
+    type IntoIter = IntoIter<T>;
+    fn into_iter(self) -> IntoIter<T> {
+        let mut a = self.a.into_vec();
+        a.rotate_left(self.j);
+        a.truncate(self.n);
+        IntoIter(a.into_iter().flatten())
+    }
+}
+
+impl<T: Clone> FromIterator<T> for Array<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let v = iter.into_iter().collect::<Vec<_>>();
+        let mut a = Self::new(std::cmp::max(v.len(), 1));
+        a.extend(v);
+        a
+    }
+}
+
+impl<T: Clone> Extend<T> for Array<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for x in iter {
+            self.add(self.n, x);
+        }
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::Array;
+    use chapter01::error::Error;
     use chapter01::interface::List;
     #[test]
     fn test_boundeddeque() {
@@ -116,5 +238,27 @@ mod test {
             bounded_deque.remove(0);
         }
         println!("\nBDeque = {:?}\n", bounded_deque);
+        let mut bounded_deque: Array<char> = "abc".chars().collect();
+        assert_eq!(bounded_deque.remove(0), Some('a'));
+        bounded_deque.extend(Some('d'));
+        assert_eq!(bounded_deque.iter().collect::<String>(), "bcd");
+        assert_eq!(bounded_deque.into_iter().rev().collect::<String>(), "dcb");
+        let mut bounded_deque: Array<char> = Array::new(2);
+        assert_eq!(
+            bounded_deque.try_add(1, 'a'),
+            Err(Error::OutOfBounds { index: 1, len: 0 })
+        );
+        assert_eq!(bounded_deque.try_add(0, 'a'), Ok(()));
+        assert_eq!(bounded_deque.try_reserve(1), Ok(()));
+        assert_eq!(bounded_deque.try_add(1, 'b'), Ok(()));
+        let full = Err(Error::CapacityExceeded { capacity: 2 });
+        assert_eq!(bounded_deque.try_reserve(1), full);
+        assert_eq!(bounded_deque.try_add(0, 'c'), full);
+        assert_eq!(bounded_deque.try_set(1, 'c'), Ok('b'));
+        assert_eq!(
+            bounded_deque.try_set(2, 'c'),
+            Err(Error::OutOfBounds { index: 2, len: 2 })
+        );
+        assert_eq!(bounded_deque.iter().collect::<String>(), "ac");
     }
 }
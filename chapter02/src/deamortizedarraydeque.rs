@@ -0,0 +1,418 @@
+use chapter01::interface::List;
+use std::iter::FromIterator;
+
+// slots prepared or elements moved, and old slots released, per operation
+const STEP: usize = 8;
+const MIN_LENGTH: usize = 8;
+
+// an ArrayDeque that resizes a few slots at a time, so no single operation copies the array
+#[derive(Clone, Debug)]
+pub struct Array<T> {
+    a: Vec<Option<T>>,
+    j: usize,
+    n: usize,
+    // the buffer being resized into; it is filled with empty slots first, then
+    // the elements at positions lo..lo + m move over from a one by one
+    b: Vec<Option<T>>,
+    lb: usize,
+    jb: usize,
+    lo: usize,
+    m: usize,
+    // a previous buffer, dropped a few slots at a time
+    old: Vec<Option<T>>,
+}
+
+pub struct Iter<'a, T> {
+    a: &'a Array<T>,
+    i: usize,
+    n: usize,
+}
+
+impl<'a, T> Iterator for Iter<'a, T> {
+    type Item = &'a T;
+    fn next(&mut self) -> Option<&'a T> {
+        if self.i < self.n {
+            self.i += 1;
+            self.a.slot(self.i - 1).as_ref()
+        } else {
+            None
+        }
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.n - self.i, Some(self.n - self.i))
+    }
+}
+
+impl<'a, T> DoubleEndedIterator for Iter<'a, T> {
+    fn next_back(&mut self) -> Option<&'a T> {
+        if self.i < self.n {
+            self.n -= 1;
+            self.a.slot(self.n).as_ref()
+        } else {
+            None
+        }
+    }
+}
+
+pub struct IntoIter<T>(std::vec::IntoIter<T>);
+
+impl<T> Iterator for IntoIter<T> {
+    type Item = T;
+    fn next(&mut self) -> Option<T> {
+        self.0.next()
+    }
+}
+
+impl<T> DoubleEndedIterator for IntoIter<T> {
+    fn next_back(&mut self) -> Option<T> {
+        self.0.next_back()
+    }
+}
+
+impl<T> Default for Array<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Array<T> {
+    pub fn new() -> Self {
+        Self {
+            a: std::iter::repeat_with(|| None).take(MIN_LENGTH).collect(),
+            j: 0,
+            n: 0,
+            b: vec![],
+            lb: 0,
+            jb: 0,
+            lo: 0,
+            m: 0,
+            old: vec![],
+        }
+    }
+
+    pub fn length(&self) -> usize {
+        self.a.len()
+    }
+
+    pub fn resizing(&self) -> bool {
+        self.lb > 0
+    }
+
+    pub fn iter(&self) -> Iter<'_, T> {
+        Iter {
+            a: self,
+            i: 0,
+            n: self.n,
+        }
+    }
+
+    fn moving(&self) -> bool {
+        self.lb > 0 && self.b.len() == self.lb
+    }
+
+    fn in_a(&self, i: usize) -> bool {
+        i >= self.lo && i - self.lo < self.m
+    }
+
+    fn slot(&self, i: usize) -> &Option<T> {
+        if self.in_a(i) {
+            &self.a[(self.j + i) % self.a.len()]
+        } else {
+            &self.b[(self.jb + i) % self.lb]
+        }
+    }
+
+    fn slot_mut(&mut self, i: usize) -> &mut Option<T> {
+        if self.in_a(i) {
+            let k = (self.j + i) % self.a.len();
+            &mut self.a[k]
+        } else {
+            let k = (self.jb + i) % self.lb;
+            &mut self.b[k]
+        }
+    }
+
+    fn shift(&mut self, from: usize, to: usize) {
+        let x = self.slot_mut(from).take();
+        *self.slot_mut(to) = x;
+    }
+
+    fn start(&mut self, length: usize) {
+        self.lb = length;
+        self.b = Vec::with_capacity(length);
+        self.jb = 0;
+    }
+
+    fn work(&mut self, mut budget: usize) {
+        while budget > 0 {
+            if self.lb == 0 {
+                break;
+            } else if self.b.len() < self.lb {
+                self.b.push(None);
+            } else if self.m > 0 {
+                let k = (self.j + self.lo) % self.a.len();
+                self.b[(self.jb + self.lo) % self.lb] = self.a[k].take();
+                self.lo += 1;
+                self.m -= 1;
+            } else {
+                self.old = std::mem::replace(&mut self.a, std::mem::take(&mut self.b));
+                self.j = self.jb;
+                self.lb = 0;
+                self.lo = 0;
+                self.m = self.n;
+            }
+            budget -= 1;
+        }
+    }
+
+    // only reached when operations outpace a resize, which the thresholds below rule out
+    fn make_room(&mut self) {
+        let length = if self.moving() { self.lb } else { self.a.len() };
+        if self.n + 1 > length {
+            self.work(usize::MAX);
+            if self.n + 1 > self.a.len() {
+                self.start(std::cmp::max(4 * (self.n + 1), MIN_LENGTH));
+                self.work(usize::MAX);
+            }
+        }
+    }
+
+    fn step(&mut self) {
+        if self.lb == 0 {
+            let length = self.a.len();
+            if 2 * self.n >= length || (8 * self.n <= length && length > MIN_LENGTH) {
+                self.start(std::cmp::max(4 * self.n, MIN_LENGTH));
+            }
+        }
+        self.old.truncate(self.old.len().saturating_sub(STEP));
+        self.work(STEP);
+    }
+}
+
+impl<T: Clone> List<T> for Array<T> {
+    fn size(&self) -> usize {
+        self.n
+    }
+
+    fn get(&self, i: usize) -> Option<T> {
+        if i < self.n {
+            self.slot(i).clone()
+        } else {
+            None
+        }
+    }
+
+    fn set(&mut self, i: usize, x: T) -> Option<T> {
+        if i < self.n {
+            self.slot_mut(i).replace(x)
+        } else {
+            None
+        }
+    }
+
+    fn add(&mut self, i: usize, x: T) {
+        assert!(i <= self.n);
+        self.make_room();
+        let moving = self.moving();
+        if i < self.n / 2 {
+            self.j = (self.j + self.a.len() - 1) % self.a.len();
+            if moving {
+                self.jb = (self.jb + self.lb - 1) % self.lb;
+                self.lo += 1;
+            } else {
+                self.m += 1;
+            }
+            self.n += 1;
+            for k in 0..i {
+                self.shift(k + 1, k);
+            }
+        } else {
+            if !moving {
+                self.m += 1;
+            }
+            self.n += 1;
+            for k in (i + 1..self.n).rev() {
+                self.shift(k - 1, k);
+            }
+        }
+        *self.slot_mut(i) = Some(x);
+        self.step();
+    }
+
+    fn remove(&mut self, i: usize) -> Option<T> {
+        if i >= self.n {
+            return None;
+        }
+        let x = self.slot_mut(i).take();
+        if i < self.n / 2 {
+            for k in (0..i).rev() {
+                self.shift(k, k + 1);
+            }
+            self.j = (self.j + 1) % self.a.len();
+            if self.lb > 0 {
+                self.jb = (self.jb + 1) % self.lb;
+            }
+            if self.lo > 0 {
+                self.lo -= 1;
+            } else if self.m > 0 {
+                self.m -= 1;
+            }
+        } else {
+            for k in i..self.n - 1 {
+                self.shift(k + 1, k);
+            }
+            if self.m > 0 && self.lo + self.m == self.n {
+                self.m -= 1;
+            }
+        }
+        self.n -= 1;
+        self.step();
+        x
+    }
+}
+
+impl<T: PartialEq> PartialEq for Array<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.n == other.n && self.iter().eq(other.iter())
+    }
+}
+
+impl<T: Eq> Eq for Array<T> {}
+
+impl<'a, T> IntoIterator for &'a Array<T> {
+    type Item = &'a T;
+    type IntoIter = Iter<'a, T>;
+    fn into_iter(self) -> Iter<'a, T> {
+        self.iter()
+    }
+}
+
+impl<T> IntoIterator for Array<T> {
+    type Item = T;
+    type IntoIter = IntoIter<T>;
+    fn into_iter(mut self) -> IntoIter<T> {
+        let xs = (0..self.n)
+            .filter_map(|i| self.slot_mut(i).take())
+            .collect::<Vec<_>>();
+        IntoIter(xs.into_iter())
+    }
+}
+
+impl<T: Clone> FromIterator<T> for Array<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut a = Self::new();
+        a.extend(iter);
+        a
+    }
+}
+
+impl<T: Clone> Extend<T> for Array<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for x in iter {
+            self.add(self.n, x);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Array, STEP};
+    use chapter01::interface::List;
+    use rand::{thread_rng, Rng};
+    use std::collections::VecDeque;
+    #[test]
+    fn test_deamortizedarraydeque() {
+        let mut array_deque: Array<char> = Array::new();
+        for (i, elem) in "abcdefgh".chars().enumerate() {
+            array_deque.add(i, elem);
+        }
+        array_deque.remove(2);
+        array_deque.add(4, 'x');
+        array_deque.add(3, 'y');
+        array_deque.add(4, 'z');
+        assert_eq!(array_deque.iter().collect::<String>(), "abdyzexfgh");
+        println!("\nDeamortizedArrayDeque = {:?}\n", array_deque);
+
+        let mut rng = thread_rng();
+        let mut array_deque: Array<usize> = Array::new();
+        let mut vecdeque = VecDeque::new();
+        for k in 0..20000 {
+            // grow, then shrink, then mix
+            let phase = k / 5000;
+            let (before, resizing) = (array_deque.b.len(), array_deque.resizing());
+            let i = match rng.gen_range(0, 4) {
+                0 => 0,
+                1 => vecdeque.len(),
+                2 => vecdeque.len() / 2,
+                _ => rng.gen_range(0, vecdeque.len() + 1),
+            };
+            if phase == 1 || (phase == 3 && rng.gen()) {
+                assert_eq!(
+                    array_deque.remove(i),
+                    if i < vecdeque.len() {
+                        vecdeque.remove(i)
+                    } else {
+                        None
+                    }
+                );
+            } else {
+                array_deque.add(i, k);
+                vecdeque.insert(i, k);
+            }
+            // a resize only ever prepares a few slots per operation
+            if resizing && array_deque.resizing() {
+                assert!(array_deque.b.len() - before <= STEP);
+            }
+            assert_eq!(array_deque.size(), vecdeque.len());
+            if k % 97 == 0 {
+                assert!(array_deque.iter().eq(vecdeque.iter()));
+                assert!(array_deque.iter().rev().eq(vecdeque.iter().rev()));
+            }
+            if !vecdeque.is_empty() {
+                let j = rng.gen_range(0, vecdeque.len());
+                assert_eq!(array_deque.get(j), vecdeque.get(j).copied());
+            }
+        }
+
+        // pushing and popping at the ends never needs to finish a resize in one go
+        let mut array_deque: Array<usize> = Array::new();
+        let mut lengths = vec![array_deque.length()];
+        for k in 0..10000 {
+            let (length, lb) = (array_deque.length(), array_deque.lb);
+            if k % 3 == 0 {
+                array_deque.add(0, k);
+            } else {
+                array_deque.add(array_deque.size(), k);
+            }
+            assert!(array_deque.length() == length || array_deque.length() == lb);
+            if array_deque.length() != length {
+                lengths.push(array_deque.length());
+            }
+        }
+        assert!(lengths.windows(2).all(|w| w[1] == 2 * w[0]));
+        while array_deque.size() > 0 {
+            let (length, lb) = (array_deque.length(), array_deque.lb);
+            array_deque.remove(array_deque.size() - 1);
+            assert!(array_deque.length() == length || array_deque.length() == lb);
+        }
+        // the shrinking finishes a few slots at a time as well
+        for k in 0..2000 {
+            array_deque.add(0, k);
+            array_deque.remove(0);
+        }
+        assert_eq!(array_deque.length(), 8);
+
+        let mut array_deque: Array<i32> = (0..50).collect();
+        let copy = array_deque.clone();
+        assert_eq!(array_deque, copy);
+        array_deque.set(10, -1);
+        assert_ne!(array_deque, copy);
+        assert_eq!(array_deque.remove(10), Some(-1));
+        array_deque.add(10, 10);
+        assert_eq!(array_deque, copy);
+        assert_eq!(
+            array_deque.into_iter().rev().collect::<Vec<_>>(),
+            (0..50).rev().collect::<Vec<_>>()
+        );
+    }
+}
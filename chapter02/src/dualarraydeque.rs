@@ -1,5 +1,7 @@
-use super::arraystack::Array as ArrayStack;
+use super::arraystack::{Array as ArrayStack, IntoIter as StackIntoIter, Iter as StackIter};
+use super::growth::Growth;
 use chapter01::interface::List;
+use std::iter::{Chain, FromIterator, Rev};
 
 #[derive(Clone, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
 pub struct Array<T> {
@@ -7,6 +9,36 @@ pub struct Array<T> {
     back: ArrayStack<T>,
 }
 
+pub struct Iter<'a, T>(Chain<Rev<StackIter<'a, T>>, StackIter<'a, T>>);
+
+impl<'a, T> Iterator for Iter<'a, T> {
+    type Item = &'a T;
+    fn next(&mut self) -> Option<&'a T> {
+        self.0.next()
+    }
+}
+
+impl<'a, T> DoubleEndedIterator for Iter<'a, T> {
+    fn next_back(&mut self) -> Option<&'a T> {
+        self.0.next_back()
+    }
+}
+
+pub struct IntoIter<T>(Chain<Rev<StackIntoIter<T>>, StackIntoIter<T>>);
+
+impl<T> Iterator for IntoIter<T> {
+    type Item = T;
+    fn next(&mut self) -> Option<T> {
+        self.0.next()
+    }
+}
+
+impl<T> DoubleEndedIterator for IntoIter<T> {
+    fn next_back(&mut self) -> Option<T> {
+        self.0.next_back()
+    }
+}
+
 impl<T: Clone> Array<T> {
     pub fn new() -> Self {
         Self {
@@ -15,21 +47,65 @@ impl<T: Clone> Array<T> {
         }
     }
 
+    pub fn with_growth(growth: Growth) -> Self {
+        Self {
+            front: ArrayStack::with_growth(growth),
+            back: ArrayStack::with_growth(growth),
+        }
+    }
+
+    pub fn iter(&self) -> Iter<'_, T> {
+        Iter(self.front.iter().rev().chain(self.back.iter()))
+    }
+
+    pub fn growth(&self) -> Growth {
+        self.back.growth()
+    }
+
+    pub fn set_growth(&mut self, growth: Growth) {
+        self.front.set_growth(growth);
+        self.back.set_growth(growth);
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.front.capacity() + self.back.capacity()
+    }
+
+    // room for additional more elements at either end; a rebalance still reallocates
+    pub fn reserve(&mut self, additional: usize) {
+        self.front.reserve(additional);
+        self.back.reserve(additional);
+    }
+
+    pub fn reserve_exact(&mut self, additional: usize) {
+        self.front.reserve_exact(additional);
+        self.back.reserve_exact(additional);
+    }
+
+    pub fn shrink_to_fit(&mut self) {
+        self.front.shrink_to_fit();
+        self.back.shrink_to_fit();
+    }
+
     pub fn balance(&mut self) {
         if 3 * self.front.size() < self.back.size() || 3 * self.back.size() < self.front.size() {
             let n = self.front.size() + self.back.size();
             let nf = n / 2;
             let nb = n - nf;
-            let mut af: ArrayStack<T> = ArrayStack::with_length(std::cmp::max(2 * nf, 1));
-            let mut bf: ArrayStack<T> = ArrayStack::with_length(std::cmp::max(2 * nb, 1));
-            for i in 0..nf {
-                af.add(nf - i - 1, self.remove(0).unwrap());
-            }
-            for i in 0..nb {
-                bf.add(i, self.remove(0).unwrap());
-            }
-            std::mem::replace(&mut self.front, af);
-            std::mem::replace(&mut self.back, bf);
+            let growth = self.growth();
+            let mut af: ArrayStack<T> = ArrayStack::with_length(growth.length(nf));
+            let mut bf: ArrayStack<T> = ArrayStack::with_length(growth.length(nb));
+            af.set_growth(growth);
+            bf.set_growth(growth);
+            // removing one by one would rebalance halfway through and scramble the order
+            let front = std::mem::replace(&mut self.front, ArrayStack::new());
+            let back = std::mem::replace(&mut self.back, ArrayStack::new());
+            let mut xs = front.into_iter().rev().chain(back);
+            let head = xs.by_ref().take(nf).collect::<Vec<_>>();
+            af.extend(head.into_iter().rev());
+            bf.extend(xs);
+            self.front = af;
+            self.back = bf;
         }
     }
 }
@@ -65,20 +141,52 @@ impl<T: Clone> List<T> for Array<T> {
     }
 
     fn remove(&mut self, i: usize) -> Option<T> {
-        let x;
-        if i < self.front.size() {
-            x = self.front.remove(self.front.size() - i - 1);
+        let x = if i < self.front.size() {
+            self.front.remove(self.front.size() - i - 1)
         } else {
-            x = self.back.remove(i - self.front.size());
-        }
+            self.back.remove(i - self.front.size())
+        };
         self.balance();
         x
     }
 }
 
+impl<'a, T: Clone> IntoIterator for &'a Array<T> {
+    type Item = &'a T;
+    type IntoIter = Iter<'a, T>;
+    fn into_iter(self) -> Iter<'a, T> {
+        self.iter()
+    }
+}
+
+impl<T> IntoIterator for Array<T> {
+    type Item = T;
+    type IntoIter = IntoIter<T>;
+    fn into_iter(self) -> IntoIter<T> {
+        IntoIter(self.front.into_iter().rev().chain(self.back))
+    }
+}
+
+impl<T: Clone> FromIterator<T> for Array<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut a = Self::new();
+        a.extend(iter);
+        a
+    }
+}
+
+impl<T: Clone> Extend<T> for Array<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for x in iter {
+            self.add(self.size(), x);
+        }
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::Array;
+    use crate::growth::Growth;
     use chapter01::interface::List;
     #[test]
     fn test_dualarraydeque() {
@@ -108,5 +216,30 @@ mod test {
         }
         while dual_array_deque.remove(0).is_some() {}
         println!("\nDualArrayDeque = {:?}\n", dual_array_deque);
+        let mut dual_array_deque: Array<i32> = (0..5).collect();
+        dual_array_deque.add(0, -1);
+        dual_array_deque.extend(5..7);
+        assert_eq!(
+            dual_array_deque.iter().copied().collect::<Vec<_>>(),
+            (-1..7).collect::<Vec<_>>()
+        );
+        assert_eq!(
+            dual_array_deque.into_iter().rev().collect::<Vec<_>>(),
+            (-1..7).rev().collect::<Vec<_>>()
+        );
+        let mut dual_array_deque: Array<i32> = Array::with_growth(Growth::Increment(3));
+        dual_array_deque.reserve(4);
+        assert!(dual_array_deque.capacity() >= 8);
+        dual_array_deque.extend(0..20);
+        assert_eq!(dual_array_deque.growth(), Growth::Increment(3));
+        dual_array_deque.shrink_to_fit();
+        assert_eq!(dual_array_deque.capacity(), 20);
+        dual_array_deque.set_growth(Growth::Double);
+        dual_array_deque.reserve_exact(2);
+        assert_eq!(dual_array_deque.capacity(), 24);
+        assert_eq!(
+            dual_array_deque.iter().copied().collect::<Vec<_>>(),
+            (0..20).collect::<Vec<_>>()
+        );
     }
 }
@@ -0,0 +1,40 @@
+// how an array-backed list picks its new length when it resizes
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum Growth {
+    #[default]
+    Double,
+    OneAndHalf,
+    Increment(usize),
+}
+
+impl Growth {
+    // the length to resize to when n elements are stored; always more than n
+    // unless n is 0, so the element being added has a slot
+    pub fn length(&self, n: usize) -> usize {
+        match *self {
+            Growth::Double => std::cmp::max(n.saturating_mul(2), 1),
+            Growth::OneAndHalf => std::cmp::max(n.saturating_add(n / 2), n.saturating_add(1)),
+            Growth::Increment(k) => n.saturating_add(std::cmp::max(k, 1)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Growth;
+    #[test]
+    fn test_growth() {
+        assert_eq!(Growth::default(), Growth::Double);
+        let lengths = |g: Growth| {
+            [0, 1, 2, 4, 10]
+                .iter()
+                .map(|&n| g.length(n))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(lengths(Growth::Double), vec![1, 2, 4, 8, 20]);
+        assert_eq!(lengths(Growth::OneAndHalf), vec![1, 2, 3, 6, 15]);
+        assert_eq!(lengths(Growth::Increment(8)), vec![8, 9, 10, 12, 18]);
+        assert_eq!(lengths(Growth::Increment(0)), vec![1, 2, 3, 5, 11]);
+        assert_eq!(Growth::Double.length(usize::MAX), usize::MAX);
+    }
+}
@@ -2,5 +2,7 @@ pub mod arraydeque;
 pub mod arrayqueue;
 pub mod arraystack;
 pub mod boundeddeque;
+pub mod deamortizedarraydeque;
 pub mod dualarraydeque;
+pub mod growth;
 pub mod rootisharraystack;
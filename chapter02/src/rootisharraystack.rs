@@ -1,6 +1,7 @@
 use super::arraystack::Array as ArrayStack;
 use chapter01::interface::List;
 use std::cell::RefCell;
+use std::iter::FromIterator;
 use std::rc::Rc;
 
 #[derive(Clone, Debug, Eq, Ord, PartialEq, PartialOrd)]
@@ -9,6 +10,72 @@ pub struct Array<T> {
     n: usize,
 }
 
+pub struct Iter<'a, T: Clone> {
+    a: &'a Array<T>,
+    i: usize,
+    n: usize,
+}
+
+impl<'a, T: Clone> Iterator for Iter<'a, T> {
+    type Item = T;
+    fn next(&mut self) -> Option<T> {
+        if self.i < self.n {
+            self.i += 1;
+            self.a.get(self.i - 1)
+        } else {
+            None
+        }
+    }
+}
+
+impl<'a, T: Clone> DoubleEndedIterator for Iter<'a, T> {
+    fn next_back(&mut self) -> Option<T> {
+        if self.i < self.n {
+            self.n -= 1;
+            self.a.get(self.n)
+        } else {
+            None
+        }
+    }
+}
+
+pub struct IntoIter<T: Clone> {
+    a: Array<T>,
+    i: usize,
+    n: usize,
+}
+
+impl<T: Clone> IntoIter<T> {
+    fn take(&mut self, i: usize) -> Option<T> {
+        let b = Array::<T>::i2b(i);
+        let j = i - b * (b + 1) / 2;
+        self.a.blocks.get(b)?[j].borrow_mut().take()
+    }
+}
+
+impl<T: Clone> Iterator for IntoIter<T> {
+    type Item = T;
+    fn next(&mut self) -> Option<T> {
+        if self.i < self.n {
+            self.i += 1;
+            self.take(self.i - 1)
+        } else {
+            None
+        }
+    }
+}
+
+impl<T: Clone> DoubleEndedIterator for IntoIter<T> {
+    fn next_back(&mut self) -> Option<T> {
+        if self.i < self.n {
+            self.n -= 1;
+            self.take(self.n)
+        } else {
+            None
+        }
+    }
+}
+
 impl<T: Clone> Default for Array<T> {
     fn default() -> Self {
         Self::new()
@@ -22,6 +89,13 @@ impl<T: Clone> Array<T> {
             n: 0,
         }
     }
+    pub fn iter(&self) -> Iter<'_, T> {
+        Iter {
+            a: self,
+            i: 0,
+            n: self.n,
+        }
+    }
     fn i2b(i: usize) -> usize {
         let db = (-3.0 + (9.0 + 8.0 * i as f64).sqrt()) / 2f64;
         db.ceil() as usize
@@ -32,6 +106,27 @@ impl<T: Clone> Array<T> {
             .collect::<Rc<_>>();
         self.blocks.add(self.blocks.size(), block);
     }
+    // blocks come in fixed sizes, so the capacity is the sum of the block sizes
+    pub fn capacity(&self) -> usize {
+        let r = self.blocks.size();
+        r * (r + 1) / 2
+    }
+    pub fn reserve(&mut self, additional: usize) {
+        let length = self.n.checked_add(additional).expect("capacity overflow");
+        while self.capacity() < length {
+            self.grow();
+        }
+    }
+    pub fn reserve_exact(&mut self, additional: usize) {
+        self.reserve(additional)
+    }
+    pub fn shrink_to_fit(&mut self) {
+        let mut r = self.blocks.size();
+        while r > 0 && (r - 1) * r / 2 >= self.n {
+            self.blocks.remove(r - 1);
+            r -= 1;
+        }
+    }
     fn shrink(&mut self) {
         let mut r = self.blocks.size();
         while r > 0 && (std::cmp::max(2, r) - 2) * (r - 1) / 2 >= self.n {
@@ -49,10 +144,10 @@ impl<T: Clone> List<T> for Array<T> {
     fn get(&self, i: usize) -> Option<T> {
         let b = Self::i2b(i);
         let j = i - b * (b + 1) / 2;
-        match self.blocks.get(b)?[j].borrow().as_ref() {
-            Some(value) => Some(value.clone()),
-            None => None,
-        }
+        self.blocks.get(b)?[j]
+            .borrow()
+            .as_ref()
+            .map(|value| value.clone())
     }
 
     fn set(&mut self, i: usize, x: T) -> Option<T> {
@@ -95,6 +190,39 @@ impl<T: Clone> List<T> for Array<T> {
     }
 }
 
+impl<'a, T: Clone> IntoIterator for &'a Array<T> {
+    type Item = T;
+    type IntoIter = Iter<'a, T>;
+    fn into_iter(self) -> Iter<'a, T> {
+        self.iter()
+    }
+}
+
+impl<T: Clone> IntoIterator for Array<T> {
+    type Item = T;
+    type IntoIter = IntoIter<T>;
+    fn into_iter(self) -> IntoIter<T> {
+        let n = self.n;
+        IntoIter { a: self, i: 0, n }
+    }
+}
+
+impl<T: Clone> FromIterator<T> for Array<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut a = Self::new();
+        a.extend(iter);
+        a
+    }
+}
+
+impl<T: Clone> Extend<T> for Array<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for x in iter {
+            self.add(self.n, x);
+        }
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::Array;
@@ -125,5 +253,31 @@ mod test {
         }
         while rootish_array_stack.remove(0).is_some() {}
         println!("\nRootishArrayStack = {:?}\n", rootish_array_stack);
+        let mut rootish_array_stack: Array<i32> = (0..7).collect();
+        rootish_array_stack.extend(7..10);
+        assert_eq!(
+            rootish_array_stack.iter().rev().collect::<Vec<_>>(),
+            (0..10).rev().collect::<Vec<_>>()
+        );
+        assert_eq!(
+            rootish_array_stack.into_iter().collect::<Vec<_>>(),
+            (0..10).collect::<Vec<_>>()
+        );
+        let mut rootish_array_stack: Array<i32> = Array::new();
+        rootish_array_stack.reserve(10);
+        assert_eq!(rootish_array_stack.capacity(), 10);
+        rootish_array_stack.extend(0..10);
+        assert_eq!(rootish_array_stack.capacity(), 10);
+        rootish_array_stack.reserve_exact(1);
+        assert_eq!(rootish_array_stack.capacity(), 15);
+        rootish_array_stack.remove(9);
+        rootish_array_stack.shrink_to_fit();
+        assert_eq!(rootish_array_stack.capacity(), 10);
+        rootish_array_stack.shrink_to_fit();
+        assert_eq!(rootish_array_stack.capacity(), 10);
+        assert_eq!(
+            rootish_array_stack.iter().collect::<Vec<_>>(),
+            (0..9).collect::<Vec<_>>()
+        );
     }
 }
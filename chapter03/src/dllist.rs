@@ -1,18 +1,28 @@
 #![allow(clippy::many_single_char_names,clippy::explicit_counter_loop)]
 use chapter01::interface::List;
 use std::cell::RefCell;
+use std::iter::FromIterator;
+use std::marker::PhantomData;
+use std::ops::Range;
 use std::rc::{Rc, Weak};
 
 type Link<T> = Option<Rc<RefCell<Node<T>>>>;
 type Wink<T> = Option<Weak<RefCell<Node<T>>>>;
+type Ref<T> = Rc<RefCell<Node<T>>>;
 
-#[derive(Clone, Debug, Default)]
+#[derive(Clone, Debug)]
 pub struct DLList<T: Clone + Default> {
     head: Link<T>,
     tail: Wink<T>,
     n: usize,
 }
 
+impl<T: Clone + Default> Default for DLList<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl<T> Drop for DLList<T>
 where
     T: Clone + Default,
@@ -39,7 +49,180 @@ impl<T> Node<T> {
     }
 }
 
+pub struct Iter<'a, T> {
+    front: Link<T>,
+    back: Link<T>,
+    n: usize,
+    list: PhantomData<&'a Node<T>>,
+}
+
+impl<'a, T: Clone> Iterator for Iter<'a, T> {
+    type Item = T;
+    fn next(&mut self) -> Option<T> {
+        if self.n == 0 {
+            return None;
+        }
+        self.n -= 1;
+        self.front.take().map(|u| {
+            self.front = u.borrow().next.clone();
+            let x = u.borrow().x.clone();
+            x
+        })
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.n, Some(self.n))
+    }
+}
+
+impl<'a, T: Clone> DoubleEndedIterator for Iter<'a, T> {
+    fn next_back(&mut self) -> Option<T> {
+        if self.n == 0 {
+            return None;
+        }
+        self.n -= 1;
+        self.back.take().map(|u| {
+            self.back = u.borrow().prev.as_ref().and_then(|p| p.upgrade());
+            let x = u.borrow().x.clone();
+            x
+        })
+    }
+}
+
+pub struct IntoIter<T: Clone + Default>(DLList<T>);
+
+impl<T: Clone + Default> Iterator for IntoIter<T> {
+    type Item = T;
+    fn next(&mut self) -> Option<T> {
+        self.0.remove(0)
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.0.n, Some(self.0.n))
+    }
+}
+
+impl<T: Clone + Default> DoubleEndedIterator for IntoIter<T> {
+    fn next_back(&mut self) -> Option<T> {
+        match self.0.n {
+            0 => None,
+            n => self.0.remove(n - 1),
+        }
+    }
+}
+
+// a position in the list, or just past its end, to edit at without walking from either end
+pub struct CursorMut<'a, T: Clone + Default> {
+    list: &'a mut DLList<T>,
+    u: Ref<T>,
+    i: usize,
+}
+
+// holds the list borrowed until the cursor is dropped, so no node the cursor
+// points at can be removed while it is alive
+impl<'a, T: Clone + Default> Drop for CursorMut<'a, T> {
+    fn drop(&mut self) {}
+}
+
+impl<'a, T: Clone + Default> CursorMut<'a, T> {
+    pub fn index(&self) -> Option<usize> {
+        if self.i < self.list.n {
+            Some(self.i)
+        } else {
+            None
+        }
+    }
+
+    pub fn current(&self) -> Option<T> {
+        self.index().map(|_| self.u.borrow().x.clone())
+    }
+
+    pub fn set(&mut self, x: T) -> Option<T> {
+        self.index()?;
+        Some(std::mem::replace(&mut self.u.borrow_mut().x, x))
+    }
+
+    // past the end wraps around to the front, and the other way round
+    pub fn move_next(&mut self) {
+        if self.i < self.list.n {
+            let next = self.u.borrow().next.clone().unwrap();
+            self.u = next;
+            self.i += 1;
+        } else {
+            let (h, _) = self.list.dummies();
+            let first = h.borrow().next.clone().unwrap();
+            self.u = first;
+            self.i = 0;
+        }
+    }
+
+    pub fn move_prev(&mut self) {
+        if self.i == 0 {
+            self.u = self.list.dummies().1;
+            self.i = self.list.n;
+        } else {
+            let prev = self.u.borrow().prev.as_ref().and_then(|p| p.upgrade());
+            self.u = prev.unwrap();
+            self.i -= 1;
+        }
+    }
+
+    pub fn insert_before(&mut self, x: T) {
+        self.list.add_before(Some(self.u.clone()), x);
+        self.i += 1;
+    }
+
+    // past the end, this inserts at the front
+    pub fn insert_after(&mut self, x: T) {
+        if self.i < self.list.n {
+            let next = self.u.borrow().next.clone();
+            self.list.add_before(next, x);
+        } else {
+            let first = self.list.dummies().0.borrow().next.clone();
+            self.list.add_before(first, x);
+            self.i += 1;
+        }
+    }
+
+    // the cursor moves on to the next element
+    pub fn remove_current(&mut self) -> Option<T> {
+        self.index()?;
+        let next = self.u.borrow().next.clone().unwrap();
+        let w = std::mem::replace(&mut self.u, next);
+        self.list.remove_link(Some(w.clone()));
+        Some(Rc::try_unwrap(w).ok().unwrap().into_inner().x)
+    }
+
+    // the current element and everything after it move to a new list; the cursor ends up past the end
+    pub fn split(&mut self) -> DLList<T> {
+        let other = self.list.split_link(self.u.clone(), self.i);
+        self.u = self.list.dummies().1;
+        other
+    }
+}
+
 impl<T: Default + Clone> DLList<T> {
+    pub fn iter(&self) -> Iter<'_, T> {
+        Iter {
+            front: self.head.as_ref().and_then(|d| d.borrow().next.clone()),
+            back: self
+                .tail
+                .as_ref()
+                .and_then(|d| d.upgrade())
+                .and_then(|d| d.borrow().prev.as_ref().and_then(|p| p.upgrade())),
+            n: self.n,
+            list: PhantomData,
+        }
+    }
+
+    pub fn cursor_mut(&mut self, i: usize) -> CursorMut<'_, T> {
+        assert!(i <= self.n);
+        let u = self.get_link(i).unwrap();
+        CursorMut { list: self, u, i }
+    }
+
+    pub fn cursor_front_mut(&mut self) -> CursorMut<'_, T> {
+        self.cursor_mut(0)
+    }
+
     pub fn new() -> Self {
         let dummy1: Rc<RefCell<Node<T>>> = Default::default();
         let dummy2: Rc<RefCell<Node<T>>> = Default::default();
@@ -92,6 +275,34 @@ impl<T: Default + Clone> DLList<T> {
         }
         self.n -= 1;
     }
+
+    fn dummies(&self) -> (Ref<T>, Ref<T>) {
+        let head = self.head.clone().unwrap();
+        let tail = self.tail.as_ref().and_then(|p| p.upgrade()).unwrap();
+        (head, tail)
+    }
+
+    // everything from u, the i-th node or the tail dummy, on moves to a new list
+    fn split_link(&mut self, u: Ref<T>, i: usize) -> Self {
+        let mut other = Self::new();
+        if i < self.n {
+            let p = u.borrow().prev.as_ref().and_then(|p| p.upgrade()).unwrap();
+            let (_, t) = self.dummies();
+            let last = t.borrow().prev.as_ref().and_then(|p| p.upgrade()).unwrap();
+            let (oh, ot) = other.dummies();
+            Self::link(&p, &t);
+            Self::link(&oh, &u);
+            Self::link(&last, &ot);
+            other.n = self.n - i;
+            self.n = i;
+        }
+        other
+    }
+
+    fn link(u: &Ref<T>, w: &Ref<T>) {
+        u.borrow_mut().next = Some(w.clone());
+        w.borrow_mut().prev = Some(Rc::downgrade(u));
+    }
 }
 
 impl<T: Clone + Default> List<T> for DLList<T> {
@@ -126,17 +337,106 @@ impl<T: Clone + Default> List<T> for DLList<T> {
         }
         let w = self.get_link(i);
         self.remove_link(w.clone());
-        match w {
-            Some(w) => Some(Rc::try_unwrap(w).ok().unwrap().into_inner().x),
-            None => None,
+        w.map(|w| Rc::try_unwrap(w).ok().unwrap().into_inner().x)
+    }
+
+    // the bulk operations relink whole runs of nodes instead of moving elements
+    fn append(&mut self, other: &mut Self) {
+        if other.n == 0 {
+            return;
+        }
+        let (oh, ot) = other.dummies();
+        let first = oh.borrow().next.clone().unwrap();
+        let last = ot.borrow().prev.as_ref().and_then(|p| p.upgrade()).unwrap();
+        let (_, t) = self.dummies();
+        let p = t.borrow().prev.as_ref().and_then(|p| p.upgrade()).unwrap();
+        Self::link(&oh, &ot);
+        Self::link(&p, &first);
+        Self::link(&last, &t);
+        self.n += other.n;
+        other.n = 0;
+    }
+
+    fn split_off(&mut self, i: usize) -> Self {
+        assert!(i <= self.n);
+        let u = self.get_link(i).unwrap();
+        self.split_link(u, i)
+    }
+
+    fn drain(&mut self, r: Range<usize>) -> Vec<T> {
+        assert!(r.start <= r.end && r.end <= self.n);
+        let mut mid = self.split_off(r.start);
+        let mut rest = mid.split_off(r.end - r.start);
+        self.append(&mut rest);
+        mid.into_iter().collect()
+    }
+
+    fn truncate(&mut self, len: usize) {
+        if len < self.n {
+            self.split_off(len);
+        }
+    }
+
+    fn insert_slice(&mut self, i: usize, xs: &[T]) {
+        let mut rest = self.split_off(i);
+        let mut mid = xs.iter().cloned().collect();
+        self.append(&mut mid);
+        self.append(&mut rest);
+    }
+}
+
+impl<'a, T: Clone + Default> IntoIterator for &'a DLList<T> {
+    type Item = T;
+    type IntoIter = Iter<'a, T>;
+    fn into_iter(self) -> Iter<'a, T> {
+        self.iter()
+    }
+}
+
+impl<T: Clone + Default> IntoIterator for DLList<T> {
+    type Item = T;
+    type IntoIter = IntoIter<T>;
+    fn into_iter(self) -> IntoIter<T> {
+        IntoIter(self)
+    }
+}
+
+impl<T: Clone + Default> FromIterator<T> for DLList<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut l = Self::new();
+        l.extend(iter);
+        l
+    }
+}
+
+impl<T: Clone + Default> Extend<T> for DLList<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for x in iter {
+            self.add(self.n, x);
         }
     }
 }
 
+#[cfg(feature = "serde")]
+impl<T: Clone + Default + serde::Serialize> serde::Serialize for DLList<T> {
+    fn serialize<S: serde::Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        s.collect_seq(self)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, T: Clone + Default + serde::Deserialize<'de>> serde::Deserialize<'de> for DLList<T> {
+    fn deserialize<D: serde::Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+        serde::Deserialize::deserialize(d).map(|xs: Vec<T>| xs.into_iter().collect())
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::DLList;
+    use chapter01::error::Error;
     use chapter01::interface::List;
+    use rand::{thread_rng, Rng};
     #[test]
     fn test_dllist() {
         let mut dllist: DLList<char> = DLList::new();
@@ -162,6 +462,17 @@ mod test {
         }
         assert_eq!(dllist.remove(0), None);
         assert_eq!(dllist.get(0), None);
+        let mut dllist: DLList<char> = "abc".chars().collect();
+        dllist.extend("de".chars());
+        assert_eq!(dllist.iter().collect::<String>(), "abcde");
+        assert_eq!(dllist.iter().rev().collect::<String>(), "edcba");
+        let mut iter = dllist.iter();
+        assert_eq!(iter.next(), Some('a'));
+        assert_eq!(iter.next_back(), Some('e'));
+        assert_eq!(iter.collect::<String>(), "bcd");
+        let mut iter = dllist.into_iter();
+        assert_eq!(iter.next_back(), Some('e'));
+        assert_eq!(iter.collect::<String>(), "abcd");
 
         // test large linked list for stack overflow.
         let mut dllist: DLList<i32> = DLList::new();
@@ -170,11 +481,140 @@ mod test {
             dllist.add(dllist.size(), i);
         }
         while dllist.remove(0).is_some() {}
+        assert_eq!(
+            dllist.try_add(1, 0),
+            Err(Error::OutOfBounds { index: 1, len: 0 })
+        );
+        assert_eq!(dllist.try_add(0, 1), Ok(()));
+        assert_eq!(dllist.try_add(0, 0), Ok(()));
+        assert_eq!(dllist.try_set(1, 2), Ok(1));
+        assert_eq!(
+            dllist.try_set(2, 3),
+            Err(Error::OutOfBounds { index: 2, len: 2 })
+        );
+        assert_eq!(dllist.size(), 2);
         let mut dllist: DLList<i32> = DLList::new();
         let num = 100000;
         for i in 0..num {
             dllist.add(dllist.size(), i);
         }
         println!("fin");
+
+        let mut rng = thread_rng();
+        let mut dllist: DLList<i32> = DLList::new();
+        let mut vec: Vec<i32> = vec![];
+        for k in 0..500 {
+            let n = vec.len();
+            let i = rng.gen_range(0, n + 1);
+            let m = rng.gen_range(0, 2 * 10 + 3) as i32;
+            match rng.gen_range(0, 5) {
+                0 => {
+                    let xs: Vec<i32> = (0..m).map(|x| 1000 * k + x).collect();
+                    dllist.insert_slice(i, &xs);
+                    vec.splice(i..i, xs);
+                }
+                1 => {
+                    let j = rng.gen_range(i, n + 1);
+                    assert_eq!(dllist.drain(i..j), vec.drain(i..j).collect::<Vec<_>>());
+                }
+                2 => {
+                    let mut rest = dllist.split_off(i);
+                    let tail = vec.split_off(i);
+                    assert_eq!(rest.iter().collect::<Vec<_>>(), tail);
+                    rest.add(0, -k);
+                    dllist.append(&mut rest);
+                    assert_eq!(rest.size(), 0);
+                    vec.push(-k);
+                    vec.extend(tail);
+                }
+                3 => {
+                    let mut other: DLList<i32> = (0..m).collect();
+                    dllist.append(&mut other);
+                    vec.extend(0..m);
+                    other.add(0, 1);
+                    assert_eq!(other.iter().collect::<Vec<_>>(), vec![1]);
+                }
+                _ => {
+                    let len = n - std::cmp::min(n, m as usize);
+                    dllist.truncate(len);
+                    vec.truncate(len);
+                }
+            }
+            assert_eq!(dllist.size(), vec.len());
+            assert_eq!(dllist.iter().collect::<Vec<_>>(), vec);
+            assert_eq!(
+                dllist.iter().rev().collect::<Vec<_>>(),
+                vec.iter().rev().copied().collect::<Vec<_>>()
+            );
+        }
+
+        // cursors against a vector and a position in it, len standing for past the end
+        let mut dllist: DLList<i32> = DLList::new();
+        let mut vec: Vec<i32> = vec![];
+        let mut c = 0;
+        for round in 0..40 {
+            let mut cursor = dllist.cursor_mut(c);
+            for k in 0..100 {
+                let x = 1000 * round + k;
+                match rng.gen_range(0, 7) {
+                    0 => {
+                        cursor.move_next();
+                        c = if c < vec.len() { c + 1 } else { 0 };
+                    }
+                    1 => {
+                        cursor.move_prev();
+                        c = if c == 0 { vec.len() } else { c - 1 };
+                    }
+                    2 | 3 => {
+                        cursor.insert_before(x);
+                        vec.insert(c, x);
+                        c += 1;
+                    }
+                    4 => {
+                        cursor.insert_after(x);
+                        if c < vec.len() {
+                            vec.insert(c + 1, x);
+                        } else {
+                            vec.insert(0, x);
+                            c += 1;
+                        }
+                    }
+                    5 => assert_eq!(
+                        cursor.remove_current(),
+                        if c < vec.len() {
+                            Some(vec.remove(c))
+                        } else {
+                            None
+                        }
+                    ),
+                    _ => assert_eq!(
+                        cursor.set(x),
+                        vec.get_mut(c).map(|y| std::mem::replace(y, x))
+                    ),
+                }
+                assert_eq!(cursor.index(), Some(c).filter(|&c| c < vec.len()));
+                assert_eq!(cursor.current(), vec.get(c).copied());
+            }
+            drop(cursor);
+            assert_eq!(dllist.iter().collect::<Vec<_>>(), vec);
+            if round % 10 == 9 {
+                let mut cursor = dllist.cursor_mut(c);
+                let rest = cursor.split();
+                assert_eq!(cursor.index(), None);
+                assert_eq!(rest.iter().collect::<Vec<_>>(), vec.split_off(c));
+                cursor.move_prev();
+                assert_eq!(cursor.current(), vec.last().copied());
+                c = vec.len();
+            }
+        }
+        #[cfg(feature = "serde")]
+        {
+            let dllist: DLList<i32> = (0..5).collect();
+            let json = serde_json::to_string(&dllist).unwrap();
+            assert_eq!(json, "[0,1,2,3,4]");
+            let copy: DLList<i32> = serde_json::from_str(&json).unwrap();
+            assert!(copy.iter().eq(dllist.iter()));
+            assert_eq!(copy.iter().rev().collect::<Vec<_>>(), vec![4, 3, 2, 1, 0]);
+        }
     }
 }
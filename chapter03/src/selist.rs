@@ -2,10 +2,13 @@
 use chapter01::interface::List;
 use chapter02::boundeddeque::Array as BDeque;
 use std::cell::RefCell;
+use std::marker::PhantomData;
+use std::ops::Range;
 use std::rc::{Rc, Weak};
 
 type Link<T> = Option<Rc<RefCell<Node<T>>>>;
 type Wink<T> = Option<Weak<RefCell<Node<T>>>>;
+type Ref<T> = Rc<RefCell<Node<T>>>;
 type Loc<T> = (Link<T>, usize);
 
 #[derive(Clone, Debug, Default)]
@@ -42,7 +45,198 @@ impl<T> Node<T> {
     }
 }
 
+pub struct Iter<'a, T> {
+    u: Link<T>,
+    j: usize,
+    n: usize,
+    list: PhantomData<&'a Node<T>>,
+}
+
+impl<'a, T: Clone> Iterator for Iter<'a, T> {
+    type Item = T;
+    fn next(&mut self) -> Option<T> {
+        if self.n == 0 {
+            return None;
+        }
+        while self.j >= self.u.as_ref().map(|p| p.borrow().block.size())? {
+            self.u = self.u.as_ref().and_then(|p| p.borrow().next.clone());
+            self.j = 0;
+        }
+        self.n -= 1;
+        self.j += 1;
+        self.u
+            .as_ref()
+            .and_then(|p| p.borrow().block.get(self.j - 1))
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.n, Some(self.n))
+    }
+}
+
+pub struct IntoIter<T: Clone + Default>(SEList<T>);
+
+impl<T: Clone + Default> Iterator for IntoIter<T> {
+    type Item = T;
+    fn next(&mut self) -> Option<T> {
+        self.0.remove(0)
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.0.n, Some(self.0.n))
+    }
+}
+
+// a position in the list, or just past its end, to edit at without walking from either end
+pub struct CursorMut<'a, T: Clone + Default> {
+    list: &'a mut SEList<T>,
+    u: Ref<T>,
+    j: usize,
+    i: usize,
+}
+
+impl<'a, T: Clone + Default> CursorMut<'a, T> {
+    pub fn index(&self) -> Option<usize> {
+        if self.i < self.list.n {
+            Some(self.i)
+        } else {
+            None
+        }
+    }
+
+    pub fn current(&self) -> Option<T> {
+        self.index()?;
+        self.u.borrow().block.get(self.j)
+    }
+
+    pub fn set(&mut self, x: T) -> Option<T> {
+        self.index()?;
+        self.u.borrow_mut().block.set(self.j, x)
+    }
+
+    fn loc(&self) -> Loc<T> {
+        (Some(self.u.clone()), self.j)
+    }
+
+    fn end(&mut self) {
+        self.u = self.list.dummies().1;
+        self.j = 0;
+        self.i = self.list.n;
+    }
+
+    // steps over the end of a block onto the start of the next one
+    fn settle(&mut self) {
+        if self.u.borrow().next.is_some() && self.j >= self.u.borrow().block.size() {
+            let next = self.u.borrow().next.clone().unwrap();
+            self.u = next;
+            self.j = 0;
+        }
+    }
+
+    // past the end wraps around to the front, and the other way round
+    pub fn move_next(&mut self) {
+        if self.i < self.list.n {
+            self.j += 1;
+            self.i += 1;
+            self.settle();
+        } else {
+            let first = self.list.dummies().0.borrow().next.clone().unwrap();
+            self.u = first;
+            self.j = 0;
+            self.i = 0;
+        }
+    }
+
+    pub fn move_prev(&mut self) {
+        if self.i == 0 {
+            self.end();
+        } else if self.j > 0 {
+            self.j -= 1;
+            self.i -= 1;
+        } else {
+            let prev = self.u.borrow().prev.as_ref().and_then(|p| p.upgrade());
+            self.u = prev.unwrap();
+            self.j = self.u.borrow().block.size() - 1;
+            self.i -= 1;
+        }
+    }
+
+    pub fn insert_before(&mut self, x: T) {
+        if self.i < self.list.n {
+            self.list.add_loc(self.loc(), x);
+            self.j += 1;
+            self.settle();
+        } else {
+            self.list.add_last(x);
+        }
+        self.i += 1;
+    }
+
+    // past the end, this inserts at the front
+    pub fn insert_after(&mut self, x: T) {
+        if self.i < self.list.n {
+            let (u, j) = if self.j + 1 < self.u.borrow().block.size() {
+                (self.u.clone(), self.j + 1)
+            } else {
+                (self.u.borrow().next.clone().unwrap(), 0)
+            };
+            if u.borrow().next.is_some() {
+                self.list.add_loc((Some(u), j), x);
+            } else {
+                self.list.add_last(x);
+            }
+        } else {
+            self.list.add(0, x);
+            self.i += 1;
+        }
+    }
+
+    // the cursor moves on to the next element
+    pub fn remove_current(&mut self) -> Option<T> {
+        self.index()?;
+        let x = self.list.remove_loc(self.loc());
+        if self.u.borrow().next.is_none() {
+            self.end();
+        } else {
+            self.settle();
+        }
+        x
+    }
+
+    // the current element and everything after it move to a new list; the cursor ends up past the end
+    pub fn split(&mut self) -> SEList<T> {
+        let other = match self.index() {
+            Some(i) => self.list.split_loc(self.loc(), i),
+            None => SEList::new(self.list.b),
+        };
+        self.end();
+        other
+    }
+}
+
 impl<T: Default + Clone> SEList<T> {
+    pub fn iter(&self) -> Iter<'_, T> {
+        Iter {
+            u: self.head.as_ref().and_then(|d| d.borrow().next.clone()),
+            j: 0,
+            n: self.n,
+            list: PhantomData,
+        }
+    }
+
+    pub fn cursor_mut(&mut self, i: usize) -> CursorMut<'_, T> {
+        assert!(i <= self.n);
+        let (u, j) = self.get_loc(i);
+        CursorMut {
+            list: self,
+            u: u.unwrap(),
+            j,
+            i,
+        }
+    }
+
+    pub fn cursor_front_mut(&mut self) -> CursorMut<'_, T> {
+        self.cursor_mut(0)
+    }
+
     pub fn new(b: usize) -> Self {
         let dummy1: Rc<RefCell<Node<T>>> = Default::default();
         let dummy2: Rc<RefCell<Node<T>>> = Default::default();
@@ -153,34 +347,9 @@ impl<T: Default + Clone> SEList<T> {
         }
         self.remove_link(w);
     }
-}
 
-impl<T: Clone + Default> List<T> for SEList<T> {
-    fn size(&self) -> usize {
-        self.n
-    }
-    fn get(&self, index: usize) -> Option<T> {
-        if self.n == 0 || index > self.n {
-            None
-        } else {
-            let (p, j) = self.get_loc(index);
-            p.and_then(|p| p.borrow().block.get(j))
-        }
-    }
-    fn set(&mut self, i: usize, x: T) -> Option<T> {
-        if self.n > 0 && i < self.n {
-            let (p, j) = self.get_loc(i);
-            p.and_then(|p| p.borrow_mut().block.set(j, x))
-        } else {
-            None
-        }
-    }
-    fn add(&mut self, i: usize, x: T) {
-        if i == self.n {
-            self.add_last(x);
-            return;
-        }
-        let (mut u, j) = self.get_loc(i);
+    // x ends up at (u, j), whatever moves around it
+    fn add_loc(&mut self, (mut u, j): Loc<T>, x: T) {
         let v = u.clone();
         let mut r = 0;
         while r < self.b
@@ -220,54 +389,239 @@ impl<T: Clone + Default> List<T> for SEList<T> {
         self.n += 1;
     }
 
+    // the elements of u before j stay where they are
+    fn remove_loc(&mut self, (mut u, j): Loc<T>) -> Option<T> {
+        let v = u.clone();
+        let mut r = 0;
+        while r < self.b
+            && u.as_ref()
+                .filter(|p| p.borrow().next.is_some() && p.borrow().prev.is_some())
+                .is_some()
+            && u.as_ref().map(|p| p.borrow().block.size()).unwrap() == self.b - 1
+        {
+            u = u.and_then(|p| p.borrow().next.clone());
+            r += 1;
+        }
+        if r == self.b {
+            self.gather(v.clone());
+        }
+        u = v;
+        let x = u.as_ref().and_then(|p| p.borrow_mut().block.remove(j));
+        while u.as_ref().map(|p| p.borrow().block.size()).unwrap() < self.b - 1
+            && u.as_ref()
+                .and_then(|p| p.borrow().next.clone())
+                .and_then(|p| p.borrow().next.clone())
+                .is_some()
+        {
+            if let Some(p) = u.clone() {
+                let l = p.borrow().next.clone();
+                let s = p.borrow().block.size();
+                let x = l.and_then(|p| p.borrow_mut().block.remove(0)).unwrap();
+                p.borrow_mut().block.add(s, x);
+            }
+            u = u.and_then(|p| p.borrow().next.clone());
+        }
+        if u.as_ref().map(|p| p.borrow().block.size()).unwrap() == 0 {
+            self.remove_link(u);
+        }
+        self.n -= 1;
+        x
+    }
+
+    // everything from (u, j), the i-th element, on moves to a new list
+    fn split_loc(&mut self, (u, j): Loc<T>, i: usize) -> Self {
+        let mut other = Self::new(self.b);
+        let u = u.unwrap();
+        let s = u.borrow().block.size();
+        let (_, t) = self.dummies();
+        let next = u.borrow().next.clone().unwrap();
+        if !Rc::ptr_eq(&next, &t) {
+            let last = t.borrow().prev.as_ref().and_then(|p| p.upgrade()).unwrap();
+            let (oh, ot) = other.dummies();
+            Self::link(&u, &t);
+            Self::link(&oh, &next);
+            Self::link(&last, &ot);
+        }
+        other.n = self.n - i - (s - j);
+        for k in (j..s).rev() {
+            let x = u.borrow_mut().block.remove(k).unwrap();
+            other.add(0, x);
+        }
+        if j == 0 {
+            self.remove_link(Some(u));
+        }
+        self.n = i;
+        other
+    }
+
+    fn dummies(&self) -> (Ref<T>, Ref<T>) {
+        let head = self.head.clone().unwrap();
+        let tail = self.tail.as_ref().and_then(|p| p.upgrade()).unwrap();
+        (head, tail)
+    }
+
+    fn link(u: &Ref<T>, w: &Ref<T>) {
+        u.borrow_mut().next = Some(w.clone());
+        w.borrow_mut().prev = Some(Rc::downgrade(u));
+    }
+}
+
+impl<T: Clone + Default> List<T> for SEList<T> {
+    fn size(&self) -> usize {
+        self.n
+    }
+    fn get(&self, index: usize) -> Option<T> {
+        if self.n == 0 || index > self.n {
+            None
+        } else {
+            let (p, j) = self.get_loc(index);
+            p.and_then(|p| p.borrow().block.get(j))
+        }
+    }
+    fn set(&mut self, i: usize, x: T) -> Option<T> {
+        if self.n > 0 && i < self.n {
+            let (p, j) = self.get_loc(i);
+            p.and_then(|p| p.borrow_mut().block.set(j, x))
+        } else {
+            None
+        }
+    }
+    fn add(&mut self, i: usize, x: T) {
+        if i == self.n {
+            self.add_last(x);
+            return;
+        }
+        let loc = self.get_loc(i);
+        self.add_loc(loc, x);
+    }
+
     fn remove(&mut self, i: usize) -> Option<T> {
         if self.n > 0 {
-            let (mut u, j) = self.get_loc(i);
-            let v = u.clone();
-            let mut r = 0;
-            while r < self.b
-                && u.as_ref()
-                    .filter(|p| p.borrow().next.is_some() && p.borrow().prev.is_some())
-                    .is_some()
-                && u.as_ref().map(|p| p.borrow().block.size()).unwrap() == self.b - 1
-            {
-                u = u.and_then(|p| p.borrow().next.clone());
-                r += 1;
-            }
-            if r == self.b {
-                self.gather(v.clone());
-            }
-            u = v;
-            let x = u.as_ref().and_then(|p| p.borrow_mut().block.remove(j));
-            while u.as_ref().map(|p| p.borrow().block.size()).unwrap() < self.b - 1
-                && u.as_ref()
-                    .and_then(|p| p.borrow().next.clone())
-                    .and_then(|p| p.borrow().next.clone())
-                    .is_some()
-            {
-                if let Some(p) = u.clone() {
-                    let l = p.borrow().next.clone();
-                    let s = p.borrow().block.size();
-                    let x = l.and_then(|p| p.borrow_mut().block.remove(0)).unwrap();
-                    p.borrow_mut().block.add(s, x);
-                }
-                u = u.and_then(|p| p.borrow().next.clone());
-            }
-            if u.as_ref().map(|p| p.borrow().block.size()).unwrap() == 0 {
-                self.remove_link(u);
-            }
-            self.n -= 1;
-            x
+            let loc = self.get_loc(i);
+            self.remove_loc(loc)
         } else {
             None
         }
     }
+
+    // the bulk operations relink whole blocks and only move the elements of the
+    // blocks at the seams, keeping every block but the last at least b - 1 full
+    fn append(&mut self, other: &mut Self) {
+        if other.n == 0 {
+            return;
+        }
+        if other.b != self.b {
+            for x in other.drain(0..other.n) {
+                self.add_last(x);
+            }
+            return;
+        }
+        let (_, t) = self.dummies();
+        let last = t.borrow().prev.as_ref().and_then(|p| p.upgrade()).unwrap();
+        let s = last.borrow().block.size();
+        if last.borrow().prev.is_some() && s < self.b - 1 {
+            for k in (0..s).rev() {
+                let x = last.borrow_mut().block.remove(k).unwrap();
+                other.add(0, x);
+            }
+            self.n -= s;
+            self.remove_link(Some(last));
+        }
+        let (oh, ot) = other.dummies();
+        let first = oh.borrow().next.clone().unwrap();
+        let olast = ot.borrow().prev.as_ref().and_then(|p| p.upgrade()).unwrap();
+        let p = t.borrow().prev.as_ref().and_then(|p| p.upgrade()).unwrap();
+        Self::link(&oh, &ot);
+        Self::link(&p, &first);
+        Self::link(&olast, &t);
+        self.n += other.n;
+        other.n = 0;
+    }
+
+    fn split_off(&mut self, i: usize) -> Self {
+        assert!(i <= self.n);
+        if i == self.n {
+            return Self::new(self.b);
+        }
+        let loc = self.get_loc(i);
+        self.split_loc(loc, i)
+    }
+
+    fn drain(&mut self, r: Range<usize>) -> Vec<T> {
+        assert!(r.start <= r.end && r.end <= self.n);
+        let mut mid = self.split_off(r.start);
+        let mut rest = mid.split_off(r.end - r.start);
+        self.append(&mut rest);
+        mid.into_iter().collect()
+    }
+
+    fn truncate(&mut self, len: usize) {
+        if len < self.n {
+            self.split_off(len);
+        }
+    }
+
+    fn insert_slice(&mut self, i: usize, xs: &[T]) {
+        let mut rest = self.split_off(i);
+        let mut mid = Self::new(self.b);
+        for x in xs.iter() {
+            mid.add_last(x.clone());
+        }
+        self.append(&mut mid);
+        self.append(&mut rest);
+    }
+}
+
+impl<'a, T: Clone + Default> IntoIterator for &'a SEList<T> {
+    type Item = T;
+    type IntoIter = Iter<'a, T>;
+    fn into_iter(self) -> Iter<'a, T> {
+        self.iter()
+    }
+}
+
+impl<T: Clone + Default> IntoIterator for SEList<T> {
+    type Item = T;
+    type IntoIter = IntoIter<T>;
+    fn into_iter(self) -> IntoIter<T> {
+        IntoIter(self)
+    }
+}
+
+impl<T: Clone + Default> Extend<T> for SEList<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for x in iter {
+            self.add(self.n, x);
+        }
+    }
+}
+
+// the block size goes along with the elements
+#[cfg(feature = "serde")]
+impl<T: Clone + Default + serde::Serialize> serde::Serialize for SEList<T> {
+    fn serialize<S: serde::Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        serde::Serialize::serialize(&(self.b, self.iter().collect::<Vec<_>>()), s)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, T: Clone + Default + serde::Deserialize<'de>> serde::Deserialize<'de> for SEList<T> {
+    fn deserialize<D: serde::Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+        let (b, xs): (usize, Vec<T>) = serde::Deserialize::deserialize(d)?;
+        if b == 0 {
+            return Err(serde::de::Error::custom("block size must be positive"));
+        }
+        let mut selist = Self::new(b);
+        selist.extend(xs);
+        Ok(selist)
+    }
 }
 
 #[cfg(test)]
 mod test {
     use super::SEList;
     use chapter01::interface::List;
+    use rand::{thread_rng, Rng};
     #[test]
     fn test_selist() {
         let mut selist: SEList<char> = SEList::new(3);
@@ -365,6 +719,16 @@ mod test {
             selist.add(selist.size(), i);
         }
         while selist.remove(0).is_some() {}
+        selist.extend(0..num);
+        selist.add(3, -1);
+        assert_eq!(
+            selist.iter().collect::<Vec<_>>(),
+            vec![0, 1, 2, -1, 3, 4, 5, 6, 7, 8, 9]
+        );
+        assert_eq!(
+            selist.into_iter().collect::<Vec<_>>(),
+            vec![0, 1, 2, -1, 3, 4, 5, 6, 7, 8, 9]
+        );
 
         // test large linked list for stack overflow.
         let mut selist: SEList<i32> = SEList::new(3);
@@ -373,5 +737,141 @@ mod test {
             selist.add(selist.size(), i);
         }
         println!("fin");
+
+        // every block but the last holds between b - 1 and b + 1 elements
+        fn check(selist: &SEList<i32>) {
+            let mut sizes = vec![];
+            let mut u = selist.head.as_ref().and_then(|p| p.borrow().next.clone());
+            while let Some(p) = u.filter(|p| p.borrow().next.is_some()) {
+                sizes.push(p.borrow().block.size());
+                u = p.borrow().next.clone();
+            }
+            assert_eq!(sizes.iter().sum::<usize>(), selist.size());
+            if let Some((_, sizes)) = sizes.split_last() {
+                assert!(sizes
+                    .iter()
+                    .all(|&s| s >= selist.b - 1 && s <= selist.b + 1));
+            }
+        }
+        let mut rng = thread_rng();
+        let mut selist: SEList<i32> = SEList::new(3);
+        let mut vec: Vec<i32> = vec![];
+        for k in 0..500 {
+            let n = vec.len();
+            let i = rng.gen_range(0, n + 1);
+            let m = rng.gen_range(0, 2 * 3 + 3) as i32;
+            match rng.gen_range(0, 5) {
+                0 => {
+                    let xs: Vec<i32> = (0..m).map(|x| 1000 * k + x).collect();
+                    selist.insert_slice(i, &xs);
+                    vec.splice(i..i, xs);
+                }
+                1 => {
+                    let j = rng.gen_range(i, n + 1);
+                    assert_eq!(selist.drain(i..j), vec.drain(i..j).collect::<Vec<_>>());
+                }
+                2 => {
+                    let mut rest = selist.split_off(i);
+                    let tail = vec.split_off(i);
+                    assert_eq!(rest.iter().collect::<Vec<_>>(), tail);
+                    rest.add(0, -k);
+                    selist.append(&mut rest);
+                    assert_eq!(rest.size(), 0);
+                    vec.push(-k);
+                    vec.extend(tail);
+                }
+                3 => {
+                    let mut other: SEList<i32> =
+                        (0..m).fold(SEList::new(rng.gen_range(2, 5)), |mut l, x| {
+                            l.add(l.size(), x);
+                            l
+                        });
+                    selist.append(&mut other);
+                    vec.extend(0..m);
+                    other.add(0, 1);
+                    assert_eq!(other.iter().collect::<Vec<_>>(), vec![1]);
+                }
+                _ => {
+                    let len = n - std::cmp::min(n, m as usize);
+                    selist.truncate(len);
+                    vec.truncate(len);
+                }
+            }
+            assert_eq!(selist.size(), vec.len());
+            assert_eq!(selist.iter().collect::<Vec<_>>(), vec);
+            check(&selist);
+        }
+
+        // cursors against a vector and a position in it, len standing for past the end
+        let mut selist: SEList<i32> = SEList::new(3);
+        let mut vec: Vec<i32> = vec![];
+        let mut c = 0;
+        for round in 0..40 {
+            let mut cursor = selist.cursor_mut(c);
+            for k in 0..100 {
+                let x = 1000 * round + k;
+                match rng.gen_range(0, 7) {
+                    0 => {
+                        cursor.move_next();
+                        c = if c < vec.len() { c + 1 } else { 0 };
+                    }
+                    1 => {
+                        cursor.move_prev();
+                        c = if c == 0 { vec.len() } else { c - 1 };
+                    }
+                    2 | 3 => {
+                        cursor.insert_before(x);
+                        vec.insert(c, x);
+                        c += 1;
+                    }
+                    4 => {
+                        cursor.insert_after(x);
+                        if c < vec.len() {
+                            vec.insert(c + 1, x);
+                        } else {
+                            vec.insert(0, x);
+                            c += 1;
+                        }
+                    }
+                    5 => assert_eq!(
+                        cursor.remove_current(),
+                        if c < vec.len() {
+                            Some(vec.remove(c))
+                        } else {
+                            None
+                        }
+                    ),
+                    _ => assert_eq!(
+                        cursor.set(x),
+                        vec.get_mut(c).map(|y| std::mem::replace(y, x))
+                    ),
+                }
+                assert_eq!(cursor.index(), Some(c).filter(|&c| c < vec.len()));
+                assert_eq!(cursor.current(), vec.get(c).copied());
+            }
+            assert_eq!(selist.iter().collect::<Vec<_>>(), vec);
+            check(&selist);
+            if round % 10 == 9 {
+                let mut cursor = selist.cursor_mut(c);
+                let rest = cursor.split();
+                assert_eq!(cursor.index(), None);
+                assert_eq!(rest.iter().collect::<Vec<_>>(), vec.split_off(c));
+                cursor.move_prev();
+                assert_eq!(cursor.current(), vec.last().copied());
+                c = vec.len();
+            }
+        }
+        #[cfg(feature = "serde")]
+        {
+            let mut selist: SEList<i32> = SEList::new(3);
+            selist.extend(0..10);
+            let json = serde_json::to_string(&selist).unwrap();
+            assert_eq!(json, "[3,[0,1,2,3,4,5,6,7,8,9]]");
+            let copy: SEList<i32> = serde_json::from_str(&json).unwrap();
+            check(&copy);
+            assert_eq!(copy.b, 3);
+            assert!(copy.iter().eq(selist.iter()));
+            assert!(serde_json::from_str::<SEList<i32>>("[0,[1]]").is_err());
+        }
     }
 }
@@ -1,6 +1,8 @@
 #![allow(clippy::many_single_char_names,clippy::explicit_counter_loop)]
 use chapter01::interface::{Queue, Stack};
 use std::cell::RefCell;
+use std::iter::FromIterator;
+use std::marker::PhantomData;
 use std::rc::Rc;
 
 type Link<T> = Option<Rc<RefCell<Node<T>>>>;
@@ -30,6 +32,34 @@ impl<T> Node<T> {
     }
 }
 
+pub struct Iter<'a, T> {
+    next: Link<T>,
+    list: PhantomData<&'a SLList<T>>,
+}
+
+impl<'a, T: Clone> Iterator for Iter<'a, T> {
+    type Item = T;
+    fn next(&mut self) -> Option<T> {
+        self.next.take().map(|u| {
+            self.next = u.borrow().next.clone();
+            let x = u.borrow().x.clone();
+            x
+        })
+    }
+}
+
+pub struct IntoIter<T>(SLList<T>);
+
+impl<T> Iterator for IntoIter<T> {
+    type Item = T;
+    fn next(&mut self) -> Option<T> {
+        self.0.remove()
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.0.n, Some(self.0.n))
+    }
+}
+
 impl<T> SLList<T> {
     pub fn new() -> Self {
         Self {
@@ -41,6 +71,44 @@ impl<T> SLList<T> {
     pub fn size(&self) -> usize {
         self.n
     }
+    pub fn iter(&self) -> Iter<'_, T> {
+        Iter {
+            next: self.head.clone(),
+            list: PhantomData,
+        }
+    }
+}
+
+impl<'a, T: Clone> IntoIterator for &'a SLList<T> {
+    type Item = T;
+    type IntoIter = Iter<'a, T>;
+    fn into_iter(self) -> Iter<'a, T> {
+        self.iter()
+    }
+}
+
+impl<T> IntoIterator for SLList<T> {
+    type Item = T;
+    type IntoIter = IntoIter<T>;
+    fn into_iter(self) -> IntoIter<T> {
+        IntoIter(self)
+    }
+}
+
+impl<T> FromIterator<T> for SLList<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut l = Self::new();
+        l.extend(iter);
+        l
+    }
+}
+
+impl<T> Extend<T> for SLList<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for x in iter {
+            self.add(x);
+        }
+    }
 }
 
 impl<T> Stack<T> for SLList<T> {
@@ -108,6 +176,10 @@ mod test {
             assert_eq!(sllist.remove(), Some(elem));
         }
         assert_eq!(sllist.pop(), None);
+        let mut sllist: SLList<char> = "abc".chars().collect();
+        sllist.extend("de".chars());
+        assert_eq!(sllist.iter().collect::<String>(), "abcde");
+        assert_eq!(sllist.into_iter().collect::<String>(), "abcde");
 
         // test large linked list for stack overflow.
         let mut sllist: SLList<i32> = SLList::new();
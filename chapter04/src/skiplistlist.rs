@@ -1,15 +1,26 @@
 #![allow(clippy::many_single_char_names,clippy::explicit_counter_loop)]
 use chapter01::interface::List;
+use chapter01::rng::Random;
+use rand::{Rng, RngCore};
 use std::cell::RefCell;
+use std::iter::FromIterator;
+use std::marker::PhantomData;
 use std::rc::Rc;
 
 type Link<T> = Option<Rc<RefCell<Node<T>>>>;
 
-#[derive(Clone, Debug, Default, Eq, Ord, PartialEq, PartialOrd)]
+#[derive(Clone, Debug, Eq, Ord, PartialEq, PartialOrd)]
 pub struct SkiplistList<T: Clone + Default> {
     head: Link<T>,
     h: usize,
     n: usize,
+    rng: Random,
+}
+
+impl<T: Clone + Default> Default for SkiplistList<T> {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl<T> Drop for SkiplistList<T>
@@ -38,17 +49,61 @@ impl<T> Node<T> {
     }
 }
 
+pub struct Iter<'a, T> {
+    u: Link<T>,
+    list: PhantomData<&'a Node<T>>,
+}
+
+impl<'a, T: Clone> Iterator for Iter<'a, T> {
+    type Item = T;
+    fn next(&mut self) -> Option<T> {
+        self.u.take().map(|u| {
+            self.u = u.borrow().next[0].clone();
+            let x = u.borrow().x.clone();
+            x
+        })
+    }
+}
+
+pub struct IntoIter<T: Clone + Default>(SkiplistList<T>);
+
+impl<T: Clone + Default> Iterator for IntoIter<T> {
+    type Item = T;
+    fn next(&mut self) -> Option<T> {
+        self.0.remove(0)
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.0.n, Some(self.0.n))
+    }
+}
+
 impl<T: Default + Clone> SkiplistList<T> {
     pub fn new() -> Self {
+        Self::with_rng(rand::thread_rng())
+    }
+    pub fn with_rng<R: RngCore>(rng: R) -> Self {
+        Self::with_random(Random::with_rng(rng))
+    }
+    pub fn with_seed(seed: u64) -> Self {
+        Self::with_random(Random::with_seed(seed))
+    }
+    fn with_random(rng: Random) -> Self {
         let sentinel = Node::new(Default::default(), 32);
         Self {
             head: Some(sentinel),
             h: 0,
             n: 0,
+            rng,
+        }
+    }
+    pub fn iter(&self) -> Iter<'_, T> {
+        Iter {
+            u: self.head.as_ref().and_then(|s| s.borrow().next[0].clone()),
+            list: PhantomData,
         }
     }
-    fn pick_height() -> usize {
-        let z = rand::random::<usize>();
+    fn pick_height(rng: &mut Random) -> usize {
+        let z = rng.gen::<usize>();
         let mut k = 0;
         let mut m = 1;
         while (z & m) != 0 {
@@ -134,7 +189,7 @@ impl<T: Clone + Default> List<T> for SkiplistList<T> {
     }
     fn add(&mut self, i: usize, x: T) {
         assert!(i <= self.size());
-        let w = Node::new(x, Self::pick_height());
+        let w = Node::new(x, Self::pick_height(&mut self.rng));
         if w.borrow().next.len() - 1 > self.h {
             if let Some(sentinel) = self
                 .head
@@ -209,10 +264,60 @@ impl<T: Clone + Default> List<T> for SkiplistList<T> {
     }
 }
 
+impl<'a, T: Clone + Default> IntoIterator for &'a SkiplistList<T> {
+    type Item = T;
+    type IntoIter = Iter<'a, T>;
+    fn into_iter(self) -> Iter<'a, T> {
+        self.iter()
+    }
+}
+
+impl<T: Clone + Default> IntoIterator for SkiplistList<T> {
+    type Item = T;
+    type IntoIter = IntoIter<T>;
+    fn into_iter(self) -> IntoIter<T> {
+        IntoIter(self)
+    }
+}
+
+impl<T: Clone + Default> FromIterator<T> for SkiplistList<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut l = Self::new();
+        l.extend(iter);
+        l
+    }
+}
+
+impl<T: Clone + Default> Extend<T> for SkiplistList<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for x in iter {
+            self.add(self.n, x);
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<T: Clone + Default + serde::Serialize> serde::Serialize for SkiplistList<T> {
+    fn serialize<S: serde::Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        s.collect_seq(self)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, T: Clone + Default + serde::Deserialize<'de>> serde::Deserialize<'de>
+    for SkiplistList<T>
+{
+    fn deserialize<D: serde::Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+        serde::Deserialize::deserialize(d).map(|xs: Vec<T>| xs.into_iter().collect())
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::SkiplistList;
     use chapter01::interface::List;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
     #[test]
     fn test_skiplistlist() {
         let mut skiplistlist: SkiplistList<char> = SkiplistList::new();
@@ -251,6 +356,26 @@ mod test {
         assert_eq!(skiplistlist.get(3), Some('4'));
         assert_eq!(skiplistlist.get(4), Some('5'));
         assert_eq!(skiplistlist.get(5), Some('6'));
+        skiplistlist.extend("78".chars());
+        assert_eq!(skiplistlist.iter().collect::<String>(), "01245678");
+        assert_eq!(skiplistlist.into_iter().collect::<String>(), "01245678");
+        let skiplistlist: SkiplistList<char> = "abc".chars().collect();
+        assert_eq!((&skiplistlist).into_iter().collect::<String>(), "abc");
+        let mut skiplistlist: SkiplistList<char> = "abcdef".chars().collect();
+        let mut tail = skiplistlist.split_off(4);
+        assert_eq!(tail.iter().collect::<String>(), "ef");
+        skiplistlist.insert_slice(1, &['x', 'y']);
+        assert_eq!(
+            skiplistlist.drain(2..4).into_iter().collect::<String>(),
+            "yb"
+        );
+        skiplistlist.append(&mut tail);
+        assert_eq!(tail.size(), 0);
+        assert_eq!(skiplistlist.iter().collect::<String>(), "axcdef");
+        skiplistlist.truncate(2);
+        assert_eq!(skiplistlist.iter().collect::<String>(), "ax");
+        tail.add(0, 'z');
+        assert_eq!(tail.iter().collect::<String>(), "z");
 
         // test large linked list for stack overflow.
         let mut skiplistlist: SkiplistList<u64> = SkiplistList::new();
@@ -259,5 +384,42 @@ mod test {
             skiplistlist.add(skiplistlist.size(), i);
         }
         println!("fin");
+        #[cfg(feature = "serde")]
+        {
+            let skiplistlist: SkiplistList<i32> = (0..5).collect();
+            let json = serde_json::to_string(&skiplistlist).unwrap();
+            assert_eq!(json, "[0,1,2,3,4]");
+            let copy: SkiplistList<i32> = serde_json::from_str(&json).unwrap();
+            assert!(copy.iter().eq(skiplistlist.iter()));
+            assert_eq!(copy.get(3), Some(3));
+        }
+        // a seed fixes the height of every tower
+        let heights = |s: SkiplistList<u32>| {
+            let mut hs = vec![];
+            let mut u = s.head.as_ref().and_then(|h| h.borrow().next[0].clone());
+            while let Some(w) = u {
+                hs.push(w.borrow().next.len());
+                u = w.borrow().next[0].clone();
+            }
+            hs
+        };
+        let build = |mut s: SkiplistList<u32>| {
+            for i in 0..500 {
+                s.add(i as usize / 2, i);
+            }
+            s
+        };
+        assert_eq!(
+            heights(build(SkiplistList::with_seed(42))),
+            heights(build(SkiplistList::with_seed(42)))
+        );
+        assert_ne!(
+            heights(build(SkiplistList::with_seed(42))),
+            heights(build(SkiplistList::with_seed(43)))
+        );
+        assert_eq!(
+            heights(build(SkiplistList::with_rng(StdRng::seed_from_u64(7)))),
+            heights(build(SkiplistList::with_rng(StdRng::seed_from_u64(7))))
+        );
     }
 }
@@ -1,6 +1,12 @@
 #![allow(clippy::many_single_char_names,clippy::explicit_counter_loop)]
-use chapter01::interface::SSet;
+use chapter01::codec::Codec;
+use chapter01::interface::{Lent, Pair, SSet, SSetMap, USetMap};
+use chapter01::rng::Random;
+use chapter01::snapshot::{self, SnapshotError};
+use rand::{Rng, RngCore};
 use std::cell::RefCell;
+use std::iter::FromIterator;
+use std::marker::PhantomData;
 use std::rc::Rc;
 
 type Link<T> = Option<Rc<RefCell<Node<T>>>>;
@@ -10,6 +16,86 @@ pub struct SkiplistSSet<T: PartialOrd + Clone + Default> {
     head: Link<T>,
     h: usize,
     n: usize,
+    rng: Random,
+}
+
+pub struct Range<'a, T: PartialOrd + Clone + Default> {
+    u: Link<T>,
+    hi: T,
+    set: PhantomData<&'a SkiplistSSet<T>>,
+}
+
+impl<'a, T: PartialOrd + Clone + Default> Iterator for Range<'a, T> {
+    type Item = T;
+    fn next(&mut self) -> Option<T> {
+        let u = self.u.take()?;
+        let x = u.borrow().x.clone();
+        if x < self.hi {
+            self.u = u.borrow().next[0].clone();
+            Some(x)
+        } else {
+            None
+        }
+    }
+}
+
+pub struct Iter<'a, T: PartialOrd + Clone + Default> {
+    front: Link<T>,
+    back: Link<T>,
+    n: usize,
+    set: &'a SkiplistSSet<T>,
+}
+
+impl<'a, T: PartialOrd + Clone + Default> Iterator for Iter<'a, T> {
+    type Item = T;
+    fn next(&mut self) -> Option<T> {
+        if self.n == 0 {
+            return None;
+        }
+        self.n -= 1;
+        self.front.take().map(|u| {
+            self.front = u.borrow().next[0].clone();
+            let x = u.borrow().x.clone();
+            x
+        })
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.n, Some(self.n))
+    }
+}
+
+impl<'a, T: PartialOrd + Clone + Default> DoubleEndedIterator for Iter<'a, T> {
+    fn next_back(&mut self) -> Option<T> {
+        if self.n == 0 {
+            return None;
+        }
+        self.n -= 1;
+        self.back.take().map(|u| {
+            let x = u.borrow().x.clone();
+            self.back = self.set.find_pred_node(&x);
+            x
+        })
+    }
+}
+
+pub struct IntoIter<T: PartialOrd + Clone + Default>(SkiplistSSet<T>);
+
+impl<T: PartialOrd + Clone + Default> Iterator for IntoIter<T> {
+    type Item = T;
+    fn next(&mut self) -> Option<T> {
+        let x = self.0.find_min()?;
+        self.0.remove(&x)
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.0.n, Some(self.0.n))
+    }
+}
+
+impl<T: PartialOrd + Clone + Default> DoubleEndedIterator for IntoIter<T> {
+    fn next_back(&mut self) -> Option<T> {
+        let x = self.0.find_max()?;
+        self.0.remove(&x)
+    }
 }
 
 impl<T: PartialOrd + Clone + Default> Drop for SkiplistSSet<T> {
@@ -27,6 +113,7 @@ impl<T: PartialOrd + Clone + Default> Drop for SkiplistSSet<T> {
 #[derive(Clone, Debug, Default, Eq, Ord, PartialEq, PartialOrd)]
 struct Node<T: PartialOrd> {
     x: T,
+    length: Vec<usize>, // how many elements next[r] lies ahead of this node
     next: Vec<Link<T>>,
 }
 
@@ -34,6 +121,7 @@ impl<T: PartialOrd> Node<T> {
     fn new(x: T, h: usize) -> Rc<RefCell<Node<T>>> {
         Rc::new(RefCell::new(Node {
             x,
+            length: vec![0; h + 1],
             next: vec![None; h + 1],
         }))
     }
@@ -41,11 +129,21 @@ impl<T: PartialOrd> Node<T> {
 
 impl<T: PartialOrd + Default + Clone> SkiplistSSet<T> {
     pub fn new() -> Self {
+        Self::with_rng(rand::thread_rng())
+    }
+    pub fn with_rng<R: RngCore>(rng: R) -> Self {
+        Self::with_random(Random::with_rng(rng))
+    }
+    pub fn with_seed(seed: u64) -> Self {
+        Self::with_random(Random::with_seed(seed))
+    }
+    fn with_random(rng: Random) -> Self {
         let sentinel = Node::new(Default::default(), 32);
         Self {
             head: Some(sentinel),
             h: 0,
             n: 0,
+            rng,
         }
     }
 
@@ -67,40 +165,114 @@ impl<T: PartialOrd + Default + Clone> SkiplistSSet<T> {
             None => None,
         }
     }
-    fn pick_height() -> usize {
-        let z = rand::random::<usize>();
-        let mut k = 0;
-        let mut m = 1;
-        while (z & m) != 0 {
-            k += 1;
-            m <<= 1;
+    fn find_last_node(&self, x: &T) -> Link<T> {
+        match self.head {
+            Some(ref sentinel) => {
+                let mut n = Rc::clone(sentinel);
+                for r in (0..=self.h).rev() {
+                    loop {
+                        let u = Rc::clone(&n);
+                        match u.borrow().next[r] {
+                            Some(ref u) if u.borrow().x <= *x => n = Rc::clone(u),
+                            _ => break,
+                        };
+                    }
+                }
+                Some(n)
+            }
+            None => None,
         }
-        k
     }
-}
-
-impl<T: PartialOrd + Clone + Default> SSet<T> for SkiplistSSet<T> {
-    fn size(&self) -> usize {
-        self.n
+    fn find_max_node(&self) -> Link<T> {
+        let sentinel = self.head.as_ref()?;
+        let mut n = Rc::clone(sentinel);
+        for r in (0..=self.h).rev() {
+            loop {
+                let u = Rc::clone(&n);
+                match u.borrow().next[r] {
+                    Some(ref u) => n = Rc::clone(u),
+                    None => break,
+                };
+            }
+        }
+        if Rc::ptr_eq(&n, sentinel) {
+            None
+        } else {
+            Some(n)
+        }
     }
-
-    fn add(&mut self, x: T) -> bool {
+    pub fn iter(&self) -> Iter<'_, T> {
+        Iter {
+            front: self.head.as_ref().and_then(|s| s.borrow().next[0].clone()),
+            back: self.find_max_node(),
+            n: self.n,
+            set: self,
+        }
+    }
+    // the number of elements smaller than x, adding up link lengths on the way down
+    pub fn rank(&self, x: &T) -> usize {
+        let mut j = 0;
+        if let Some(ref sentinel) = self.head {
+            let mut n = Rc::clone(sentinel);
+            for r in (0..=self.h).rev() {
+                loop {
+                    let u = Rc::clone(&n);
+                    match u.borrow().next[r] {
+                        Some(ref u) if u.borrow().x < *x => {
+                            j += n.borrow().length[r];
+                            n = Rc::clone(u);
+                        }
+                        _ => break,
+                    };
+                }
+            }
+        }
+        j
+    }
+    pub fn range(&self, r: std::ops::Range<T>) -> Range<'_, T> {
+        Range {
+            u: self
+                .find_pred_node(&r.start)
+                .and_then(|u| u.borrow().next[0].clone()),
+            hi: r.end,
+            set: PhantomData,
+        }
+    }
+    // adds x, or puts it in the place of the element equal to it and returns that one
+    pub fn replace(&mut self, x: T) -> Option<T> {
+        self.put(x, true).1
+    }
+    // one descent for add and replace: whether x was added, and what it replaced
+    fn put(&mut self, x: T, replace: bool) -> (bool, Option<T>) {
         match self.head {
             Some(ref sentinel) => {
                 let mut stack: Vec<Link<T>> = vec![None; sentinel.borrow().next.len()];
+                let mut pos = vec![0; sentinel.borrow().next.len()];
                 let mut n = Rc::clone(sentinel);
+                let mut j = 0;
                 for r in (0..=self.h).rev() {
                     loop {
                         let u = Rc::clone(&n);
                         match u.borrow().next[r] {
-                            Some(ref u) if u.borrow().x < x => n = Rc::clone(u),
-                            Some(ref u) if u.borrow().x == x => return false,
+                            Some(ref u) if u.borrow().x < x => {
+                                j += n.borrow().length[r];
+                                n = Rc::clone(u);
+                            }
+                            Some(ref u) if u.borrow().x == x => {
+                                let old = if replace {
+                                    Some(std::mem::replace(&mut u.borrow_mut().x, x))
+                                } else {
+                                    None
+                                };
+                                return (false, old);
+                            }
                             _ => break,
                         };
                     }
                     stack[r] = Some(Rc::clone(&n));
+                    pos[r] = j;
                 }
-                let w = Node::new(x, Self::pick_height());
+                let w = Node::new(x, Self::pick_height(&mut self.rng));
                 let height = w.borrow().next.len() - 1;
                 while self.h < height {
                     if let Some(sentinel) = self
@@ -109,28 +281,70 @@ impl<T: PartialOrd + Clone + Default> SSet<T> for SkiplistSSet<T> {
                         .filter(|sentinel| sentinel.borrow().next.len() < w.borrow().next.len())
                     {
                         sentinel.borrow_mut().next.push(None);
+                        sentinel.borrow_mut().length.push(0);
                     }
                     self.h += 1;
                     if let Some(e) = stack.get_mut(self.h) {
                         e.replace(Rc::clone(sentinel));
                     } else {
                         stack.push(Some(Rc::clone(sentinel)));
+                        pos.push(0);
                     }
                 }
-                for (i, item) in stack.iter_mut().enumerate().take(height + 1) {
-                    match item.take() {
-                        Some(ref u) => {
-                            w.borrow_mut().next[i] = u.borrow_mut().next[i].take();
-                            u.borrow_mut().next[i] = Some(Rc::clone(&w));
+                // w becomes element pos[0] + 1; links passing over it get one longer
+                let p = pos[0] + 1;
+                for (r, item) in stack.iter_mut().enumerate().take(self.h + 1) {
+                    if let Some(ref u) = item.take() {
+                        let mut u = u.borrow_mut();
+                        if r > height {
+                            if u.next[r].is_some() {
+                                u.length[r] += 1;
+                            }
+                            continue;
                         }
-                        None => break,
+                        let mut v = w.borrow_mut();
+                        v.next[r] = u.next[r].take();
+                        if v.next[r].is_some() {
+                            v.length[r] = pos[r] + u.length[r] + 1 - p;
+                        }
+                        u.next[r] = Some(Rc::clone(&w));
+                        u.length[r] = p - pos[r];
                     }
                 }
                 self.n += 1;
-                true
+                (true, None)
             }
-            None => false,
+            None => (false, None),
+        }
+    }
+    fn pick_height(rng: &mut Random) -> usize {
+        let z = rng.gen::<usize>();
+        let mut k = 0;
+        let mut m = 1;
+        while (z & m) != 0 {
+            k += 1;
+            m <<= 1;
         }
+        k
+    }
+}
+
+impl<T: PartialOrd + Clone + Default + Codec> SkiplistSSet<T> {
+    pub fn dump(&self) -> Vec<u8> {
+        snapshot::dump(self.iter())
+    }
+    pub fn load(buf: &[u8]) -> Result<Self, SnapshotError> {
+        snapshot::load(buf).map(|xs: Vec<T>| xs.into_iter().collect())
+    }
+}
+
+impl<T: PartialOrd + Clone + Default> SSet<T> for SkiplistSSet<T> {
+    fn size(&self) -> usize {
+        self.n
+    }
+
+    fn add(&mut self, x: T) -> bool {
+        self.put(x, false).0
     }
 
     fn remove(&mut self, x: &T) -> Option<T> {
@@ -138,6 +352,7 @@ impl<T: PartialOrd + Clone + Default> SSet<T> for SkiplistSSet<T> {
             Some(ref sentinel) => {
                 let mut n = Rc::clone(sentinel);
                 let mut del = None;
+                let mut over = vec![];
                 let rh = self.h;
                 for r in (0..=rh).rev() {
                     let removed = loop {
@@ -145,14 +360,20 @@ impl<T: PartialOrd + Clone + Default> SSet<T> for SkiplistSSet<T> {
                         match u.borrow().next[r] {
                             Some(ref u) if u.borrow().x < *x => n = Rc::clone(u),
                             Some(ref u) if u.borrow().x == *x => break true,
-                            _ => break false,
+                            Some(_) => {
+                                over.push((Rc::clone(&n), r));
+                                break false;
+                            }
+                            None => break false,
                         };
                     };
                     if removed {
                         del = n.borrow_mut().next[r].take();
                         if let Some(del) = del.as_ref() {
+                            let length = del.borrow().length[r];
                             if let Some(next) = del.borrow_mut().next[r].take() {
                                 n.borrow_mut().next[r] = Some(next);
+                                n.borrow_mut().length[r] += length - 1;
                             } else if Rc::ptr_eq(&n, self.head.as_ref().unwrap()) && self.h > 0 {
                                 self.h -= 1;
                             }
@@ -160,6 +381,10 @@ impl<T: PartialOrd + Clone + Default> SSet<T> for SkiplistSSet<T> {
                     }
                 }
                 del.map(|del| {
+                    // links that jumped over x are one shorter
+                    for (u, r) in over {
+                        u.borrow_mut().length[r] -= 1;
+                    }
                     self.n -= 1;
                     Rc::try_unwrap(del).ok().unwrap().into_inner().x
                 })
@@ -176,13 +401,181 @@ impl<T: PartialOrd + Clone + Default> SSet<T> for SkiplistSSet<T> {
             _ => None,
         }
     }
+    fn successor(&self, x: &T) -> Option<T> {
+        match self.find_last_node(x) {
+            Some(ref u) if u.borrow().next[0].is_some() => {
+                u.borrow().next[0].as_ref().map(|u| u.borrow().x.clone())
+            }
+            _ => None,
+        }
+    }
+    fn predecessor(&self, x: &T) -> Option<T> {
+        match self.find_pred_node(x) {
+            Some(ref u) if !Rc::ptr_eq(u, self.head.as_ref().unwrap()) => {
+                Some(u.borrow().x.clone())
+            }
+            _ => None,
+        }
+    }
+    fn find_min(&self) -> Option<T> {
+        self.head
+            .as_ref()
+            .and_then(|s| s.borrow().next[0].as_ref().map(|u| u.borrow().x.clone()))
+    }
+    fn find_max(&self) -> Option<T> {
+        self.find_max_node().map(|u| u.borrow().x.clone())
+    }
+    fn count_range(&self, r: std::ops::Range<T>) -> usize {
+        if r.start >= r.end {
+            return 0;
+        }
+        self.rank(&r.end) - self.rank(&r.start)
+    }
+}
+
+impl<'a, T: PartialOrd + Clone + Default> IntoIterator for &'a SkiplistSSet<T> {
+    type Item = T;
+    type IntoIter = Iter<'a, T>;
+    fn into_iter(self) -> Iter<'a, T> {
+        self.iter()
+    }
+}
+
+impl<T: PartialOrd + Clone + Default> IntoIterator for SkiplistSSet<T> {
+    type Item = T;
+    type IntoIter = IntoIter<T>;
+    fn into_iter(self) -> IntoIter<T> {
+        IntoIter(self)
+    }
+}
+
+impl<T: PartialOrd + Clone + Default> FromIterator<T> for SkiplistSSet<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut s = Self::new();
+        s.extend(iter);
+        s
+    }
+}
+
+impl<T: PartialOrd + Clone + Default> Extend<T> for SkiplistSSet<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for x in iter {
+            self.add(x);
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<T: PartialOrd + Clone + Default + serde::Serialize> serde::Serialize for SkiplistSSet<T> {
+    fn serialize<S: serde::Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        s.collect_seq(self)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, T: PartialOrd + Clone + Default + serde::Deserialize<'de>> serde::Deserialize<'de>
+    for SkiplistSSet<T>
+{
+    fn deserialize<D: serde::Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+        serde::Deserialize::deserialize(d).map(|xs: Vec<T>| xs.into_iter().collect())
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct SkiplistSSetMap<K, V>
+where
+    K: PartialOrd + Clone + Default,
+    V: Clone,
+{
+    t: SkiplistSSet<Pair<K, V>>,
+}
+
+impl<K, V> SkiplistSSetMap<K, V>
+where
+    K: PartialOrd + Clone + Default,
+    V: Clone,
+{
+    pub fn new() -> Self {
+        Self {
+            t: SkiplistSSet::new(),
+        }
+    }
+}
+
+impl<K, V> Default for SkiplistSSetMap<K, V>
+where
+    K: PartialOrd + Clone + Default,
+    V: Clone,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K, V> USetMap<K, V> for SkiplistSSetMap<K, V>
+where
+    K: PartialOrd + Clone + Default,
+    V: Clone,
+{
+    type ValueMut<'a>
+        = Lent<'a, V>
+    where
+        Self: 'a;
+    fn size(&self) -> usize {
+        self.t.size()
+    }
+    fn insert(&mut self, k: K, v: V) -> Option<V> {
+        self.t.replace(Pair::new(k, v)).and_then(|p| p.v)
+    }
+    fn get(&self, k: &K) -> Option<V> {
+        self.t
+            .find(&Pair::probe(k.clone()))
+            .filter(|p| p.k == *k)
+            .and_then(|p| p.v)
+    }
+    fn get_mut(&mut self, k: &K) -> Option<Lent<'_, V>> {
+        let u = self.t.find_pred_node(&Pair::probe(k.clone()))?;
+        let u = u.borrow().next[0]
+            .clone()
+            .filter(|u| u.borrow().x.k == *k)?;
+        let v = u.borrow_mut().x.v.take()?;
+        Some(Lent::new(v, move |v| u.borrow_mut().x.v = Some(v)))
+    }
+    fn remove(&mut self, k: &K) -> Option<V> {
+        self.t.remove(&Pair::probe(k.clone())).and_then(|p| p.v)
+    }
+}
+
+impl<K, V> SSetMap<K, V> for SkiplistSSetMap<K, V>
+where
+    K: PartialOrd + Clone + Default,
+    V: Clone,
+{
+    fn successor(&self, k: &K) -> Option<(K, V)> {
+        self.t
+            .successor(&Pair::probe(k.clone()))
+            .and_then(Pair::into_tuple)
+    }
+    fn predecessor(&self, k: &K) -> Option<(K, V)> {
+        self.t
+            .predecessor(&Pair::probe(k.clone()))
+            .and_then(Pair::into_tuple)
+    }
+    fn find_min(&self) -> Option<(K, V)> {
+        self.t.find_min().and_then(Pair::into_tuple)
+    }
+    fn find_max(&self) -> Option<(K, V)> {
+        self.t.find_max().and_then(Pair::into_tuple)
+    }
 }
 
 #[cfg(test)]
 mod test {
-    use super::SkiplistSSet;
+    use super::{SkiplistSSet, SkiplistSSetMap};
     use chapter01::interface::SSet;
-    use rand::{thread_rng, Rng};
+    use chapter01::testing::check_ssetmap;
+    use rand::rngs::StdRng;
+    use rand::{thread_rng, Rng, SeedableRng};
     #[test]
     fn test_skiplistsset() {
         let mut skiplistsset: SkiplistSSet<u64> = SkiplistSSet::new();
@@ -212,6 +605,35 @@ mod test {
             assert_eq!(skiplistsset.find(&i), Some(i));
         }
         assert_eq!(skiplistsset.remove(&9), None);
+        assert_eq!(skiplistsset.successor(&3), Some(5));
+        assert_eq!(skiplistsset.predecessor(&5), Some(3));
+        assert_eq!(skiplistsset.floor(&4), Some(3));
+        assert_eq!(skiplistsset.ceiling(&4), Some(5));
+        assert_eq!(skiplistsset.predecessor(&0), None);
+        assert_eq!(skiplistsset.successor(&7), None);
+        assert_eq!(skiplistsset.find_min(), Some(0));
+        assert_eq!(skiplistsset.find_max(), Some(7));
+        assert_eq!(skiplistsset.range(2..6).collect::<Vec<_>>(), vec![2, 3, 5]);
+        assert_eq!(skiplistsset.count_range(4..8), 3);
+        assert_eq!(skiplistsset.count_range(8..10), 0);
+        assert_eq!(
+            skiplistsset.iter().collect::<Vec<_>>(),
+            vec![0, 1, 2, 3, 5, 6, 7]
+        );
+        assert_eq!(
+            skiplistsset.iter().rev().collect::<Vec<_>>(),
+            vec![7, 6, 5, 3, 2, 1, 0]
+        );
+        let mut iter = skiplistsset.iter();
+        assert_eq!(iter.next_back(), Some(7));
+        assert_eq!(iter.next(), Some(0));
+        assert_eq!(iter.size_hint(), (5, Some(5)));
+        let mut skiplistsset: SkiplistSSet<u64> = vec![3, 1, 2, 3].into_iter().collect();
+        skiplistsset.extend(vec![0, 4]);
+        assert_eq!(skiplistsset.size(), 5);
+        let mut iter = skiplistsset.into_iter();
+        assert_eq!(iter.next_back(), Some(4));
+        assert_eq!(iter.collect::<Vec<_>>(), vec![0, 1, 2, 3]);
         let mut skiplistsset: SkiplistSSet<u64> = SkiplistSSet::new();
         let n = 200;
         let mut rng = thread_rng();
@@ -224,6 +646,16 @@ mod test {
                 let x = rng.gen_range(0, 5 * n);
                 skiplistsset.remove(&x);
             }
+            // count_range adds up link lengths; range walks the elements
+            for _ in 0..n {
+                let a = rng.gen_range(0, 5 * n);
+                let b = rng.gen_range(0, 5 * n);
+                assert_eq!(
+                    skiplistsset.count_range(a..b),
+                    skiplistsset.range(a..b).count()
+                );
+            }
+            assert_eq!(skiplistsset.rank(&(5 * n)), skiplistsset.size());
         }
 
         // test large linked list for stack overflow.
@@ -233,5 +665,59 @@ mod test {
             skiplistsset.add(i);
         }
         println!("fin");
+        #[cfg(feature = "serde")]
+        {
+            let skiplistsset: SkiplistSSet<u64> = vec![4, 0, 2, 8, 6].into_iter().collect();
+            let json = serde_json::to_string(&skiplistsset).unwrap();
+            assert_eq!(json, "[0,2,4,6,8]");
+            let copy: SkiplistSSet<u64> = serde_json::from_str(&json).unwrap();
+            assert!(copy.iter().eq(skiplistsset.iter()));
+            assert_eq!(copy.find(&5), Some(6));
+            let copy: SkiplistSSet<u64> = serde_json::from_str("[3,1,3,2]").unwrap();
+            assert_eq!(
+                (copy.size(), copy.iter().collect::<Vec<_>>()),
+                (3, vec![1, 2, 3])
+            );
+        }
+        let skiplistsset: SkiplistSSet<u64> = (0..100).map(|x| x * x).collect();
+        let buf = skiplistsset.dump();
+        let copy = SkiplistSSet::<u64>::load(&buf).unwrap();
+        assert!(copy.iter().eq(skiplistsset.iter()));
+        assert_eq!(copy.find(&50), Some(64));
+        assert!(SkiplistSSet::<u64>::load(&buf[..buf.len() - 1]).is_err());
+        assert!(SkiplistSSet::<u32>::load(&buf).is_err());
+        // a seed fixes the height of every tower
+        let heights = |s: SkiplistSSet<u32>| {
+            let mut hs = vec![];
+            let mut u = s.head.as_ref().and_then(|h| h.borrow().next[0].clone());
+            while let Some(w) = u {
+                hs.push(w.borrow().next.len());
+                u = w.borrow().next[0].clone();
+            }
+            hs
+        };
+        let build = |mut s: SkiplistSSet<u32>| {
+            for i in 0..500 {
+                s.add(i * 7 % 500);
+            }
+            s
+        };
+        assert_eq!(
+            heights(build(SkiplistSSet::with_seed(42))),
+            heights(build(SkiplistSSet::with_seed(42)))
+        );
+        assert_ne!(
+            heights(build(SkiplistSSet::with_seed(42))),
+            heights(build(SkiplistSSet::with_seed(43)))
+        );
+        assert_eq!(
+            heights(build(SkiplistSSet::with_rng(StdRng::seed_from_u64(7)))),
+            heights(build(SkiplistSSet::with_rng(StdRng::seed_from_u64(7))))
+        );
+    }
+
+    #[test]
+    fn test_skiplistssetmap() {
+        check_ssetmap(SkiplistSSetMap::<i32, usize>::new);
     }
 }
@@ -1,30 +1,71 @@
 #![allow(clippy::many_single_char_names,clippy::explicit_counter_loop, clippy::redundant_closure)]
-use super::hashcode;
-use chapter01::interface::{List, USet};
+use super::hasher::{mix, MultiplicativeHash};
+use chapter01::interface::{List, Pair, USet, USetMap};
 use chapter02::arraystack::Array as ArrayStack;
-use std::hash::Hash;
+use rand::RngCore;
+use std::hash::{BuildHasher, Hash};
+use std::iter::{Flatten, FromIterator};
 
 #[derive(Clone, Debug, Default, Eq, Ord, PartialEq, PartialOrd)]
-pub struct ChainedHashTable<T> {
+pub struct ChainedHashTable<T, S = MultiplicativeHash> {
     t: Box<[ArrayStack<T>]>,
     n: usize,
     d: usize,
-    z: usize,
+    hasher: S,
+}
+
+pub struct Iter<'a, T>(Flatten<std::slice::Iter<'a, ArrayStack<T>>>);
+
+impl<'a, T> Iterator for Iter<'a, T> {
+    type Item = &'a T;
+    fn next(&mut self) -> Option<&'a T> {
+        self.0.next()
+    }
+}
+
+pub struct IntoIter<T>(Flatten<std::vec::IntoIter<ArrayStack<T>>>);
+
+impl<T> Iterator for IntoIter<T> {
+    type Item = T;
+    fn next(&mut self) -> Option<T> {
+        self.0.next()
+    }
 }
 
 impl<T> ChainedHashTable<T>
 where
     T: PartialEq + Clone + Hash,
 {
-    const W: usize = std::mem::size_of::<usize>() * 8;
     pub fn new() -> Self {
+        Self::with_hasher(MultiplicativeHash::new())
+    }
+    pub fn with_rng<R: RngCore>(rng: R) -> Self {
+        Self::with_hasher(MultiplicativeHash::with_rng(rng))
+    }
+    pub fn with_seed(seed: u64) -> Self {
+        Self::with_hasher(MultiplicativeHash::with_seed(seed))
+    }
+}
+
+impl<T, S> ChainedHashTable<T, S>
+where
+    T: PartialEq + Clone + Hash,
+    S: BuildHasher,
+{
+    pub fn with_hasher(hasher: S) -> Self {
         Self {
             t: Self::allocate_in_heap(2),
             n: 0,
             d: 1,
-            z: rand::random::<usize>() | 1,
+            hasher,
         }
     }
+    pub fn hasher(&self) -> &S {
+        &self.hasher
+    }
+    pub fn iter(&self) -> Iter<'_, T> {
+        Iter(self.t.iter().flatten())
+    }
     fn allocate_in_heap(size: usize) -> Box<[ArrayStack<T>]> {
         std::iter::repeat_with(|| ArrayStack::new())
             .take(size)
@@ -47,14 +88,37 @@ where
         }
     }
     fn hash(&self, x: &T) -> usize {
-        (((self.z as u128 * hashcode(x) as u128) % ((1 as u128) << Self::W as u128))
-            >> (Self::W - self.d) as u128) as usize
+        (mix(self.hasher.hash_one(x)) >> (64 - self.d)) as usize
+    }
+    fn find_mut(&mut self, x: &T) -> Option<&mut T> {
+        let t = self.t.get_mut(self.hash(x))?;
+        let i = t.iter().position(|y| y == x)?;
+        t.get_mut(i)
+    }
+    // x must not be in the table yet
+    fn push(&mut self, x: T) {
+        if self.n + 1 > self.t.len() {
+            self.resize();
+        }
+        if let Some(t) = self.t.get_mut(self.hash(&x)) {
+            t.add(t.size(), x)
+        }
+        self.n += 1;
+    }
+    // adds x, or puts it in the place of the element equal to it and returns that one
+    pub fn replace(&mut self, x: T) -> Option<T> {
+        if let Some(y) = self.find_mut(&x) {
+            return Some(std::mem::replace(y, x));
+        }
+        self.push(x);
+        None
     }
 }
 
-impl<T> USet<T> for ChainedHashTable<T>
+impl<T, S> USet<T> for ChainedHashTable<T, S>
 where
     T: PartialEq + Clone + Hash,
+    S: BuildHasher,
 {
     fn size(&self) -> usize {
         self.n
@@ -63,13 +127,7 @@ where
         if self.find(&x).is_some() {
             return false;
         }
-        if self.n + 1 > self.t.len() {
-            self.resize();
-        }
-        if let Some(t) = self.t.get_mut(self.hash(&x)) {
-            t.add(t.size(), x)
-        }
-        self.n += 1;
+        self.push(x);
         true
     }
     fn remove(&mut self, x: &T) -> Option<T> {
@@ -97,10 +155,141 @@ where
     }
 }
 
+impl<'a, T, S> IntoIterator for &'a ChainedHashTable<T, S>
+where
+    T: PartialEq + Clone + Hash,
+    S: BuildHasher,
+{
+    type Item = &'a T;
+    type IntoIter = Iter<'a, T>;
+    fn into_iter(self) -> Iter<'a, T> {
+        self.iter()
+    }
+}
+
+impl<T, S> IntoIterator for ChainedHashTable<T, S> {
+    type Item = T;
+    type IntoIter = IntoIter<T>;
+    fn into_iter(self) -> IntoIter<T> {
+        IntoIter(self.t.into_vec().into_iter().flatten())
+    }
+}
+
+impl<T, S> FromIterator<T> for ChainedHashTable<T, S>
+where
+    T: PartialEq + Clone + Hash,
+    S: BuildHasher + Default,
+{
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut s = Self::with_hasher(S::default());
+        s.extend(iter);
+        s
+    }
+}
+
+impl<T, S> Extend<T> for ChainedHashTable<T, S>
+where
+    T: PartialEq + Clone + Hash,
+    S: BuildHasher,
+{
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for x in iter {
+            self.add(x);
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<T, S> serde::Serialize for ChainedHashTable<T, S>
+where
+    T: PartialEq + Clone + Hash + serde::Serialize,
+    S: BuildHasher,
+{
+    fn serialize<Z: serde::Serializer>(&self, s: Z) -> Result<Z::Ok, Z::Error> {
+        s.collect_seq(self)
+    }
+}
+
+// the elements are rehashed with a default hasher
+#[cfg(feature = "serde")]
+impl<'de, T, S> serde::Deserialize<'de> for ChainedHashTable<T, S>
+where
+    T: PartialEq + Clone + Hash + serde::Deserialize<'de>,
+    S: BuildHasher + Default,
+{
+    fn deserialize<D: serde::Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+        serde::Deserialize::deserialize(d).map(|xs: Vec<T>| xs.into_iter().collect())
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct ChainedHashTableMap<K, V>
+where
+    K: PartialEq + Clone + Hash,
+    V: Clone,
+{
+    t: ChainedHashTable<Pair<K, V>>,
+}
+
+impl<K, V> ChainedHashTableMap<K, V>
+where
+    K: PartialEq + Clone + Hash,
+    V: Clone,
+{
+    pub fn new() -> Self {
+        Self {
+            t: ChainedHashTable::new(),
+        }
+    }
+}
+
+impl<K, V> Default for ChainedHashTableMap<K, V>
+where
+    K: PartialEq + Clone + Hash,
+    V: Clone,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K, V> USetMap<K, V> for ChainedHashTableMap<K, V>
+where
+    K: PartialEq + Clone + Hash,
+    V: Clone,
+{
+    type ValueMut<'a>
+        = &'a mut V
+    where
+        Self: 'a;
+    fn size(&self) -> usize {
+        self.t.size()
+    }
+    fn insert(&mut self, k: K, v: V) -> Option<V> {
+        self.t.replace(Pair::new(k, v)).and_then(|p| p.v)
+    }
+    fn get(&self, k: &K) -> Option<V> {
+        self.t.find(&Pair::probe(k.clone())).and_then(|p| p.v)
+    }
+    fn get_mut(&mut self, k: &K) -> Option<&mut V> {
+        self.t.find_mut(&Pair::probe(k.clone()))?.v.as_mut()
+    }
+    fn remove(&mut self, k: &K) -> Option<V> {
+        self.t.remove(&Pair::probe(k.clone())).and_then(|p| p.v)
+    }
+}
+
 #[cfg(test)]
 mod test {
-    use super::ChainedHashTable;
-    use chapter01::interface::USet;
+    use super::{ChainedHashTable, ChainedHashTableMap};
+    use crate::hasher::{IdentityHasher, MultiplicativeHash};
+    use chapter01::interface::{List, USet};
+    use chapter01::testing::check_usetmap;
+    use rand::rngs::StdRng;
+    use rand::{thread_rng, Rng, SeedableRng};
+    use std::collections::hash_map::{DefaultHasher, RandomState};
+    use std::collections::HashSet;
+    use std::hash::BuildHasherDefault;
 
     #[test]
     fn test_chainedhashtable() {
@@ -133,5 +322,86 @@ mod test {
         assert_eq!(chainedhashtable.remove(&'h'), Some('h'));
         assert_eq!(chainedhashtable.remove(&'i'), Some('i'));
         assert_eq!(chainedhashtable.remove(&'x'), None);
+        let mut elems = chainedhashtable.iter().copied().collect::<Vec<_>>();
+        elems.sort();
+        assert_eq!(elems, "jklm".chars().collect::<Vec<_>>());
+        let mut chainedhashtable: ChainedHashTable<char> = "abcab".chars().collect();
+        chainedhashtable.extend("cd".chars());
+        assert_eq!(chainedhashtable.size(), 4);
+        let mut elems = chainedhashtable.into_iter().collect::<Vec<_>>();
+        elems.sort();
+        assert_eq!(elems, "abcd".chars().collect::<Vec<_>>());
+        #[cfg(feature = "serde")]
+        {
+            let chainedhashtable: ChainedHashTable<i32> = (0..100).collect();
+            let json = serde_json::to_string(&chainedhashtable).unwrap();
+            let mut elems = serde_json::from_str::<Vec<i32>>(&json).unwrap();
+            elems.sort();
+            assert_eq!(elems, (0..100).collect::<Vec<_>>());
+            let copy: ChainedHashTable<i32> = serde_json::from_str(&json).unwrap();
+            assert_eq!(copy.size(), 100);
+            assert!((0..100).all(|x| copy.find(&x) == Some(x)));
+            let copy: ChainedHashTable<i32> = serde_json::from_str("[1,2,1]").unwrap();
+            assert_eq!(copy.size(), 2);
+        }
+        // any BuildHasher will do; a deterministic one lays the table out the same way every run
+        let mut rng = thread_rng();
+        let xs: Vec<u32> = (0..1000).map(|_| rng.gen()).collect();
+        let a: ChainedHashTable<u32, BuildHasherDefault<DefaultHasher>> =
+            xs.iter().copied().collect();
+        let b: ChainedHashTable<u32, BuildHasherDefault<DefaultHasher>> =
+            xs.iter().copied().collect();
+        assert!(a.iter().eq(b.iter()));
+        let mut sip = ChainedHashTable::with_hasher(RandomState::new());
+        let mut own = ChainedHashTable::with_hasher(MultiplicativeHash::new());
+        let mut reference = HashSet::new();
+        for _ in 0..5000 {
+            let x = rng.gen_range(0, 1000);
+            if rng.gen() {
+                let added = reference.insert(x);
+                assert_eq!(sip.add(x), added);
+                assert_eq!(own.add(x), added);
+            } else {
+                let removed = reference.take(&x);
+                assert_eq!(sip.remove(&x), removed);
+                assert_eq!(own.remove(&x), removed);
+            }
+            assert_eq!(sip.size(), reference.len());
+            assert_eq!(own.size(), reference.len());
+            assert_eq!(own.find(&x), sip.find(&x));
+        }
+        // an identity hash of a small key is zero in the high bits; the mix
+        // still spreads keys that differ only in the low bits
+        let mut weak: ChainedHashTable<u32, BuildHasherDefault<IdentityHasher>> =
+            (0..1000).collect();
+        assert!(weak.t.iter().all(|l| l.size() <= 8));
+        for x in 0..1000 {
+            assert_eq!(weak.find(&x), Some(x));
+        }
+        assert_eq!(weak.remove(&500), Some(500));
+        assert_eq!(weak.find(&500), None);
+        assert_eq!(weak.size(), 999);
+        // a seed fixes the hash function, and so the layout of the table
+        let layout = |mut t: ChainedHashTable<u32>| {
+            t.extend(xs.iter().copied());
+            t.iter().copied().collect::<Vec<_>>()
+        };
+        assert_eq!(
+            layout(ChainedHashTable::with_seed(42)),
+            layout(ChainedHashTable::with_seed(42))
+        );
+        assert_ne!(
+            layout(ChainedHashTable::with_seed(42)),
+            layout(ChainedHashTable::with_seed(43))
+        );
+        assert_eq!(
+            layout(ChainedHashTable::with_rng(StdRng::seed_from_u64(7))),
+            layout(ChainedHashTable::with_rng(StdRng::seed_from_u64(7)))
+        );
+    }
+
+    #[test]
+    fn test_chainedhashtablemap() {
+        check_usetmap(ChainedHashTableMap::<i32, usize>::new);
     }
 }
@@ -0,0 +1,376 @@
+#![allow(clippy::many_single_char_names,clippy::explicit_counter_loop, clippy::redundant_closure)]
+use super::hasher::TabulationHash;
+use chapter01::interface::USet;
+use chapter01::rng::Random;
+use rand::RngCore;
+use std::hash::{BuildHasher, Hash};
+use std::iter::{Chain, Flatten, FromIterator};
+
+// two tables of 2^d slots, each with its own tabulation hash; x lives in
+// t[0][h0(x)] or t[1][h1(x)], so find looks at two slots at most
+#[derive(Clone, Debug)]
+pub struct CuckooHashTable<T> {
+    t: [Slots<T>; 2],
+    n: usize,
+    d: u32,
+    h: [TabulationHash; 2],
+    rng: Random,
+}
+
+type Slots<T> = Box<[Option<T>]>;
+type IntoSlots<T> = std::vec::IntoIter<Option<T>>;
+
+pub struct Iter<'a, T>(Flatten<Flatten<std::slice::Iter<'a, Slots<T>>>>);
+
+impl<'a, T> Iterator for Iter<'a, T> {
+    type Item = &'a T;
+    fn next(&mut self) -> Option<&'a T> {
+        self.0.next()
+    }
+}
+
+pub struct IntoIter<T>(Flatten<Chain<IntoSlots<T>, IntoSlots<T>>>);
+
+impl<T> Iterator for IntoIter<T> {
+    type Item = T;
+    fn next(&mut self) -> Option<T> {
+        self.0.next()
+    }
+}
+
+impl<T> Default for CuckooHashTable<T>
+where
+    T: PartialEq + Clone + Hash,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> CuckooHashTable<T>
+where
+    T: PartialEq + Clone + Hash,
+{
+    pub fn new() -> Self {
+        Self::with_rng(rand::thread_rng())
+    }
+    pub fn with_rng<R: RngCore>(rng: R) -> Self {
+        Self::with_random(Random::with_rng(rng))
+    }
+    pub fn with_seed(seed: u64) -> Self {
+        Self::with_random(Random::with_seed(seed))
+    }
+    fn with_random(mut rng: Random) -> Self {
+        Self {
+            t: [Self::allocate_in_heap(2), Self::allocate_in_heap(2)],
+            n: 0,
+            d: 1,
+            h: [
+                TabulationHash::with_rng(&mut rng),
+                TabulationHash::with_rng(&mut rng),
+            ],
+            rng,
+        }
+    }
+    pub fn iter(&self) -> Iter<'_, T> {
+        Iter(self.t.iter().flatten().flatten())
+    }
+    fn allocate_in_heap(size: usize) -> Slots<T> {
+        std::iter::repeat_with(|| None)
+            .take(size)
+            .collect::<Vec<_>>()
+            .into_boxed_slice()
+    }
+    fn hash(&self, i: usize, x: &T) -> usize {
+        (self.h[i].hash_one(x) >> (64 - self.d)) as usize
+    }
+    // an eviction chain longer than this is taken as a cycle
+    fn max_loop(&self) -> usize {
+        6 * self.d as usize + 8
+    }
+    // kicks elements back and forth between the tables until one lands in an
+    // empty slot; on a cycle, the element still without a slot is handed back
+    fn place(&mut self, mut x: T) -> Result<(), T> {
+        let mut i = 0;
+        for _ in 0..self.max_loop() {
+            let j = self.hash(i, &x);
+            match self.t[i][j].replace(x) {
+                None => return Ok(()),
+                Some(y) => x = y,
+            }
+            i ^= 1;
+        }
+        Err(x)
+    }
+    // rebuilds both tables with 2^d slots and fresh hash functions, drawing
+    // again while some element cannot be placed; every few failed draws the
+    // tables grow, and once they are far larger than the elements need, the
+    // elements are taken to share too few Hash values to ever fit
+    fn rehash(&mut self, mut d: u32, mut pending: Vec<T>) {
+        for draw in 1.. {
+            for t in self.t.iter_mut() {
+                pending.extend(t.iter_mut().filter_map(Option::take));
+            }
+            assert!(
+                1 << d <= 64 * pending.len().max(1),
+                "cuckoo hashing cannot place {} elements: too many share a Hash value",
+                pending.len()
+            );
+            self.d = d;
+            self.t = [
+                Self::allocate_in_heap(1 << d),
+                Self::allocate_in_heap(1 << d),
+            ];
+            self.h = [
+                TabulationHash::with_rng(&mut self.rng),
+                TabulationHash::with_rng(&mut self.rng),
+            ];
+            let mut xs = std::mem::take(&mut pending).into_iter();
+            match xs.by_ref().find_map(|x| self.place(x).err()) {
+                None => return,
+                Some(x) => {
+                    pending.extend(xs);
+                    pending.push(x);
+                }
+            }
+            if draw % 4 == 0 {
+                d += 1;
+            }
+        }
+    }
+}
+
+impl<T> USet<T> for CuckooHashTable<T>
+where
+    T: PartialEq + Clone + Hash,
+{
+    fn size(&self) -> usize {
+        self.n
+    }
+    fn add(&mut self, x: T) -> bool {
+        if self.find(&x).is_some() {
+            return false;
+        }
+        if 2 * (self.n + 1) > 1 << self.d {
+            self.rehash(self.d + 1, vec![]);
+        }
+        if let Err(y) = self.place(x) {
+            self.rehash(self.d, vec![y]);
+        }
+        self.n += 1;
+        true
+    }
+    fn remove(&mut self, x: &T) -> Option<T> {
+        for i in 0..2 {
+            let j = self.hash(i, x);
+            if self.t[i][j].as_ref() == Some(x) {
+                let y = self.t[i][j].take();
+                self.n -= 1;
+                if 8 * self.n < 1 << self.d && self.d > 1 {
+                    self.rehash(self.d - 1, vec![]);
+                }
+                return y;
+            }
+        }
+        None
+    }
+    fn find(&self, x: &T) -> Option<T> {
+        (0..2)
+            .filter_map(|i| self.t[i][self.hash(i, x)].as_ref())
+            .find(|y| *y == x)
+            .cloned()
+    }
+}
+
+impl<'a, T> IntoIterator for &'a CuckooHashTable<T>
+where
+    T: PartialEq + Clone + Hash,
+{
+    type Item = &'a T;
+    type IntoIter = Iter<'a, T>;
+    fn into_iter(self) -> Iter<'a, T> {
+        self.iter()
+    }
+}
+
+impl<T> IntoIterator for CuckooHashTable<T> {
+    type Item = T;
+    type IntoIter = IntoIter<T>;
+    fn into_iter(self) -> IntoIter<T> {
+        let [t0, t1] = self.t;
+        IntoIter(t0.into_vec().into_iter().chain(t1.into_vec()).flatten())
+    }
+}
+
+impl<T> FromIterator<T> for CuckooHashTable<T>
+where
+    T: PartialEq + Clone + Hash,
+{
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut s = Self::new();
+        s.extend(iter);
+        s
+    }
+}
+
+impl<T> Extend<T> for CuckooHashTable<T>
+where
+    T: PartialEq + Clone + Hash,
+{
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for x in iter {
+            self.add(x);
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<T> serde::Serialize for CuckooHashTable<T>
+where
+    T: PartialEq + Clone + Hash + serde::Serialize,
+{
+    fn serialize<S: serde::Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        s.collect_seq(self)
+    }
+}
+
+// the elements are rehashed with fresh hash functions
+#[cfg(feature = "serde")]
+impl<'de, T> serde::Deserialize<'de> for CuckooHashTable<T>
+where
+    T: PartialEq + Clone + Hash + serde::Deserialize<'de>,
+{
+    fn deserialize<D: serde::Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+        serde::Deserialize::deserialize(d).map(|xs: Vec<T>| xs.into_iter().collect())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::CuckooHashTable;
+    use crate::chainedhashtable::ChainedHashTable;
+    use chapter01::interface::USet;
+    use rand::{thread_rng, Rng};
+    use std::hash::{Hash, Hasher};
+
+    // a key whose Hash sees only x / k, so k keys in a row hash alike
+    #[derive(Clone, Debug, PartialEq)]
+    struct Coarse(u32, u32);
+
+    impl Hash for Coarse {
+        fn hash<H: Hasher>(&self, state: &mut H) {
+            (self.0 / self.1).hash(state)
+        }
+    }
+
+    #[test]
+    fn test_cuckoohashtable() {
+        let mut cuckoohashtable = CuckooHashTable::<char>::new();
+        for elem in "abcdefghijklmx".chars() {
+            assert!(cuckoohashtable.add(elem));
+        }
+        assert!(!cuckoohashtable.add('x'));
+        for elem in "abcdefghijklmx".chars() {
+            assert_eq!(cuckoohashtable.find(&elem), Some(elem));
+        }
+        assert_eq!(cuckoohashtable.find(&'y'), None);
+        for elem in "xabcdefghi".chars() {
+            assert_eq!(cuckoohashtable.remove(&elem), Some(elem));
+        }
+        assert_eq!(cuckoohashtable.remove(&'x'), None);
+        let mut elems = cuckoohashtable.iter().copied().collect::<Vec<_>>();
+        elems.sort();
+        assert_eq!(elems, "jklm".chars().collect::<Vec<_>>());
+        let mut cuckoohashtable: CuckooHashTable<char> = "abcab".chars().collect();
+        cuckoohashtable.extend("cd".chars());
+        assert_eq!(cuckoohashtable.size(), 4);
+        let mut elems = cuckoohashtable.into_iter().collect::<Vec<_>>();
+        elems.sort();
+        assert_eq!(elems, "abcd".chars().collect::<Vec<_>>());
+
+        // every element sits in one of its two slots, through growing and shrinking
+        let mut rng = thread_rng();
+        let n = 2000;
+        let mut cuckoohashtable = CuckooHashTable::<u32>::new();
+        let mut chainedhashtable = ChainedHashTable::<u32>::new();
+        for k in 0..10 * n {
+            let x = rng.gen_range(0, 2 * n);
+            if k < 5 * n && rng.gen_range(0, 3) > 0 || k >= 5 * n && rng.gen_range(0, 3) == 0 {
+                assert_eq!(cuckoohashtable.add(x), chainedhashtable.add(x));
+            } else {
+                assert_eq!(cuckoohashtable.remove(&x), chainedhashtable.remove(&x));
+            }
+            assert_eq!(cuckoohashtable.size(), chainedhashtable.size());
+            let x = rng.gen_range(0, 2 * n);
+            assert_eq!(cuckoohashtable.find(&x), chainedhashtable.find(&x));
+            if k % 997 == 0 {
+                for (i, t) in cuckoohashtable.t.iter().enumerate() {
+                    for (j, y) in t.iter().enumerate() {
+                        if let Some(y) = y {
+                            assert_eq!(cuckoohashtable.hash(i, y), j);
+                        }
+                    }
+                }
+                let mut xs = cuckoohashtable.iter().copied().collect::<Vec<_>>();
+                let mut ys = chainedhashtable.iter().copied().collect::<Vec<_>>();
+                xs.sort();
+                ys.sort();
+                assert_eq!(xs, ys);
+            }
+        }
+
+        // four slots cannot hold five elements: an eviction chain gives up,
+        // and a rehash into larger tables keeps every element
+        let mut cuckoohashtable = CuckooHashTable::<u32>::new();
+        let mut homeless = vec![];
+        for x in 0..5 {
+            if let Err(y) = cuckoohashtable.place(x) {
+                homeless.push(y);
+            }
+        }
+        assert!(!homeless.is_empty());
+        cuckoohashtable.rehash(3, homeless);
+        let mut elems = cuckoohashtable.iter().copied().collect::<Vec<_>>();
+        elems.sort();
+        assert_eq!(elems, (0..5).collect::<Vec<_>>());
+
+        // two keys that hash alike fill both of their slots, so three pairs
+        // fit only once the tables are large enough to keep the pairs apart
+        let mut cuckoohashtable = CuckooHashTable::new();
+        for x in 0..6 {
+            assert!(cuckoohashtable.add(Coarse(x, 2)));
+        }
+        assert!((0..6).all(|x| cuckoohashtable.find(&Coarse(x, 2)).is_some()));
+        assert_eq!(cuckoohashtable.remove(&Coarse(3, 2)), Some(Coarse(3, 2)));
+        assert_eq!(cuckoohashtable.find(&Coarse(2, 2)), Some(Coarse(2, 2)));
+        assert_eq!(cuckoohashtable.size(), 5);
+        // three keys that hash alike have two slots between them whatever the
+        // size, so the third add fails loudly instead of drawing forever
+        let mut cuckoohashtable = CuckooHashTable::new();
+        assert!(cuckoohashtable.add(Coarse(0, 3)));
+        assert!(cuckoohashtable.add(Coarse(1, 3)));
+        let third = std::panic::catch_unwind(move || cuckoohashtable.add(Coarse(2, 3)));
+        assert!(third.is_err());
+
+        // a seed fixes the hash functions, and so the layout of the table
+        let layout = |mut t: CuckooHashTable<u32>| {
+            t.extend(0..1000);
+            t.iter().copied().collect::<Vec<_>>()
+        };
+        assert_eq!(
+            layout(CuckooHashTable::with_seed(42)),
+            layout(CuckooHashTable::with_seed(42))
+        );
+        #[cfg(feature = "serde")]
+        {
+            let cuckoohashtable: CuckooHashTable<i32> = (0..100).collect();
+            let json = serde_json::to_string(&cuckoohashtable).unwrap();
+            let mut elems = serde_json::from_str::<Vec<i32>>(&json).unwrap();
+            elems.sort();
+            assert_eq!(elems, (0..100).collect::<Vec<_>>());
+            let copy: CuckooHashTable<i32> = serde_json::from_str(&json).unwrap();
+            assert_eq!(copy.size(), 100);
+            assert!((0..100).all(|x| copy.find(&x) == Some(x)));
+        }
+    }
+}
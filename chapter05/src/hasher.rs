@@ -0,0 +1,161 @@
+use super::byte_chunks_64;
+use chapter01::rng::Random;
+use lazy_static::lazy_static;
+use rand::{thread_rng, Rng, RngCore};
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::Arc;
+
+lazy_static! {
+    pub static ref TAB: [[u64; 256]; 8] = {
+        let mut array = [[0; 256]; 8];
+        for item in &mut array {
+            thread_rng().fill(item);
+        }
+        array
+    };
+    static ref SHARED: Arc<[[u64; 256]; 8]> = Arc::new(*TAB);
+}
+
+// the tables keep the high bits of a hash, so the hashers below mix the
+// std hash code of a key into all 64 bits
+
+// a user's hasher may leave the high bits alone (an identity hash of a small
+// integer is zero there), so the tables mix its output once more; an odd
+// multiplier keeps distinct hashes distinct
+pub fn mix(h: u64) -> u64 {
+    h.wrapping_mul(0x9e37_79b9_7f4a_7c15)
+}
+
+// hashes an integer key to itself, the weakest hasher a user could pass in
+#[cfg(test)]
+#[derive(Default)]
+pub struct IdentityHasher(u64);
+
+#[cfg(test)]
+impl Hasher for IdentityHasher {
+    fn write(&mut self, bytes: &[u8]) {
+        for b in bytes.iter().rev() {
+            self.0 = self.0 << 8 | *b as u64;
+        }
+    }
+    fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+// multiplicative hashing by a random odd z
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct MultiplicativeHash {
+    z: u64,
+}
+
+pub struct MultiplicativeHasher {
+    z: u64,
+    s: DefaultHasher,
+}
+
+impl MultiplicativeHash {
+    pub fn new() -> Self {
+        Self::with_rng(thread_rng())
+    }
+    pub fn with_rng<R: RngCore>(mut rng: R) -> Self {
+        Self {
+            z: rng.next_u64() | 1,
+        }
+    }
+    pub fn with_seed(seed: u64) -> Self {
+        Self::with_rng(Random::with_seed(seed))
+    }
+}
+
+impl Default for MultiplicativeHash {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl BuildHasher for MultiplicativeHash {
+    type Hasher = MultiplicativeHasher;
+    fn build_hasher(&self) -> MultiplicativeHasher {
+        MultiplicativeHasher {
+            z: self.z,
+            s: DefaultHasher::new(),
+        }
+    }
+}
+
+impl Hasher for MultiplicativeHasher {
+    fn write(&mut self, bytes: &[u8]) {
+        self.s.write(bytes)
+    }
+    fn finish(&self) -> u64 {
+        self.z.wrapping_mul(self.s.finish())
+    }
+}
+
+// u64 tabulation hashing; the default shares the global TAB
+#[derive(Clone, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct TabulationHash {
+    tab: Arc<[[u64; 256]; 8]>,
+}
+
+pub struct TabulationHasher {
+    tab: Arc<[[u64; 256]; 8]>,
+    s: DefaultHasher,
+}
+
+impl TabulationHash {
+    // a table of its own
+    pub fn new() -> Self {
+        Self::with_rng(thread_rng())
+    }
+    pub fn with_rng<R: RngCore>(mut rng: R) -> Self {
+        let mut tab = [[0; 256]; 8];
+        for item in &mut tab {
+            rng.fill(item);
+        }
+        Self { tab: Arc::new(tab) }
+    }
+    pub fn with_seed(seed: u64) -> Self {
+        Self::with_rng(Random::with_seed(seed))
+    }
+}
+
+impl Default for TabulationHash {
+    fn default() -> Self {
+        Self {
+            tab: SHARED.clone(),
+        }
+    }
+}
+
+impl std::fmt::Debug for TabulationHash {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TabulationHash").finish_non_exhaustive()
+    }
+}
+
+impl BuildHasher for TabulationHash {
+    type Hasher = TabulationHasher;
+    fn build_hasher(&self) -> TabulationHasher {
+        TabulationHasher {
+            tab: self.tab.clone(),
+            s: DefaultHasher::new(),
+        }
+    }
+}
+
+impl Hasher for TabulationHasher {
+    fn write(&mut self, bytes: &[u8]) {
+        self.s.write(bytes)
+    }
+    fn finish(&self) -> u64 {
+        let chunks = byte_chunks_64(self.s.finish());
+        let mut v = 0u64;
+        for (i, c) in chunks.iter().enumerate() {
+            v ^= self.tab[i][*c as usize];
+        }
+        v
+    }
+}
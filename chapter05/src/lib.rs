@@ -1,4 +1,6 @@
 pub mod chainedhashtable;
+pub mod cuckoohashtable;
+pub mod hasher;
 pub mod linearhashtable;
 
 use std::collections::hash_map::DefaultHasher;
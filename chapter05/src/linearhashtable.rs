@@ -1,54 +1,89 @@
 #![allow(clippy::many_single_char_names,clippy::explicit_counter_loop, clippy::redundant_closure)]
-use super::{byte_chunks_64, Tabulation};
-use chapter01::interface::USet;
-use lazy_static::lazy_static;
-use rand::{thread_rng, Rng};
-use std::hash::Hash;
+pub use super::hasher::TAB;
+use super::hasher::{mix, TabulationHash};
+use super::Tabulation;
+use chapter01::interface::{Pair, USet, USetMap};
+use rand::RngCore;
+use std::hash::{BuildHasher, Hash};
+use std::iter::FromIterator;
 
-lazy_static! {
-    pub static ref TAB: [[u64; 256]; 8] = {
-        let mut array = [[0; 256]; 8];
-        for item in &mut array {
-            thread_rng().fill(item);
-        }
-        array
-    };
-}
-
-#[derive(Clone, Debug, Eq, Ord, PartialEq, PartialOrd, Copy)]
+#[derive(Clone, Debug, Default, Eq, Ord, PartialEq, PartialOrd, Copy)]
 enum Elem<T> {
     Val(T),
+    #[default]
     Null,
     Del,
 }
 
 #[derive(Clone, Debug, Default, Eq, Ord, PartialEq, PartialOrd)]
-pub struct LinearHashTable<T> {
+pub struct LinearHashTable<T, S = TabulationHash> {
     t: Box<[Elem<T>]>,
     n: usize,
     q: usize,
     d: u32,
+    hasher: S,
 }
 
-impl<T> Default for Elem<T> {
-    fn default() -> Self {
-        Elem::Null
+pub struct Iter<'a, T>(std::slice::Iter<'a, Elem<T>>);
+
+impl<'a, T> Iterator for Iter<'a, T> {
+    type Item = &'a T;
+    fn next(&mut self) -> Option<&'a T> {
+        self.0.find_map(|elem| match elem {
+            Elem::Val(x) => Some(x),
+            _ => None,
+        })
+    }
+}
+
+pub struct IntoIter<T>(std::vec::IntoIter<Elem<T>>);
+
+impl<T> Iterator for IntoIter<T> {
+    type Item = T;
+    fn next(&mut self) -> Option<T> {
+        self.0.find_map(|elem| match elem {
+            Elem::Val(x) => Some(x),
+            _ => None,
+        })
     }
 }
+
 impl<T: Hash> Tabulation for T {}
 impl<T> LinearHashTable<T>
 where
     T: PartialEq + Clone + Hash,
 {
-    const W: u32 = (std::mem::size_of::<usize>() * 8) as u32;
     pub fn new() -> Self {
+        Self::with_hasher(TabulationHash::default())
+    }
+    pub fn with_rng<R: RngCore>(rng: R) -> Self {
+        Self::with_hasher(TabulationHash::with_rng(rng))
+    }
+    pub fn with_seed(seed: u64) -> Self {
+        Self::with_hasher(TabulationHash::with_seed(seed))
+    }
+}
+
+impl<T, S> LinearHashTable<T, S>
+where
+    T: PartialEq + Clone + Hash,
+    S: BuildHasher,
+{
+    pub fn with_hasher(hasher: S) -> Self {
         Self {
             t: Self::allocate_in_heap(2),
             n: 0,
             q: 1,
             d: 1,
+            hasher,
         }
     }
+    pub fn hasher(&self) -> &S {
+        &self.hasher
+    }
+    pub fn iter(&self) -> Iter<'_, T> {
+        Iter(self.t.iter())
+    }
     fn allocate_in_heap(size: usize) -> Box<[Elem<T>]> {
         std::iter::repeat_with(|| Default::default())
             .take(size)
@@ -56,16 +91,52 @@ where
             .into_boxed_slice()
     }
     fn hash(&self, x: &T) -> usize {
-        // u64 tabulation hashing
-
-        let mut v = 0u64;
-        let h = x.hashcode();
-        let chunks = byte_chunks_64(h as u64);
-        for (i, c) in chunks.iter().enumerate() {
-            v ^= TAB[i][*c as usize];
+        mix(self.hasher.hash_one(x)).overflowing_shr(64 - self.d).0 as usize
+    }
+    fn find_mut(&mut self, x: &T) -> Option<&mut T> {
+        let mut i = self.hash(x);
+        loop {
+            match self.t.get(i)? {
+                Elem::Null => return None,
+                Elem::Val(y) if y == x => break,
+                _ => i = if i == self.t.len() - 1 { 0 } else { i + 1 },
+            }
+        }
+        match self.t.get_mut(i) {
+            Some(Elem::Val(y)) => Some(y),
+            _ => None,
         }
-        v = v.overflowing_shr(Self::W - self.d).0;
-        v as usize
+    }
+    // x must not be in the table yet
+    fn push(&mut self, x: T) {
+        if 2 * (self.q + 1) > self.t.len() {
+            self.resize();
+        }
+        let mut i = self.hash(&x);
+        loop {
+            match self.t.get(i) {
+                Some(elem) => match elem {
+                    Elem::Val(_y) => i = if i == self.t.len() - 1 { 0 } else { i + 1 },
+                    _ => break,
+                },
+                None => return,
+            }
+        }
+        if self.t.get(i).unwrap() == &Elem::Null {
+            self.q += 1
+        }
+        self.n += 1;
+        if let Some(elem) = self.t.get_mut(i) {
+            *elem = Elem::Val(x)
+        }
+    }
+    // adds x, or puts it in the place of the element equal to it and returns that one
+    pub fn replace(&mut self, x: T) -> Option<T> {
+        if let Some(y) = self.find_mut(&x) {
+            return Some(std::mem::replace(y, x));
+        }
+        self.push(x);
+        None
     }
     fn resize(&mut self) {
         self.d = 1;
@@ -96,9 +167,10 @@ where
     }
 }
 
-impl<T> USet<T> for LinearHashTable<T>
+impl<T, S> USet<T> for LinearHashTable<T, S>
 where
     T: PartialEq + Clone + Hash,
+    S: BuildHasher,
 {
     fn size(&self) -> usize {
         self.n
@@ -107,26 +179,7 @@ where
         if self.find(&x).is_some() {
             return false;
         }
-        if 2 * (self.q + 1) > self.t.len() {
-            self.resize();
-        }
-        let mut i = self.hash(&x);
-        loop {
-            match self.t.get(i) {
-                Some(elem) => match elem {
-                    Elem::Val(_y) => i = if i == self.t.len() - 1 { 0 } else { i + 1 },
-                    _ => break,
-                },
-                None => return false,
-            }
-        }
-        if self.t.get(i).unwrap() == &Elem::Null {
-            self.q += 1
-        }
-        self.n += 1;
-        if let Some(elem) = self.t.get_mut(i) {
-            *elem = Elem::Val(x)
-        }
+        self.push(x);
         true
     }
     fn remove(&mut self, x: &T) -> Option<T> {
@@ -165,10 +218,141 @@ where
     }
 }
 
+impl<'a, T, S> IntoIterator for &'a LinearHashTable<T, S>
+where
+    T: PartialEq + Clone + Hash,
+    S: BuildHasher,
+{
+    type Item = &'a T;
+    type IntoIter = Iter<'a, T>;
+    fn into_iter(self) -> Iter<'a, T> {
+        self.iter()
+    }
+}
+
+impl<T, S> IntoIterator for LinearHashTable<T, S> {
+    type Item = T;
+    type IntoIter = IntoIter<T>;
+    fn into_iter(self) -> IntoIter<T> {
+        IntoIter(self.t.into_vec().into_iter())
+    }
+}
+
+impl<T, S> FromIterator<T> for LinearHashTable<T, S>
+where
+    T: PartialEq + Clone + Hash,
+    S: BuildHasher + Default,
+{
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut s = Self::with_hasher(S::default());
+        s.extend(iter);
+        s
+    }
+}
+
+impl<T, S> Extend<T> for LinearHashTable<T, S>
+where
+    T: PartialEq + Clone + Hash,
+    S: BuildHasher,
+{
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for x in iter {
+            self.add(x);
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<T, S> serde::Serialize for LinearHashTable<T, S>
+where
+    T: PartialEq + Clone + Hash + serde::Serialize,
+    S: BuildHasher,
+{
+    fn serialize<Z: serde::Serializer>(&self, s: Z) -> Result<Z::Ok, Z::Error> {
+        s.collect_seq(self)
+    }
+}
+
+// the elements are rehashed with a default hasher
+#[cfg(feature = "serde")]
+impl<'de, T, S> serde::Deserialize<'de> for LinearHashTable<T, S>
+where
+    T: PartialEq + Clone + Hash + serde::Deserialize<'de>,
+    S: BuildHasher + Default,
+{
+    fn deserialize<D: serde::Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+        serde::Deserialize::deserialize(d).map(|xs: Vec<T>| xs.into_iter().collect())
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct LinearHashTableMap<K, V>
+where
+    K: PartialEq + Clone + Hash,
+    V: Clone,
+{
+    t: LinearHashTable<Pair<K, V>>,
+}
+
+impl<K, V> LinearHashTableMap<K, V>
+where
+    K: PartialEq + Clone + Hash,
+    V: Clone,
+{
+    pub fn new() -> Self {
+        Self {
+            t: LinearHashTable::new(),
+        }
+    }
+}
+
+impl<K, V> Default for LinearHashTableMap<K, V>
+where
+    K: PartialEq + Clone + Hash,
+    V: Clone,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K, V> USetMap<K, V> for LinearHashTableMap<K, V>
+where
+    K: PartialEq + Clone + Hash,
+    V: Clone,
+{
+    type ValueMut<'a>
+        = &'a mut V
+    where
+        Self: 'a;
+    fn size(&self) -> usize {
+        self.t.size()
+    }
+    fn insert(&mut self, k: K, v: V) -> Option<V> {
+        self.t.replace(Pair::new(k, v)).and_then(|p| p.v)
+    }
+    fn get(&self, k: &K) -> Option<V> {
+        self.t.find(&Pair::probe(k.clone())).and_then(|p| p.v)
+    }
+    fn get_mut(&mut self, k: &K) -> Option<&mut V> {
+        self.t.find_mut(&Pair::probe(k.clone()))?.v.as_mut()
+    }
+    fn remove(&mut self, k: &K) -> Option<V> {
+        self.t.remove(&Pair::probe(k.clone())).and_then(|p| p.v)
+    }
+}
+
 #[cfg(test)]
 mod test {
-    use super::LinearHashTable;
+    use super::{LinearHashTable, LinearHashTableMap};
+    use crate::hasher::{IdentityHasher, TabulationHash};
     use chapter01::interface::USet;
+    use chapter01::testing::check_usetmap;
+    use rand::rngs::StdRng;
+    use rand::{thread_rng, Rng, SeedableRng};
+    use std::collections::hash_map::{DefaultHasher, RandomState};
+    use std::collections::HashSet;
+    use std::hash::BuildHasherDefault;
     #[test]
     fn test_linearhashtable() {
         let mut linearhashtable = LinearHashTable::<char>::new();
@@ -178,7 +362,7 @@ mod test {
         linearhashtable.add('d');
         linearhashtable.add('e');
         linearhashtable.add('x');
-        assert_eq!(false, linearhashtable.add('x'));
+        assert!(!linearhashtable.add('x'));
         for elem in "abcdex".chars() {
             assert_eq!(linearhashtable.find(&elem), Some(elem));
         }
@@ -189,6 +373,86 @@ mod test {
         assert_eq!(linearhashtable.remove(&'c'), Some('c'));
         assert_eq!(linearhashtable.remove(&'e'), Some('e'));
         assert_eq!(linearhashtable.remove(&'a'), None);
+        assert_eq!(linearhashtable.iter().collect::<Vec<_>>(), vec![&'d']);
         println!("{:?}", linearhashtable);
+        let mut linearhashtable: LinearHashTable<char> = "abcab".chars().collect();
+        linearhashtable.extend("cd".chars());
+        assert_eq!(linearhashtable.size(), 4);
+        let mut elems = linearhashtable.into_iter().collect::<Vec<_>>();
+        elems.sort();
+        assert_eq!(elems, "abcd".chars().collect::<Vec<_>>());
+        #[cfg(feature = "serde")]
+        {
+            let linearhashtable: LinearHashTable<i32> = (0..100).collect();
+            let json = serde_json::to_string(&linearhashtable).unwrap();
+            let mut elems = serde_json::from_str::<Vec<i32>>(&json).unwrap();
+            elems.sort();
+            assert_eq!(elems, (0..100).collect::<Vec<_>>());
+            let copy: LinearHashTable<i32> = serde_json::from_str(&json).unwrap();
+            assert_eq!(copy.size(), 100);
+            assert!((0..100).all(|x| copy.find(&x) == Some(x)));
+            let copy: LinearHashTable<i32> = serde_json::from_str("[1,2,1]").unwrap();
+            assert_eq!(copy.size(), 2);
+        }
+        // any BuildHasher will do; a deterministic one lays the table out the same way every run
+        let mut rng = thread_rng();
+        let xs: Vec<u32> = (0..1000).map(|_| rng.gen()).collect();
+        let a: LinearHashTable<u32, BuildHasherDefault<DefaultHasher>> =
+            xs.iter().copied().collect();
+        let b: LinearHashTable<u32, BuildHasherDefault<DefaultHasher>> =
+            xs.iter().copied().collect();
+        assert!(a.iter().eq(b.iter()));
+        let mut sip = LinearHashTable::with_hasher(RandomState::new());
+        let mut own = LinearHashTable::with_hasher(TabulationHash::new());
+        let mut reference = HashSet::new();
+        for _ in 0..5000 {
+            let x = rng.gen_range(0, 1000);
+            if rng.gen() {
+                let added = reference.insert(x);
+                assert_eq!(sip.add(x), added);
+                assert_eq!(own.add(x), added);
+            } else {
+                let removed = reference.take(&x);
+                assert_eq!(sip.remove(&x), removed);
+                assert_eq!(own.remove(&x), removed);
+            }
+            assert_eq!(sip.size(), reference.len());
+            assert_eq!(own.size(), reference.len());
+            assert_eq!(own.find(&x), sip.find(&x));
+        }
+        // an identity hash of a small key is zero in the high bits; the mix
+        // still spreads keys that differ only in the low bits
+        let mut weak: LinearHashTable<u32, BuildHasherDefault<IdentityHasher>> =
+            (0..1000).collect();
+        let buckets = (0..1000).map(|x| weak.hash(&x)).collect::<HashSet<_>>();
+        assert!(buckets.len() > 500);
+        for x in 0..1000 {
+            assert_eq!(weak.find(&x), Some(x));
+        }
+        assert_eq!(weak.remove(&500), Some(500));
+        assert_eq!(weak.find(&500), None);
+        assert_eq!(weak.size(), 999);
+        // a seed fixes the hash function, and so the layout of the table
+        let layout = |mut t: LinearHashTable<u32>| {
+            t.extend(xs.iter().copied());
+            t.iter().copied().collect::<Vec<_>>()
+        };
+        assert_eq!(
+            layout(LinearHashTable::with_seed(42)),
+            layout(LinearHashTable::with_seed(42))
+        );
+        assert_ne!(
+            layout(LinearHashTable::with_seed(42)),
+            layout(LinearHashTable::with_seed(43))
+        );
+        assert_eq!(
+            layout(LinearHashTable::with_rng(StdRng::seed_from_u64(7))),
+            layout(LinearHashTable::with_rng(StdRng::seed_from_u64(7)))
+        );
+    }
+
+    #[test]
+    fn test_linearhashtablemap() {
+        check_usetmap(LinearHashTableMap::<i32, usize>::new);
     }
 }
@@ -1,6 +1,11 @@
 #![allow(clippy::many_single_char_names,clippy::explicit_counter_loop, clippy::redundant_closure)]
+use chapter01::codec::Codec;
 use chapter01::interface::SSet;
-use std::cell::RefCell;
+use chapter01::searchtree::{self, SearchNode};
+use chapter01::snapshot::{self, SnapshotError};
+use std::cell::{Ref, RefCell};
+use std::iter::FromIterator;
+use std::marker::PhantomData;
 use std::rc::{Rc, Weak};
 
 type Tree<T> = Option<Rc<BSTNode<T>>>;
@@ -13,12 +18,81 @@ pub struct BSTNode<T> {
     parent: RefCell<Option<Weak<BSTNode<T>>>>,
 }
 
+impl<T> SearchNode<T> for BSTNode<T> {
+    fn x(&self) -> Ref<'_, T> {
+        self.x.borrow()
+    }
+    fn left(&self) -> Option<Rc<Self>> {
+        self.left.borrow().clone()
+    }
+    fn right(&self) -> Option<Rc<Self>> {
+        self.right.borrow().clone()
+    }
+}
+
 #[derive(Clone, Debug, Default)]
 pub struct BinarySearchTree<T: PartialOrd + Clone> {
     n: usize,
     r: Option<Rc<BSTNode<T>>>,
 }
 
+pub struct Range<'a, T: PartialOrd + Clone> {
+    u: Tree<T>,
+    hi: T,
+    tree: PhantomData<&'a BinarySearchTree<T>>,
+}
+
+impl<'a, T: PartialOrd + Clone> Iterator for Range<'a, T> {
+    type Item = T;
+    fn next(&mut self) -> Option<T> {
+        let u = self.u.take()?;
+        let x = u.x.borrow().clone();
+        if x < self.hi {
+            self.u = BinarySearchTree::next_node(&u);
+            Some(x)
+        } else {
+            None
+        }
+    }
+}
+
+pub struct Iter<'a, T: PartialOrd + Clone> {
+    front: Tree<T>,
+    back: Tree<T>,
+    n: usize,
+    tree: PhantomData<&'a BinarySearchTree<T>>,
+}
+
+impl<'a, T: PartialOrd + Clone> Iterator for Iter<'a, T> {
+    type Item = T;
+    fn next(&mut self) -> Option<T> {
+        if self.n == 0 {
+            return None;
+        }
+        self.n -= 1;
+        let u = self.front.take()?;
+        self.front = BinarySearchTree::next_node(&u);
+        let x = u.x.borrow().clone();
+        Some(x)
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.n, Some(self.n))
+    }
+}
+
+impl<'a, T: PartialOrd + Clone> DoubleEndedIterator for Iter<'a, T> {
+    fn next_back(&mut self) -> Option<T> {
+        if self.n == 0 {
+            return None;
+        }
+        self.n -= 1;
+        let u = self.back.take()?;
+        self.back = BinarySearchTree::prev_node(&u);
+        let x = u.x.borrow().clone();
+        Some(x)
+    }
+}
+
 impl<T: PartialOrd + Clone> Drop for BinarySearchTree<T> {
     fn drop(&mut self) {
         while let Some(r) = self.r.clone() {
@@ -69,6 +143,92 @@ impl<T: PartialOrd + Clone> BinarySearchTree<T> {
             w = next;
         }
     }
+    fn find_ceiling_node(&self, x: &T) -> Tree<T> {
+        let mut w = self.r.clone();
+        let mut z: Tree<T> = None;
+        let mut next;
+        loop {
+            match w {
+                Some(ref u) if x < &*u.x.borrow() => {
+                    z = w.clone();
+                    next = u.left.borrow().clone()
+                }
+                Some(ref u) if x > &*u.x.borrow() => next = u.right.borrow().clone(),
+                Some(_) => break w,
+                None => break z,
+            }
+            w = next;
+        }
+    }
+    fn next_node(u: &Rc<BSTNode<T>>) -> Tree<T> {
+        let mut w = u.right.borrow().clone();
+        if w.is_some() {
+            while let Some(v) = w.as_ref().and_then(|w| w.left.borrow().clone()) {
+                w = Some(v);
+            }
+            return w;
+        }
+        let mut w = u.clone();
+        loop {
+            let p = w.parent.borrow().as_ref().and_then(|p| p.upgrade());
+            match p {
+                Some(p) => {
+                    let left = p.left.borrow().clone();
+                    match left {
+                        Some(ref left) if Rc::ptr_eq(left, &w) => break Some(p),
+                        _ => w = p,
+                    }
+                }
+                None => break None,
+            }
+        }
+    }
+    fn prev_node(u: &Rc<BSTNode<T>>) -> Tree<T> {
+        let mut w = u.left.borrow().clone();
+        if w.is_some() {
+            while let Some(v) = w.as_ref().and_then(|w| w.right.borrow().clone()) {
+                w = Some(v);
+            }
+            return w;
+        }
+        let mut w = u.clone();
+        loop {
+            let p = w.parent.borrow().as_ref().and_then(|p| p.upgrade());
+            match p {
+                Some(p) => {
+                    let right = p.right.borrow().clone();
+                    match right {
+                        Some(ref right) if Rc::ptr_eq(right, &w) => break Some(p),
+                        _ => w = p,
+                    }
+                }
+                None => break None,
+            }
+        }
+    }
+    pub fn iter(&self) -> Iter<'_, T> {
+        let mut front = self.r.clone();
+        while let Some(u) = front.as_ref().and_then(|u| u.left.borrow().clone()) {
+            front = Some(u);
+        }
+        let mut back = self.r.clone();
+        while let Some(u) = back.as_ref().and_then(|u| u.right.borrow().clone()) {
+            back = Some(u);
+        }
+        Iter {
+            front,
+            back,
+            n: self.n,
+            tree: PhantomData,
+        }
+    }
+    pub fn range(&self, r: std::ops::Range<T>) -> Range<'_, T> {
+        Range {
+            u: self.find_ceiling_node(&r.start),
+            hi: r.end,
+            tree: PhantomData,
+        }
+    }
     fn find_last(&self, x: &T) -> Tree<T> {
         let mut w = self.r.clone();
         let mut prev = None;
@@ -167,6 +327,34 @@ impl<T: PartialOrd + Clone> BinarySearchTree<T> {
     }
 }
 
+impl<T: Ord + Clone + Default> BinarySearchTree<T> {
+    // sorted elements added middle first keep the tree balanced instead of making a path
+    fn add_middles(&mut self, xs: &mut [Option<T>]) {
+        if !xs.is_empty() {
+            let m = xs.len() / 2;
+            self.add(xs[m].take().unwrap());
+            let (l, r) = xs.split_at_mut(m);
+            self.add_middles(l);
+            self.add_middles(&mut r[1..]);
+        }
+    }
+    pub fn dump(&self) -> Vec<u8>
+    where
+        T: Codec,
+    {
+        snapshot::dump(self.iter())
+    }
+    pub fn load(buf: &[u8]) -> Result<Self, SnapshotError>
+    where
+        T: Codec,
+    {
+        let mut xs: Vec<Option<T>> = snapshot::load(buf)?.into_iter().map(Some).collect();
+        let mut t = Self::new();
+        t.add_middles(&mut xs);
+        Ok(t)
+    }
+}
+
 impl<T> SSet<T> for BinarySearchTree<T>
 where
     T: Ord + Clone + Default,
@@ -197,16 +385,85 @@ where
                 }
                 Some(ref u) if x > &*u.x.borrow() => next = u.right.borrow().clone(),
                 Some(ref u) if x == &*u.x.borrow() => break Some(u.x.borrow().clone()),
-                _ => {
-                    break match z {
-                        Some(z) => Some(z.x.borrow().clone()),
-                        None => None,
-                    }
-                }
+                _ => break z.map(|z| z.x.borrow().clone()),
             }
             w = next;
         }
     }
+    fn successor(&self, x: &T) -> Option<T> {
+        searchtree::successor(&self.r, x)
+    }
+    fn predecessor(&self, x: &T) -> Option<T> {
+        searchtree::predecessor(&self.r, x)
+    }
+    fn find_min(&self) -> Option<T> {
+        searchtree::find_min(&self.r)
+    }
+    fn find_max(&self) -> Option<T> {
+        searchtree::find_max(&self.r)
+    }
+    // O(h + k) for k elements in r: the nodes keep no subtree sizes to rank with
+    fn count_range(&self, r: std::ops::Range<T>) -> usize {
+        self.range(r).count()
+    }
+}
+
+impl<'a, T: PartialOrd + Clone> IntoIterator for &'a BinarySearchTree<T> {
+    type Item = T;
+    type IntoIter = Iter<'a, T>;
+    fn into_iter(self) -> Iter<'a, T> {
+        self.iter()
+    }
+}
+
+impl<T: PartialOrd + Clone> IntoIterator for BinarySearchTree<T> {
+    type Item = T;
+    type IntoIter = std::vec::IntoIter<T>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter().collect::<Vec<_>>().into_iter()
+    }
+}
+
+impl<T> FromIterator<T> for BinarySearchTree<T>
+where
+    T: Ord + Clone + Default,
+{
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut s = Self::new();
+        s.extend(iter);
+        s
+    }
+}
+
+impl<T> Extend<T> for BinarySearchTree<T>
+where
+    T: Ord + Clone + Default,
+{
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for x in iter {
+            self.add(x);
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<T: PartialOrd + Clone + serde::Serialize> serde::Serialize for BinarySearchTree<T> {
+    fn serialize<S: serde::Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        s.collect_seq(self)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, T> serde::Deserialize<'de> for BinarySearchTree<T>
+where
+    T: Ord + Clone + Default + serde::Deserialize<'de>,
+{
+    fn deserialize<D: serde::Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+        let xs: Vec<T> = serde::Deserialize::deserialize(d)?;
+        let mut t = Self::new();
+        t.add_middles(&mut xs.into_iter().map(Some).collect::<Vec<_>>());
+        Ok(t)
+    }
 }
 
 #[cfg(test)]
@@ -228,13 +485,48 @@ mod test {
         binarysearchtree.add(8);
         binarysearchtree.add(12);
         binarysearchtree.add(14);
-        assert_eq!(false, binarysearchtree.add(8));
+        assert!(!binarysearchtree.add(8));
         assert_eq!(Some(6), binarysearchtree.remove(&6));
         assert_eq!(Some(9), binarysearchtree.remove(&9));
         assert_eq!(Some(11), binarysearchtree.remove(&11));
         assert_eq!(None, binarysearchtree.remove(&11));
         assert_eq!(Some(12), binarysearchtree.find(&12));
         assert_eq!(9, binarysearchtree.size());
+        assert_eq!(Some(13), binarysearchtree.successor(&12));
+        assert_eq!(Some(8), binarysearchtree.successor(&7));
+        assert_eq!(Some(5), binarysearchtree.predecessor(&6));
+        assert_eq!(Some(7), binarysearchtree.floor(&7));
+        assert_eq!(Some(8), binarysearchtree.floor(&9));
+        assert_eq!(Some(12), binarysearchtree.ceiling(&10));
+        assert_eq!(None, binarysearchtree.predecessor(&1));
+        assert_eq!(None, binarysearchtree.successor(&14));
+        assert_eq!(Some(1), binarysearchtree.find_min());
+        assert_eq!(Some(14), binarysearchtree.find_max());
+        assert_eq!(
+            vec![3, 4, 5, 7, 8],
+            binarysearchtree.range(2..9).collect::<Vec<_>>()
+        );
+        assert_eq!(3, binarysearchtree.count_range(12..20));
+        assert_eq!(0, binarysearchtree.count_range(9..12));
+        assert_eq!(
+            vec![1, 3, 4, 5, 7, 8, 12, 13, 14],
+            binarysearchtree.iter().collect::<Vec<_>>()
+        );
+        assert_eq!(
+            vec![14, 13, 12, 8, 7, 5, 4, 3, 1],
+            binarysearchtree.iter().rev().collect::<Vec<_>>()
+        );
+        let mut iter = binarysearchtree.iter();
+        assert_eq!(Some(1), iter.next());
+        assert_eq!(Some(14), iter.next_back());
+        assert_eq!(vec![3, 4, 5, 7, 8, 12, 13], iter.collect::<Vec<_>>());
+        binarysearchtree.extend(vec![2, 6]);
+        assert_eq!(
+            (1..9).chain(12..15).collect::<Vec<_>>(),
+            binarysearchtree.into_iter().collect::<Vec<_>>()
+        );
+        let binarysearchtree: BinarySearchTree<u32> = vec![5, 2, 8, 2].into_iter().collect();
+        assert_eq!(3, binarysearchtree.size());
         //println!("{:?}", binarysearchtree);
 
         // test large linked list for stack overflow.
@@ -244,5 +536,25 @@ mod test {
             bst.add(i);
         }
         println!("fin");
+        #[cfg(feature = "serde")]
+        {
+            let binarysearchtree: BinarySearchTree<u32> = (0..255).collect();
+            let json = serde_json::to_string(&binarysearchtree).unwrap();
+            assert_eq!(
+                serde_json::from_str::<Vec<u32>>(&json).unwrap(),
+                (0..255).collect::<Vec<_>>()
+            );
+            let copy: BinarySearchTree<u32> = serde_json::from_str(&json).unwrap();
+            assert!(copy.iter().eq(binarysearchtree.iter()));
+            assert_eq!((copy.size(), copy.height()), (255, 7));
+        }
+        let binarysearchtree: BinarySearchTree<u32> = (0..255).collect();
+        let buf = binarysearchtree.dump();
+        let copy = BinarySearchTree::<u32>::load(&buf).unwrap();
+        assert!(copy.iter().eq(0..255));
+        assert_eq!((copy.size(), copy.height()), (255, 7));
+        for n in 0..buf.len() {
+            assert!(BinarySearchTree::<u32>::load(&buf[..n]).is_err());
+        }
     }
 }
@@ -108,12 +108,9 @@ impl BinaryTree {
     }
 
     fn traverse_u(u: &Tree) {
-        match u {
-            Some(n) => {
-                Self::traverse_u(&n.left.borrow());
-                Self::traverse_u(&n.right.borrow());
-            }
-            None => (),
+        if let Some(n) = u {
+            Self::traverse_u(&n.left.borrow());
+            Self::traverse_u(&n.right.borrow());
         }
     }
 
@@ -1,6 +1,13 @@
 #![allow(clippy::many_single_char_names,clippy::explicit_counter_loop, clippy::redundant_closure)]
-use chapter01::interface::SSet;
-use std::cell::RefCell;
+use chapter01::codec::Codec;
+use chapter01::interface::{Lent, Pair, SSet, SSetMap, USetMap};
+use chapter01::rng::Random;
+use chapter01::searchtree::{self, SearchNode};
+use chapter01::snapshot::{self, SnapshotError};
+use rand::{Rng, RngCore};
+use std::cell::{Ref, RefCell};
+use std::iter::FromIterator;
+use std::marker::PhantomData;
 use std::rc::{Rc, Weak};
 
 type Tree<T> = Option<Rc<TreapNode<T>>>;
@@ -9,15 +16,86 @@ type Tree<T> = Option<Rc<TreapNode<T>>>;
 pub struct TreapNode<T> {
     p: RefCell<usize>,
     x: RefCell<T>,
+    size: RefCell<usize>,
     left: RefCell<Option<Rc<TreapNode<T>>>>,
     right: RefCell<Option<Rc<TreapNode<T>>>>,
     parent: RefCell<Option<Weak<TreapNode<T>>>>,
 }
 
+impl<T> SearchNode<T> for TreapNode<T> {
+    fn x(&self) -> Ref<'_, T> {
+        self.x.borrow()
+    }
+    fn left(&self) -> Option<Rc<Self>> {
+        self.left.borrow().clone()
+    }
+    fn right(&self) -> Option<Rc<Self>> {
+        self.right.borrow().clone()
+    }
+}
+
 #[derive(Clone, Debug, Default)]
 pub struct Treap<T: PartialOrd + Clone> {
     n: usize,
     r: Option<Rc<TreapNode<T>>>,
+    rng: Random,
+}
+
+pub struct Range<'a, T: PartialOrd + Clone> {
+    u: Tree<T>,
+    hi: T,
+    tree: PhantomData<&'a Treap<T>>,
+}
+
+impl<'a, T: PartialOrd + Clone> Iterator for Range<'a, T> {
+    type Item = T;
+    fn next(&mut self) -> Option<T> {
+        let u = self.u.take()?;
+        let x = u.x.borrow().clone();
+        if x < self.hi {
+            self.u = Treap::next_node(&u);
+            Some(x)
+        } else {
+            None
+        }
+    }
+}
+
+pub struct Iter<'a, T: PartialOrd + Clone> {
+    front: Tree<T>,
+    back: Tree<T>,
+    n: usize,
+    tree: PhantomData<&'a Treap<T>>,
+}
+
+impl<'a, T: PartialOrd + Clone> Iterator for Iter<'a, T> {
+    type Item = T;
+    fn next(&mut self) -> Option<T> {
+        if self.n == 0 {
+            return None;
+        }
+        self.n -= 1;
+        let u = self.front.take()?;
+        self.front = Treap::next_node(&u);
+        let x = u.x.borrow().clone();
+        Some(x)
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.n, Some(self.n))
+    }
+}
+
+impl<'a, T: PartialOrd + Clone> DoubleEndedIterator for Iter<'a, T> {
+    fn next_back(&mut self) -> Option<T> {
+        if self.n == 0 {
+            return None;
+        }
+        self.n -= 1;
+        let u = self.back.take()?;
+        self.back = Treap::prev_node(&u);
+        let x = u.x.borrow().clone();
+        Some(x)
+    }
 }
 
 impl<T: PartialOrd + Clone> Drop for Treap<T> {
@@ -32,6 +110,7 @@ impl<T: Default> TreapNode<T> {
     pub fn new(x: T) -> Self {
         Self {
             x: RefCell::new(x),
+            size: RefCell::new(1),
             ..Default::default()
         }
     }
@@ -42,7 +121,67 @@ where
     T: PartialOrd + Clone,
 {
     pub fn new() -> Self {
-        Self { n: 0, r: None }
+        Self::with_rng(rand::thread_rng())
+    }
+    // the priorities are drawn from rng, so a seeded rng gives the same tree every run
+    pub fn with_rng<R: RngCore>(rng: R) -> Self {
+        Self {
+            n: 0,
+            r: None,
+            rng: Random::with_rng(rng),
+        }
+    }
+    pub fn with_seed(seed: u64) -> Self {
+        Self {
+            n: 0,
+            r: None,
+            rng: Random::with_seed(seed),
+        }
+    }
+    fn size_u(u: &Tree<T>) -> usize {
+        u.as_ref().map(|u| *u.size.borrow()).unwrap_or(0)
+    }
+    fn update_size(u: &Rc<TreapNode<T>>) {
+        let s = 1 + Self::size_u(&u.left.borrow()) + Self::size_u(&u.right.borrow());
+        *u.size.borrow_mut() = s;
+    }
+    fn update_ancestors(u: Tree<T>, delta: isize) {
+        let mut w = u;
+        while let Some(u) = w {
+            let s = *u.size.borrow() as isize + delta;
+            *u.size.borrow_mut() = s as usize;
+            w = u.parent.borrow().as_ref().and_then(|p| p.upgrade());
+        }
+    }
+    pub fn select(&self, k: usize) -> Option<T> {
+        let mut w = self.r.clone();
+        let mut k = k;
+        while let Some(u) = w {
+            let l = Self::size_u(&u.left.borrow());
+            if k < l {
+                w = u.left.borrow().clone();
+            } else if k > l {
+                k -= l + 1;
+                w = u.right.borrow().clone();
+            } else {
+                let x = u.x.borrow().clone();
+                return Some(x);
+            }
+        }
+        None
+    }
+    pub fn rank(&self, x: &T) -> usize {
+        let mut w = self.r.clone();
+        let mut k = 0;
+        while let Some(u) = w {
+            if x > &*u.x.borrow() {
+                k += Self::size_u(&u.left.borrow()) + 1;
+                w = u.right.borrow().clone();
+            } else {
+                w = u.left.borrow().clone();
+            }
+        }
+        k
     }
     fn rotate_left(&mut self, u: &Rc<TreapNode<T>>) {
         let w = u.right.borrow_mut().take().unwrap();
@@ -65,6 +204,8 @@ where
         }
         u.parent.borrow_mut().replace(Rc::downgrade(&w));
         w.left.borrow_mut().replace(u.clone());
+        Self::update_size(u);
+        Self::update_size(&w);
         if Rc::ptr_eq(u, self.r.as_ref().unwrap()) {
             self.r.replace(w);
         }
@@ -90,6 +231,8 @@ where
         }
         u.parent.borrow_mut().replace(Rc::downgrade(&w));
         w.right.borrow_mut().replace(u.clone());
+        Self::update_size(u);
+        Self::update_size(&w);
         if Rc::ptr_eq(u, self.r.as_ref().unwrap()) {
             self.r.replace(w);
         }
@@ -112,6 +255,92 @@ where
             self.r.replace(u.clone());
         }
     }
+    fn find_ceiling_node(&self, x: &T) -> Tree<T> {
+        let mut w = self.r.clone();
+        let mut z: Tree<T> = None;
+        let mut next;
+        loop {
+            match w {
+                Some(ref u) if x < &*u.x.borrow() => {
+                    z = w.clone();
+                    next = u.left.borrow().clone()
+                }
+                Some(ref u) if x > &*u.x.borrow() => next = u.right.borrow().clone(),
+                Some(_) => break w,
+                None => break z,
+            }
+            w = next;
+        }
+    }
+    fn next_node(u: &Rc<TreapNode<T>>) -> Tree<T> {
+        let mut w = u.right.borrow().clone();
+        if w.is_some() {
+            while let Some(v) = w.as_ref().and_then(|w| w.left.borrow().clone()) {
+                w = Some(v);
+            }
+            return w;
+        }
+        let mut w = u.clone();
+        loop {
+            let p = w.parent.borrow().as_ref().and_then(|p| p.upgrade());
+            match p {
+                Some(p) => {
+                    let left = p.left.borrow().clone();
+                    match left {
+                        Some(ref left) if Rc::ptr_eq(left, &w) => break Some(p),
+                        _ => w = p,
+                    }
+                }
+                None => break None,
+            }
+        }
+    }
+    fn prev_node(u: &Rc<TreapNode<T>>) -> Tree<T> {
+        let mut w = u.left.borrow().clone();
+        if w.is_some() {
+            while let Some(v) = w.as_ref().and_then(|w| w.right.borrow().clone()) {
+                w = Some(v);
+            }
+            return w;
+        }
+        let mut w = u.clone();
+        loop {
+            let p = w.parent.borrow().as_ref().and_then(|p| p.upgrade());
+            match p {
+                Some(p) => {
+                    let right = p.right.borrow().clone();
+                    match right {
+                        Some(ref right) if Rc::ptr_eq(right, &w) => break Some(p),
+                        _ => w = p,
+                    }
+                }
+                None => break None,
+            }
+        }
+    }
+    pub fn iter(&self) -> Iter<'_, T> {
+        let mut front = self.r.clone();
+        while let Some(u) = front.as_ref().and_then(|u| u.left.borrow().clone()) {
+            front = Some(u);
+        }
+        let mut back = self.r.clone();
+        while let Some(u) = back.as_ref().and_then(|u| u.right.borrow().clone()) {
+            back = Some(u);
+        }
+        Iter {
+            front,
+            back,
+            n: self.n,
+            tree: PhantomData,
+        }
+    }
+    pub fn range(&self, r: std::ops::Range<T>) -> Range<'_, T> {
+        Range {
+            u: self.find_ceiling_node(&r.start),
+            hi: r.end,
+            tree: PhantomData,
+        }
+    }
     fn find_last(&self, x: &T) -> Tree<T> {
         let mut w = self.r.clone();
         let mut prev = None;
@@ -144,6 +373,7 @@ where
                     return false;
                 }
                 u.parent.borrow_mut().replace(Rc::downgrade(p));
+                Self::update_ancestors(Some(p.clone()), 1);
             }
             None => self.r = Some(u),
         }
@@ -181,7 +411,7 @@ where
                 }
             }
         }
-        match (s, p) {
+        match (s, &p) {
             (Some(ref s), Some(ref p)) => {
                 s.parent.borrow_mut().replace(Rc::downgrade(p));
             }
@@ -190,6 +420,7 @@ where
             }
             _ => (),
         }
+        Self::update_ancestors(p, -1);
         self.n -= 1;
         Some(Rc::try_unwrap(u).ok().unwrap().x.into_inner())
     }
@@ -215,6 +446,18 @@ impl<T> Treap<T>
 where
     T: PartialOrd + Clone + Default,
 {
+    // adds x, or puts it in the place of the element equal to it and returns that one
+    pub fn replace(&mut self, x: T) -> Option<T> {
+        let p = self.find_last(&x);
+        if let Some(p) = p.as_ref().filter(|p| *p.x.borrow() == x) {
+            return Some(std::mem::replace(&mut *p.x.borrow_mut(), x));
+        }
+        let u = Rc::new(TreapNode::new(x));
+        *u.p.borrow_mut() = self.rng.gen();
+        self.add_child(&p, u.clone());
+        self.bubbleup(&u);
+        None
+    }
     pub fn split(&mut self, x: T) -> Treap<T> {
         let mut u = self.find_last(&x);
         let s = Rc::new(TreapNode::<T>::new(Default::default()));
@@ -233,19 +476,20 @@ where
                 }
             }
         }
-        *s.parent.borrow_mut() = u.as_ref().map(|u| Rc::downgrade(&u));
+        *s.parent.borrow_mut() = u.as_ref().map(|u| Rc::downgrade(u));
         *s.p.borrow_mut() = usize::MIN;
         self.bubbleup(&s);
         self.r = s.right.borrow_mut().take();
         if let Some(ref r) = self.r {
             *r.parent.borrow_mut() = None;
         }
-        let mut ret = Treap::<T>::new();
+        let mut ret = Treap::<T>::with_rng(&mut self.rng);
         ret.r = s.left.borrow_mut().take();
         if let Some(ref r) = ret.r {
             *r.parent.borrow_mut() = None;
         }
-        ret.n = self.n;
+        self.n = Self::size_u(&self.r);
+        ret.n = Self::size_u(&ret.r);
         ret
     }
     pub fn absorb(&mut self, mut t: Treap<T>) {
@@ -259,11 +503,23 @@ where
             r2.parent.borrow_mut().replace(Rc::downgrade(&s));
         }
         self.r.replace(s.clone());
+        Self::update_size(&s);
+        self.n += t.n + 1;
+        t.n = 0;
         self.trickle_down(&s);
         self.splice(s);
     }
 }
 
+impl<T: PartialOrd + Clone + Default + Codec> Treap<T> {
+    pub fn dump(&self) -> Vec<u8> {
+        snapshot::dump(self.iter())
+    }
+    pub fn load(buf: &[u8]) -> Result<Self, SnapshotError> {
+        snapshot::load(buf).map(|xs: Vec<T>| xs.into_iter().collect())
+    }
+}
+
 impl<T> SSet<T> for Treap<T>
 where
     T: PartialOrd + Clone + Default,
@@ -273,7 +529,7 @@ where
     }
     fn add(&mut self, x: T) -> bool {
         let u = Rc::new(TreapNode::new(x));
-        *u.p.borrow_mut() = rand::random();
+        *u.p.borrow_mut() = self.rng.gen();
         if self.add_u(u.clone()) {
             self.bubbleup(&u);
             true
@@ -302,24 +558,178 @@ where
                 }
                 Some(ref u) if x > &*u.x.borrow() => next = u.right.borrow().clone(),
                 Some(ref u) if x == &*u.x.borrow() => break Some(u.x.borrow().clone()),
-                _ => {
-                    break match z {
-                        Some(z) => Some(z.x.borrow().clone()),
-                        None => None,
-                    }
-                }
+                _ => break z.map(|z| z.x.borrow().clone()),
             }
             w = next;
         }
     }
+    fn successor(&self, x: &T) -> Option<T> {
+        searchtree::successor(&self.r, x)
+    }
+    fn predecessor(&self, x: &T) -> Option<T> {
+        searchtree::predecessor(&self.r, x)
+    }
+    fn find_min(&self) -> Option<T> {
+        searchtree::find_min(&self.r)
+    }
+    fn find_max(&self) -> Option<T> {
+        searchtree::find_max(&self.r)
+    }
+    fn count_range(&self, r: std::ops::Range<T>) -> usize {
+        self.rank(&r.end).saturating_sub(self.rank(&r.start))
+    }
+}
+
+impl<'a, T: PartialOrd + Clone> IntoIterator for &'a Treap<T> {
+    type Item = T;
+    type IntoIter = Iter<'a, T>;
+    fn into_iter(self) -> Iter<'a, T> {
+        self.iter()
+    }
+}
+
+impl<T: PartialOrd + Clone> IntoIterator for Treap<T> {
+    type Item = T;
+    type IntoIter = std::vec::IntoIter<T>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter().collect::<Vec<_>>().into_iter()
+    }
+}
+
+impl<T> FromIterator<T> for Treap<T>
+where
+    T: PartialOrd + Clone + Default,
+{
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut s = Self::new();
+        s.extend(iter);
+        s
+    }
+}
+
+impl<T> Extend<T> for Treap<T>
+where
+    T: PartialOrd + Clone + Default,
+{
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for x in iter {
+            self.add(x);
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<T: PartialOrd + Clone + serde::Serialize> serde::Serialize for Treap<T> {
+    fn serialize<S: serde::Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        s.collect_seq(self)
+    }
+}
+
+// priorities are not stored; every element draws a new one on the way in
+#[cfg(feature = "serde")]
+impl<'de, T> serde::Deserialize<'de> for Treap<T>
+where
+    T: PartialOrd + Clone + Default + serde::Deserialize<'de>,
+{
+    fn deserialize<D: serde::Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+        serde::Deserialize::deserialize(d).map(|xs: Vec<T>| xs.into_iter().collect())
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct TreapMap<K, V>
+where
+    K: PartialOrd + Clone + Default,
+    V: Clone,
+{
+    t: Treap<Pair<K, V>>,
+}
+
+impl<K, V> TreapMap<K, V>
+where
+    K: PartialOrd + Clone + Default,
+    V: Clone,
+{
+    pub fn new() -> Self {
+        Self { t: Treap::new() }
+    }
+}
+
+impl<K, V> Default for TreapMap<K, V>
+where
+    K: PartialOrd + Clone + Default,
+    V: Clone,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K, V> USetMap<K, V> for TreapMap<K, V>
+where
+    K: PartialOrd + Clone + Default,
+    V: Clone,
+{
+    type ValueMut<'a>
+        = Lent<'a, V>
+    where
+        Self: 'a;
+    fn size(&self) -> usize {
+        self.t.size()
+    }
+    fn insert(&mut self, k: K, v: V) -> Option<V> {
+        self.t.replace(Pair::new(k, v)).and_then(|p| p.v)
+    }
+    fn get(&self, k: &K) -> Option<V> {
+        self.t
+            .find(&Pair::probe(k.clone()))
+            .filter(|p| p.k == *k)
+            .and_then(|p| p.v)
+    }
+    fn get_mut(&mut self, k: &K) -> Option<Lent<'_, V>> {
+        let u = self
+            .t
+            .find_last(&Pair::probe(k.clone()))
+            .filter(|u| u.x.borrow().k == *k)?;
+        let v = u.x.borrow_mut().v.take()?;
+        Some(Lent::new(v, move |v| u.x.borrow_mut().v = Some(v)))
+    }
+    fn remove(&mut self, k: &K) -> Option<V> {
+        self.t.remove(&Pair::probe(k.clone())).and_then(|p| p.v)
+    }
+}
+
+impl<K, V> SSetMap<K, V> for TreapMap<K, V>
+where
+    K: PartialOrd + Clone + Default,
+    V: Clone,
+{
+    fn successor(&self, k: &K) -> Option<(K, V)> {
+        self.t
+            .successor(&Pair::probe(k.clone()))
+            .and_then(Pair::into_tuple)
+    }
+    fn predecessor(&self, k: &K) -> Option<(K, V)> {
+        self.t
+            .predecessor(&Pair::probe(k.clone()))
+            .and_then(Pair::into_tuple)
+    }
+    fn find_min(&self) -> Option<(K, V)> {
+        self.t.find_min().and_then(Pair::into_tuple)
+    }
+    fn find_max(&self) -> Option<(K, V)> {
+        self.t.find_max().and_then(Pair::into_tuple)
+    }
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
     use chapter01::interface::SSet;
+    use chapter01::testing::check_ssetmap;
     use chapter09::redblacktree::RedBlackTree;
-    use rand::{thread_rng, Rng};
+    use rand::rngs::StdRng;
+    use rand::{thread_rng, Rng, SeedableRng};
     #[test]
     fn test_treap() {
         let mut treap = Treap::<u32>::new();
@@ -333,12 +743,18 @@ mod test {
         treap.add(7);
         treap.add(6);
         treap.add(8);
-        assert_eq!(false, treap.add(8));
+        assert!(!treap.add(8));
         assert_eq!(Some(3), treap.find(&3));
         assert_eq!(None, treap.find(&10));
         assert_eq!(Some(9), treap.remove(&9));
         assert_eq!(Some(8), treap.remove(&8));
         assert_eq!(None, treap.remove(&8));
+        assert_eq!(
+            vec![0, 1, 2, 3, 4, 5, 6, 7],
+            treap.iter().collect::<Vec<_>>()
+        );
+        let treap: Treap<u32> = treap.into_iter().rev().collect();
+        assert_eq!(8, treap.size());
         //println!("{:?}", treap);
         let mut rng = thread_rng();
         let n = 200;
@@ -371,8 +787,54 @@ mod test {
                 let y2 = treap.find(&x);
                 assert_eq!(y1, y2);
             }
+            for _ in 0..n {
+                let x = rng.gen_range(0, 5 * n);
+                assert_eq!(redblacktree.successor(&x), treap.successor(&x));
+                assert_eq!(redblacktree.predecessor(&x), treap.predecessor(&x));
+                assert_eq!(redblacktree.floor(&x), treap.floor(&x));
+                assert_eq!(redblacktree.ceiling(&x), treap.ceiling(&x));
+            }
+            assert_eq!(redblacktree.find_min(), treap.find_min());
+            assert_eq!(redblacktree.find_max(), treap.find_max());
+            for _ in 0..n {
+                let x = rng.gen_range(0, 5 * n);
+                let y = x + rng.gen_range(0, n);
+                let r1 = redblacktree.range(x..y).collect::<Vec<_>>();
+                let r2 = treap.range(x..y).collect::<Vec<_>>();
+                assert_eq!(r1, r2);
+                assert_eq!(r1.len(), treap.count_range(x..y));
+            }
+            assert_eq!(
+                redblacktree.iter().collect::<Vec<_>>(),
+                treap.iter().collect::<Vec<_>>()
+            );
+            assert_eq!(
+                redblacktree.iter().rev().collect::<Vec<_>>(),
+                (&treap).into_iter().rev().collect::<Vec<_>>()
+            );
+            let v = redblacktree.iter().collect::<Vec<_>>();
+            for (k, x) in v.iter().enumerate() {
+                assert_eq!(Some(*x), treap.select(k));
+            }
+            assert_eq!(None, treap.select(v.len()));
+            for _ in 0..n {
+                let x = rng.gen_range(0, 5 * n);
+                assert_eq!(v.iter().filter(|y| **y < x).count(), treap.rank(&x));
+            }
         }
 
+        let mut treap: Treap<i32> = (0..100).collect();
+        let t = treap.split(49);
+        assert_eq!(50, t.size());
+        assert_eq!(50, treap.size());
+        assert_eq!(Some(49), t.select(49));
+        assert_eq!(Some(50), treap.select(0));
+        assert_eq!(50, t.rank(&60));
+        treap.absorb(t);
+        assert_eq!(100, treap.size());
+        assert_eq!(Some(70), treap.select(70));
+        assert_eq!(70, treap.rank(&70));
+
         // test large linked list for stack overflow.
         let mut bst = Treap::<i32>::new();
         let num = 100000;
@@ -380,5 +842,65 @@ mod test {
             bst.add(i);
         }
         println!("fin");
+        #[cfg(feature = "serde")]
+        {
+            fn heap_ordered(u: &Option<Rc<TreapNode<u32>>>) -> bool {
+                u.as_ref().is_none_or(|u| {
+                    [&*u.left.borrow(), &*u.right.borrow()].iter().all(|c| {
+                        c.as_ref().is_none_or(|c| *c.p.borrow() >= *u.p.borrow()) && heap_ordered(c)
+                    })
+                })
+            }
+            let treap: Treap<u32> = (0..100).collect();
+            let json = serde_json::to_string(&treap).unwrap();
+            assert_eq!(
+                serde_json::from_str::<Vec<u32>>(&json).unwrap(),
+                (0..100).collect::<Vec<_>>()
+            );
+            let copy: Treap<u32> = serde_json::from_str(&json).unwrap();
+            assert!(copy.iter().eq(treap.iter()));
+            assert!(heap_ordered(&copy.r));
+            assert_eq!(copy.size(), 100);
+        }
+        let treap: Treap<u32> = (0..100).rev().collect();
+        let buf = treap.dump();
+        let copy = Treap::<u32>::load(&buf).unwrap();
+        assert!(copy.iter().eq(0..100));
+        assert_eq!(copy.size(), 100);
+        for n in 0..buf.len() {
+            assert!(Treap::<u32>::load(&buf[..n]).is_err());
+        }
+        // a seed fixes every priority, and so the shape of the tree
+        fn shape(u: &Option<Rc<TreapNode<i32>>>, out: &mut Vec<(i32, usize)>) {
+            if let Some(u) = u {
+                out.push((*u.x.borrow(), *u.p.borrow()));
+                shape(&u.left.borrow(), out);
+                shape(&u.right.borrow(), out);
+            }
+        }
+        let xs: Vec<i32> = (0..500).map(|_| rng.gen_range(0, 1000)).collect();
+        let shapes = |mut treap: Treap<i32>| {
+            treap.extend(xs.iter().copied());
+            for x in xs.iter().step_by(3) {
+                treap.remove(x);
+            }
+            let mut t = treap.split(500);
+            t.extend(1000..1100);
+            let (mut a, mut b) = (vec![], vec![]);
+            shape(&treap.r, &mut a);
+            shape(&t.r, &mut b);
+            (a, b)
+        };
+        assert_eq!(shapes(Treap::with_seed(42)), shapes(Treap::with_seed(42)));
+        assert_ne!(shapes(Treap::with_seed(42)), shapes(Treap::with_seed(43)));
+        assert_eq!(
+            shapes(Treap::with_rng(StdRng::seed_from_u64(7))),
+            shapes(Treap::with_rng(StdRng::seed_from_u64(7)))
+        );
+    }
+
+    #[test]
+    fn test_treapmap() {
+        check_ssetmap(TreapMap::<i32, usize>::new);
     }
 }
@@ -1,17 +1,35 @@
 #![allow(clippy::many_single_char_names,clippy::explicit_counter_loop, clippy::redundant_closure)]
+use chapter01::codec::Codec;
 use chapter01::interface::SSet;
-use std::cell::RefCell;
+use chapter01::searchtree::{self, SearchNode};
+use chapter01::snapshot::{self, SnapshotError};
+use std::cell::{Ref, RefCell};
+use std::iter::FromIterator;
+use std::marker::PhantomData;
 use std::rc::{Rc, Weak};
 
 type Tree<T> = Option<Rc<BSTNode<T>>>;
 #[derive(Clone, Debug, Default)]
 pub struct BSTNode<T> {
     x: RefCell<T>,
+    size: RefCell<usize>,
     left: RefCell<Option<Rc<BSTNode<T>>>>,
     right: RefCell<Option<Rc<BSTNode<T>>>>,
     parent: RefCell<Option<Weak<BSTNode<T>>>>,
 }
 
+impl<T> SearchNode<T> for BSTNode<T> {
+    fn x(&self) -> Ref<'_, T> {
+        self.x.borrow()
+    }
+    fn left(&self) -> Option<Rc<Self>> {
+        self.left.borrow().clone()
+    }
+    fn right(&self) -> Option<Rc<Self>> {
+        self.right.borrow().clone()
+    }
+}
+
 #[derive(Clone, Debug, Default)]
 pub struct ScapegoatTree<T: PartialOrd + Clone> {
     n: usize,
@@ -19,6 +37,63 @@ pub struct ScapegoatTree<T: PartialOrd + Clone> {
     r: Option<Rc<BSTNode<T>>>,
 }
 
+pub struct Range<'a, T: PartialOrd + Clone> {
+    u: Tree<T>,
+    hi: T,
+    tree: PhantomData<&'a ScapegoatTree<T>>,
+}
+
+impl<'a, T: PartialOrd + Clone> Iterator for Range<'a, T> {
+    type Item = T;
+    fn next(&mut self) -> Option<T> {
+        let u = self.u.take()?;
+        let x = u.x.borrow().clone();
+        if x < self.hi {
+            self.u = ScapegoatTree::next_node(&u);
+            Some(x)
+        } else {
+            None
+        }
+    }
+}
+
+pub struct Iter<'a, T: PartialOrd + Clone> {
+    front: Tree<T>,
+    back: Tree<T>,
+    n: usize,
+    tree: PhantomData<&'a ScapegoatTree<T>>,
+}
+
+impl<'a, T: PartialOrd + Clone> Iterator for Iter<'a, T> {
+    type Item = T;
+    fn next(&mut self) -> Option<T> {
+        if self.n == 0 {
+            return None;
+        }
+        self.n -= 1;
+        let u = self.front.take()?;
+        self.front = ScapegoatTree::next_node(&u);
+        let x = u.x.borrow().clone();
+        Some(x)
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.n, Some(self.n))
+    }
+}
+
+impl<'a, T: PartialOrd + Clone> DoubleEndedIterator for Iter<'a, T> {
+    fn next_back(&mut self) -> Option<T> {
+        if self.n == 0 {
+            return None;
+        }
+        self.n -= 1;
+        let u = self.back.take()?;
+        self.back = ScapegoatTree::prev_node(&u);
+        let x = u.x.borrow().clone();
+        Some(x)
+    }
+}
+
 impl<T: PartialOrd + Clone> Drop for ScapegoatTree<T> {
     fn drop(&mut self) {
         while let Some(r) = self.r.clone() {
@@ -31,6 +106,7 @@ impl<T: Default> BSTNode<T> {
     pub fn new(x: T) -> Self {
         Self {
             x: RefCell::new(x),
+            size: RefCell::new(1),
             ..Default::default()
         }
     }
@@ -48,9 +124,134 @@ where
         }
     }
     fn size_u(u: &Tree<T>) -> usize {
-        match u {
-            Some(n) => 1 + Self::size_u(&n.left.borrow()) + Self::size_u(&n.right.borrow()),
-            None => 0,
+        u.as_ref().map(|u| *u.size.borrow()).unwrap_or(0)
+    }
+    fn update_size(u: &Rc<BSTNode<T>>) {
+        let s = 1 + Self::size_u(&u.left.borrow()) + Self::size_u(&u.right.borrow());
+        *u.size.borrow_mut() = s;
+    }
+    fn update_ancestors(u: Tree<T>, delta: isize) {
+        let mut w = u;
+        while let Some(u) = w {
+            let s = *u.size.borrow() as isize + delta;
+            *u.size.borrow_mut() = s as usize;
+            w = u.parent.borrow().as_ref().and_then(|p| p.upgrade());
+        }
+    }
+    pub fn select(&self, k: usize) -> Option<T> {
+        let mut w = self.r.clone();
+        let mut k = k;
+        while let Some(u) = w {
+            let l = Self::size_u(&u.left.borrow());
+            if k < l {
+                w = u.left.borrow().clone();
+            } else if k > l {
+                k -= l + 1;
+                w = u.right.borrow().clone();
+            } else {
+                let x = u.x.borrow().clone();
+                return Some(x);
+            }
+        }
+        None
+    }
+    pub fn rank(&self, x: &T) -> usize {
+        let mut w = self.r.clone();
+        let mut k = 0;
+        while let Some(u) = w {
+            if x > &*u.x.borrow() {
+                k += Self::size_u(&u.left.borrow()) + 1;
+                w = u.right.borrow().clone();
+            } else {
+                w = u.left.borrow().clone();
+            }
+        }
+        k
+    }
+    fn find_ceiling_node(&self, x: &T) -> Tree<T> {
+        let mut w = self.r.clone();
+        let mut z: Tree<T> = None;
+        let mut next;
+        loop {
+            match w {
+                Some(ref u) if x < &*u.x.borrow() => {
+                    z = w.clone();
+                    next = u.left.borrow().clone()
+                }
+                Some(ref u) if x > &*u.x.borrow() => next = u.right.borrow().clone(),
+                Some(_) => break w,
+                None => break z,
+            }
+            w = next;
+        }
+    }
+    fn next_node(u: &Rc<BSTNode<T>>) -> Tree<T> {
+        let mut w = u.right.borrow().clone();
+        if w.is_some() {
+            while let Some(v) = w.as_ref().and_then(|w| w.left.borrow().clone()) {
+                w = Some(v);
+            }
+            return w;
+        }
+        let mut w = u.clone();
+        loop {
+            let p = w.parent.borrow().as_ref().and_then(|p| p.upgrade());
+            match p {
+                Some(p) => {
+                    let left = p.left.borrow().clone();
+                    match left {
+                        Some(ref left) if Rc::ptr_eq(left, &w) => break Some(p),
+                        _ => w = p,
+                    }
+                }
+                None => break None,
+            }
+        }
+    }
+    fn prev_node(u: &Rc<BSTNode<T>>) -> Tree<T> {
+        let mut w = u.left.borrow().clone();
+        if w.is_some() {
+            while let Some(v) = w.as_ref().and_then(|w| w.right.borrow().clone()) {
+                w = Some(v);
+            }
+            return w;
+        }
+        let mut w = u.clone();
+        loop {
+            let p = w.parent.borrow().as_ref().and_then(|p| p.upgrade());
+            match p {
+                Some(p) => {
+                    let right = p.right.borrow().clone();
+                    match right {
+                        Some(ref right) if Rc::ptr_eq(right, &w) => break Some(p),
+                        _ => w = p,
+                    }
+                }
+                None => break None,
+            }
+        }
+    }
+    pub fn iter(&self) -> Iter<'_, T> {
+        let mut front = self.r.clone();
+        while let Some(u) = front.as_ref().and_then(|u| u.left.borrow().clone()) {
+            front = Some(u);
+        }
+        let mut back = self.r.clone();
+        while let Some(u) = back.as_ref().and_then(|u| u.right.borrow().clone()) {
+            back = Some(u);
+        }
+        Iter {
+            front,
+            back,
+            n: self.n,
+            tree: PhantomData,
+        }
+    }
+    pub fn range(&self, r: std::ops::Range<T>) -> Range<'_, T> {
+        Range {
+            u: self.find_ceiling_node(&r.start),
+            hi: r.end,
+            tree: PhantomData,
         }
     }
     fn find_last(&self, x: &T) -> Tree<T> {
@@ -89,6 +290,7 @@ where
             if let Some(right) = &*u.right.borrow() {
                 right.parent.borrow_mut().replace(Rc::downgrade(u));
             }
+            Self::update_size(u);
         }
         elem
     }
@@ -175,6 +377,8 @@ where
                     d += 1;
                 }
                 None => {
+                    let p = u.parent.borrow().as_ref().and_then(|p| p.upgrade());
+                    Self::update_ancestors(p, 1);
                     self.n += 1;
                     self.q += 1;
                     break d;
@@ -210,7 +414,7 @@ where
                 }
             }
         }
-        match (s, p) {
+        match (s, &p) {
             (Some(ref s), Some(ref p)) => {
                 s.parent.borrow_mut().replace(Rc::downgrade(p));
             }
@@ -219,6 +423,7 @@ where
             }
             _ => (),
         }
+        Self::update_ancestors(p, -1);
         self.n -= 1;
         Some(Rc::try_unwrap(u).ok().unwrap().x.into_inner())
     }
@@ -249,6 +454,24 @@ where
     }
 }
 
+impl<T: PartialOrd + Clone + Default + Codec> ScapegoatTree<T> {
+    pub fn dump(&self) -> Vec<u8> {
+        snapshot::dump(self.iter())
+    }
+    // a snapshot is sorted, so it goes straight into build_balanced
+    pub fn load(buf: &[u8]) -> Result<Self, SnapshotError> {
+        let a: Vec<Tree<T>> = snapshot::load(buf)?
+            .into_iter()
+            .map(|x| Some(Rc::new(BSTNode::new(x))))
+            .collect();
+        Ok(Self {
+            n: a.len(),
+            q: a.len(),
+            r: Self::build_balanced(&a, 0, a.len()),
+        })
+    }
+}
+
 impl<T> SSet<T> for ScapegoatTree<T>
 where
     T: PartialOrd + Clone + Default,
@@ -306,16 +529,81 @@ where
                 }
                 Some(ref u) if x > &*u.x.borrow() => next = u.right.borrow().clone(),
                 Some(ref u) if x == &*u.x.borrow() => break Some(u.x.borrow().clone()),
-                _ => {
-                    break match z {
-                        Some(z) => Some(z.x.borrow().clone()),
-                        None => None,
-                    }
-                }
+                _ => break z.map(|z| z.x.borrow().clone()),
             }
             w = next;
         }
     }
+    fn successor(&self, x: &T) -> Option<T> {
+        searchtree::successor(&self.r, x)
+    }
+    fn predecessor(&self, x: &T) -> Option<T> {
+        searchtree::predecessor(&self.r, x)
+    }
+    fn find_min(&self) -> Option<T> {
+        searchtree::find_min(&self.r)
+    }
+    fn find_max(&self) -> Option<T> {
+        searchtree::find_max(&self.r)
+    }
+    fn count_range(&self, r: std::ops::Range<T>) -> usize {
+        self.rank(&r.end).saturating_sub(self.rank(&r.start))
+    }
+}
+
+impl<'a, T: PartialOrd + Clone> IntoIterator for &'a ScapegoatTree<T> {
+    type Item = T;
+    type IntoIter = Iter<'a, T>;
+    fn into_iter(self) -> Iter<'a, T> {
+        self.iter()
+    }
+}
+
+impl<T: PartialOrd + Clone> IntoIterator for ScapegoatTree<T> {
+    type Item = T;
+    type IntoIter = std::vec::IntoIter<T>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter().collect::<Vec<_>>().into_iter()
+    }
+}
+
+impl<T> FromIterator<T> for ScapegoatTree<T>
+where
+    T: PartialOrd + Clone + Default,
+{
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut s = Self::new();
+        s.extend(iter);
+        s
+    }
+}
+
+impl<T> Extend<T> for ScapegoatTree<T>
+where
+    T: PartialOrd + Clone + Default,
+{
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for x in iter {
+            self.add(x);
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<T: PartialOrd + Clone + serde::Serialize> serde::Serialize for ScapegoatTree<T> {
+    fn serialize<S: serde::Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        s.collect_seq(self)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, T> serde::Deserialize<'de> for ScapegoatTree<T>
+where
+    T: PartialOrd + Clone + Default + serde::Deserialize<'de>,
+{
+    fn deserialize<D: serde::Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+        serde::Deserialize::deserialize(d).map(|xs: Vec<T>| xs.into_iter().collect())
+    }
 }
 
 #[cfg(test)]
@@ -389,6 +677,15 @@ mod test {
         assert_eq!(Some(4), scapegoattree.remove(&4));
         assert_eq!(Some(1), scapegoattree.remove(&1));
         println!("{:?}", scapegoattree);
+        assert_eq!(
+            vec![0, 3, 6, 7, 11],
+            scapegoattree.iter().collect::<Vec<_>>()
+        );
+        scapegoattree.extend(vec![5, 8]);
+        assert_eq!(
+            vec![11, 8, 7, 6, 5, 3, 0],
+            scapegoattree.into_iter().rev().collect::<Vec<_>>()
+        );
 
         let mut rng = thread_rng();
         let n = 200;
@@ -421,6 +718,40 @@ mod test {
                 let y2 = scapegoattree.find(&x);
                 assert_eq!(y1, y2);
             }
+            for _ in 0..n {
+                let x = rng.gen_range(0, 5 * n);
+                assert_eq!(redblacktree.successor(&x), scapegoattree.successor(&x));
+                assert_eq!(redblacktree.predecessor(&x), scapegoattree.predecessor(&x));
+                assert_eq!(redblacktree.floor(&x), scapegoattree.floor(&x));
+                assert_eq!(redblacktree.ceiling(&x), scapegoattree.ceiling(&x));
+            }
+            assert_eq!(redblacktree.find_min(), scapegoattree.find_min());
+            assert_eq!(redblacktree.find_max(), scapegoattree.find_max());
+            for _ in 0..n {
+                let x = rng.gen_range(0, 5 * n);
+                let y = x + rng.gen_range(0, n);
+                let r1 = redblacktree.range(x..y).collect::<Vec<_>>();
+                let r2 = scapegoattree.range(x..y).collect::<Vec<_>>();
+                assert_eq!(r1, r2);
+                assert_eq!(r1.len(), scapegoattree.count_range(x..y));
+            }
+            assert_eq!(
+                redblacktree.iter().collect::<Vec<_>>(),
+                scapegoattree.iter().collect::<Vec<_>>()
+            );
+            assert_eq!(
+                redblacktree.iter().rev().collect::<Vec<_>>(),
+                (&scapegoattree).into_iter().rev().collect::<Vec<_>>()
+            );
+            let v = redblacktree.iter().collect::<Vec<_>>();
+            for (k, x) in v.iter().enumerate() {
+                assert_eq!(Some(*x), scapegoattree.select(k));
+            }
+            assert_eq!(None, scapegoattree.select(v.len()));
+            for _ in 0..n {
+                let x = rng.gen_range(0, 5 * n);
+                assert_eq!(v.iter().filter(|y| **y < x).count(), scapegoattree.rank(&x));
+            }
         }
 
         // test large linked list for stack overflow.
@@ -430,5 +761,33 @@ mod test {
             bst.add(i);
         }
         println!("fin");
+        #[cfg(feature = "serde")]
+        {
+            let scapegoattree: ScapegoatTree<u32> = (0..100).rev().collect();
+            let json = serde_json::to_string(&scapegoattree).unwrap();
+            assert_eq!(
+                serde_json::from_str::<Vec<u32>>(&json).unwrap(),
+                (0..100).collect::<Vec<_>>()
+            );
+            let copy: ScapegoatTree<u32> = serde_json::from_str(&json).unwrap();
+            assert!(copy.iter().eq(scapegoattree.iter()));
+            assert_eq!((copy.size(), copy.find(&50)), (100, Some(50)));
+        }
+        // a loaded tree starts out perfectly balanced
+        let scapegoattree: ScapegoatTree<i32> = (0..1000).rev().collect();
+        let buf = scapegoattree.dump();
+        let mut copy = ScapegoatTree::<i32>::load(&buf).unwrap();
+        assert!(copy.iter().eq(scapegoattree.iter()));
+        let r = copy.r.clone().unwrap();
+        assert_eq!(*r.size.borrow(), 1000);
+        assert_eq!(*r.x.borrow(), 500);
+        for x in 1000..1100 {
+            assert!(copy.add(x));
+        }
+        assert_eq!(copy.remove(&500), Some(500));
+        assert_eq!(copy.size(), 1099);
+        for n in 0..buf.len() {
+            assert!(ScapegoatTree::<i32>::load(&buf[..n]).is_err());
+        }
     }
 }
@@ -1,38 +1,108 @@
 #![allow(clippy::many_single_char_names,clippy::explicit_counter_loop, clippy::redundant_closure)]
-use chapter01::interface::SSet;
-use std::cell::RefCell;
+use chapter01::codec::Codec;
+use chapter01::interface::{Lent, Pair, SSet, SSetMap, USetMap};
+use chapter01::searchtree::{self, SearchNode};
+use chapter01::snapshot::{self, SnapshotError};
+use std::cell::{Ref, RefCell};
+use std::iter::FromIterator;
+use std::marker::PhantomData;
 use std::rc::{Rc, Weak};
 
-#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd, Default)]
 pub enum Color {
-    Red,    // 0
+    #[default]
+    Red, // 0
     Black,  // 1
     WBlack, // 2
 }
 
-impl Default for Color {
-    fn default() -> Self {
-        Color::Red
-    }
-}
-
 type Tree<T> = Option<Rc<RBTNode<T>>>;
 
 #[derive(Clone, Debug, Default)]
 pub struct RBTNode<T> {
     color: RefCell<Color>,
     x: RefCell<T>,
+    size: RefCell<usize>,
     left: RefCell<Option<Rc<RBTNode<T>>>>,
     right: RefCell<Option<Rc<RBTNode<T>>>>,
     parent: RefCell<Option<Weak<RBTNode<T>>>>,
 }
 
+impl<T> SearchNode<T> for RBTNode<T> {
+    fn x(&self) -> Ref<'_, T> {
+        self.x.borrow()
+    }
+    fn left(&self) -> Option<Rc<Self>> {
+        self.left.borrow().clone()
+    }
+    fn right(&self) -> Option<Rc<Self>> {
+        self.right.borrow().clone()
+    }
+}
+
 #[derive(Clone, Debug, Default)]
 pub struct RedBlackTree<T: PartialOrd + Clone> {
     n: usize,
     r: Option<Rc<RBTNode<T>>>,
 }
 
+pub struct Range<'a, T: PartialOrd + Clone> {
+    u: Tree<T>,
+    hi: T,
+    tree: PhantomData<&'a RedBlackTree<T>>,
+}
+
+impl<'a, T: PartialOrd + Clone> Iterator for Range<'a, T> {
+    type Item = T;
+    fn next(&mut self) -> Option<T> {
+        let u = self.u.take()?;
+        let x = u.x.borrow().clone();
+        if x < self.hi {
+            self.u = RedBlackTree::next_node(&u);
+            Some(x)
+        } else {
+            None
+        }
+    }
+}
+
+pub struct Iter<'a, T: PartialOrd + Clone> {
+    front: Tree<T>,
+    back: Tree<T>,
+    n: usize,
+    tree: PhantomData<&'a RedBlackTree<T>>,
+}
+
+impl<'a, T: PartialOrd + Clone> Iterator for Iter<'a, T> {
+    type Item = T;
+    fn next(&mut self) -> Option<T> {
+        if self.n == 0 {
+            return None;
+        }
+        self.n -= 1;
+        let u = self.front.take()?;
+        self.front = RedBlackTree::next_node(&u);
+        let x = u.x.borrow().clone();
+        Some(x)
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.n, Some(self.n))
+    }
+}
+
+impl<'a, T: PartialOrd + Clone> DoubleEndedIterator for Iter<'a, T> {
+    fn next_back(&mut self) -> Option<T> {
+        if self.n == 0 {
+            return None;
+        }
+        self.n -= 1;
+        let u = self.back.take()?;
+        self.back = RedBlackTree::prev_node(&u);
+        let x = u.x.borrow().clone();
+        Some(x)
+    }
+}
+
 impl<T: PartialOrd + Clone> Drop for RedBlackTree<T> {
     fn drop(&mut self) {
         while let Some(r) = self.r.clone() {
@@ -45,6 +115,7 @@ impl<T: Default> RBTNode<T> {
     pub fn new(x: T) -> Self {
         Self {
             x: RefCell::new(x),
+            size: RefCell::new(1),
             ..Default::default()
         }
     }
@@ -54,6 +125,137 @@ impl<T: PartialOrd + Clone> RedBlackTree<T> {
     pub fn new() -> Self {
         Self { n: 0, r: None }
     }
+    fn size_u(u: &Tree<T>) -> usize {
+        u.as_ref().map(|u| *u.size.borrow()).unwrap_or(0)
+    }
+    fn update_size(u: &Rc<RBTNode<T>>) {
+        let s = 1 + Self::size_u(&u.left.borrow()) + Self::size_u(&u.right.borrow());
+        *u.size.borrow_mut() = s;
+    }
+    fn update_ancestors(u: Tree<T>, delta: isize) {
+        let mut w = u;
+        while let Some(u) = w {
+            let s = *u.size.borrow() as isize + delta;
+            *u.size.borrow_mut() = s as usize;
+            w = u.parent.borrow().as_ref().and_then(|p| p.upgrade());
+        }
+    }
+    pub fn select(&self, k: usize) -> Option<T> {
+        let mut w = self.r.clone();
+        let mut k = k;
+        while let Some(u) = w {
+            let l = Self::size_u(&u.left.borrow());
+            if k < l {
+                w = u.left.borrow().clone();
+            } else if k > l {
+                k -= l + 1;
+                w = u.right.borrow().clone();
+            } else {
+                let x = u.x.borrow().clone();
+                return Some(x);
+            }
+        }
+        None
+    }
+    pub fn rank(&self, x: &T) -> usize {
+        let mut w = self.r.clone();
+        let mut k = 0;
+        while let Some(u) = w {
+            if x > &*u.x.borrow() {
+                k += Self::size_u(&u.left.borrow()) + 1;
+                w = u.right.borrow().clone();
+            } else {
+                w = u.left.borrow().clone();
+            }
+        }
+        k
+    }
+    fn find_ceiling_node(&self, x: &T) -> Tree<T> {
+        let mut w = self.r.clone();
+        let mut z: Tree<T> = None;
+        let mut next;
+        loop {
+            match w {
+                Some(ref u) if x < &*u.x.borrow() => {
+                    z = w.clone();
+                    next = u.left.borrow().clone()
+                }
+                Some(ref u) if x > &*u.x.borrow() => next = u.right.borrow().clone(),
+                Some(_) => break w,
+                None => break z,
+            }
+            w = next;
+        }
+    }
+    fn next_node(u: &Rc<RBTNode<T>>) -> Tree<T> {
+        let mut w = u.right.borrow().clone();
+        if w.is_some() {
+            while let Some(v) = w.as_ref().and_then(|w| w.left.borrow().clone()) {
+                w = Some(v);
+            }
+            return w;
+        }
+        let mut w = u.clone();
+        loop {
+            let p = w.parent.borrow().as_ref().and_then(|p| p.upgrade());
+            match p {
+                Some(p) => {
+                    let left = p.left.borrow().clone();
+                    match left {
+                        Some(ref left) if Rc::ptr_eq(left, &w) => break Some(p),
+                        _ => w = p,
+                    }
+                }
+                None => break None,
+            }
+        }
+    }
+    fn prev_node(u: &Rc<RBTNode<T>>) -> Tree<T> {
+        let mut w = u.left.borrow().clone();
+        if w.is_some() {
+            while let Some(v) = w.as_ref().and_then(|w| w.right.borrow().clone()) {
+                w = Some(v);
+            }
+            return w;
+        }
+        let mut w = u.clone();
+        loop {
+            let p = w.parent.borrow().as_ref().and_then(|p| p.upgrade());
+            match p {
+                Some(p) => {
+                    let right = p.right.borrow().clone();
+                    match right {
+                        Some(ref right) if Rc::ptr_eq(right, &w) => break Some(p),
+                        _ => w = p,
+                    }
+                }
+                None => break None,
+            }
+        }
+    }
+    pub fn iter(&self) -> Iter<'_, T> {
+        let mut front = self.r.clone();
+        while let Some(u) = front.as_ref().and_then(|u| u.left.borrow().clone()) {
+            front = Some(u);
+        }
+        let mut back = self.r.clone();
+        while let Some(u) = back.as_ref().and_then(|u| u.right.borrow().clone()) {
+            back = Some(u);
+        }
+        Iter {
+            front,
+            back,
+            n: self.n,
+            tree: PhantomData,
+        }
+    }
+    pub fn range(&self, r: std::ops::Range<T>) -> Range<'_, T> {
+        Range {
+            u: self.find_ceiling_node(&r.start),
+            hi: r.end,
+            tree: PhantomData,
+        }
+    }
     fn find_last(&self, x: &T) -> Tree<T> {
         let mut w = self.r.clone();
         let mut prev = None;
@@ -96,6 +298,8 @@ impl<T: PartialOrd + Clone> RedBlackTree<T> {
         }
         u.parent.borrow_mut().replace(Rc::downgrade(&w));
         w.left.borrow_mut().replace(u.clone());
+        Self::update_size(u);
+        Self::update_size(&w);
         if Rc::ptr_eq(u, self.r.as_ref().unwrap()) {
             self.r.replace(w);
         }
@@ -121,6 +325,8 @@ impl<T: PartialOrd + Clone> RedBlackTree<T> {
         }
         u.parent.borrow_mut().replace(Rc::downgrade(&w));
         w.right.borrow_mut().replace(u.clone());
+        Self::update_size(u);
+        Self::update_size(&w);
         if Rc::ptr_eq(u, self.r.as_ref().unwrap()) {
             self.r.replace(w);
         }
@@ -195,6 +401,7 @@ impl<T: PartialOrd + Clone> RedBlackTree<T> {
                     return false;
                 }
                 u.parent.borrow_mut().replace(Rc::downgrade(p));
+                Self::update_ancestors(Some(p.clone()), 1);
             }
             None => self.r = Some(u),
         }
@@ -265,7 +472,7 @@ impl<T: PartialOrd + Clone> RedBlackTree<T> {
                 }
             }
         }
-        match (s, p) {
+        match (s, &p) {
             (Some(ref s), Some(ref p)) => {
                 s.parent.borrow_mut().replace(Rc::downgrade(p));
             }
@@ -274,6 +481,7 @@ impl<T: PartialOrd + Clone> RedBlackTree<T> {
             }
             _ => (),
         }
+        Self::update_ancestors(p, -1);
         self.n -= 1;
         Some(Rc::try_unwrap(u).ok().unwrap().x.into_inner())
     }
@@ -496,6 +704,61 @@ impl<T: PartialOrd + Clone> RedBlackTree<T> {
     }
 }
 
+impl<T: PartialOrd + Clone + Default + Codec> RedBlackTree<T> {
+    pub fn dump(&self) -> Vec<u8> {
+        snapshot::dump(self.iter())
+    }
+    // a snapshot is sorted, so the tree is built in one pass without any rotations
+    pub fn load(buf: &[u8]) -> Result<Self, SnapshotError> {
+        let mut xs: Vec<Option<T>> = snapshot::load(buf)?.into_iter().map(Some).collect();
+        let n = xs.len();
+        let deepest = (usize::BITS - 1).saturating_sub(n.leading_zeros()) as usize;
+        Ok(Self {
+            n,
+            r: Self::build_sorted(&mut xs, 0, deepest),
+        })
+    }
+    // halving with the larger half on the left leaves every leaf on the last two
+    // levels and no right child without a left sibling, so colouring the deepest
+    // level red gives equal black heights and a left-leaning tree
+    fn build_sorted(xs: &mut [Option<T>], depth: usize, deepest: usize) -> Tree<T> {
+        if xs.is_empty() {
+            return None;
+        }
+        let m = xs.len() / 2;
+        let u = Rc::new(RBTNode::new(xs[m].take().unwrap()));
+        let (l, r) = xs.split_at_mut(m);
+        *u.left.borrow_mut() = Self::build_sorted(l, depth + 1, deepest);
+        *u.right.borrow_mut() = Self::build_sorted(&mut r[1..], depth + 1, deepest);
+        for c in [&*u.left.borrow(), &*u.right.borrow()]
+            .iter()
+            .copied()
+            .flatten()
+        {
+            c.parent.borrow_mut().replace(Rc::downgrade(&u));
+        }
+        Self::update_size(&u);
+        if depth == 0 || depth < deepest {
+            *u.color.borrow_mut() = Color::Black;
+        }
+        Some(u)
+    }
+}
+
+impl<T: PartialOrd + Clone + Default> RedBlackTree<T> {
+    // adds x, or puts it in the place of the element equal to it and returns that one
+    pub fn replace(&mut self, x: T) -> Option<T> {
+        let p = self.find_last(&x);
+        if let Some(p) = p.as_ref().filter(|p| *p.x.borrow() == x) {
+            return Some(std::mem::replace(&mut *p.x.borrow_mut(), x));
+        }
+        let u = Rc::new(RBTNode::new(x));
+        self.add_child(&p, u.clone());
+        self.add_fixup(u);
+        None
+    }
+}
+
 impl<T> SSet<T> for RedBlackTree<T>
 where
     T: PartialOrd + Clone + Default,
@@ -566,22 +829,177 @@ where
                 }
                 Some(ref u) if x > &*u.x.borrow() => next = u.right.borrow().clone(),
                 Some(ref u) if x == &*u.x.borrow() => break Some(u.x.borrow().clone()),
-                _ => {
-                    break match z {
-                        Some(z) => Some(z.x.borrow().clone()),
-                        None => None,
-                    }
-                }
+                _ => break z.map(|z| z.x.borrow().clone()),
             }
             w = next;
         }
     }
+    fn successor(&self, x: &T) -> Option<T> {
+        searchtree::successor(&self.r, x)
+    }
+    fn predecessor(&self, x: &T) -> Option<T> {
+        searchtree::predecessor(&self.r, x)
+    }
+    fn find_min(&self) -> Option<T> {
+        searchtree::find_min(&self.r)
+    }
+    fn find_max(&self) -> Option<T> {
+        searchtree::find_max(&self.r)
+    }
+    fn count_range(&self, r: std::ops::Range<T>) -> usize {
+        self.rank(&r.end).saturating_sub(self.rank(&r.start))
+    }
+}
+
+impl<'a, T: PartialOrd + Clone> IntoIterator for &'a RedBlackTree<T> {
+    type Item = T;
+    type IntoIter = Iter<'a, T>;
+    fn into_iter(self) -> Iter<'a, T> {
+        self.iter()
+    }
+}
+
+impl<T: PartialOrd + Clone> IntoIterator for RedBlackTree<T> {
+    type Item = T;
+    type IntoIter = std::vec::IntoIter<T>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter().collect::<Vec<_>>().into_iter()
+    }
+}
+
+impl<T> FromIterator<T> for RedBlackTree<T>
+where
+    T: PartialOrd + Clone + Default,
+{
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut s = Self::new();
+        s.extend(iter);
+        s
+    }
+}
+
+impl<T> Extend<T> for RedBlackTree<T>
+where
+    T: PartialOrd + Clone + Default,
+{
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for x in iter {
+            self.add(x);
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<T: PartialOrd + Clone + serde::Serialize> serde::Serialize for RedBlackTree<T> {
+    fn serialize<S: serde::Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        s.collect_seq(self)
+    }
+}
+
+// colours are not stored; adding the elements again recolours the tree
+#[cfg(feature = "serde")]
+impl<'de, T> serde::Deserialize<'de> for RedBlackTree<T>
+where
+    T: PartialOrd + Clone + Default + serde::Deserialize<'de>,
+{
+    fn deserialize<D: serde::Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+        serde::Deserialize::deserialize(d).map(|xs: Vec<T>| xs.into_iter().collect())
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct RedBlackTreeMap<K, V>
+where
+    K: PartialOrd + Clone + Default,
+    V: Clone,
+{
+    t: RedBlackTree<Pair<K, V>>,
+}
+
+impl<K, V> RedBlackTreeMap<K, V>
+where
+    K: PartialOrd + Clone + Default,
+    V: Clone,
+{
+    pub fn new() -> Self {
+        Self {
+            t: RedBlackTree::new(),
+        }
+    }
+}
+
+impl<K, V> Default for RedBlackTreeMap<K, V>
+where
+    K: PartialOrd + Clone + Default,
+    V: Clone,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K, V> USetMap<K, V> for RedBlackTreeMap<K, V>
+where
+    K: PartialOrd + Clone + Default,
+    V: Clone,
+{
+    type ValueMut<'a>
+        = Lent<'a, V>
+    where
+        Self: 'a;
+    fn size(&self) -> usize {
+        self.t.size()
+    }
+    fn insert(&mut self, k: K, v: V) -> Option<V> {
+        self.t.replace(Pair::new(k, v)).and_then(|p| p.v)
+    }
+    fn get(&self, k: &K) -> Option<V> {
+        self.t
+            .find(&Pair::probe(k.clone()))
+            .filter(|p| p.k == *k)
+            .and_then(|p| p.v)
+    }
+    fn get_mut(&mut self, k: &K) -> Option<Lent<'_, V>> {
+        let u = self
+            .t
+            .find_last(&Pair::probe(k.clone()))
+            .filter(|u| u.x.borrow().k == *k)?;
+        let v = u.x.borrow_mut().v.take()?;
+        Some(Lent::new(v, move |v| u.x.borrow_mut().v = Some(v)))
+    }
+    fn remove(&mut self, k: &K) -> Option<V> {
+        self.t.remove(&Pair::probe(k.clone())).and_then(|p| p.v)
+    }
+}
+
+impl<K, V> SSetMap<K, V> for RedBlackTreeMap<K, V>
+where
+    K: PartialOrd + Clone + Default,
+    V: Clone,
+{
+    fn successor(&self, k: &K) -> Option<(K, V)> {
+        self.t
+            .successor(&Pair::probe(k.clone()))
+            .and_then(Pair::into_tuple)
+    }
+    fn predecessor(&self, k: &K) -> Option<(K, V)> {
+        self.t
+            .predecessor(&Pair::probe(k.clone()))
+            .and_then(Pair::into_tuple)
+    }
+    fn find_min(&self) -> Option<(K, V)> {
+        self.t.find_min().and_then(Pair::into_tuple)
+    }
+    fn find_max(&self) -> Option<(K, V)> {
+        self.t.find_max().and_then(Pair::into_tuple)
+    }
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
     use chapter01::interface::SSet;
+    use chapter01::testing::check_ssetmap;
     use chapter04::skiplistsset::SkiplistSSet;
     use rand::{thread_rng, Rng};
     #[test]
@@ -642,6 +1060,13 @@ mod test {
         assert_eq!(redblacktree.remove(&8), Some(8));
         //println!("{:#?}", redblacktree);
         assert!(redblacktree.is_a_valid_red_black_tree());
+        assert_eq!(vec![10, 15], redblacktree.iter().collect::<Vec<_>>());
+        let redblacktree: RedBlackTree<usize> = (0..20).rev().collect();
+        assert!(redblacktree.is_a_valid_red_black_tree());
+        assert_eq!(
+            (0..20).collect::<Vec<_>>(),
+            redblacktree.into_iter().collect::<Vec<_>>()
+        );
         let mut rng = thread_rng();
         let n = 200;
         let mut redblacktree = RedBlackTree::<i32>::new();
@@ -674,6 +1099,40 @@ mod test {
                 let y2 = redblacktree.find(&x);
                 assert_eq!(y1, y2);
             }
+            for _ in 0..n {
+                let x = rng.gen_range(0, 5 * n);
+                assert_eq!(set.successor(&x), redblacktree.successor(&x));
+                assert_eq!(set.predecessor(&x), redblacktree.predecessor(&x));
+                assert_eq!(set.floor(&x), redblacktree.floor(&x));
+                assert_eq!(set.ceiling(&x), redblacktree.ceiling(&x));
+            }
+            assert_eq!(set.find_min(), redblacktree.find_min());
+            assert_eq!(set.find_max(), redblacktree.find_max());
+            for _ in 0..n {
+                let x = rng.gen_range(0, 5 * n);
+                let y = x + rng.gen_range(0, n);
+                let r1 = set.range(x..y).collect::<Vec<_>>();
+                let r2 = redblacktree.range(x..y).collect::<Vec<_>>();
+                assert_eq!(r1, r2);
+                assert_eq!(r1.len(), redblacktree.count_range(x..y));
+            }
+            assert_eq!(
+                set.iter().collect::<Vec<_>>(),
+                redblacktree.iter().collect::<Vec<_>>()
+            );
+            assert_eq!(
+                set.iter().rev().collect::<Vec<_>>(),
+                (&redblacktree).into_iter().rev().collect::<Vec<_>>()
+            );
+            let v = set.iter().collect::<Vec<_>>();
+            for (k, x) in v.iter().enumerate() {
+                assert_eq!(Some(*x), redblacktree.select(k));
+            }
+            assert_eq!(None, redblacktree.select(v.len()));
+            for _ in 0..n {
+                let x = rng.gen_range(0, 5 * n);
+                assert_eq!(v.iter().filter(|y| **y < x).count(), redblacktree.rank(&x));
+            }
         }
 
         let n = 3;
@@ -712,5 +1171,44 @@ mod test {
             bst.add(i);
         }
         println!("fin");
+        #[cfg(feature = "serde")]
+        {
+            let redblacktree: RedBlackTree<usize> = (0..100).collect();
+            let json = serde_json::to_string(&redblacktree).unwrap();
+            assert_eq!(
+                serde_json::from_str::<Vec<usize>>(&json).unwrap(),
+                (0..100).collect::<Vec<_>>()
+            );
+            let copy: RedBlackTree<usize> = serde_json::from_str(&json).unwrap();
+            assert!(copy.iter().eq(redblacktree.iter()));
+            assert!(copy.is_a_valid_red_black_tree());
+            assert_eq!(copy.size(), 100);
+        }
+        // snapshots load into valid trees of every shape
+        for n in 0..130 {
+            let redblacktree: RedBlackTree<usize> = (0..n).map(|x| 2 * x).collect();
+            let buf = redblacktree.dump();
+            let mut copy = RedBlackTree::<usize>::load(&buf).unwrap();
+            assert!(copy.is_a_valid_red_black_tree());
+            assert!(copy.iter().eq(redblacktree.iter()));
+            assert_eq!(copy.size(), n);
+            assert_eq!(copy.select(n / 2), redblacktree.select(n / 2));
+            assert_eq!(copy.rank(&n), redblacktree.rank(&n));
+            copy.add(n + 1);
+            copy.remove(&0);
+            assert!(copy.is_a_valid_red_black_tree());
+            if n > 0 {
+                assert!(RedBlackTree::<usize>::load(&buf[..buf.len() - 1]).is_err());
+            }
+        }
+        assert_eq!(
+            RedBlackTree::<usize>::load(b"no snapshot").err(),
+            Some(chapter01::snapshot::SnapshotError::BadMagic)
+        );
+    }
+
+    #[test]
+    fn test_redblacktreemap() {
+        check_ssetmap(RedBlackTreeMap::<i32, usize>::new);
     }
 }
@@ -1,5 +1,6 @@
 #![allow(clippy::many_single_char_names,clippy::explicit_counter_loop, clippy::redundant_closure)]
 use chapter01::interface::Queue;
+use std::iter::FromIterator;
 
 #[derive(Clone, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
 pub struct BinaryHeap<T> {
@@ -7,11 +8,43 @@ pub struct BinaryHeap<T> {
     n: usize,
 }
 
+pub struct Iter<'a, T>(std::slice::Iter<'a, Option<T>>);
+
+impl<'a, T> Iterator for Iter<'a, T> {
+    type Item = &'a T;
+    fn next(&mut self) -> Option<&'a T> {
+        self.0.next().and_then(|x| x.as_ref())
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+pub struct IntoIter<T>(BinaryHeap<T>);
+
+impl<T: PartialOrd> Iterator for IntoIter<T> {
+    type Item = T;
+    fn next(&mut self) -> Option<T> {
+        if self.0.n == 0 {
+            None
+        } else {
+            self.0.remove()
+        }
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.0.n, Some(self.0.n))
+    }
+}
+
 impl<T: PartialOrd> BinaryHeap<T> {
     pub fn length(&self) -> usize {
         self.a.len()
     }
 
+    pub fn iter(&self) -> Iter<'_, T> {
+        Iter(self.a[..self.n].iter())
+    }
+
     pub fn new() -> Self {
         Self::with_length(1)
     }
@@ -124,6 +157,9 @@ impl<T: PartialOrd> Queue<T> for BinaryHeap<T> {
     }
 
     fn remove(&mut self) -> Option<T> {
+        if self.n == 0 {
+            return None;
+        }
         let x = self.a.get_mut(0)?.take();
         self.a[0] = self.a.get_mut(self.n - 1)?.take();
         self.n -= 1;
@@ -135,6 +171,53 @@ impl<T: PartialOrd> Queue<T> for BinaryHeap<T> {
     }
 }
 
+impl<'a, T: PartialOrd> IntoIterator for &'a BinaryHeap<T> {
+    type Item = &'a T;
+    type IntoIter = Iter<'a, T>;
+    fn into_iter(self) -> Iter<'a, T> {
+        self.iter()
+    }
+}
+
+impl<T: PartialOrd> IntoIterator for BinaryHeap<T> {
+    type Item = T;
+    type IntoIter = IntoIter<T>;
+    fn into_iter(self) -> IntoIter<T> {
+        IntoIter(self)
+    }
+}
+
+impl<T: PartialOrd> FromIterator<T> for BinaryHeap<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut h = Self::new();
+        h.extend(iter);
+        h
+    }
+}
+
+impl<T: PartialOrd> Extend<T> for BinaryHeap<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for x in iter {
+            self.add(x);
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<T: PartialOrd + serde::Serialize> serde::Serialize for BinaryHeap<T> {
+    fn serialize<S: serde::Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        s.collect_seq(self)
+    }
+}
+
+// the input is not trusted to be in heap order, so the heap is rebuilt by adding
+#[cfg(feature = "serde")]
+impl<'de, T: PartialOrd + serde::Deserialize<'de>> serde::Deserialize<'de> for BinaryHeap<T> {
+    fn deserialize<D: serde::Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+        serde::Deserialize::deserialize(d).map(|xs: Vec<T>| xs.into_iter().collect())
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -155,7 +238,7 @@ mod test {
         binaryheap.add(93);
         binaryheap.add(55);
         binaryheap.add(6);
-        assert_eq!(&Some(4), binaryheap.a.get(0).unwrap());
+        assert_eq!(&Some(4), binaryheap.a.first().unwrap());
         assert_eq!(&Some(9), binaryheap.a.get(1).unwrap());
         assert_eq!(&Some(6), binaryheap.a.get(2).unwrap());
         assert_eq!(&Some(17), binaryheap.a.get(3).unwrap());
@@ -169,7 +252,7 @@ mod test {
         assert_eq!(&Some(55), binaryheap.a.get(11).unwrap());
         assert_eq!(&Some(50), binaryheap.a.get(12).unwrap());
         assert_eq!(Some(4), binaryheap.remove());
-        assert_eq!(&Some(6), binaryheap.a.get(0).unwrap());
+        assert_eq!(&Some(6), binaryheap.a.first().unwrap());
         assert_eq!(&Some(9), binaryheap.a.get(1).unwrap());
         assert_eq!(&Some(8), binaryheap.a.get(2).unwrap());
         assert_eq!(&Some(17), binaryheap.a.get(3).unwrap());
@@ -181,6 +264,27 @@ mod test {
         assert_eq!(&Some(32), binaryheap.a.get(9).unwrap());
         assert_eq!(&Some(93), binaryheap.a.get(10).unwrap());
         assert_eq!(&Some(55), binaryheap.a.get(11).unwrap());
+        assert_eq!(
+            vec![6, 9, 8, 17, 26, 50, 16, 19, 69, 32, 93, 55],
+            binaryheap.iter().copied().collect::<Vec<_>>()
+        );
+        binaryheap.extend(vec![1, 100]);
+        assert_eq!(
+            vec![1, 6, 8, 9, 16, 17, 19, 26, 32, 50, 55, 69, 93, 100],
+            binaryheap.into_iter().collect::<Vec<_>>()
+        );
+        let binaryheap: BinaryHeap<usize> = vec![3, 1, 2].into_iter().collect();
+        assert_eq!(Some(&1), binaryheap.iter().next());
         //println!("{:?}", binaryheap);
+        #[cfg(feature = "serde")]
+        {
+            let binaryheap: BinaryHeap<usize> = vec![5, 3, 9, 1, 7].into_iter().collect();
+            let json = serde_json::to_string(&binaryheap).unwrap();
+            assert_eq!(json, "[1,3,9,5,7]");
+            let copy: BinaryHeap<usize> = serde_json::from_str(&json).unwrap();
+            assert_eq!(copy.into_iter().collect::<Vec<_>>(), vec![1, 3, 5, 7, 9]);
+            let copy: BinaryHeap<usize> = serde_json::from_str("[9,7,5,3,1]").unwrap();
+            assert_eq!(copy.into_iter().collect::<Vec<_>>(), vec![1, 3, 5, 7, 9]);
+        }
     }
 }
@@ -1,6 +1,10 @@
 #![allow(clippy::many_single_char_names,clippy::explicit_counter_loop, clippy::redundant_closure)]
 use chapter01::interface::Queue;
+use chapter01::rng::Random;
+use rand::{Rng, RngCore};
 use std::cell::RefCell;
+use std::iter::FromIterator;
+use std::marker::PhantomData;
 use std::rc::{Rc, Weak};
 
 type Tree<T> = Option<Rc<MHNode<T>>>;
@@ -17,6 +21,39 @@ pub struct MHNode<T> {
 pub struct MeldableHeap<T: PartialOrd + Clone> {
     n: usize,
     r: Option<Rc<MHNode<T>>>,
+    rng: Random,
+}
+
+pub struct Iter<'a, T: PartialOrd + Clone> {
+    stack: Vec<Rc<MHNode<T>>>,
+    heap: PhantomData<&'a MeldableHeap<T>>,
+}
+
+impl<'a, T: PartialOrd + Clone> Iterator for Iter<'a, T> {
+    type Item = T;
+    fn next(&mut self) -> Option<T> {
+        let u = self.stack.pop()?;
+        if let Some(right) = u.right.borrow().clone() {
+            self.stack.push(right);
+        }
+        if let Some(left) = u.left.borrow().clone() {
+            self.stack.push(left);
+        }
+        let x = u.x.borrow().clone();
+        Some(x)
+    }
+}
+
+pub struct IntoIter<T: PartialOrd + Clone>(MeldableHeap<T>);
+
+impl<T: PartialOrd + Clone + Default> Iterator for IntoIter<T> {
+    type Item = T;
+    fn next(&mut self) -> Option<T> {
+        self.0.remove()
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.0.n, Some(self.0.n))
+    }
 }
 
 impl<T: PartialOrd + Clone> Drop for MeldableHeap<T> {
@@ -38,25 +75,39 @@ impl<T: Default> MHNode<T> {
 
 impl<T: PartialOrd + Clone> MeldableHeap<T> {
     pub fn new() -> Self {
-        Self { n: 0, r: None }
+        Self::with_rng(rand::thread_rng())
     }
-    fn merge(h1: Tree<T>, h2: Tree<T>) -> Tree<T> {
+    pub fn with_rng<R: RngCore>(rng: R) -> Self {
+        Self {
+            n: 0,
+            r: None,
+            rng: Random::with_rng(rng),
+        }
+    }
+    pub fn with_seed(seed: u64) -> Self {
+        Self {
+            n: 0,
+            r: None,
+            rng: Random::with_seed(seed),
+        }
+    }
+    fn merge(rng: &mut Random, h1: Tree<T>, h2: Tree<T>) -> Tree<T> {
         match (h1, h2) {
             (None, h2) => h2,
             (h1, None) => h1,
             (Some(h1), Some(h2)) if *h1.x.borrow() > *h2.x.borrow() => {
-                Self::merge(Some(h2), Some(h1))
+                Self::merge(rng, Some(h2), Some(h1))
             }
             (Some(h1), Some(h2)) => {
-                if rand::random::<bool>() {
-                    let u = Self::merge(h1.left.borrow().clone(), Some(h2));
+                if rng.gen::<bool>() {
+                    let u = Self::merge(rng, h1.left.borrow().clone(), Some(h2));
                     *h1.left.borrow_mut() = u;
                     if let Some(left) = &*h1.left.borrow() {
                         left.parent.borrow_mut().replace(Rc::downgrade(&h1));
                     }
                     Some(h1)
                 } else {
-                    let u = Self::merge(h1.right.borrow().clone(), Some(h2));
+                    let u = Self::merge(rng, h1.right.borrow().clone(), Some(h2));
                     *h1.right.borrow_mut() = u;
                     if let Some(right) = &*h1.right.borrow() {
                         right.parent.borrow_mut().replace(Rc::downgrade(&h1));
@@ -66,6 +117,12 @@ impl<T: PartialOrd + Clone> MeldableHeap<T> {
             }
         }
     }
+    pub fn iter(&self) -> Iter<'_, T> {
+        Iter {
+            stack: self.r.iter().cloned().collect(),
+            heap: PhantomData,
+        }
+    }
     pub fn find_min(&self) -> Option<T> {
         self.r.as_ref().map(|r| r.x.borrow().clone())
     }
@@ -116,13 +173,14 @@ where
 {
     fn add(&mut self, x: T) {
         let u = Rc::new(MHNode::new(x));
-        self.r = Self::merge(Some(u), self.r.clone());
+        self.r = Self::merge(&mut self.rng, Some(u), self.r.clone());
         self.r.as_ref().and_then(|r| r.parent.borrow_mut().take());
         self.n += 1;
     }
     fn remove(&mut self) -> Option<T> {
         let u = self.r.take();
         self.r = Self::merge(
+            &mut self.rng,
             u.as_ref().and_then(|r| r.left.borrow_mut().take()),
             u.as_ref().and_then(|r| r.right.borrow_mut().take()),
         );
@@ -134,6 +192,54 @@ where
     }
 }
 
+impl<'a, T: PartialOrd + Clone> IntoIterator for &'a MeldableHeap<T> {
+    type Item = T;
+    type IntoIter = Iter<'a, T>;
+    fn into_iter(self) -> Iter<'a, T> {
+        self.iter()
+    }
+}
+
+impl<T: PartialOrd + Clone + Default> IntoIterator for MeldableHeap<T> {
+    type Item = T;
+    type IntoIter = IntoIter<T>;
+    fn into_iter(self) -> IntoIter<T> {
+        IntoIter(self)
+    }
+}
+
+impl<T: PartialOrd + Clone + Default> FromIterator<T> for MeldableHeap<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut h = Self::new();
+        h.extend(iter);
+        h
+    }
+}
+
+impl<T: PartialOrd + Clone + Default> Extend<T> for MeldableHeap<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for x in iter {
+            self.add(x);
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<T: PartialOrd + Clone + serde::Serialize> serde::Serialize for MeldableHeap<T> {
+    fn serialize<S: serde::Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        s.collect_seq(self)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, T: PartialOrd + Clone + Default + serde::Deserialize<'de>> serde::Deserialize<'de>
+    for MeldableHeap<T>
+{
+    fn deserialize<D: serde::Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+        serde::Deserialize::deserialize(d).map(|xs: Vec<T>| xs.into_iter().collect())
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -156,6 +262,16 @@ mod test {
         assert_eq!(meldableheap.n, 0);
         assert_eq!(meldableheap.remove(), None);
         println!("{:?}", meldableheap);
+        let mut meldableheap: MeldableHeap<usize> = vec![7, 8, 9].into_iter().collect();
+        meldableheap.extend(vec![26, 4]);
+        let mut elems = meldableheap.iter().collect::<Vec<_>>();
+        assert_eq!(Some(&4), elems.first());
+        elems.sort();
+        assert_eq!(vec![4, 7, 8, 9, 26], elems);
+        assert_eq!(
+            vec![4, 7, 8, 9, 26],
+            meldableheap.into_iter().collect::<Vec<_>>()
+        );
 
         // test large linked list for stack overflow.
         let mut bst = MeldableHeap::<i32>::new();
@@ -164,5 +280,33 @@ mod test {
             bst.add(i);
         }
         println!("fin");
+        #[cfg(feature = "serde")]
+        {
+            let meldableheap: MeldableHeap<usize> = vec![5, 3, 9, 1, 7].into_iter().collect();
+            let json = serde_json::to_string(&meldableheap).unwrap();
+            let mut elems = serde_json::from_str::<Vec<usize>>(&json).unwrap();
+            elems.sort();
+            assert_eq!(elems, vec![1, 3, 5, 7, 9]);
+            let copy: MeldableHeap<usize> = serde_json::from_str(&json).unwrap();
+            assert_eq!(copy.into_iter().collect::<Vec<_>>(), vec![1, 3, 5, 7, 9]);
+            let copy: MeldableHeap<usize> = serde_json::from_str("[9,7,5,3,1]").unwrap();
+            assert_eq!(copy.into_iter().collect::<Vec<_>>(), vec![1, 3, 5, 7, 9]);
+        }
+        // a seed fixes every coin flip, and so the shape of the heap
+        let shape = |mut h: MeldableHeap<usize>| {
+            h.extend((0..300).map(|i| i * 7 % 300));
+            for _ in 0..100 {
+                h.remove();
+            }
+            h.iter().collect::<Vec<_>>()
+        };
+        assert_eq!(
+            shape(MeldableHeap::with_seed(42)),
+            shape(MeldableHeap::with_seed(42))
+        );
+        assert_ne!(
+            shape(MeldableHeap::with_seed(42)),
+            shape(MeldableHeap::with_seed(43))
+        );
     }
 }
@@ -1,6 +1,9 @@
 #![allow(clippy::many_single_char_names,clippy::explicit_counter_loop, clippy::redundant_closure)]
 pub fn countingsort(a: &mut [usize]) {
-    let k = *a.iter().max().unwrap() + 1;
+    let k = match a.iter().max() {
+        Some(m) => m + 1,
+        None => return,
+    };
     let mut c = vec![0usize; k];
     for ai in a.iter() {
         c[*ai] += 1;
@@ -29,6 +32,8 @@ mod test {
             &a,
             &[0, 0, 0, 1, 1, 2, 2, 2, 3, 4, 4, 5, 6, 7, 7, 9, 9, 9, 9, 9]
         );
+        let mut a: [usize; 0] = [];
+        countingsort(&mut a);
         let mut rng = thread_rng();
         for _ in 0u32..50000u32 {
             let len: usize = rng.gen();
@@ -15,12 +15,12 @@ impl AdjacencyLists {
             adj: vec![ArrayStack::new(); n],
         }
     }
-    pub fn nvertices(&self) -> usize {
-        self.n
-    }
 }
 
 impl Graph for AdjacencyLists {
+    fn nvertices(&self) -> usize {
+        self.n
+    }
     fn add_edge(&mut self, i: usize, j: usize) {
         if let Some(e) = self.adj.get_mut(i) {
             e.add(e.size(), j);
@@ -69,6 +69,7 @@ impl Graph for AdjacencyLists {
 #[cfg(test)]
 mod test {
     use super::*;
+    use chapter01::error::Error;
     use rand::{thread_rng, Rng};
     use std::collections::HashSet;
     #[test]
@@ -116,5 +117,18 @@ mod test {
             assert_eq!(adjm.out_edges(i).len(), oe);
             assert_eq!(adjm.in_edges(i).len(), ie);
         }
+        // out of range edges are reported instead of dropped
+        let mut adjm = AdjacencyLists::new(3);
+        assert_eq!(adjm.try_add_edge(0, 2), Ok(()));
+        assert_eq!(
+            adjm.try_add_edge(3, 0),
+            Err(Error::OutOfBounds { index: 3, len: 3 })
+        );
+        assert_eq!(
+            adjm.try_add_edge(1, 5),
+            Err(Error::OutOfBounds { index: 5, len: 3 })
+        );
+        assert_eq!(adjm.out_edges(0), vec![2]);
+        assert!(adjm.in_edges(0).is_empty());
     }
 }
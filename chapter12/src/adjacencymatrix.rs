@@ -31,6 +31,9 @@ impl AdjacencyMatrix {
 }
 
 impl Graph for AdjacencyMatrix {
+    fn nvertices(&self) -> usize {
+        self.n
+    }
     fn add_edge(&mut self, i: usize, j: usize) {
         if let Some(e) = self.get_mut(i, j) {
             *e = true;
@@ -1,31 +1,71 @@
 #![allow(clippy::many_single_char_names,clippy::explicit_counter_loop, clippy::redundant_closure)]
-use super::adjacencylists::AdjacencyLists;
 use chapter01::interface::{Graph, Queue};
 use chapter03::sllist::SLList;
 
-pub fn bfs(g: &AdjacencyLists, r: usize) {
-    let mut seen = vec![false; g.nvertices()];
+#[derive(Clone, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct BfsTraversal {
+    order: Vec<usize>,
+    parent: Vec<Option<usize>>,
+    dist: Vec<Option<usize>>,
+}
+
+impl BfsTraversal {
+    pub fn order(&self) -> &[usize] {
+        &self.order
+    }
+    pub fn parent(&self, i: usize) -> Option<usize> {
+        self.parent.get(i).cloned().flatten()
+    }
+    pub fn dist(&self, i: usize) -> Option<usize> {
+        self.dist.get(i).cloned().flatten()
+    }
+    pub fn is_reached(&self, i: usize) -> bool {
+        self.dist(i).is_some()
+    }
+    pub fn path_to(&self, t: usize) -> Option<Vec<usize>> {
+        self.dist(t)?;
+        let mut path = vec![t];
+        let mut u = t;
+        while let Some(p) = self.parent(u) {
+            path.push(p);
+            u = p;
+        }
+        path.reverse();
+        Some(path)
+    }
+}
+
+pub fn bfs<G: Graph>(g: &G, r: usize) -> BfsTraversal {
+    let n = g.nvertices();
+    let mut t = BfsTraversal {
+        order: vec![],
+        parent: vec![None; n],
+        dist: vec![None; n],
+    };
     let mut q = SLList::new();
-    q.add(r);
-    if let Some(e) = seen.get_mut(r) {
-        *e = true
+    if let Some(e) = t.dist.get_mut(r) {
+        *e = Some(0);
+        q.add(r);
     }
     while let Some(i) = q.remove() {
+        t.order.push(i);
         let edges = g.out_edges(i);
         for j in edges.into_iter() {
-            if !seen[j] {
+            if t.dist[j].is_none() {
                 q.add(j);
-                if let Some(e) = seen.get_mut(j) {
-                    *e = true
-                }
+                t.dist[j] = t.dist[i].map(|d| d + 1);
+                t.parent[j] = Some(i);
             }
         }
     }
+    t
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::adjacencylists::AdjacencyLists;
+    use crate::adjacencymatrix::AdjacencyMatrix;
     use rand::{thread_rng, Rng};
     use std::collections::HashSet;
     #[test]
@@ -41,7 +81,47 @@ mod test {
                 adjm.add_edge(i, j);
             }
         }
-        bfs(&adjm, 0);
+        let t = bfs(&adjm, 0);
+        assert_eq!(t.order()[0], 0);
+        assert_eq!(t.dist(0), Some(0));
+        assert_eq!(t.parent(0), None);
+        // every reached vertex is visited exactly once, in order of distance
+        let seen: HashSet<usize> = t.order().iter().cloned().collect();
+        assert_eq!(seen.len(), t.order().len());
+        for w in t.order().windows(2) {
+            assert!(t.dist(w[0]) <= t.dist(w[1]));
+        }
+        // no edge can shorten a bfs distance
+        for &(i, j) in set.iter() {
+            if let Some(di) = t.dist(i) {
+                assert!(t.dist(j).unwrap() <= di + 1);
+            }
+        }
+        for i in 0..n {
+            assert_eq!(t.is_reached(i), seen.contains(&i));
+            if let Some(path) = t.path_to(i) {
+                assert_eq!(path.len(), t.dist(i).unwrap() + 1);
+                for e in path.windows(2) {
+                    assert!(set.contains(&(e[0], e[1])));
+                }
+            }
+        }
         println!("done");
     }
+    #[test]
+    fn test_bfs_adjacencymatrix() {
+        let mut g = AdjacencyMatrix::new(6);
+        g.add_edge(0, 1);
+        g.add_edge(0, 2);
+        g.add_edge(1, 3);
+        g.add_edge(2, 3);
+        g.add_edge(3, 4);
+        let t = bfs(&g, 0);
+        assert_eq!(t.order(), &[0, 1, 2, 3, 4]);
+        assert_eq!(t.dist(4), Some(3));
+        assert_eq!(t.path_to(4), Some(vec![0, 1, 3, 4]));
+        assert_eq!(t.dist(5), None);
+        assert_eq!(t.path_to(5), None);
+        assert!(bfs(&g, 6).order().is_empty());
+    }
 }
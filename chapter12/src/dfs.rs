@@ -1,16 +1,67 @@
 #![allow(clippy::many_single_char_names,clippy::explicit_counter_loop, clippy::redundant_closure)]
-use super::adjacencylists::AdjacencyLists;
 use chapter01::interface::{Graph, Stack};
 use chapter03::sllist::SLList;
 
 #[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
-enum Color {
+pub(crate) enum Color {
     White,
     Grey,
     Black,
 }
 
-fn do_dfs(g: &AdjacencyLists, i: usize, c: &mut [Color]) {
+#[derive(Clone, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct DfsTraversal {
+    order: Vec<usize>,
+    postorder: Vec<usize>,
+    parent: Vec<Option<usize>>,
+    discovery: Vec<Option<usize>>,
+    finish: Vec<Option<usize>>,
+    time: usize,
+}
+
+impl DfsTraversal {
+    fn new(n: usize) -> Self {
+        Self {
+            order: vec![],
+            postorder: vec![],
+            parent: vec![None; n],
+            discovery: vec![None; n],
+            finish: vec![None; n],
+            time: 0,
+        }
+    }
+    fn discover(&mut self, i: usize, p: Option<usize>) {
+        self.order.push(i);
+        self.parent[i] = p;
+        self.discovery[i] = Some(self.time);
+        self.time += 1;
+    }
+    fn finish(&mut self, i: usize) {
+        self.postorder.push(i);
+        self.finish[i] = Some(self.time);
+        self.time += 1;
+    }
+    pub fn order(&self) -> &[usize] {
+        &self.order
+    }
+    pub fn postorder(&self) -> &[usize] {
+        &self.postorder
+    }
+    pub fn parent(&self, i: usize) -> Option<usize> {
+        self.parent.get(i).cloned().flatten()
+    }
+    pub fn discovery_time(&self, i: usize) -> Option<usize> {
+        self.discovery.get(i).cloned().flatten()
+    }
+    pub fn finish_time(&self, i: usize) -> Option<usize> {
+        self.finish.get(i).cloned().flatten()
+    }
+    pub fn is_reached(&self, i: usize) -> bool {
+        self.discovery_time(i).is_some()
+    }
+}
+
+fn do_dfs<G: Graph>(g: &G, i: usize, c: &mut [Color], t: &mut DfsTraversal) {
     if let Some(e) = c.get_mut(i) {
         *e = Color::Grey
     }
@@ -20,41 +71,111 @@ fn do_dfs(g: &AdjacencyLists, i: usize, c: &mut [Color]) {
             if let Some(e) = c.get_mut(j) {
                 *e = Color::Grey
             }
-            do_dfs(g, j, c);
+            t.discover(j, Some(i));
+            do_dfs(g, j, c, t);
         }
     }
     if let Some(e) = c.get_mut(i) {
         *e = Color::Black
     }
+    t.finish(i);
 }
 
-pub fn dfs(g: &AdjacencyLists, r: usize) {
+pub fn dfs<G: Graph>(g: &G, r: usize) -> DfsTraversal {
     let mut c = vec![Color::White; g.nvertices()];
-    do_dfs(g, r, &mut c);
+    let mut t = DfsTraversal::new(g.nvertices());
+    if r < g.nvertices() {
+        t.discover(r, None);
+        do_dfs(g, r, &mut c, &mut t);
+    }
+    t
 }
 
-pub fn dfs2(g: &AdjacencyLists, r: usize) {
+pub fn dfs_forest<G: Graph>(g: &G) -> DfsTraversal {
     let mut c = vec![Color::White; g.nvertices()];
+    let mut t = DfsTraversal::new(g.nvertices());
+    for r in 0..g.nvertices() {
+        if let Color::White = c[r] {
+            t.discover(r, None);
+            do_dfs(g, r, &mut c, &mut t);
+        }
+    }
+    t
+}
+
+enum Step {
+    Visit(usize, Option<usize>),
+    Finish(usize),
+}
+
+pub fn dfs2<G: Graph>(g: &G, r: usize) -> DfsTraversal {
+    let mut c = vec![Color::White; g.nvertices()];
+    let mut t = DfsTraversal::new(g.nvertices());
     let mut s = SLList::new();
-    s.push(r);
-    while let Some(i) = s.pop() {
-        if let Some(Color::White) = c.get(i) {
-            if let Some(e) = c.get_mut(i) {
-                *e = Color::Grey
+    s.push(Step::Visit(r, None));
+    while let Some(step) = s.pop() {
+        match step {
+            Step::Visit(i, p) => {
+                if let Some(Color::White) = c.get(i) {
+                    if let Some(e) = c.get_mut(i) {
+                        *e = Color::Grey
+                    }
+                    t.discover(i, p);
+                    // i finishes once everything pushed after it is done
+                    s.push(Step::Finish(i));
+                    let edges = g.out_edges(i);
+                    for j in edges.into_iter() {
+                        s.push(Step::Visit(j, Some(i)));
+                    }
+                }
             }
-            let edges = g.out_edges(i);
-            for j in edges.into_iter() {
-                s.push(j);
+            Step::Finish(i) => {
+                if let Some(e) = c.get_mut(i) {
+                    *e = Color::Black
+                }
+                t.finish(i);
             }
         }
     }
+    t
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::adjacencylists::AdjacencyLists;
+    use crate::adjacencymatrix::AdjacencyMatrix;
     use rand::{thread_rng, Rng};
     use std::collections::HashSet;
+
+    fn check<G: Graph>(g: &G, set: &HashSet<(usize, usize)>, t: &DfsTraversal) {
+        let n = g.nvertices();
+        assert_eq!(t.order().len(), t.postorder().len());
+        for i in 0..n {
+            match (t.discovery_time(i), t.finish_time(i)) {
+                (Some(d), Some(f)) => {
+                    assert!(d < f);
+                    // a tree edge nests the child's interval inside the parent's
+                    if let Some(p) = t.parent(i) {
+                        assert!(set.contains(&(p, i)));
+                        assert!(t.discovery_time(p).unwrap() < d);
+                        assert!(f < t.finish_time(p).unwrap());
+                    }
+                }
+                (None, None) => assert!(t.parent(i).is_none()),
+                _ => panic!("vertex {} only half visited", i),
+            }
+        }
+        // every edge out of a reached vertex leads to a reached vertex
+        for &(i, j) in set.iter() {
+            if t.is_reached(i) {
+                assert!(t.is_reached(j));
+                // no edge goes to a vertex discovered after i has finished
+                assert!(t.discovery_time(j) < t.finish_time(i));
+            }
+        }
+    }
+
     #[test]
     fn test_dfs() {
         let n = 50;
@@ -68,9 +189,41 @@ mod test {
                 adjm.add_edge(i, j);
             }
         }
-        dfs(&adjm, 0);
+        let t = dfs(&adjm, 0);
+        check(&adjm, &set, &t);
         println!("done");
-        dfs2(&adjm, 0);
+        let t2 = dfs2(&adjm, 0);
+        check(&adjm, &set, &t2);
+        let r1: HashSet<usize> = t.order().iter().cloned().collect();
+        let r2: HashSet<usize> = t2.order().iter().cloned().collect();
+        assert_eq!(r1, r2);
         println!("done2");
     }
+
+    #[test]
+    fn test_dfs_adjacencymatrix() {
+        let mut g = AdjacencyMatrix::new(5);
+        let mut set: HashSet<(usize, usize)> = HashSet::new();
+        for &(i, j) in [(0, 1), (1, 2), (0, 3), (3, 1)].iter() {
+            g.add_edge(i, j);
+            set.insert((i, j));
+        }
+        let t = dfs(&g, 0);
+        check(&g, &set, &t);
+        assert_eq!(t.order(), &[0, 1, 2, 3]);
+        assert_eq!(t.postorder(), &[2, 1, 3, 0]);
+        assert_eq!(t.discovery_time(2), Some(2));
+        assert_eq!(t.finish_time(0), Some(7));
+        assert!(!t.is_reached(4));
+        let t2 = dfs2(&g, 0);
+        check(&g, &set, &t2);
+        assert_eq!(t2.order(), &[0, 3, 1, 2]);
+        assert!(dfs(&g, 5).order().is_empty());
+        assert!(dfs2(&g, 5).order().is_empty());
+        let f = dfs_forest(&g);
+        check(&g, &set, &f);
+        assert_eq!(f.order(), &[0, 1, 2, 3, 4]);
+        assert_eq!(f.parent(4), None);
+        assert_eq!(f.finish_time(4), Some(9));
+    }
 }
@@ -2,3 +2,8 @@ pub mod adjacencylists;
 pub mod adjacencymatrix;
 pub mod bfs;
 pub mod dfs;
+pub mod scc;
+pub mod shortestpaths;
+pub mod toposort;
+pub mod weightedadjacencylists;
+pub mod weightedadjacencymatrix;
@@ -0,0 +1,218 @@
+#![allow(clippy::many_single_char_names,clippy::explicit_counter_loop, clippy::redundant_closure)]
+use super::adjacencylists::AdjacencyLists;
+use super::dfs::dfs_forest;
+use chapter01::interface::Graph;
+
+struct Tarjan {
+    index: Vec<Option<usize>>,
+    low: Vec<usize>,
+    on_stack: Vec<bool>,
+    stack: Vec<usize>,
+    next: usize,
+    components: Vec<Vec<usize>>,
+}
+
+impl Tarjan {
+    fn visit<G: Graph>(&mut self, g: &G, i: usize) {
+        self.index[i] = Some(self.next);
+        self.low[i] = self.next;
+        self.next += 1;
+        self.stack.push(i);
+        self.on_stack[i] = true;
+        for j in g.out_edges(i).into_iter() {
+            match self.index[j] {
+                None => {
+                    self.visit(g, j);
+                    self.low[i] = std::cmp::min(self.low[i], self.low[j]);
+                }
+                Some(k) if self.on_stack[j] => {
+                    self.low[i] = std::cmp::min(self.low[i], k);
+                }
+                _ => (),
+            }
+        }
+        if Some(self.low[i]) == self.index[i] {
+            let mut component = vec![];
+            while let Some(j) = self.stack.pop() {
+                self.on_stack[j] = false;
+                component.push(j);
+                if j == i {
+                    break;
+                }
+            }
+            self.components.push(component);
+        }
+    }
+}
+
+// components come out in reverse topological order of the condensation
+pub fn tarjan_scc<G: Graph>(g: &G) -> Vec<Vec<usize>> {
+    let n = g.nvertices();
+    let mut t = Tarjan {
+        index: vec![None; n],
+        low: vec![0; n],
+        on_stack: vec![false; n],
+        stack: vec![],
+        next: 0,
+        components: vec![],
+    };
+    for i in 0..n {
+        if t.index[i].is_none() {
+            t.visit(g, i);
+        }
+    }
+    t.components
+}
+
+fn collect<G: Graph>(g: &G, i: usize, c: usize, comp: &mut [Option<usize>], out: &mut Vec<usize>) {
+    comp[i] = Some(c);
+    out.push(i);
+    for j in g.in_edges(i).into_iter() {
+        if comp[j].is_none() {
+            collect(g, j, c, comp, out);
+        }
+    }
+}
+
+// components come out in topological order of the condensation
+pub fn kosaraju_scc<G: Graph>(g: &G) -> Vec<Vec<usize>> {
+    let n = g.nvertices();
+    let t = dfs_forest(g);
+    let mut comp = vec![None; n];
+    let mut components = vec![];
+    for &i in t.postorder().iter().rev() {
+        if comp[i].is_none() {
+            let mut component = vec![];
+            collect(g, i, components.len(), &mut comp, &mut component);
+            components.push(component);
+        }
+    }
+    components
+}
+
+#[derive(Clone, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct Condensation {
+    component: Vec<usize>,
+    components: Vec<Vec<usize>>,
+    dag: AdjacencyLists,
+}
+
+impl Condensation {
+    pub fn component(&self, i: usize) -> Option<usize> {
+        self.component.get(i).cloned()
+    }
+    pub fn components(&self) -> &[Vec<usize>] {
+        &self.components
+    }
+    // vertex c of the dag is components()[c]; every edge goes from a smaller to a larger index
+    pub fn dag(&self) -> &AdjacencyLists {
+        &self.dag
+    }
+}
+
+pub fn condensation<G: Graph>(g: &G) -> Condensation {
+    let n = g.nvertices();
+    let mut components = tarjan_scc(g);
+    components.reverse();
+    let mut component = vec![0; n];
+    for (c, vs) in components.iter().enumerate() {
+        for &i in vs.iter() {
+            component[i] = c;
+        }
+    }
+    let mut dag = AdjacencyLists::new(components.len());
+    for i in 0..n {
+        for j in g.out_edges(i).into_iter() {
+            let (a, b) = (component[i], component[j]);
+            if a != b && !dag.has_edge(a, b) {
+                dag.add_edge(a, b);
+            }
+        }
+    }
+    Condensation {
+        component,
+        components,
+        dag,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::adjacencymatrix::AdjacencyMatrix;
+    use crate::bfs::bfs;
+    use crate::toposort::topological_sort;
+    use rand::{thread_rng, Rng};
+
+    fn normalize(mut components: Vec<Vec<usize>>) -> Vec<Vec<usize>> {
+        for c in components.iter_mut() {
+            c.sort();
+        }
+        components.sort();
+        components
+    }
+
+    #[test]
+    fn test_scc() {
+        let n = 60;
+        let mut rng = thread_rng();
+        let mut g = AdjacencyLists::new(n);
+        for _ in 0..(3 * n / 2) {
+            let (i, j) = (rng.gen_range(0, n), rng.gen_range(0, n));
+            if !g.has_edge(i, j) {
+                g.add_edge(i, j);
+            }
+        }
+        let reach: Vec<_> = (0..n).map(|i| bfs(&g, i)).collect();
+        let tarjan = normalize(tarjan_scc(&g));
+        let kosaraju = normalize(kosaraju_scc(&g));
+        assert_eq!(tarjan, kosaraju);
+        assert_eq!(tarjan.iter().map(|c| c.len()).sum::<usize>(), n);
+        let c = condensation(&g);
+        assert_eq!(normalize(c.components().to_vec()), tarjan);
+        for i in 0..n {
+            for j in 0..n {
+                let same = reach[i].is_reached(j) && reach[j].is_reached(i);
+                assert_eq!(same, c.component(i) == c.component(j));
+            }
+        }
+        let order = topological_sort(c.dag()).unwrap();
+        assert_eq!(order.len(), c.components().len());
+        for a in 0..c.components().len() {
+            for b in c.dag().out_edges(a) {
+                assert!(a < b);
+            }
+        }
+        for i in 0..n {
+            for j in g.out_edges(i) {
+                let (a, b) = (c.component(i).unwrap(), c.component(j).unwrap());
+                assert!(a == b || c.dag().has_edge(a, b));
+            }
+        }
+    }
+
+    #[test]
+    fn test_scc_small() {
+        let mut g = AdjacencyMatrix::new(6);
+        for &(i, j) in [(0, 1), (1, 2), (2, 0), (2, 3), (3, 4), (4, 3), (5, 4)].iter() {
+            g.add_edge(i, j);
+        }
+        assert_eq!(tarjan_scc(&g), vec![vec![4, 3], vec![2, 1, 0], vec![5]]);
+        assert_eq!(
+            normalize(kosaraju_scc(&g)),
+            vec![vec![0, 1, 2], vec![3, 4], vec![5]]
+        );
+        let c = condensation(&g);
+        assert_eq!(c.component(0), c.component(2));
+        assert_eq!(c.component(3), c.component(4));
+        assert_eq!(c.component(6), None);
+        let (a, b, d) = (
+            c.component(0).unwrap(),
+            c.component(3).unwrap(),
+            c.component(5).unwrap(),
+        );
+        assert!(c.dag().has_edge(a, b));
+        assert!(c.dag().has_edge(d, b));
+        assert_eq!(c.dag().out_edges(b), vec![]);
+    }
+}
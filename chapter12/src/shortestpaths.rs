@@ -0,0 +1,280 @@
+#![allow(clippy::many_single_char_names,clippy::explicit_counter_loop, clippy::redundant_closure)]
+use super::weightedadjacencymatrix::WeightedAdjacencyMatrix;
+use chapter01::interface::{Queue, WeightedGraph};
+use chapter10::binaryheap::BinaryHeap;
+use std::ops::Add;
+
+// W::default() is taken as the zero weight
+pub trait Weight: Clone + PartialOrd + Default + Add<Output = Self> {}
+
+impl<W: Clone + PartialOrd + Default + Add<Output = W>> Weight for W {}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ShortestPaths<W> {
+    s: usize,
+    dist: Vec<Option<W>>,
+    parent: Vec<Option<usize>>,
+}
+
+impl<W: Clone> ShortestPaths<W> {
+    fn new(n: usize, s: usize, zero: W) -> Self {
+        let mut dist = vec![None; n];
+        if let Some(d) = dist.get_mut(s) {
+            *d = Some(zero);
+        }
+        Self {
+            s,
+            dist,
+            parent: vec![None; n],
+        }
+    }
+    pub fn source(&self) -> usize {
+        self.s
+    }
+    pub fn dist(&self, t: usize) -> Option<W> {
+        self.dist.get(t).cloned().flatten()
+    }
+    pub fn parent(&self, t: usize) -> Option<usize> {
+        self.parent.get(t).cloned().flatten()
+    }
+    pub fn path_to(&self, t: usize) -> Option<Vec<usize>> {
+        self.dist(t)?;
+        let mut path = vec![t];
+        let mut u = t;
+        while let Some(p) = self.parent(u) {
+            path.push(p);
+            u = p;
+        }
+        path.reverse();
+        Some(path)
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct AllPairsShortestPaths<W> {
+    dist: Vec<Vec<Option<W>>>,
+    next: Vec<Vec<Option<usize>>>,
+}
+
+impl<W: Clone> AllPairsShortestPaths<W> {
+    pub fn dist(&self, i: usize, j: usize) -> Option<W> {
+        self.dist.get(i)?.get(j).cloned().flatten()
+    }
+    pub fn path(&self, i: usize, j: usize) -> Option<Vec<usize>> {
+        self.dist(i, j)?;
+        let mut path = vec![i];
+        let mut u = i;
+        while u != j {
+            u = self.next[u][j]?;
+            path.push(u);
+        }
+        Some(path)
+    }
+}
+
+fn relax<W: Weight>(sp: &mut ShortestPaths<W>, i: usize, j: usize, w: W) -> bool {
+    let d = match sp.dist(i) {
+        Some(d) => d + w,
+        None => return false,
+    };
+    match sp.dist(j) {
+        Some(dj) if dj <= d => false,
+        _ => {
+            sp.dist[j] = Some(d);
+            sp.parent[j] = Some(i);
+            true
+        }
+    }
+}
+
+// weights must be non-negative
+pub fn dijkstra<W: Weight, G: WeightedGraph<W>>(g: &G, s: usize) -> ShortestPaths<W> {
+    let n = g.nvertices();
+    let mut sp = ShortestPaths::new(n, s, W::default());
+    let mut done = vec![false; n];
+    let mut q = BinaryHeap::new();
+    if s < n {
+        q.add((W::default(), s));
+    }
+    while let Some((_, i)) = q.remove() {
+        if done[i] {
+            continue;
+        }
+        done[i] = true;
+        for (j, w) in g.out_edges(i) {
+            if !done[j] && relax(&mut sp, i, j, w) {
+                if let Some(d) = sp.dist(j) {
+                    q.add((d, j));
+                }
+            }
+        }
+    }
+    sp
+}
+
+// returns None if a negative cycle is reachable from s
+pub fn bellman_ford<W: Weight, G: WeightedGraph<W>>(g: &G, s: usize) -> Option<ShortestPaths<W>> {
+    let n = g.nvertices();
+    let mut sp = ShortestPaths::new(n, s, W::default());
+    let edges: Vec<(usize, usize, W)> = (0..n)
+        .flat_map(|i| g.out_edges(i).into_iter().map(move |(j, w)| (i, j, w)))
+        .collect();
+    for _ in 1..n {
+        let mut changed = false;
+        for (i, j, w) in edges.iter().cloned() {
+            changed |= relax(&mut sp, i, j, w);
+        }
+        if !changed {
+            return Some(sp);
+        }
+    }
+    // after n - 1 rounds only a cycle can still shorten a path
+    for (i, j, w) in edges {
+        if relax(&mut sp, i, j, w) {
+            return None;
+        }
+    }
+    Some(sp)
+}
+
+// returns None if the graph contains a negative cycle
+pub fn floyd_warshall<W: Weight>(
+    g: &WeightedAdjacencyMatrix<W>,
+) -> Option<AllPairsShortestPaths<W>> {
+    let n = g.nvertices();
+    let mut dist = vec![vec![None; n]; n];
+    let mut next = vec![vec![None; n]; n];
+    for i in 0..n {
+        for (j, w) in g.out_edges(i) {
+            dist[i][j] = Some(w);
+            next[i][j] = Some(j);
+        }
+        match &dist[i][i] {
+            Some(w) if *w < W::default() => return None,
+            _ => {
+                dist[i][i] = Some(W::default());
+                next[i][i] = Some(i);
+            }
+        }
+    }
+    for k in 0..n {
+        for i in 0..n {
+            let dik = match dist[i][k].clone() {
+                Some(d) => d,
+                None => continue,
+            };
+            for j in 0..n {
+                if let Some(dkj) = dist[k][j].clone() {
+                    let d = dik.clone() + dkj;
+                    match &dist[i][j] {
+                        Some(dij) if *dij <= d => (),
+                        _ => {
+                            dist[i][j] = Some(d);
+                            next[i][j] = next[i][k];
+                        }
+                    }
+                }
+            }
+        }
+        if (0..n).any(|i| matches!(&dist[i][i], Some(d) if *d < W::default())) {
+            return None;
+        }
+    }
+    Some(AllPairsShortestPaths { dist, next })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::weightedadjacencylists::WeightedAdjacencyLists;
+    use rand::{thread_rng, Rng};
+
+    fn path_weight<W: Weight, G: WeightedGraph<W>>(g: &G, path: &[usize]) -> W {
+        path.windows(2)
+            .fold(W::default(), |d, e| d + g.weight(e[0], e[1]).unwrap())
+    }
+
+    #[test]
+    fn test_shortestpaths() {
+        let mut g = WeightedAdjacencyLists::new(6);
+        g.add_edge(0, 1, 7u32);
+        g.add_edge(0, 2, 9);
+        g.add_edge(0, 5, 14);
+        g.add_edge(1, 2, 10);
+        g.add_edge(1, 3, 15);
+        g.add_edge(2, 3, 11);
+        g.add_edge(2, 5, 2);
+        g.add_edge(3, 4, 6);
+        g.add_edge(5, 4, 9);
+        let sp = dijkstra(&g, 0);
+        assert_eq!(sp.source(), 0);
+        assert_eq!(sp.dist(4), Some(20));
+        assert_eq!(sp.path_to(4), Some(vec![0, 2, 5, 4]));
+        assert_eq!(sp.path_to(0), Some(vec![0]));
+        assert_eq!(sp.parent(3), Some(2));
+        let sp = dijkstra(&g, 4);
+        assert_eq!(sp.dist(0), None);
+        assert_eq!(sp.path_to(0), None);
+        assert_eq!(bellman_ford(&g, 0), Some(dijkstra(&g, 0)));
+
+        // negative edges without and with a negative cycle
+        let mut g = WeightedAdjacencyMatrix::new(4);
+        g.add_edge(0, 1, 4i32);
+        g.add_edge(0, 2, 5);
+        g.add_edge(2, 1, -3);
+        g.add_edge(1, 3, 2);
+        let sp = bellman_ford(&g, 0).unwrap();
+        assert_eq!(sp.dist(3), Some(4));
+        assert_eq!(sp.path_to(3), Some(vec![0, 2, 1, 3]));
+        let apsp = floyd_warshall(&g).unwrap();
+        assert_eq!(apsp.dist(0, 3), Some(4));
+        assert_eq!(apsp.path(0, 3), Some(vec![0, 2, 1, 3]));
+        assert_eq!(apsp.path(3, 0), None);
+        g.add_edge(3, 2, -5);
+        assert_eq!(bellman_ford(&g, 0), None);
+        assert_eq!(floyd_warshall(&g), None);
+        // a negative cycle unreachable from the source is ignored
+        let mut h = WeightedAdjacencyLists::new(3);
+        h.add_edge(1, 2, -1i32);
+        h.add_edge(2, 1, -1);
+        assert!(bellman_ford(&h, 0).is_some());
+        // None means a cycle, never an empty graph
+        let e = WeightedAdjacencyLists::<i32>::new(0);
+        assert_eq!(bellman_ford(&e, 0), Some(dijkstra(&e, 0)));
+        let mut h = WeightedAdjacencyLists::new(1);
+        assert_eq!(bellman_ford(&h, 0).and_then(|sp| sp.dist(0)), Some(0));
+        h.add_edge(0, 0, -1i32);
+        assert_eq!(bellman_ford(&h, 0), None);
+    }
+
+    #[test]
+    fn test_shortestpaths_random() {
+        let n = 40;
+        let mut rng = thread_rng();
+        let mut gl = WeightedAdjacencyLists::new(n);
+        let mut gm = WeightedAdjacencyMatrix::new(n);
+        for _ in 0..(5 * n) {
+            let (i, j, w) = (
+                rng.gen_range(0, n),
+                rng.gen_range(0, n),
+                rng.gen_range(0, 100u64),
+            );
+            gl.add_edge(i, j, w);
+            gm.add_edge(i, j, w);
+        }
+        let apsp = floyd_warshall(&gm).unwrap();
+        for s in 0..n {
+            let d = dijkstra(&gl, s);
+            let b = bellman_ford(&gm, s).unwrap();
+            for t in 0..n {
+                assert_eq!(d.dist(t), apsp.dist(s, t));
+                assert_eq!(b.dist(t), apsp.dist(s, t));
+                if let Some(dt) = d.dist(t) {
+                    assert_eq!(path_weight(&gl, &d.path_to(t).unwrap()), dt);
+                    assert_eq!(path_weight(&gm, &b.path_to(t).unwrap()), dt);
+                    assert_eq!(path_weight(&gm, &apsp.path(s, t).unwrap()), dt);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,144 @@
+#![allow(clippy::many_single_char_names,clippy::explicit_counter_loop, clippy::redundant_closure)]
+use super::dfs::Color;
+use chapter01::interface::Graph;
+
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct CycleError {
+    cycle: Vec<usize>,
+}
+
+impl CycleError {
+    // the vertices of the cycle in edge order; the last one has an edge back to the first
+    pub fn cycle(&self) -> &[usize] {
+        &self.cycle
+    }
+}
+
+impl std::fmt::Display for CycleError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "graph has a cycle:")?;
+        for i in self.cycle.iter().chain(self.cycle.first()) {
+            write!(f, " {}", i)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for CycleError {}
+
+fn visit<G: Graph>(
+    g: &G,
+    i: usize,
+    c: &mut [Color],
+    parent: &mut [Option<usize>],
+    order: &mut Vec<usize>,
+) -> Result<(), CycleError> {
+    c[i] = Color::Grey;
+    for j in g.out_edges(i).into_iter() {
+        match c[j] {
+            Color::White => {
+                parent[j] = Some(i);
+                visit(g, j, c, parent, order)?;
+            }
+            Color::Grey => {
+                // i -> j is a back edge, so j is an ancestor of i
+                let mut cycle = vec![i];
+                let mut u = i;
+                while u != j {
+                    u = parent[u].unwrap();
+                    cycle.push(u);
+                }
+                cycle.reverse();
+                return Err(CycleError { cycle });
+            }
+            Color::Black => (),
+        }
+    }
+    c[i] = Color::Black;
+    order.push(i);
+    Ok(())
+}
+
+pub fn topological_sort<G: Graph>(g: &G) -> Result<Vec<usize>, CycleError> {
+    let n = g.nvertices();
+    let mut c = vec![Color::White; n];
+    let mut parent = vec![None; n];
+    let mut order = Vec::with_capacity(n);
+    for r in 0..n {
+        if let Color::White = c[r] {
+            visit(g, r, &mut c, &mut parent, &mut order)?;
+        }
+    }
+    order.reverse();
+    Ok(order)
+}
+
+pub fn find_cycle<G: Graph>(g: &G) -> Option<Vec<usize>> {
+    topological_sort(g).err().map(|e| e.cycle)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::adjacencylists::AdjacencyLists;
+    use crate::adjacencymatrix::AdjacencyMatrix;
+    use rand::{thread_rng, Rng};
+    #[test]
+    fn test_toposort() {
+        let n = 50;
+        let mut rng = thread_rng();
+        // edges only go from smaller to larger labels under a random relabelling
+        let mut label: Vec<usize> = (0..n).collect();
+        for i in (1..n).rev() {
+            label.swap(i, rng.gen_range(0, i + 1));
+        }
+        let mut g = AdjacencyLists::new(n);
+        for _ in 0..(5 * n) {
+            let (a, b) = (rng.gen_range(0, n), rng.gen_range(0, n));
+            if a < b && !g.has_edge(label[a], label[b]) {
+                g.add_edge(label[a], label[b]);
+            }
+        }
+        let order = topological_sort(&g).unwrap();
+        assert_eq!(order.len(), n);
+        let mut pos = vec![0; n];
+        for (k, &i) in order.iter().enumerate() {
+            pos[i] = k;
+        }
+        for i in 0..n {
+            for j in g.out_edges(i) {
+                assert!(pos[i] < pos[j]);
+            }
+        }
+        assert_eq!(find_cycle(&g), None);
+
+        // closing any path creates a cycle that must be reported
+        let (a, b) = (label[3], label[n - 1]);
+        g.add_edge(a, label[n - 2]);
+        g.add_edge(label[n - 2], b);
+        g.add_edge(b, a);
+        let e = topological_sort(&g).unwrap_err();
+        let cycle = e.cycle();
+        assert!(!cycle.is_empty());
+        for k in 0..cycle.len() {
+            assert!(g.has_edge(cycle[k], cycle[(k + 1) % cycle.len()]));
+        }
+        println!("{}", e);
+    }
+    #[test]
+    fn test_toposort_small() {
+        let mut g = AdjacencyMatrix::new(4);
+        g.add_edge(0, 1);
+        g.add_edge(1, 2);
+        g.add_edge(0, 3);
+        g.add_edge(3, 2);
+        assert_eq!(topological_sort(&g), Ok(vec![0, 3, 1, 2]));
+        g.add_edge(2, 3);
+        let e = topological_sort(&g).unwrap_err();
+        assert_eq!(e.cycle(), &[2, 3]);
+        assert_eq!(e.to_string(), "graph has a cycle: 2 3 2");
+        let mut h = AdjacencyLists::new(2);
+        h.add_edge(1, 1);
+        assert_eq!(find_cycle(&h), Some(vec![1]));
+    }
+}
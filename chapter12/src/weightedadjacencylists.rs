@@ -0,0 +1,132 @@
+#![allow(clippy::many_single_char_names,clippy::explicit_counter_loop, clippy::redundant_closure)]
+use chapter01::interface::{List, WeightedGraph};
+use chapter02::arraystack::Array as ArrayStack;
+
+#[derive(Clone, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct WeightedAdjacencyLists<W> {
+    n: usize,
+    adj: Vec<ArrayStack<(usize, W)>>,
+}
+
+impl<W: Clone> WeightedAdjacencyLists<W> {
+    pub fn new(n: usize) -> Self {
+        Self {
+            n,
+            adj: vec![ArrayStack::new(); n],
+        }
+    }
+    fn find(&self, i: usize, j: usize) -> Option<usize> {
+        let e = self.adj.get(i)?;
+        for k in 0..e.size() {
+            if let Some((u, _)) = e.get(k) {
+                if u == j {
+                    return Some(k);
+                }
+            }
+        }
+        None
+    }
+}
+
+impl<W: Clone> WeightedGraph<W> for WeightedAdjacencyLists<W> {
+    fn nvertices(&self) -> usize {
+        self.n
+    }
+    fn add_edge(&mut self, i: usize, j: usize, w: W) {
+        let k = self.find(i, j);
+        if let Some(e) = self.adj.get_mut(i) {
+            match k {
+                Some(k) => {
+                    e.set(k, (j, w));
+                }
+                None => e.add(e.size(), (j, w)),
+            }
+        }
+    }
+    fn remove_edge(&mut self, i: usize, j: usize) {
+        if let Some(k) = self.find(i, j) {
+            self.adj[i].remove(k);
+        }
+    }
+    fn has_edge(&self, i: usize, j: usize) -> bool {
+        self.find(i, j).is_some()
+    }
+    fn weight(&self, i: usize, j: usize) -> Option<W> {
+        let k = self.find(i, j)?;
+        self.adj[i].get(k).map(|(_, w)| w)
+    }
+    fn out_edges(&self, i: usize) -> Vec<(usize, W)> {
+        let mut edges = vec![];
+        if let Some(e) = self.adj.get(i) {
+            for k in 0..e.size() {
+                if let Some(u) = e.get(k) {
+                    edges.push(u)
+                }
+            }
+        }
+        edges
+    }
+    fn in_edges(&self, i: usize) -> Vec<(usize, W)> {
+        let mut edges = vec![];
+        for j in 0..self.n {
+            if let Some(w) = self.weight(j, i) {
+                edges.push((j, w));
+            }
+        }
+        edges
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rand::{thread_rng, Rng};
+    use std::collections::HashMap;
+    #[test]
+    fn test_weightedadjacencylists() {
+        let n = 50;
+        let mut adjl = WeightedAdjacencyLists::new(n);
+        let mut map: HashMap<(usize, usize), i64> = HashMap::new();
+        let mut rng = thread_rng();
+        // add test, re-adding an edge replaces its weight
+        for _ in 0..(5 * n) {
+            let (i, j, w) = (
+                rng.gen_range(0, n),
+                rng.gen_range(0, n),
+                rng.gen_range(-10, 100),
+            );
+            map.insert((i, j), w);
+            adjl.add_edge(i, j, w);
+        }
+        for i in 0..n {
+            for j in 0..n {
+                assert_eq!(adjl.has_edge(i, j), map.contains_key(&(i, j)));
+                assert_eq!(adjl.weight(i, j), map.get(&(i, j)).cloned());
+            }
+        }
+        // remove test
+        for _ in 0..n {
+            let (i, j) = (rng.gen_range(0, n), rng.gen_range(0, n));
+            map.remove(&(i, j));
+            adjl.remove_edge(i, j);
+        }
+        for i in 0..n {
+            for j in 0..n {
+                assert_eq!(adjl.weight(i, j), map.get(&(i, j)).cloned());
+            }
+        }
+        // check that in and out edges carry their weights
+        for i in 0..n {
+            let oe = adjl.out_edges(i);
+            let ie = adjl.in_edges(i);
+            assert_eq!(oe.len(), map.keys().filter(|e| e.0 == i).count());
+            assert_eq!(ie.len(), map.keys().filter(|e| e.1 == i).count());
+            for (j, w) in oe {
+                assert_eq!(map.get(&(i, j)), Some(&w));
+            }
+            for (j, w) in ie {
+                assert_eq!(map.get(&(j, i)), Some(&w));
+            }
+        }
+    }
+}
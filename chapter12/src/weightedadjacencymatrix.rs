@@ -0,0 +1,125 @@
+#![allow(clippy::many_single_char_names,clippy::explicit_counter_loop, clippy::redundant_closure)]
+use chapter01::interface::WeightedGraph;
+
+#[derive(Clone, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct WeightedAdjacencyMatrix<W> {
+    n: usize,
+    a: Vec<Vec<Option<W>>>,
+}
+
+impl<W: Clone> WeightedAdjacencyMatrix<W> {
+    pub fn new(n: usize) -> Self {
+        Self {
+            n,
+            a: vec![vec![None; n]; n],
+        }
+    }
+    fn get_mut(&mut self, i: usize, j: usize) -> Option<&mut Option<W>> {
+        if let Some(ii) = self.a.get_mut(i) {
+            ii.get_mut(j)
+        } else {
+            None
+        }
+    }
+    fn get(&self, i: usize, j: usize) -> Option<&Option<W>> {
+        if let Some(ii) = self.a.get(i) {
+            ii.get(j)
+        } else {
+            None
+        }
+    }
+}
+
+impl<W: Clone> WeightedGraph<W> for WeightedAdjacencyMatrix<W> {
+    fn nvertices(&self) -> usize {
+        self.n
+    }
+    fn add_edge(&mut self, i: usize, j: usize, w: W) {
+        if let Some(e) = self.get_mut(i, j) {
+            *e = Some(w);
+        }
+    }
+    fn remove_edge(&mut self, i: usize, j: usize) {
+        if let Some(e) = self.get_mut(i, j) {
+            *e = None;
+        }
+    }
+    fn has_edge(&self, i: usize, j: usize) -> bool {
+        matches!(self.get(i, j), Some(Some(_)))
+    }
+    fn weight(&self, i: usize, j: usize) -> Option<W> {
+        self.get(i, j).cloned().flatten()
+    }
+    fn out_edges(&self, i: usize) -> Vec<(usize, W)> {
+        let mut edges = vec![];
+        for j in 0..self.n {
+            if let Some(w) = self.weight(i, j) {
+                edges.push((j, w));
+            }
+        }
+        edges
+    }
+    fn in_edges(&self, i: usize) -> Vec<(usize, W)> {
+        let mut edges = vec![];
+        for j in 0..self.n {
+            if let Some(w) = self.weight(j, i) {
+                edges.push((j, w));
+            }
+        }
+        edges
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rand::{thread_rng, Rng};
+    use std::collections::HashMap;
+    #[test]
+    fn test_weightedadjacencymatrix() {
+        let n = 50;
+        let mut adjm = WeightedAdjacencyMatrix::new(n);
+        let mut map: HashMap<(usize, usize), i64> = HashMap::new();
+        let mut rng = thread_rng();
+        // add test
+        for _ in 0..(5 * n) {
+            let (i, j, w) = (
+                rng.gen_range(0, n),
+                rng.gen_range(0, n),
+                rng.gen_range(-10, 100),
+            );
+            map.insert((i, j), w);
+            adjm.add_edge(i, j, w);
+        }
+        for i in 0..n {
+            for j in 0..n {
+                assert_eq!(adjm.has_edge(i, j), map.contains_key(&(i, j)));
+                assert_eq!(adjm.weight(i, j), map.get(&(i, j)).cloned());
+            }
+        }
+        // remove test
+        for _ in 0..n {
+            let (i, j) = (rng.gen_range(0, n), rng.gen_range(0, n));
+            map.remove(&(i, j));
+            adjm.remove_edge(i, j);
+        }
+        for i in 0..n {
+            for j in 0..n {
+                assert_eq!(adjm.weight(i, j), map.get(&(i, j)).cloned());
+            }
+        }
+        // check that in and out edges carry their weights
+        for i in 0..n {
+            let oe = adjm.out_edges(i);
+            let ie = adjm.in_edges(i);
+            assert_eq!(oe.len(), map.keys().filter(|e| e.0 == i).count());
+            assert_eq!(ie.len(), map.keys().filter(|e| e.1 == i).count());
+            for (j, w) in oe {
+                assert_eq!(map.get(&(i, j)), Some(&w));
+            }
+            for (j, w) in ie {
+                assert_eq!(map.get(&(j, i)), Some(&w));
+            }
+        }
+    }
+}
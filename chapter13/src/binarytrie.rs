@@ -1,7 +1,11 @@
 #![allow(clippy::many_single_char_names,clippy::explicit_counter_loop)]
 use crate::USizeV;
+use chapter01::codec::Codec;
 use chapter01::interface::SSet;
-use std::cell::RefCell;
+use chapter01::snapshot::{self, SnapshotError};
+use std::cell::{Cell, RefCell};
+use std::iter::FromIterator;
+use std::marker::PhantomData;
 use std::rc::{Rc, Weak};
 
 #[derive(Clone, Debug, Default)]
@@ -12,6 +16,7 @@ pub struct BTNode<T: USizeV + Default> {
     parent: RefCell<Option<Weak<BTNode<T>>>>,
     prev: RefCell<Option<Weak<BTNode<T>>>>, // left
     next: RefCell<Option<Rc<BTNode<T>>>>,   // right
+    size: Cell<usize>,                      // the number of leaves below
 }
 
 impl<T: USizeV + Default> BTNode<T> {
@@ -28,6 +33,63 @@ pub struct BinaryTrie<T: USizeV + Default + PartialOrd + Clone> {
     tail: Option<Weak<BTNode<T>>>, // dummy2
 }
 
+pub struct Range<'a, T: USizeV + Default + PartialOrd + Clone> {
+    u: Option<Rc<BTNode<T>>>,
+    hi: T,
+    set: PhantomData<&'a BinaryTrie<T>>,
+}
+
+impl<'a, T: USizeV + Default + PartialOrd + Clone> Iterator for Range<'a, T> {
+    type Item = T;
+    fn next(&mut self) -> Option<T> {
+        let u = self.u.take().filter(|u| u.next.borrow().is_some())?;
+        let x = u.x.borrow().clone();
+        if x.usize_value() < self.hi.usize_value() {
+            self.u = u.next.borrow().clone();
+            Some(x)
+        } else {
+            None
+        }
+    }
+}
+
+pub struct Iter<'a, T: USizeV + Default + PartialOrd + Clone> {
+    front: Option<Rc<BTNode<T>>>,
+    back: Option<Rc<BTNode<T>>>,
+    n: usize,
+    set: PhantomData<&'a BinaryTrie<T>>,
+}
+
+impl<'a, T: USizeV + Default + PartialOrd + Clone> Iterator for Iter<'a, T> {
+    type Item = T;
+    fn next(&mut self) -> Option<T> {
+        if self.n == 0 {
+            return None;
+        }
+        self.n -= 1;
+        let u = self.front.take()?;
+        self.front = u.next.borrow().clone();
+        let x = u.x.borrow().clone();
+        Some(x)
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.n, Some(self.n))
+    }
+}
+
+impl<'a, T: USizeV + Default + PartialOrd + Clone> DoubleEndedIterator for Iter<'a, T> {
+    fn next_back(&mut self) -> Option<T> {
+        if self.n == 0 {
+            return None;
+        }
+        self.n -= 1;
+        let u = self.back.take()?;
+        self.back = u.prev.borrow().as_ref().and_then(|p| p.upgrade());
+        let x = u.x.borrow().clone();
+        Some(x)
+    }
+}
+
 impl<T: PartialOrd + Clone + Default + USizeV> Drop for BinaryTrie<T> {
     fn drop(&mut self) {
         while let Some(ref x) = self.head.as_ref().and_then(|s| {
@@ -58,6 +120,84 @@ impl<T: USizeV + Default + PartialOrd + Clone> BinaryTrie<T> {
             tail: Some(Rc::downgrade(&dummy2)),
         }
     }
+    pub fn iter(&self) -> Iter<'_, T> {
+        Iter {
+            front: self.head.as_ref().and_then(|h| h.next.borrow().clone()),
+            back: self
+                .tail
+                .as_ref()
+                .and_then(|t| t.upgrade())
+                .and_then(|t| t.prev.borrow().as_ref().and_then(|p| p.upgrade())),
+            n: self.n,
+            set: PhantomData,
+        }
+    }
+    pub fn range(&self, r: std::ops::Range<T>) -> Range<'_, T> {
+        Range {
+            u: self.find_node(r.start.usize_value()),
+            hi: r.end,
+            set: PhantomData,
+        }
+    }
+    // the number of elements smaller than x: the leaves left of the search path for x
+    pub fn rank(&self, x: &T) -> usize {
+        let ix = x.usize_value();
+        let mut k = 0;
+        let mut u = self.r.clone();
+        for i in 0..Self::W {
+            let c = (ix >> (Self::W - i - 1)) & 1;
+            if c == 1 {
+                k += u.child[0].borrow().as_ref().map_or(0, |l| l.size.get());
+            }
+            let next = match *u.child[c].borrow() {
+                Some(ref c) => c.clone(),
+                None => break,
+            };
+            u = next;
+        }
+        k
+    }
+    fn find_node(&self, ix: usize) -> Option<Rc<BTNode<T>>> {
+        let mut i = 0;
+        let mut c = 0;
+        let mut u = self.r.clone();
+        let mut next;
+        for _ in 0..Self::W {
+            c = (ix >> (Self::W - i - 1)) & 1;
+            match *u.child[c].borrow() {
+                Some(ref c) => next = c.clone(),
+                None => break,
+            }
+            u = next;
+            i += 1;
+        }
+        if i == Self::W {
+            return Some(u);
+        }
+        let n = if c == 0 {
+            u.jump.borrow().clone()
+        } else {
+            let j = u.jump.borrow().clone();
+            match j {
+                Some(ref j) => j.next.borrow().clone(),
+                None => None,
+            }
+        };
+        match n {
+            Some(ref n) if n.next.borrow().is_none() => None,
+            Some(ref n) if n.prev.borrow().is_none() => None,
+            _ => n,
+        }
+    }
+}
+
+impl<T: USizeV + Default + PartialOrd + Clone + Codec> BinaryTrie<T> {
+    pub fn dump(&self) -> Vec<u8> {
+        snapshot::dump(self.iter())
+    }
+    pub fn load(buf: &[u8]) -> Result<Self, SnapshotError> {
+        snapshot::load(buf).map(|xs: Vec<T>| xs.into_iter().collect())
+    }
 }
 
 impl<T: USizeV + Default + PartialOrd + Clone> SSet<T> for BinaryTrie<T> {
@@ -105,9 +245,10 @@ impl<T: USizeV + Default + PartialOrd + Clone> SSet<T> for BinaryTrie<T> {
             i += 1;
         }
         *u.x.borrow_mut() = x;
+        u.size.set(1);
 
         // 3 - add u to linked list
-        *u.prev.borrow_mut() = pred.as_ref().map(|p| Rc::downgrade(&p));
+        *u.prev.borrow_mut() = pred.as_ref().map(Rc::downgrade);
         *u.next.borrow_mut() = pred.as_ref().and_then(|p| p.next.borrow().clone());
         u.prev
             .borrow()
@@ -140,6 +281,7 @@ impl<T: USizeV + Default + PartialOrd + Clone> SSet<T> for BinaryTrie<T> {
             {
                 vi.jump.borrow_mut().replace(u.clone());
             }
+            vi.size.set(vi.size.get() + 1);
             v = vi.parent.borrow().as_ref().and_then(|p| p.upgrade());
         }
         self.n += 1;
@@ -191,6 +333,7 @@ impl<T: USizeV + Default + PartialOrd + Clone> SSet<T> for BinaryTrie<T> {
         }
 
         // 4 - update jump pointers
+        v.size.set(v.size.get() - 1);
         c = if v.child[0].borrow().is_none() { 1 } else { 0 };
         *v.jump.borrow_mut() = if c == 0 {
             prev.as_ref().and_then(|p| p.upgrade())
@@ -213,6 +356,7 @@ impl<T: USizeV + Default + PartialOrd + Clone> SSet<T> for BinaryTrie<T> {
                     next.clone()
                 };
             }
+            vi.size.set(vi.size.get() - 1);
             v = vi.parent.borrow().as_ref().and_then(|p| p.upgrade());
         }
         self.n -= 1;
@@ -251,6 +395,77 @@ impl<T: USizeV + Default + PartialOrd + Clone> SSet<T> for BinaryTrie<T> {
             _ => n.as_ref().map(|u| u.x.borrow().clone()),
         }
     }
+    fn successor(&self, x: &T) -> Option<T> {
+        let ix = x.usize_value();
+        match self.find_node(ix) {
+            Some(u) if u.x.borrow().usize_value() == ix => u
+                .next
+                .borrow()
+                .as_ref()
+                .filter(|n| n.next.borrow().is_some())
+                .map(|n| n.x.borrow().clone()),
+            Some(u) => Some(u.x.borrow().clone()),
+            None => None,
+        }
+    }
+    fn predecessor(&self, x: &T) -> Option<T> {
+        let u = self
+            .find_node(x.usize_value())
+            .or_else(|| self.tail.as_ref().and_then(|t| t.upgrade()))?;
+        let p = u.prev.borrow().as_ref().and_then(|p| p.upgrade());
+        p.filter(|p| p.prev.borrow().is_some())
+            .map(|p| p.x.borrow().clone())
+    }
+    fn find_min(&self) -> Option<T> {
+        self.head.as_ref().and_then(|h| {
+            h.next
+                .borrow()
+                .as_ref()
+                .filter(|n| n.next.borrow().is_some())
+                .map(|n| n.x.borrow().clone())
+        })
+    }
+    fn find_max(&self) -> Option<T> {
+        let t = self.tail.as_ref().and_then(|t| t.upgrade())?;
+        let p = t.prev.borrow().as_ref().and_then(|p| p.upgrade());
+        p.filter(|p| p.prev.borrow().is_some())
+            .map(|p| p.x.borrow().clone())
+    }
+    fn count_range(&self, r: std::ops::Range<T>) -> usize {
+        self.rank(&r.end).saturating_sub(self.rank(&r.start))
+    }
+}
+
+impl<'a, T: USizeV + Default + PartialOrd + Clone> IntoIterator for &'a BinaryTrie<T> {
+    type Item = T;
+    type IntoIter = Iter<'a, T>;
+    fn into_iter(self) -> Iter<'a, T> {
+        self.iter()
+    }
+}
+
+impl<T: USizeV + Default + PartialOrd + Clone> IntoIterator for BinaryTrie<T> {
+    type Item = T;
+    type IntoIter = std::vec::IntoIter<T>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter().collect::<Vec<_>>().into_iter()
+    }
+}
+
+impl<T: USizeV + Default + PartialOrd + Clone> FromIterator<T> for BinaryTrie<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut s = Self::new();
+        s.extend(iter);
+        s
+    }
+}
+
+impl<T: USizeV + Default + PartialOrd + Clone> Extend<T> for BinaryTrie<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for x in iter {
+            self.add(x);
+        }
+    }
 }
 
 #[cfg(test)]
@@ -292,8 +507,49 @@ mod test {
                 let y2 = binarytrie.find(&x);
                 assert_eq!(y1, y2);
             }
+            for _ in 0..n {
+                let x = rng.gen_range(0, 5 * n);
+                assert_eq!(redblacktree.successor(&x), binarytrie.successor(&x));
+                assert_eq!(redblacktree.predecessor(&x), binarytrie.predecessor(&x));
+                assert_eq!(redblacktree.floor(&x), binarytrie.floor(&x));
+                assert_eq!(redblacktree.ceiling(&x), binarytrie.ceiling(&x));
+            }
+            assert_eq!(redblacktree.find_min(), binarytrie.find_min());
+            assert_eq!(redblacktree.find_max(), binarytrie.find_max());
+            for _ in 0..n {
+                let x = rng.gen_range(0, 5 * n);
+                let y = x + rng.gen_range(0, n);
+                let r1 = redblacktree.range(x..y).collect::<Vec<_>>();
+                let r2 = binarytrie.range(x..y).collect::<Vec<_>>();
+                assert_eq!(r1, r2);
+                assert_eq!(r1.len(), binarytrie.count_range(x..y));
+                assert_eq!(redblacktree.rank(&x), binarytrie.rank(&x));
+            }
+            assert_eq!(
+                redblacktree.iter().collect::<Vec<_>>(),
+                binarytrie.iter().collect::<Vec<_>>()
+            );
+            assert_eq!(
+                redblacktree.iter().rev().collect::<Vec<_>>(),
+                (&binarytrie).into_iter().rev().collect::<Vec<_>>()
+            );
         }
 
+        let mut binarytrie: BinaryTrie<i32> = vec![5, 3, 9, 3].into_iter().collect();
+        binarytrie.extend(vec![1, 7]);
+        assert_eq!(
+            vec![9, 7, 5, 3, 1],
+            binarytrie.into_iter().rev().collect::<Vec<_>>()
+        );
+        let binarytrie: BinaryTrie<i32> = (0..100).map(|x| x * x).collect();
+        let buf = binarytrie.dump();
+        let copy = BinaryTrie::<i32>::load(&buf).unwrap();
+        assert!(copy.iter().eq(binarytrie.iter()));
+        assert_eq!(copy.find(&50), Some(64));
+        assert_eq!(copy.rank(&50), 8);
+        assert!(BinaryTrie::<i32>::load(&buf[..buf.len() - 1]).is_err());
+        assert!(BinaryTrie::<i32>::load(&buf[1..]).is_err());
+
         // test large linked list for stack overflow.
         let mut bst = BinaryTrie::<i32>::new();
         let num = 100000;
@@ -1,9 +1,13 @@
 #![allow(clippy::many_single_char_names,clippy::explicit_counter_loop)]
 use crate::USizeV;
+use chapter01::codec::Codec;
 use chapter01::interface::{SSet, USet};
+use chapter01::snapshot::{self, SnapshotError};
 use chapter05::linearhashtable::LinearHashTable;
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 use std::hash::{Hash, Hasher};
+use std::iter::FromIterator;
+use std::marker::PhantomData;
 use std::rc::{Rc, Weak};
 
 #[derive(Clone, Debug, Default)]
@@ -15,6 +19,7 @@ pub struct BTNode<T: USizeV + Default> {
     parent: RefCell<Option<Weak<BTNode<T>>>>,
     prev: RefCell<Option<Weak<BTNode<T>>>>,   // left
     pub next: RefCell<Option<Rc<BTNode<T>>>>, // right
+    size: Cell<usize>,                        // the total weight of the leaves below
 }
 
 impl<T: USizeV + Default> BTNode<T> {
@@ -44,6 +49,63 @@ pub struct XFastTrie<T: USizeV + Default + PartialOrd + Clone> {
     t: Box<[LinearHashTable<Rc<BTNode<T>>>]>,
 }
 
+pub struct Range<'a, T: USizeV + Default + PartialOrd + Clone> {
+    u: Option<Rc<BTNode<T>>>,
+    hi: T,
+    set: PhantomData<&'a XFastTrie<T>>,
+}
+
+impl<'a, T: USizeV + Default + PartialOrd + Clone> Iterator for Range<'a, T> {
+    type Item = T;
+    fn next(&mut self) -> Option<T> {
+        let u = self.u.take().filter(|u| u.next.borrow().is_some())?;
+        let x = u.x.borrow().clone();
+        if x.usize_value() < self.hi.usize_value() {
+            self.u = u.next.borrow().clone();
+            Some(x)
+        } else {
+            None
+        }
+    }
+}
+
+pub struct Iter<'a, T: USizeV + Default + PartialOrd + Clone> {
+    front: Option<Rc<BTNode<T>>>,
+    back: Option<Rc<BTNode<T>>>,
+    n: usize,
+    set: PhantomData<&'a XFastTrie<T>>,
+}
+
+impl<'a, T: USizeV + Default + PartialOrd + Clone> Iterator for Iter<'a, T> {
+    type Item = T;
+    fn next(&mut self) -> Option<T> {
+        if self.n == 0 {
+            return None;
+        }
+        self.n -= 1;
+        let u = self.front.take()?;
+        self.front = u.next.borrow().clone();
+        let x = u.x.borrow().clone();
+        Some(x)
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.n, Some(self.n))
+    }
+}
+
+impl<'a, T: USizeV + Default + PartialOrd + Clone> DoubleEndedIterator for Iter<'a, T> {
+    fn next_back(&mut self) -> Option<T> {
+        if self.n == 0 {
+            return None;
+        }
+        self.n -= 1;
+        let u = self.back.take()?;
+        self.back = u.prev.borrow().as_ref().and_then(|p| p.upgrade());
+        let x = u.x.borrow().clone();
+        Some(x)
+    }
+}
+
 impl<T: PartialOrd + Clone + Default + USizeV> Drop for XFastTrie<T> {
     fn drop(&mut self) {
         while let Some(ref x) = self.head.as_ref().and_then(|s| {
@@ -113,10 +175,58 @@ impl<T: USizeV + Default + PartialOrd + Clone> XFastTrie<T> {
             _ => None,
         }
     }
+    // the weight of the leaves left of the search path for x; every leaf weighs 1
+    // unless reweighed, so this is the number of elements smaller than x
+    pub fn rank(&self, x: &T) -> usize {
+        let ix = x.usize_value();
+        let mut k = 0;
+        let mut u = self.r.clone();
+        for i in 0..Self::W {
+            let c = (ix >> (Self::W - i - 1)) & 1;
+            if c == 1 {
+                k += u.child[0].borrow().as_ref().map_or(0, |l| l.size.get());
+            }
+            let next = match *u.child[c].borrow() {
+                Some(ref c) => c.clone(),
+                None => break,
+            };
+            u = next;
+        }
+        k
+    }
+    // sets the weight rank counts for the leaf u
+    pub fn reweigh(&self, u: &Rc<BTNode<T>>, weight: usize) {
+        let old = u.size.replace(weight);
+        let mut v = u.parent.borrow().as_ref().and_then(|p| p.upgrade());
+        while let Some(vi) = v {
+            vi.size.set(vi.size.get() + weight - old);
+            v = vi.parent.borrow().as_ref().and_then(|p| p.upgrade());
+        }
+    }
+    pub fn iter(&self) -> Iter<'_, T> {
+        Iter {
+            front: self.head.as_ref().and_then(|h| h.next.borrow().clone()),
+            back: self
+                .tail
+                .as_ref()
+                .and_then(|t| t.upgrade())
+                .and_then(|t| t.prev.borrow().as_ref().and_then(|p| p.upgrade())),
+            n: self.n,
+            set: PhantomData,
+        }
+    }
+    pub fn range(&self, r: std::ops::Range<T>) -> Range<'_, T> {
+        Range {
+            u: self.find_node(r.start.usize_value()),
+            hi: r.end,
+            set: PhantomData,
+        }
+    }
     pub fn remove_node(&mut self, u: Rc<BTNode<T>>) -> Option<T> {
         let ix = u.x.borrow().usize_value();
         let mut c;
         // 2 - remove u from linked list
+        let weight = u.size.get();
         let next = u.next.borrow_mut().take();
         let prev = u.prev.borrow_mut().take();
         if let Some(n) = next.as_ref() {
@@ -144,6 +254,7 @@ impl<T: USizeV + Default + PartialOrd + Clone> XFastTrie<T> {
         }
 
         // 4 - update jump pointers
+        v.size.set(v.size.get() - weight);
         c = if v.child[0].borrow().is_none() { 1 } else { 0 };
         *v.jump.borrow_mut() = if c == 0 {
             prev.as_ref().and_then(|p| p.upgrade())
@@ -166,6 +277,7 @@ impl<T: USizeV + Default + PartialOrd + Clone> XFastTrie<T> {
                     next.clone()
                 };
             }
+            vi.size.set(vi.size.get() - weight);
             v = vi.parent.borrow().as_ref().and_then(|p| p.upgrade());
         }
         self.n -= 1;
@@ -173,6 +285,15 @@ impl<T: USizeV + Default + PartialOrd + Clone> XFastTrie<T> {
     }
 }
 
+impl<T: USizeV + Default + PartialOrd + Clone + Codec> XFastTrie<T> {
+    pub fn dump(&self) -> Vec<u8> {
+        snapshot::dump(self.iter())
+    }
+    pub fn load(buf: &[u8]) -> Result<Self, SnapshotError> {
+        snapshot::load(buf).map(|xs: Vec<T>| xs.into_iter().collect())
+    }
+}
+
 impl<T: USizeV + Default + PartialOrd + Clone> SSet<T> for XFastTrie<T> {
     fn size(&self) -> usize {
         self.n
@@ -222,9 +343,10 @@ impl<T: USizeV + Default + PartialOrd + Clone> SSet<T> for XFastTrie<T> {
             i += 1;
         }
         *u.x.borrow_mut() = x;
+        u.size.set(1);
 
         // 3 - add u to linked list
-        *u.prev.borrow_mut() = pred.as_ref().map(|p| Rc::downgrade(&p));
+        *u.prev.borrow_mut() = pred.as_ref().map(Rc::downgrade);
         *u.next.borrow_mut() = pred.as_ref().and_then(|p| p.next.borrow().clone());
         u.prev
             .borrow()
@@ -257,6 +379,7 @@ impl<T: USizeV + Default + PartialOrd + Clone> SSet<T> for XFastTrie<T> {
             {
                 vi.jump.borrow_mut().replace(u.clone());
             }
+            vi.size.set(vi.size.get() + 1);
             v = vi.parent.borrow().as_ref().and_then(|p| p.upgrade());
         }
         self.n += 1;
@@ -281,6 +404,7 @@ impl<T: USizeV + Default + PartialOrd + Clone> SSet<T> for XFastTrie<T> {
         }
 
         // 2 - remove u from linked list
+        let weight = u.size.get();
         let next = u.next.borrow_mut().take();
         let prev = u.prev.borrow_mut().take();
         if let Some(n) = next.as_ref() {
@@ -308,6 +432,7 @@ impl<T: USizeV + Default + PartialOrd + Clone> SSet<T> for XFastTrie<T> {
         }
 
         // 4 - update jump pointers
+        v.size.set(v.size.get() - weight);
         c = if v.child[0].borrow().is_none() { 1 } else { 0 };
         *v.jump.borrow_mut() = if c == 0 {
             prev.as_ref().and_then(|p| p.upgrade())
@@ -330,6 +455,7 @@ impl<T: USizeV + Default + PartialOrd + Clone> SSet<T> for XFastTrie<T> {
                     next.clone()
                 };
             }
+            vi.size.set(vi.size.get() - weight);
             v = vi.parent.borrow().as_ref().and_then(|p| p.upgrade());
         }
         self.n -= 1;
@@ -375,6 +501,77 @@ impl<T: USizeV + Default + PartialOrd + Clone> SSet<T> for XFastTrie<T> {
             _ => None,
         }
     }
+    fn successor(&self, x: &T) -> Option<T> {
+        let ix = x.usize_value();
+        match self.find_node(ix) {
+            Some(u) if u.x.borrow().usize_value() == ix => u
+                .next
+                .borrow()
+                .as_ref()
+                .filter(|n| n.next.borrow().is_some())
+                .map(|n| n.x.borrow().clone()),
+            Some(u) => Some(u.x.borrow().clone()),
+            None => None,
+        }
+    }
+    fn predecessor(&self, x: &T) -> Option<T> {
+        let u = self
+            .find_node(x.usize_value())
+            .or_else(|| self.tail.as_ref().and_then(|t| t.upgrade()))?;
+        let p = u.prev.borrow().as_ref().and_then(|p| p.upgrade());
+        p.filter(|p| p.prev.borrow().is_some())
+            .map(|p| p.x.borrow().clone())
+    }
+    fn find_min(&self) -> Option<T> {
+        self.head.as_ref().and_then(|h| {
+            h.next
+                .borrow()
+                .as_ref()
+                .filter(|n| n.next.borrow().is_some())
+                .map(|n| n.x.borrow().clone())
+        })
+    }
+    fn find_max(&self) -> Option<T> {
+        let t = self.tail.as_ref().and_then(|t| t.upgrade())?;
+        let p = t.prev.borrow().as_ref().and_then(|p| p.upgrade());
+        p.filter(|p| p.prev.borrow().is_some())
+            .map(|p| p.x.borrow().clone())
+    }
+    fn count_range(&self, r: std::ops::Range<T>) -> usize {
+        self.rank(&r.end).saturating_sub(self.rank(&r.start))
+    }
+}
+
+impl<'a, T: USizeV + Default + PartialOrd + Clone> IntoIterator for &'a XFastTrie<T> {
+    type Item = T;
+    type IntoIter = Iter<'a, T>;
+    fn into_iter(self) -> Iter<'a, T> {
+        self.iter()
+    }
+}
+
+impl<T: USizeV + Default + PartialOrd + Clone> IntoIterator for XFastTrie<T> {
+    type Item = T;
+    type IntoIter = std::vec::IntoIter<T>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter().collect::<Vec<_>>().into_iter()
+    }
+}
+
+impl<T: USizeV + Default + PartialOrd + Clone> FromIterator<T> for XFastTrie<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut s = Self::new();
+        s.extend(iter);
+        s
+    }
+}
+
+impl<T: USizeV + Default + PartialOrd + Clone> Extend<T> for XFastTrie<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for x in iter {
+            self.add(x);
+        }
+    }
 }
 
 #[cfg(test)]
@@ -415,7 +612,48 @@ mod test {
                 let y2 = xfasttrie.find(&x);
                 assert_eq!(y1, y2);
             }
+            for _ in 0..n {
+                let x = rng.gen_range(0, 5 * n);
+                assert_eq!(redblacktree.successor(&x), xfasttrie.successor(&x));
+                assert_eq!(redblacktree.predecessor(&x), xfasttrie.predecessor(&x));
+                assert_eq!(redblacktree.floor(&x), xfasttrie.floor(&x));
+                assert_eq!(redblacktree.ceiling(&x), xfasttrie.ceiling(&x));
+            }
+            assert_eq!(redblacktree.find_min(), xfasttrie.find_min());
+            assert_eq!(redblacktree.find_max(), xfasttrie.find_max());
+            for _ in 0..n {
+                let x = rng.gen_range(0, 5 * n);
+                let y = x + rng.gen_range(0, n);
+                let r1 = redblacktree.range(x..y).collect::<Vec<_>>();
+                let r2 = xfasttrie.range(x..y).collect::<Vec<_>>();
+                assert_eq!(r1, r2);
+                assert_eq!(r1.len(), xfasttrie.count_range(x..y));
+                assert_eq!(redblacktree.rank(&x), xfasttrie.rank(&x));
+            }
+            assert_eq!(
+                redblacktree.iter().collect::<Vec<_>>(),
+                xfasttrie.iter().collect::<Vec<_>>()
+            );
+            assert_eq!(
+                redblacktree.iter().rev().collect::<Vec<_>>(),
+                (&xfasttrie).into_iter().rev().collect::<Vec<_>>()
+            );
         }
+        let mut xfasttrie: XFastTrie<i32> = vec![5, 3, 9, 3].into_iter().collect();
+        xfasttrie.extend(vec![1, 7]);
+        assert_eq!(
+            vec![9, 7, 5, 3, 1],
+            xfasttrie.into_iter().rev().collect::<Vec<_>>()
+        );
+        let xfasttrie: XFastTrie<i32> = (0..100).map(|x| x * x).collect();
+        let buf = xfasttrie.dump();
+        let copy = XFastTrie::<i32>::load(&buf).unwrap();
+        assert!(copy.iter().eq(xfasttrie.iter()));
+        assert_eq!(copy.find(&50), Some(64));
+        assert_eq!(copy.rank(&50), 8);
+        assert!(XFastTrie::<i32>::load(&buf[..buf.len() - 1]).is_err());
+        assert!(XFastTrie::<i32>::load(&buf[1..]).is_err());
+
         // test large linked list for stack overflow.
         let mut bst = XFastTrie::<i32>::new();
         let num = 100000;
@@ -1,8 +1,15 @@
 #![allow(clippy::many_single_char_names)]
-use crate::{xfasttrie::XFastTrie, USizeV};
+use crate::{
+    xfasttrie::{self, BTNode, XFastTrie},
+    USizeV,
+};
+use chapter01::codec::Codec;
 use chapter01::interface::SSet;
+use chapter01::snapshot::{self, SnapshotError};
 use chapter07::treap::Treap;
 use std::cell::RefCell;
+use std::iter::{FlatMap, FromIterator};
+use std::marker::PhantomData;
 use std::rc::Rc;
 
 #[derive(Clone, Debug, Default)]
@@ -62,6 +69,67 @@ where
     n: usize,
 }
 
+pub struct Range<'a, T>
+where
+    T: USizeV + Default + PartialOrd + Clone,
+{
+    u: Option<Rc<BTNode<YPair<T>>>>,
+    buf: std::vec::IntoIter<T>,
+    lo: T,
+    hi: T,
+    set: PhantomData<&'a YFastTrie<T>>,
+}
+
+impl<'a, T> Iterator for Range<'a, T>
+where
+    T: USizeV + Default + PartialOrd + Clone,
+{
+    type Item = T;
+    fn next(&mut self) -> Option<T> {
+        loop {
+            if let Some(x) = self.buf.next() {
+                return Some(x);
+            }
+            let u = self.u.take().filter(|u| u.next.borrow().is_some())?;
+            let y = u.x.borrow();
+            self.buf =
+                y.t.borrow()
+                    .range(self.lo.clone()..self.hi.clone())
+                    .collect::<Vec<_>>()
+                    .into_iter();
+            if y.ix < self.hi.usize_value() {
+                self.u = u.next.borrow().clone();
+            }
+        }
+    }
+}
+
+type Block<T> = std::vec::IntoIter<T>;
+type Unpack<T> = fn(YPair<T>) -> Block<T>;
+
+pub struct Iter<'a, T>(FlatMap<xfasttrie::Iter<'a, YPair<T>>, Block<T>, Unpack<T>>)
+where
+    T: USizeV + Default + PartialOrd + Clone;
+
+impl<'a, T> Iterator for Iter<'a, T>
+where
+    T: USizeV + Default + PartialOrd + Clone,
+{
+    type Item = T;
+    fn next(&mut self) -> Option<T> {
+        self.0.next()
+    }
+}
+
+impl<'a, T> DoubleEndedIterator for Iter<'a, T>
+where
+    T: USizeV + Default + PartialOrd + Clone,
+{
+    fn next_back(&mut self) -> Option<T> {
+        self.0.next_back()
+    }
+}
+
 impl<T> YFastTrie<T>
 where
     T: USizeV + Default + PartialOrd + Clone,
@@ -72,6 +140,46 @@ where
         xft.add(YPair::with_x((1 << Self::W) - 1));
         Self { n: 0, xft }
     }
+    pub fn iter(&self) -> Iter<'_, T> {
+        fn block<T>(y: YPair<T>) -> Block<T>
+        where
+            T: USizeV + Default + PartialOrd + Clone,
+        {
+            let t = y.t.borrow();
+            t.iter().collect::<Vec<_>>().into_iter()
+        }
+        Iter(self.xft.iter().flat_map(block as Unpack<T>))
+    }
+    // the elements in the treaps before x's, then those below x in its own
+    pub fn rank(&self, x: &T) -> usize {
+        let ix = x.usize_value();
+        let k = self.xft.rank(&YPair::with_x(ix));
+        match self.xft.find_node(ix) {
+            Some(u) => k + u.x.borrow().t.borrow().rank(x),
+            None => k,
+        }
+    }
+    pub fn range(&self, r: std::ops::Range<T>) -> Range<'_, T> {
+        Range {
+            u: self.xft.find_node(r.start.usize_value()),
+            buf: Vec::new().into_iter(),
+            lo: r.start,
+            hi: r.end,
+            set: PhantomData,
+        }
+    }
+}
+
+impl<T> YFastTrie<T>
+where
+    T: USizeV + Default + PartialOrd + Clone + std::fmt::Debug + Codec,
+{
+    pub fn dump(&self) -> Vec<u8> {
+        snapshot::dump(self.iter())
+    }
+    pub fn load(buf: &[u8]) -> Result<Self, SnapshotError> {
+        snapshot::load(buf).map(|xs: Vec<T>| xs.into_iter().collect())
+    }
 }
 
 impl<T> SSet<T> for YFastTrie<T>
@@ -81,24 +189,28 @@ where
     fn size(&self) -> usize {
         self.n
     }
+    // each leaf of xft weighs as much as its treap holds, so xft ranks elements
     fn add(&mut self, x: T) -> bool {
         let ix = x.usize_value();
-        let mut t = self.xft.find(&YPair::with_x(ix)).map(|y| y.t);
-        match t {
-            Some(ref mut t) => {
-                if t.borrow_mut().add(x.clone()) {
-                    self.n += 1;
-                    if rand::random::<usize>() % Self::W == 0 {
-                        let t1 = t.borrow_mut().split(x);
-                        self.xft.add(YPair::with_xt(ix, t1));
-                    }
-                    true
-                } else {
-                    false
-                }
+        let u = match self.xft.find_node(ix) {
+            Some(u) => u,
+            None => return false,
+        };
+        let t = u.x.borrow().t.clone();
+        if !t.borrow_mut().add(x.clone()) {
+            return false;
+        }
+        self.n += 1;
+        if rand::random::<usize>().is_multiple_of(Self::W) {
+            let t1 = t.borrow_mut().split(x);
+            let k = t1.size();
+            self.xft.add(YPair::with_xt(ix, t1));
+            if let Some(w) = self.xft.find_node(ix) {
+                self.xft.reweigh(&w, k);
             }
-            None => false,
         }
+        self.xft.reweigh(&u, t.borrow().size());
+        true
     }
     fn remove(&mut self, x: &T) -> Option<T> {
         let ix = x.usize_value();
@@ -111,12 +223,12 @@ where
             self.n -= 1;
         }
         if let Some(u) = u {
+            self.xft.reweigh(&u, u.x.borrow().t.borrow().size());
             if u.x.borrow().ix == ix && ix != (1 << Self::W) - 1 {
                 if let Some(n) = u.next.borrow().as_ref() {
-                    n.x.borrow()
-                        .t
-                        .borrow_mut()
-                        .absorb(u.x.borrow().t.replace(Treap::new()));
+                    let t = n.x.borrow().t.clone();
+                    t.borrow_mut().absorb(u.x.borrow().t.replace(Treap::new()));
+                    self.xft.reweigh(n, t.borrow().size());
                 }
                 self.xft.remove_node(u);
             }
@@ -126,7 +238,80 @@ where
     fn find(&self, x: &T) -> Option<T> {
         self.xft
             .find(&YPair::with_x(x.usize_value()))
-            .and_then(|y| y.t.borrow().find(&x))
+            .and_then(|y| y.t.borrow().find(x))
+    }
+    fn successor(&self, x: &T) -> Option<T> {
+        let y = self.xft.find(&YPair::with_x(x.usize_value()))?;
+        let z = y.t.borrow().successor(x);
+        z.or_else(|| self.xft.successor(&y).and_then(|y| y.t.borrow().find_min()))
+    }
+    fn predecessor(&self, x: &T) -> Option<T> {
+        let y = self.xft.find(&YPair::with_x(x.usize_value()))?;
+        let z = y.t.borrow().predecessor(x);
+        z.or_else(|| {
+            self.xft
+                .predecessor(&y)
+                .and_then(|y| y.t.borrow().find_max())
+        })
+    }
+    fn find_min(&self) -> Option<T> {
+        self.xft.find_min().and_then(|y| y.t.borrow().find_min())
+    }
+    fn find_max(&self) -> Option<T> {
+        let y = self.xft.find_max()?;
+        let z = y.t.borrow().find_max();
+        z.or_else(|| {
+            self.xft
+                .predecessor(&y)
+                .and_then(|y| y.t.borrow().find_max())
+        })
+    }
+    fn count_range(&self, r: std::ops::Range<T>) -> usize {
+        self.rank(&r.end).saturating_sub(self.rank(&r.start))
+    }
+}
+
+impl<'a, T> IntoIterator for &'a YFastTrie<T>
+where
+    T: USizeV + Default + PartialOrd + Clone,
+{
+    type Item = T;
+    type IntoIter = Iter<'a, T>;
+    fn into_iter(self) -> Iter<'a, T> {
+        self.iter()
+    }
+}
+
+impl<T> IntoIterator for YFastTrie<T>
+where
+    T: USizeV + Default + PartialOrd + Clone,
+{
+    type Item = T;
+    type IntoIter = std::vec::IntoIter<T>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter().collect::<Vec<_>>().into_iter()
+    }
+}
+
+impl<T> FromIterator<T> for YFastTrie<T>
+where
+    T: USizeV + Default + PartialOrd + Clone + std::fmt::Debug,
+{
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut s = Self::new();
+        s.extend(iter);
+        s
+    }
+}
+
+impl<T> Extend<T> for YFastTrie<T>
+where
+    T: USizeV + Default + PartialOrd + Clone + std::fmt::Debug,
+{
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for x in iter {
+            self.add(x);
+        }
     }
 }
 
@@ -169,7 +354,47 @@ mod test {
                 let y2 = yfasttrie.find(&x);
                 assert_eq!(y1, y2);
             }
+            for _ in 0..n {
+                let x = rng.gen_range(0, 5 * n);
+                assert_eq!(redblacktree.successor(&x), yfasttrie.successor(&x));
+                assert_eq!(redblacktree.predecessor(&x), yfasttrie.predecessor(&x));
+                assert_eq!(redblacktree.floor(&x), yfasttrie.floor(&x));
+                assert_eq!(redblacktree.ceiling(&x), yfasttrie.ceiling(&x));
+            }
+            assert_eq!(redblacktree.find_min(), yfasttrie.find_min());
+            assert_eq!(redblacktree.find_max(), yfasttrie.find_max());
+            for _ in 0..n {
+                let x = rng.gen_range(0, 5 * n);
+                let y = x + rng.gen_range(0, n);
+                let r1 = redblacktree.range(x..y).collect::<Vec<_>>();
+                let r2 = yfasttrie.range(x..y).collect::<Vec<_>>();
+                assert_eq!(r1, r2);
+                assert_eq!(r1.len(), yfasttrie.count_range(x..y));
+                assert_eq!(redblacktree.rank(&x), yfasttrie.rank(&x));
+            }
+            assert_eq!(
+                redblacktree.iter().collect::<Vec<_>>(),
+                yfasttrie.iter().collect::<Vec<_>>()
+            );
+            assert_eq!(
+                redblacktree.iter().rev().collect::<Vec<_>>(),
+                (&yfasttrie).into_iter().rev().collect::<Vec<_>>()
+            );
         }
+        let mut yfasttrie: YFastTrie<i32> = vec![5, 3, 9, 3].into_iter().collect();
+        yfasttrie.extend(vec![1, 7]);
+        assert_eq!(
+            vec![9, 7, 5, 3, 1],
+            yfasttrie.into_iter().rev().collect::<Vec<_>>()
+        );
+        let yfasttrie: YFastTrie<i32> = (0..100).map(|x| x * x).collect();
+        let buf = yfasttrie.dump();
+        let copy = YFastTrie::<i32>::load(&buf).unwrap();
+        assert!(copy.iter().eq(yfasttrie.iter()));
+        assert_eq!(copy.find(&50), Some(64));
+        assert_eq!(copy.rank(&50), 8);
+        assert!(YFastTrie::<i32>::load(&buf[..buf.len() - 1]).is_err());
+        assert!(YFastTrie::<i32>::load(&buf[1..]).is_err());
         // test large linked list for stack overflow.
         let mut bst = YFastTrie::<i32>::new();
         let num = 100000;
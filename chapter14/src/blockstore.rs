@@ -1,11 +1,101 @@
 #![allow(clippy::many_single_char_names,clippy::explicit_counter_loop)]
 use chapter01::interface::List;
 use chapter02::arraystack::Array as ArrayStack;
+use std::cell::Cell;
+
+// the external memory model: blocks are read and written as a whole
+pub trait BlockDevice<T> {
+    fn place_block(&mut self, block: T) -> usize;
+    fn free_block(&mut self, i: usize);
+    fn read_block(&self, i: usize) -> Option<T>;
+    fn write_block(&mut self, i: usize, block: T);
+    // every block id in use is below this
+    fn bound(&self) -> usize;
+    // marks the end of an update along with the owner's metadata; stores that
+    // keep a log make the update durable here
+    fn commit(&mut self, _meta: &[u8]) {}
+    fn stats(&self) -> IoStats;
+    fn reset_stats(&mut self);
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct IoStats {
+    pub reads: usize,
+    pub writes: usize,
+    pub allocs: usize,
+    pub frees: usize,
+    pub hits: usize,   // reads answered from a cache
+    pub misses: usize, // reads a cache had to pass on
+}
+
+impl IoStats {
+    // block transfers, the cost measure of the external memory model
+    pub fn transfers(&self) -> usize {
+        self.reads + self.writes
+    }
+}
+
+impl std::ops::Sub for IoStats {
+    type Output = IoStats;
+    fn sub(self, other: IoStats) -> IoStats {
+        IoStats {
+            reads: self.reads.saturating_sub(other.reads),
+            writes: self.writes.saturating_sub(other.writes),
+            allocs: self.allocs.saturating_sub(other.allocs),
+            frees: self.frees.saturating_sub(other.frees),
+            hits: self.hits.saturating_sub(other.hits),
+            misses: self.misses.saturating_sub(other.misses),
+        }
+    }
+}
+
+// counters never take part in comparing the stores that keep them
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Counters(Cell<IoStats>);
+
+impl Counters {
+    pub(crate) fn get(&self) -> IoStats {
+        self.0.get()
+    }
+    pub(crate) fn reset(&self) {
+        self.0.set(IoStats::default());
+    }
+    pub(crate) fn update<F: FnOnce(&mut IoStats)>(&self, f: F) {
+        let mut s = self.0.get();
+        f(&mut s);
+        self.0.set(s);
+    }
+}
+
+impl PartialEq for Counters {
+    fn eq(&self, _: &Self) -> bool {
+        true
+    }
+}
+
+impl Eq for Counters {}
+
+impl PartialOrd for Counters {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Counters {
+    fn cmp(&self, _: &Self) -> std::cmp::Ordering {
+        std::cmp::Ordering::Equal
+    }
+}
+
+impl std::hash::Hash for Counters {
+    fn hash<H: std::hash::Hasher>(&self, _: &mut H) {}
+}
 
 #[derive(Clone, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
 pub struct BlockStore<T: Clone> {
     blocks: ArrayStack<T>,
     free: ArrayStack<usize>,
+    stats: Counters,
 }
 
 impl<T: Clone> BlockStore<T> {
@@ -13,11 +103,15 @@ impl<T: Clone> BlockStore<T> {
         Self {
             blocks: ArrayStack::new(),
             free: ArrayStack::new(),
+            stats: Counters::default(),
         }
     }
     pub fn place_block(&mut self, block: T) -> usize {
+        self.stats.update(|s| s.allocs += 1);
         if self.free.size() > 0 {
-            self.free.remove(self.free.size() - 1).unwrap()
+            let i = self.free.remove(self.free.size() - 1).unwrap();
+            self.blocks.set(i, block);
+            i
         } else {
             let i = self.blocks.size();
             self.blocks.add(i, block);
@@ -25,13 +119,92 @@ impl<T: Clone> BlockStore<T> {
         }
     }
     pub fn free_block(&mut self, i: usize) {
+        self.stats.update(|s| s.frees += 1);
         self.blocks.take(i);
         self.free.add(self.free.size(), i);
     }
     pub fn read_block(&self, i: usize) -> Option<T> {
+        self.stats.update(|s| s.reads += 1);
         self.blocks.get(i)
     }
     pub fn write_block(&mut self, i: usize, block: T) {
+        self.stats.update(|s| s.writes += 1);
         self.blocks.set(i, block);
     }
+    // block i changed in place, which costs a read and a write
+    pub fn block_mut(&mut self, i: usize) -> Option<&mut T> {
+        self.stats.update(|s| {
+            s.reads += 1;
+            s.writes += 1
+        });
+        self.blocks.get_mut(i)
+    }
+    pub fn stats(&self) -> IoStats {
+        self.stats.get()
+    }
+    pub fn reset_stats(&mut self) {
+        self.stats.reset();
+    }
+}
+
+impl<T: Clone> BlockDevice<T> for BlockStore<T> {
+    fn place_block(&mut self, block: T) -> usize {
+        BlockStore::place_block(self, block)
+    }
+    fn free_block(&mut self, i: usize) {
+        BlockStore::free_block(self, i)
+    }
+    fn read_block(&self, i: usize) -> Option<T> {
+        BlockStore::read_block(self, i)
+    }
+    fn write_block(&mut self, i: usize, block: T) {
+        BlockStore::write_block(self, i, block)
+    }
+    fn bound(&self) -> usize {
+        self.blocks.size()
+    }
+    fn stats(&self) -> IoStats {
+        BlockStore::stats(self)
+    }
+    fn reset_stats(&mut self) {
+        BlockStore::reset_stats(self)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[test]
+    fn test_blockstore() {
+        let mut bs = BlockStore::<usize>::new();
+        for i in 0..5 {
+            assert_eq!(bs.place_block(i), i);
+        }
+        bs.free_block(1);
+        bs.free_block(3);
+        assert_eq!(bs.read_block(3), None);
+        assert_eq!(bs.place_block(33), 3);
+        assert_eq!(bs.read_block(3), Some(33));
+        bs.write_block(0, 10);
+        let before = bs.stats();
+        assert_eq!(
+            before,
+            IoStats {
+                reads: 2,
+                writes: 1,
+                allocs: 6,
+                frees: 2,
+                hits: 0,
+                misses: 0,
+            }
+        );
+        assert_eq!(before.transfers(), 3);
+        bs.read_block(0);
+        assert_eq!((bs.stats() - before).reads, 1);
+        // counters do not affect comparisons
+        let mut other = bs.clone();
+        other.reset_stats();
+        assert_eq!(other, bs);
+        assert_eq!(other.stats(), IoStats::default());
+    }
 }
@@ -0,0 +1,943 @@
+#![allow(clippy::many_single_char_names,clippy::explicit_counter_loop)]
+use crate::blockstore::{BlockDevice, BlockStore, IoStats};
+use crate::codec::Codec;
+use crate::fileblockstore::FileBlockStore;
+use chapter01::error::Error;
+use chapter01::interface::{Pair, SSet, SSetMap, USetMap};
+use chapter01::snapshot::{self, SnapshotError};
+use std::io;
+use std::path::Path;
+
+#[derive(Clone, Debug, Default, Eq, Ord, PartialEq, PartialOrd)]
+pub struct Node<T> {
+    id: usize,
+    keys: Vec<T>,
+    children: Vec<i32>, // empty in a leaf
+    prev: i32,          // neighbouring leaves, -1 at either end
+    next: i32,
+}
+
+#[allow(non_snake_case)]
+#[derive(Clone, Debug, Default, Eq, Ord, PartialEq, PartialOrd)]
+pub struct BPlusTree<T: Clone + PartialOrd, S = BlockStore<Node<T>>> {
+    b: usize,  // the maximum number of children of a node
+    B: usize,  // the minimum number of keys in a node other than the root
+    n: usize,  // number of elements stored in the tree
+    ri: usize, // index of the root
+    bs: S,
+    t: std::marker::PhantomData<T>,
+}
+
+pub struct Range<'a, T: Clone + PartialOrd, S = BlockStore<Node<T>>> {
+    t: &'a BPlusTree<T, S>,
+    cur: Option<(Node<T>, usize)>,
+    hi: T,
+}
+
+impl<'a, T: Clone + PartialOrd, S: BlockDevice<Node<T>>> Iterator for Range<'a, T, S> {
+    type Item = T;
+    fn next(&mut self) -> Option<T> {
+        let x = self.t.step_forward(&mut self.cur)?;
+        if x >= self.hi {
+            self.cur = None;
+            return None;
+        }
+        Some(x)
+    }
+}
+
+pub struct Iter<'a, T: Clone + PartialOrd, S = BlockStore<Node<T>>> {
+    t: &'a BPlusTree<T, S>,
+    front: Option<(Node<T>, usize)>,
+    back: Option<(Node<T>, usize)>,
+    n: usize,
+}
+
+impl<'a, T: Clone + PartialOrd, S: BlockDevice<Node<T>>> Iterator for Iter<'a, T, S> {
+    type Item = T;
+    fn next(&mut self) -> Option<T> {
+        if self.n == 0 {
+            return None;
+        }
+        self.n -= 1;
+        self.t.step_forward(&mut self.front)
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.n, Some(self.n))
+    }
+}
+
+impl<'a, T: Clone + PartialOrd, S: BlockDevice<Node<T>>> DoubleEndedIterator for Iter<'a, T, S> {
+    fn next_back(&mut self) -> Option<T> {
+        if self.n == 0 {
+            return None;
+        }
+        self.n -= 1;
+        self.t.step_back(&mut self.back)
+    }
+}
+
+impl<T: Clone + PartialOrd> Node<T> {
+    fn new(keys: Vec<T>, children: Vec<i32>) -> Self {
+        Self {
+            id: 0,
+            keys,
+            children,
+            prev: -1,
+            next: -1,
+        }
+    }
+    fn is_leaf(&self) -> bool {
+        self.children.is_empty()
+    }
+    // the number of keys <= x, which is the child whose subtree x belongs in
+    fn child_index(&self, x: &T) -> usize {
+        self.keys.partition_point(|k| k <= x)
+    }
+    // the number of keys < x
+    fn lower_bound(&self, x: &T) -> usize {
+        self.keys.partition_point(|k| k < x)
+    }
+}
+
+impl<T: Codec> Codec for Node<T> {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        self.id.encode(buf);
+        self.keys.encode(buf);
+        self.children.encode(buf);
+        self.prev.encode(buf);
+        self.next.encode(buf);
+    }
+    fn decode(buf: &mut &[u8]) -> Option<Self> {
+        Some(Self {
+            id: usize::decode(buf)?,
+            keys: Vec::decode(buf)?,
+            children: Vec::decode(buf)?,
+            prev: i32::decode(buf)?,
+            next: i32::decode(buf)?,
+        })
+    }
+}
+
+impl<T: Clone + PartialOrd> BPlusTree<T> {
+    pub fn new(b: usize) -> Self {
+        Self::with_store(b, BlockStore::new())
+    }
+    pub fn bulk_load<I: IntoIterator<Item = T>>(b: usize, iter: I) -> Result<Self, Error> {
+        Self::bulk_load_with_store(b, BlockStore::new(), iter)
+    }
+    pub fn load(b: usize, buf: &[u8]) -> Result<Self, SnapshotError>
+    where
+        T: Codec,
+    {
+        Self::load_with_store(b, BlockStore::new(), buf)
+    }
+    // the element equal to x, changed in place in its leaf
+    fn find_mut(&mut self, x: &T) -> Option<&mut T> {
+        let u = self.leaf_for(x)?;
+        let i = u.lower_bound(x);
+        if i == u.keys.len() || u.keys[i] != *x {
+            return None;
+        }
+        self.bs.block_mut(u.id)?.keys.get_mut(i)
+    }
+}
+
+impl<T: Clone + PartialOrd + Codec> BPlusTree<T, FileBlockStore<Node<T>>> {
+    pub fn create<P: AsRef<Path>>(
+        path: P,
+        b: usize,
+        page_size: usize,
+        capacity: usize,
+    ) -> io::Result<Self> {
+        let mut tree = Self::with_store(b, FileBlockStore::create(path, page_size, capacity)?);
+        tree.flush()?;
+        Ok(tree)
+    }
+    pub fn open<P: AsRef<Path>>(path: P, capacity: usize) -> io::Result<Self> {
+        let bs = FileBlockStore::open(path, capacity)?;
+        let mut meta = bs.meta();
+        match (
+            usize::decode(&mut meta),
+            usize::decode(&mut meta),
+            usize::decode(&mut meta),
+        ) {
+            (Some(b), Some(n), Some(ri)) if b >= 3 && ri < bs.pages() => Ok(Self {
+                b,
+                B: (b - 1) / 2,
+                n,
+                ri,
+                bs,
+                t: std::marker::PhantomData,
+            }),
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "not a b+-tree file",
+            )),
+        }
+    }
+    // a checkpoint, with the same guarantees as BTree::flush
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.bs.set_meta(&self.meta())?;
+        self.bs.flush()
+    }
+    pub fn enable_wal(&mut self) -> io::Result<()> {
+        self.bs.set_meta(&self.meta())?;
+        self.bs.enable_wal()
+    }
+    pub fn disable_wal(&mut self) -> io::Result<()> {
+        self.bs.set_meta(&self.meta())?;
+        self.bs.disable_wal()
+    }
+}
+
+impl<T: Clone + PartialOrd, S: BlockDevice<Node<T>>> BPlusTree<T, S> {
+    // bs must not hold any blocks yet
+    pub fn with_store(b: usize, bs: S) -> Self {
+        let b = std::cmp::max(b, 3);
+        let mut tree = Self {
+            b,
+            B: (b - 1) / 2,
+            n: 0,
+            ri: 0,
+            bs,
+            t: std::marker::PhantomData,
+        };
+        tree.ri = tree.place(Node::new(vec![], vec![])).id;
+        tree
+    }
+    // builds the tree bottom up from sorted input, dropping repeats and leaving
+    // every leaf as full as possible
+    pub fn bulk_load_with_store<I: IntoIterator<Item = T>>(
+        b: usize,
+        bs: S,
+        iter: I,
+    ) -> Result<Self, Error> {
+        let mut keys: Vec<T> = vec![];
+        for (index, x) in iter.into_iter().enumerate() {
+            match keys.last() {
+                Some(last) if *last > x => return Err(Error::Unsorted { index }),
+                Some(last) if *last == x => continue,
+                _ => keys.push(x),
+            }
+        }
+        Ok(Self::build(b, bs, keys))
+    }
+    // keys must be strictly increasing
+    fn build(b: usize, bs: S, mut keys: Vec<T>) -> Self {
+        let mut tree = Self::with_store(b, bs);
+        if keys.is_empty() {
+            tree.commit();
+            return tree;
+        }
+        tree.n = keys.len();
+        let root = tree.ri;
+        tree.bs.free_block(root);
+
+        // each level is a list of (node, smallest key below it)
+        let mut level: Vec<(i32, T)> = vec![];
+        let mut prev: Option<Node<T>> = None;
+        for chunk in Self::chunks(keys.len(), tree.b - 1, tree.B) {
+            let rest = keys.split_off(chunk);
+            let mut u = Node::new(std::mem::replace(&mut keys, rest), vec![]);
+            if let Some(p) = prev.as_ref() {
+                u.prev = p.id as i32;
+            }
+            let u = tree.place(u);
+            if let Some(mut p) = prev.take() {
+                p.next = u.id as i32;
+                tree.bs.write_block(p.id, p);
+            }
+            level.push((u.id as i32, u.keys[0].clone()));
+            prev = Some(u);
+        }
+        if let Some(p) = prev {
+            tree.bs.write_block(p.id, p);
+        }
+        while level.len() > 1 {
+            let mut next = vec![];
+            let mut rest = level.into_iter();
+            for chunk in Self::chunks(rest.len(), tree.b, tree.B + 1) {
+                let group: Vec<(i32, T)> = rest.by_ref().take(chunk).collect();
+                let lo = group[0].1.clone();
+                let keys = group.iter().skip(1).map(|(_, k)| k.clone()).collect();
+                let children = group.iter().map(|(c, _)| *c).collect();
+                let u = tree.place(Node::new(keys, children));
+                next.push((u.id as i32, lo));
+            }
+            level = next;
+        }
+        tree.ri = level[0].0 as usize;
+        tree.commit();
+        tree
+    }
+    pub fn dump(&self) -> Vec<u8>
+    where
+        T: Codec,
+    {
+        snapshot::dump(self.iter())
+    }
+    // a snapshot is sorted, so it is bulk loaded
+    pub fn load_with_store(b: usize, bs: S, buf: &[u8]) -> Result<Self, SnapshotError>
+    where
+        T: Codec,
+    {
+        Ok(Self::build(b, bs, snapshot::load(buf)?))
+    }
+    // splits n items into groups of at most max, and at least min when there is more than one
+    fn chunks(n: usize, max: usize, min: usize) -> Vec<usize> {
+        let mut sizes = vec![max; n / max];
+        let r = n % max;
+        if r > 0 {
+            sizes.push(r);
+        }
+        let k = sizes.len();
+        if k > 1 && sizes[k - 1] < min {
+            let total = sizes[k - 2] + sizes[k - 1];
+            sizes[k - 2] = total - total / 2;
+            sizes[k - 1] = total / 2;
+        }
+        sizes
+    }
+    pub fn store(&self) -> &S {
+        &self.bs
+    }
+    pub fn stats(&self) -> IoStats {
+        self.bs.stats()
+    }
+    pub fn reset_stats(&mut self) {
+        self.bs.reset_stats()
+    }
+    fn meta(&self) -> Vec<u8> {
+        let mut meta = vec![];
+        self.b.encode(&mut meta);
+        self.n.encode(&mut meta);
+        self.ri.encode(&mut meta);
+        meta
+    }
+    fn commit(&mut self) {
+        let meta = self.meta();
+        self.bs.commit(&meta);
+    }
+    fn place(&mut self, mut u: Node<T>) -> Node<T> {
+        u.id = self.bs.place_block(u.clone());
+        self.bs.write_block(u.id, u.clone());
+        u
+    }
+    fn node(&self, ui: i32) -> Option<Node<T>> {
+        if ui < 0 {
+            None
+        } else {
+            self.bs.read_block(ui as usize)
+        }
+    }
+    fn leaf_for(&self, x: &T) -> Option<Node<T>> {
+        let mut u = self.node(self.ri as i32)?;
+        while !u.is_leaf() {
+            u = self.node(u.children[u.child_index(x)])?;
+        }
+        Some(u)
+    }
+    fn leftmost_leaf(&self) -> Option<Node<T>> {
+        let mut u = self.node(self.ri as i32)?;
+        while !u.is_leaf() {
+            u = self.node(u.children[0])?;
+        }
+        Some(u)
+    }
+    fn rightmost_leaf(&self) -> Option<Node<T>> {
+        let mut u = self.node(self.ri as i32)?;
+        while !u.is_leaf() {
+            u = self.node(u.children[u.children.len() - 1])?;
+        }
+        Some(u)
+    }
+    fn step_forward(&self, cur: &mut Option<(Node<T>, usize)>) -> Option<T> {
+        loop {
+            let (u, i) = cur.as_mut()?;
+            if *i < u.keys.len() {
+                *i += 1;
+                return Some(u.keys[*i - 1].clone());
+            }
+            *cur = self.node(u.next).map(|v| (v, 0));
+        }
+    }
+    fn step_back(&self, cur: &mut Option<(Node<T>, usize)>) -> Option<T> {
+        loop {
+            let (u, i) = cur.as_mut()?;
+            if *i > 0 {
+                *i -= 1;
+                return Some(u.keys[*i].clone());
+            }
+            *cur = self.node(u.prev).map(|v| {
+                let i = v.keys.len();
+                (v, i)
+            });
+        }
+    }
+    pub fn iter(&self) -> Iter<'_, T, S> {
+        Iter {
+            t: self,
+            front: self.leftmost_leaf().map(|u| (u, 0)),
+            back: self.rightmost_leaf().map(|u| {
+                let i = u.keys.len();
+                (u, i)
+            }),
+            n: self.n,
+        }
+    }
+    pub fn range(&self, r: std::ops::Range<T>) -> Range<'_, T, S> {
+        Range {
+            t: self,
+            cur: self.leaf_for(&r.start).map(|u| {
+                let i = u.lower_bound(&r.start);
+                (u, i)
+            }),
+            hi: r.end,
+        }
+    }
+    fn split(&mut self, u: &mut Node<T>) -> (T, i32) {
+        let (sep, w) = if u.is_leaf() {
+            let mut w = Node::new(u.keys.split_off(u.keys.len() / 2), vec![]);
+            w.prev = u.id as i32;
+            w.next = u.next;
+            let w = self.place(w);
+            if let Some(mut v) = self.node(u.next) {
+                v.prev = w.id as i32;
+                self.bs.write_block(v.id, v);
+            }
+            u.next = w.id as i32;
+            (w.keys[0].clone(), w)
+        } else {
+            let m = u.keys.len() / 2;
+            let keys = u.keys.split_off(m + 1);
+            let children = u.children.split_off(m + 1);
+            let sep = u.keys.pop().unwrap();
+            (sep, self.place(Node::new(keys, children)))
+        };
+        self.bs.write_block(u.id, u.clone());
+        (sep, w.id as i32)
+    }
+    // Err holds the key x took the place of when replacing
+    fn add_recursive(
+        &mut self,
+        x: T,
+        ui: i32,
+        replace: bool,
+    ) -> Result<Option<(T, i32)>, Option<T>> {
+        let mut u = self.node(ui).ok_or(None)?;
+        if u.is_leaf() {
+            let i = u.lower_bound(&x);
+            if i < u.keys.len() && u.keys[i] == x {
+                if !replace {
+                    return Err(None);
+                }
+                let y = std::mem::replace(&mut u.keys[i], x);
+                self.bs.write_block(u.id, u);
+                return Err(Some(y));
+            }
+            u.keys.insert(i, x);
+        } else {
+            let i = u.child_index(&x);
+            if let Some((sep, wi)) = self.add_recursive(x, u.children[i], replace)? {
+                u.keys.insert(i, sep);
+                u.children.insert(i + 1, wi);
+            } else {
+                return Ok(None);
+            }
+        }
+        if u.keys.len() >= self.b {
+            Ok(Some(self.split(&mut u)))
+        } else {
+            self.bs.write_block(u.id, u);
+            Ok(None)
+        }
+    }
+    // one descent for add and replace: Err holds what x replaced, if anything
+    fn put(&mut self, x: T, replace: bool) -> Result<(), Option<T>> {
+        match self.add_recursive(x, self.ri as i32, replace) {
+            Ok(w) => {
+                if let Some((sep, wi)) = w {
+                    let root = Node::new(vec![sep], vec![self.ri as i32, wi]);
+                    self.ri = self.place(root).id;
+                }
+                self.n += 1;
+                self.commit();
+                Ok(())
+            }
+            Err(Some(y)) => {
+                self.commit();
+                Err(Some(y))
+            }
+            Err(None) => Err(None),
+        }
+    }
+    // adds x, or puts it in the place of the element equal to it and returns that one
+    pub fn replace(&mut self, x: T) -> Option<T> {
+        self.put(x, true).err().flatten()
+    }
+    // merges child i + 1 of u into child i
+    fn merge(&mut self, u: &mut Node<T>, i: usize, v: &mut Node<T>, w: Node<T>) {
+        let sep = u.keys.remove(i);
+        u.children.remove(i + 1);
+        if v.is_leaf() {
+            v.next = w.next;
+            if let Some(mut z) = self.node(w.next) {
+                z.prev = v.id as i32;
+                self.bs.write_block(z.id, z);
+            }
+        } else {
+            v.keys.push(sep);
+        }
+        v.keys.extend(w.keys);
+        v.children.extend(w.children);
+        self.bs.free_block(w.id);
+        self.bs.write_block(v.id, v.clone());
+    }
+    // moves one key from v (child i - 1 of u) into w (child i)
+    fn shift_lr(&mut self, u: &mut Node<T>, i: usize, v: &mut Node<T>, w: &mut Node<T>) {
+        let x = v.keys.pop().unwrap();
+        if w.is_leaf() {
+            u.keys[i - 1] = x.clone();
+            w.keys.insert(0, x);
+        } else {
+            w.keys.insert(0, std::mem::replace(&mut u.keys[i - 1], x));
+            w.children.insert(0, v.children.pop().unwrap());
+        }
+        self.bs.write_block(v.id, v.clone());
+        self.bs.write_block(w.id, w.clone());
+    }
+    // moves one key from v (child i + 1 of u) into w (child i)
+    fn shift_rl(&mut self, u: &mut Node<T>, i: usize, v: &mut Node<T>, w: &mut Node<T>) {
+        let x = v.keys.remove(0);
+        if w.is_leaf() {
+            u.keys[i] = v.keys[0].clone();
+            w.keys.push(x);
+        } else {
+            w.keys.push(std::mem::replace(&mut u.keys[i], x));
+            w.children.push(v.children.remove(0));
+        }
+        self.bs.write_block(v.id, v.clone());
+        self.bs.write_block(w.id, w.clone());
+    }
+    fn check_underflow(&mut self, u: &mut Node<T>, i: usize) -> Option<()> {
+        let mut w = self.node(u.children[i])?;
+        if w.keys.len() >= self.B {
+            return Some(());
+        }
+        if i > 0 {
+            let mut v = self.node(u.children[i - 1])?;
+            if v.keys.len() > self.B {
+                self.shift_lr(u, i, &mut v, &mut w);
+            } else {
+                self.merge(u, i - 1, &mut v, w);
+            }
+        } else {
+            let mut v = self.node(u.children[i + 1])?;
+            if v.keys.len() > self.B {
+                self.shift_rl(u, i, &mut v, &mut w);
+            } else {
+                self.merge(u, i, &mut w, v);
+            }
+        }
+        self.bs.write_block(u.id, u.clone());
+        Some(())
+    }
+    fn remove_recursive(&mut self, x: &T, ui: i32) -> Option<T> {
+        let mut u = self.node(ui)?;
+        if u.is_leaf() {
+            let i = u.lower_bound(x);
+            if i < u.keys.len() && u.keys[i] == *x {
+                let y = u.keys.remove(i);
+                self.bs.write_block(u.id, u);
+                Some(y)
+            } else {
+                None
+            }
+        } else {
+            // separators may keep copies of removed keys; they still route correctly
+            let i = u.child_index(x);
+            let y = self.remove_recursive(x, u.children[i])?;
+            self.check_underflow(&mut u, i);
+            Some(y)
+        }
+    }
+}
+
+impl<T, S> SSet<T> for BPlusTree<T, S>
+where
+    T: Clone + PartialOrd,
+    S: BlockDevice<Node<T>>,
+{
+    fn size(&self) -> usize {
+        self.n
+    }
+    fn add(&mut self, x: T) -> bool {
+        self.put(x, false).is_ok()
+    }
+    fn remove(&mut self, x: &T) -> Option<T> {
+        let y = self.remove_recursive(x, self.ri as i32)?;
+        self.n -= 1;
+        if let Some(r) = self.node(self.ri as i32) {
+            if r.keys.is_empty() && !r.is_leaf() {
+                self.ri = r.children[0] as usize;
+                self.bs.free_block(r.id);
+            }
+        }
+        self.commit();
+        Some(y)
+    }
+    fn find(&self, x: &T) -> Option<T> {
+        let u = self.leaf_for(x)?;
+        let i = u.lower_bound(x);
+        self.step_forward(&mut Some((u, i)))
+    }
+    fn successor(&self, x: &T) -> Option<T> {
+        let u = self.leaf_for(x)?;
+        let i = u.child_index(x);
+        self.step_forward(&mut Some((u, i)))
+    }
+    fn predecessor(&self, x: &T) -> Option<T> {
+        let u = self.leaf_for(x)?;
+        let i = u.lower_bound(x);
+        self.step_back(&mut Some((u, i)))
+    }
+    fn find_min(&self) -> Option<T> {
+        self.leftmost_leaf()?.keys.first().cloned()
+    }
+    fn find_max(&self) -> Option<T> {
+        self.rightmost_leaf()?.keys.last().cloned()
+    }
+    fn count_range(&self, r: std::ops::Range<T>) -> usize {
+        self.range(r).count()
+    }
+}
+
+impl<'a, T: Clone + PartialOrd, S: BlockDevice<Node<T>>> IntoIterator for &'a BPlusTree<T, S> {
+    type Item = T;
+    type IntoIter = Iter<'a, T, S>;
+    fn into_iter(self) -> Iter<'a, T, S> {
+        self.iter()
+    }
+}
+
+impl<T: Clone + PartialOrd, S: BlockDevice<Node<T>>> IntoIterator for BPlusTree<T, S> {
+    type Item = T;
+    type IntoIter = std::vec::IntoIter<T>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter().collect::<Vec<_>>().into_iter()
+    }
+}
+
+impl<T: Clone + PartialOrd, S: BlockDevice<Node<T>>> Extend<T> for BPlusTree<T, S> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for x in iter {
+            self.add(x);
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct BPlusTreeMap<K, V>
+where
+    K: PartialOrd + Clone,
+    V: Clone,
+{
+    t: BPlusTree<Pair<K, V>>,
+}
+
+impl<K, V> BPlusTreeMap<K, V>
+where
+    K: PartialOrd + Clone,
+    V: Clone,
+{
+    pub fn new(b: usize) -> Self {
+        Self {
+            t: BPlusTree::new(b),
+        }
+    }
+    // the pairs must be sorted by key
+    pub fn bulk_load<I: IntoIterator<Item = (K, V)>>(b: usize, iter: I) -> Result<Self, Error> {
+        Ok(Self {
+            t: BPlusTree::bulk_load(b, iter.into_iter().map(|(k, v)| Pair::new(k, v)))?,
+        })
+    }
+    pub fn range(&self, r: std::ops::Range<K>) -> impl Iterator<Item = (K, V)> + '_ {
+        self.t
+            .range(Pair::probe(r.start)..Pair::probe(r.end))
+            .filter_map(Pair::into_tuple)
+    }
+}
+
+impl<K, V> USetMap<K, V> for BPlusTreeMap<K, V>
+where
+    K: PartialOrd + Clone,
+    V: Clone,
+{
+    type ValueMut<'a>
+        = &'a mut V
+    where
+        Self: 'a;
+    fn size(&self) -> usize {
+        self.t.size()
+    }
+    fn insert(&mut self, k: K, v: V) -> Option<V> {
+        self.t.replace(Pair::new(k, v)).and_then(|p| p.v)
+    }
+    fn get(&self, k: &K) -> Option<V> {
+        self.t
+            .find(&Pair::probe(k.clone()))
+            .filter(|p| p.k == *k)
+            .and_then(|p| p.v)
+    }
+    fn get_mut(&mut self, k: &K) -> Option<&mut V> {
+        self.t.find_mut(&Pair::probe(k.clone()))?.v.as_mut()
+    }
+    fn remove(&mut self, k: &K) -> Option<V> {
+        self.t.remove(&Pair::probe(k.clone())).and_then(|p| p.v)
+    }
+}
+
+impl<K, V> SSetMap<K, V> for BPlusTreeMap<K, V>
+where
+    K: PartialOrd + Clone,
+    V: Clone,
+{
+    fn successor(&self, k: &K) -> Option<(K, V)> {
+        self.t
+            .successor(&Pair::probe(k.clone()))
+            .and_then(Pair::into_tuple)
+    }
+    fn predecessor(&self, k: &K) -> Option<(K, V)> {
+        self.t
+            .predecessor(&Pair::probe(k.clone()))
+            .and_then(Pair::into_tuple)
+    }
+    fn find_min(&self) -> Option<(K, V)> {
+        self.t.find_min().and_then(Pair::into_tuple)
+    }
+    fn find_max(&self) -> Option<(K, V)> {
+        self.t.find_max().and_then(Pair::into_tuple)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use chapter01::interface::{SSet, USetMap};
+    use chapter01::testing::check_ssetmap;
+    use chapter09::redblacktree::RedBlackTree;
+    use rand::{thread_rng, Rng};
+    use std::collections::BTreeSet;
+    #[test]
+    fn test_bplustree() {
+        let mut rng = thread_rng();
+        let n = 200;
+        for &b in [3, 4, 11].iter() {
+            let mut redblacktree = RedBlackTree::<i32>::new();
+            let mut bplustree = BPlusTree::<i32>::new(b);
+            for _ in 0..5 {
+                for _ in 0..n {
+                    let x = rng.gen_range(0, 5 * n);
+                    assert_eq!(redblacktree.add(x), bplustree.add(x));
+                    assert_eq!(redblacktree.size(), bplustree.size());
+                }
+                for _ in 0..n {
+                    let x = rng.gen_range(0, 5 * n);
+                    assert_eq!(redblacktree.find(&x), bplustree.find(&x));
+                }
+                for _ in 0..n {
+                    let x = rng.gen_range(0, 5 * n);
+                    assert_eq!(redblacktree.remove(&x), bplustree.remove(&x));
+                    assert_eq!(redblacktree.size(), bplustree.size());
+                }
+                for _ in 0..n {
+                    let x = rng.gen_range(-1, 5 * n + 1);
+                    assert_eq!(redblacktree.find(&x), bplustree.find(&x));
+                    assert_eq!(redblacktree.successor(&x), bplustree.successor(&x));
+                    assert_eq!(redblacktree.predecessor(&x), bplustree.predecessor(&x));
+                }
+                assert_eq!(redblacktree.find_min(), bplustree.find_min());
+                assert_eq!(redblacktree.find_max(), bplustree.find_max());
+                for _ in 0..n {
+                    let x = rng.gen_range(0, 5 * n);
+                    let y = x + rng.gen_range(0, n);
+                    let r1 = redblacktree.range(x..y).collect::<Vec<_>>();
+                    let r2 = bplustree.range(x..y).collect::<Vec<_>>();
+                    assert_eq!(r1, r2);
+                    assert_eq!(r1.len(), bplustree.count_range(x..y));
+                }
+                assert_eq!(
+                    redblacktree.iter().collect::<Vec<_>>(),
+                    bplustree.iter().collect::<Vec<_>>()
+                );
+                assert_eq!(
+                    redblacktree.iter().rev().collect::<Vec<_>>(),
+                    (&bplustree).into_iter().rev().collect::<Vec<_>>()
+                );
+            }
+            // emptying the tree collapses it back to a single leaf
+            for x in 0..5 * n {
+                assert_eq!(redblacktree.remove(&x), bplustree.remove(&x));
+            }
+            assert_eq!(bplustree.size(), 0);
+            assert_eq!(bplustree.find_min(), None);
+            assert_eq!(bplustree.iter().next(), None);
+        }
+        let mut bplustree = BPlusTree::<i32>::new(3);
+        bplustree.extend((0..20).rev());
+        let mut iter = bplustree.iter();
+        assert_eq!(iter.next(), Some(0));
+        assert_eq!(iter.next_back(), Some(19));
+        assert_eq!(iter.size_hint(), (18, Some(18)));
+        assert_eq!(iter.collect::<Vec<_>>(), (1..19).collect::<Vec<_>>());
+        assert_eq!(
+            bplustree.into_iter().collect::<Vec<_>>(),
+            (0..20).collect::<Vec<_>>()
+        );
+        // an add that splits no node writes its leaf and nothing else,
+        // however deep the tree
+        let mut bplustree = BPlusTree::<i32>::new(5);
+        bplustree.extend((0..1000).map(|x| 2 * x));
+        let mut plain = 0;
+        for x in 0..1000 {
+            let stats = bplustree.stats();
+            assert!(bplustree.add(2 * x + 1));
+            let io = bplustree.stats() - stats;
+            if io.allocs == 0 {
+                assert_eq!(io.writes, 1);
+                plain += 1;
+            }
+        }
+        assert!(plain > 500);
+    }
+
+    #[test]
+    fn test_bplustree_bulk_load() {
+        let mut rng = thread_rng();
+        for &b in [3, 4, 5, 16].iter() {
+            for &n in [0, 1, 2, 3, 7, 100, 1000].iter() {
+                let mut bplustree =
+                    BPlusTree::bulk_load(b, (0..n).flat_map(|x| vec![x, x])).unwrap();
+                assert_eq!(bplustree.size(), n as usize);
+                assert_eq!(
+                    bplustree.iter().collect::<Vec<_>>(),
+                    (0..n).collect::<Vec<_>>()
+                );
+                assert_eq!(
+                    bplustree.iter().rev().collect::<Vec<_>>(),
+                    (0..n).rev().collect::<Vec<_>>()
+                );
+                // the loaded tree keeps working as a regular one
+                let mut redblacktree = RedBlackTree::<i32>::new();
+                redblacktree.extend(0..n);
+                for _ in 0..2 * n {
+                    let x = rng.gen_range(0, 2 * n + 1);
+                    if rng.gen_range(0, 2) == 0 {
+                        assert_eq!(redblacktree.add(x), bplustree.add(x));
+                    } else {
+                        assert_eq!(redblacktree.remove(&x), bplustree.remove(&x));
+                    }
+                }
+                assert_eq!(
+                    redblacktree.iter().collect::<Vec<_>>(),
+                    bplustree.iter().collect::<Vec<_>>()
+                );
+            }
+        }
+        let bplustree = BPlusTree::bulk_load(5, 0..1000).unwrap();
+        let buf = bplustree.dump();
+        let copy = BPlusTree::<i32>::load(5, &buf).unwrap();
+        assert!(copy.iter().eq(0..1000));
+        assert_eq!(copy.size(), 1000);
+        assert_eq!(copy.find(&500), Some(500));
+        assert!(BPlusTree::<i32>::load(5, &buf[..buf.len() - 1]).is_err());
+        assert_eq!(
+            BPlusTree::<i64>::load(5, &buf).err(),
+            Some(SnapshotError::BadElement { index: 500 })
+        );
+    }
+
+    #[test]
+    fn test_bplustree_bulk_load_unsorted() {
+        assert_eq!(
+            BPlusTree::bulk_load(5, vec![1, 3, 3, 2]).err(),
+            Some(Error::Unsorted { index: 3 })
+        );
+        assert!(BPlusTreeMap::bulk_load(5, vec![(2, 0), (1, 0)]).is_err());
+    }
+
+    #[test]
+    fn test_bplustree_file() {
+        let path = std::env::temp_dir().join(format!("ods-bplustree-{}.db", std::process::id()));
+        let mut tree = BPlusTree::<u32, _>::create(&path, 8, 256, 4).unwrap();
+        tree.extend((0..1000).map(|x| x * 7 % 1000));
+        for x in (0..1000).step_by(3) {
+            tree.remove(&x);
+        }
+        tree.flush().unwrap();
+        drop(tree);
+        let mut tree = BPlusTree::<u32, _>::open(&path, 4).unwrap();
+        let expected = (0..1000).filter(|x| x % 3 != 0).collect::<Vec<_>>();
+        assert_eq!(tree.size(), expected.len());
+        assert_eq!(tree.iter().collect::<Vec<_>>(), expected);
+        // updates dropped without a flush leave the file at the last
+        // checkpoint, the state after some complete update
+        let mut reference = expected.iter().copied().collect::<BTreeSet<_>>();
+        let mut states = vec![expected];
+        for x in 1000..1500 {
+            tree.add(x);
+            reference.insert(x);
+            states.push(reference.iter().copied().collect());
+        }
+        for x in (0..1000).step_by(2) {
+            tree.remove(&x);
+            reference.remove(&x);
+            states.push(reference.iter().copied().collect());
+        }
+        drop(tree);
+        let tree = BPlusTree::<u32, _>::open(&path, 4).unwrap();
+        let found = tree.iter().collect::<Vec<_>>();
+        assert!(states.contains(&found));
+        assert_eq!(tree.size(), found.len());
+        assert!(tree
+            .range(100..110)
+            .eq(found.iter().copied().filter(|x| (100..110).contains(x))));
+        drop(tree);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_bplustreemap() {
+        check_ssetmap(|| BPlusTreeMap::<i32, usize>::new(5));
+        let mut rng = thread_rng();
+        let n = 200;
+        let mut bplustreemap =
+            BPlusTreeMap::<i32, usize>::bulk_load(5, (0..10).map(|k| (k, 0))).unwrap();
+        let mut reference = (0..10)
+            .map(|k| (k, 0))
+            .collect::<std::collections::BTreeMap<_, _>>();
+        for i in 0..5 * n {
+            let x = rng.gen_range(0, 5 * n as i32);
+            match rng.gen_range(0, 3) {
+                0 => assert_eq!(bplustreemap.insert(x, i), reference.insert(x, i)),
+                1 => assert_eq!(bplustreemap.remove(&x), reference.remove(&x)),
+                _ => {
+                    if let Some(v) = bplustreemap.get_mut(&x) {
+                        *v += 1;
+                    }
+                    if let Some(v) = reference.get_mut(&x) {
+                        *v += 1;
+                    }
+                }
+            }
+        }
+        assert_eq!(
+            bplustreemap.range(100..300).collect::<Vec<_>>(),
+            reference
+                .range(100..300)
+                .map(|(k, v)| (*k, *v))
+                .collect::<Vec<_>>()
+        );
+    }
+}
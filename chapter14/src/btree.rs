@@ -1,9 +1,16 @@
 #![allow(clippy::many_single_char_names,clippy::explicit_counter_loop)]
-use crate::blockstore::BlockStore;
-use chapter01::interface::SSet;
+use crate::blockstore::{BlockDevice, BlockStore, IoStats};
+use crate::codec::Codec;
+use crate::fileblockstore::FileBlockStore;
+use chapter01::error::Error;
+use chapter01::interface::{Pair, SSet, SSetMap, USetMap};
+use chapter01::snapshot::{self, SnapshotError};
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
 
 #[derive(Clone, Debug, Default, Eq, Ord, PartialEq, PartialOrd)]
-struct Node<T: Clone + PartialOrd> {
+pub struct Node<T: Clone + PartialOrd> {
     id: usize,
     keys: Box<[Option<T>]>,
     children: Box<[i32]>,
@@ -11,16 +18,222 @@ struct Node<T: Clone + PartialOrd> {
 
 #[allow(non_snake_case)]
 #[derive(Clone, Debug, Default, Eq, Ord, PartialEq, PartialOrd)]
-pub struct BTree<T: Clone + PartialOrd> {
+pub struct BTree<T: Clone + PartialOrd, S = BlockStore<Node<T>>> {
     b: usize,  // the maximum number of children of a node (must be odd)
     B: usize,  // d div 2
     n: usize,  // number of elements stored in the tree
     ri: usize, // index of the root
-    bs: BlockStore<Node<T>>,
+    bs: S,
+    refs: std::collections::BTreeMap<usize, usize>, // extra references to blocks shared with snapshots
+    snapshots: Vec<(Arc<()>, usize)>, // the token and root of every snapshot handed out
+    t: std::marker::PhantomData<T>,
+}
+
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum BTreeError {
+    Dangling {
+        parent: Option<usize>,
+        id: usize,
+    },
+    Revisited {
+        id: usize,
+    },
+    WrongId {
+        id: usize,
+        found: usize,
+    },
+    Malformed {
+        id: usize,
+    },
+    Unordered {
+        id: usize,
+    },
+    Occupancy {
+        id: usize,
+        keys: usize,
+    },
+    Children {
+        id: usize,
+        keys: usize,
+        children: usize,
+    },
+    Depth {
+        id: usize,
+        depth: usize,
+        expected: usize,
+    },
+    Size {
+        n: usize,
+        found: usize,
+    },
+    Leaked {
+        ids: Vec<usize>,
+    },
+}
+
+impl std::fmt::Display for BTreeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BTreeError::Dangling {
+                parent: Some(p),
+                id,
+            } => {
+                write!(f, "node {} has child {} but there is no such block", p, id)
+            }
+            BTreeError::Dangling { parent: None, id } => {
+                write!(f, "the root {} is not a block", id)
+            }
+            BTreeError::Revisited { id } => write!(f, "node {} is reached twice", id),
+            BTreeError::WrongId { id, found } => {
+                write!(f, "block {} holds a node that says it is {}", id, found)
+            }
+            BTreeError::Malformed { id } => write!(f, "node {} has gaps between its keys", id),
+            BTreeError::Unordered { id } => {
+                write!(
+                    f,
+                    "keys of node {} are out of order or outside its range",
+                    id
+                )
+            }
+            BTreeError::Occupancy { id, keys } => {
+                write!(f, "node {} has {} keys, too few or too many", id, keys)
+            }
+            BTreeError::Children { id, keys, children } => {
+                write!(f, "node {} has {} keys and {} children", id, keys, children)
+            }
+            BTreeError::Depth {
+                id,
+                depth,
+                expected,
+            } => write!(
+                f,
+                "leaf {} is at depth {} but other leaves are at {}",
+                id, depth, expected
+            ),
+            BTreeError::Size { n, found } => {
+                write!(f, "the tree says it has {} keys but holds {}", n, found)
+            }
+            BTreeError::Leaked { ids } => {
+                write!(f, "blocks not reachable from the root:")?;
+                for i in ids.iter() {
+                    write!(f, " {}", i)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl std::error::Error for BTreeError {}
+
+// a frozen version of a BTree, read through BTree::at. the tree it came from
+// reclaims its blocks at the first commit after it is dropped or released;
+// snapshots do not survive reopening a file, whose open reclaims them instead
+#[derive(Debug, Eq, PartialEq)]
+pub struct Snapshot {
+    ri: usize,
+    n: usize,
+    token: Arc<()>, // shared with the tree, which sees the drop in the count
+}
+
+pub struct Version<'a, T: Clone + PartialOrd, S = BlockStore<Node<T>>> {
+    t: &'a BTree<T, S>,
+    ri: usize,
+    n: usize,
+}
+
+impl<'a, T: Clone + PartialOrd, S: BlockDevice<Node<T>>> Version<'a, T, S> {
+    pub fn size(&self) -> usize {
+        self.n
+    }
+    pub fn find(&self, x: &T) -> Option<T> {
+        self.t.find_from(self.ri, x)
+    }
+    pub fn iter(&self) -> Iter<'a, T, S> {
+        self.t.iter_from(self.ri, self.n)
+    }
+    pub fn range(&self, r: std::ops::Range<T>) -> Range<'a, T, S> {
+        self.t.range_from(self.ri, r)
+    }
+}
+
+pub struct Range<'a, T: Clone + PartialOrd, S = BlockStore<Node<T>>> {
+    t: &'a BTree<T, S>,
+    stack: Vec<(Node<T>, usize)>,
+    hi: T,
+}
+
+impl<'a, T: Clone + PartialOrd, S: BlockDevice<Node<T>>> Iterator for Range<'a, T, S> {
+    type Item = T;
+    fn next(&mut self) -> Option<T> {
+        loop {
+            let (u, i) = self.stack.pop()?;
+            if i < u.size() {
+                let x = u.keys[i].clone()?;
+                if x >= self.hi {
+                    self.stack.clear();
+                    return None;
+                }
+                let ci = u.children[i + 1];
+                self.stack.push((u, i + 1));
+                self.t.push_leftmost(&mut self.stack, ci);
+                return Some(x);
+            }
+        }
+    }
+}
+
+pub struct Iter<'a, T: Clone + PartialOrd, S = BlockStore<Node<T>>> {
+    t: &'a BTree<T, S>,
+    front: Vec<(Node<T>, usize)>,
+    back: Vec<(Node<T>, usize)>,
+    n: usize,
+}
+
+impl<'a, T: Clone + PartialOrd, S: BlockDevice<Node<T>>> Iterator for Iter<'a, T, S> {
+    type Item = T;
+    fn next(&mut self) -> Option<T> {
+        if self.n == 0 {
+            return None;
+        }
+        loop {
+            let (u, i) = self.front.pop()?;
+            if i < u.size() {
+                let x = u.keys[i].clone();
+                let ci = u.children[i + 1];
+                self.front.push((u, i + 1));
+                self.t.push_leftmost(&mut self.front, ci);
+                self.n -= 1;
+                return x;
+            }
+        }
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.n, Some(self.n))
+    }
+}
+
+impl<'a, T: Clone + PartialOrd, S: BlockDevice<Node<T>>> DoubleEndedIterator for Iter<'a, T, S> {
+    fn next_back(&mut self) -> Option<T> {
+        if self.n == 0 {
+            return None;
+        }
+        loop {
+            let (u, i) = self.back.pop()?;
+            if i > 0 {
+                let x = u.keys[i - 1].clone();
+                let ci = u.children[i - 1];
+                self.back.push((u, i - 1));
+                self.t.push_rightmost(&mut self.back, ci);
+                self.n -= 1;
+                return x;
+            }
+        }
+    }
 }
 
 impl<T: Clone + PartialOrd> Node<T> {
-    fn new(t: &mut BTree<T>) -> Self {
+    fn new<S: BlockDevice<Node<T>>>(t: &mut BTree<T, S>) -> Self {
         let b = t.b;
         let mut obj = Self {
             keys: vec![None; b].into_boxed_slice(),
@@ -79,7 +292,7 @@ impl<T: Clone + PartialOrd> Node<T> {
         self.keys[i..n].rotate_left(1);
         y
     }
-    fn split(&mut self, t: &mut BTree<T>) -> Option<Node<T>> {
+    fn split<S: BlockDevice<Node<T>>>(&mut self, t: &mut BTree<T, S>) -> Option<Node<T>> {
         let mut w = Self::new(t);
         let j = self.keys.len() / 2;
         for (i, key) in self.keys[j..].iter_mut().enumerate() {
@@ -94,18 +307,456 @@ impl<T: Clone + PartialOrd> Node<T> {
     }
 }
 
+impl<T: Clone + PartialOrd + Codec> Codec for Node<T> {
+    // id, number of key slots, the keys in use, then every child index
+    fn encode(&self, buf: &mut Vec<u8>) {
+        let size = self.size();
+        self.id.encode(buf);
+        self.keys.len().encode(buf);
+        size.encode(buf);
+        for x in self.keys[..size].iter().flatten() {
+            x.encode(buf);
+        }
+        for c in self.children.iter() {
+            c.encode(buf);
+        }
+    }
+    fn decode(buf: &mut &[u8]) -> Option<Self> {
+        let id = usize::decode(buf)?;
+        let b = usize::decode(buf)?;
+        let size = usize::decode(buf)?;
+        if size > b || b > buf.len() {
+            return None;
+        }
+        let mut keys = vec![None; b];
+        for key in keys[..size].iter_mut() {
+            *key = Some(T::decode(buf)?);
+        }
+        let mut children = vec![-1i32; b + 1];
+        for c in children.iter_mut() {
+            *c = i32::decode(buf)?;
+        }
+        Some(Self {
+            id,
+            keys: keys.into_boxed_slice(),
+            children: children.into_boxed_slice(),
+        })
+    }
+}
+
 impl<T: Clone + PartialOrd> BTree<T> {
     pub fn new(b: usize) -> Self {
+        Self::with_store(b, BlockStore::new())
+    }
+    pub fn bulk_load<I: IntoIterator<Item = T>>(
+        b: usize,
+        iter: I,
+        fill: f64,
+    ) -> Result<Self, Error> {
+        Self::bulk_load_with_store(b, BlockStore::new(), iter, fill)
+    }
+    pub fn load(b: usize, buf: &[u8], fill: f64) -> Result<Self, SnapshotError>
+    where
+        T: Codec,
+    {
+        Self::load_with_store(b, BlockStore::new(), buf, fill)
+    }
+    // the element equal to x, changed in place; nodes shared with a snapshot
+    // are copied on the way down as add would
+    fn find_mut(&mut self, x: &T) -> Option<&mut T> {
+        self.own_root();
+        let mut ui = self.ri;
+        loop {
+            let mut u = self.bs.read_block(ui)?;
+            let i = Self::find_it(&u.keys, x);
+            if i < 0 {
+                return self.bs.block_mut(ui)?.keys[(-(i + 1)) as usize].as_mut();
+            }
+            if u.children[i as usize] < 0 {
+                return None;
+            }
+            ui = self.own_child(&mut u, i as usize) as usize;
+        }
+    }
+}
+
+impl<T: Clone + PartialOrd + Codec> BTree<T, FileBlockStore<Node<T>>> {
+    pub fn create<P: AsRef<Path>>(
+        path: P,
+        b: usize,
+        page_size: usize,
+        capacity: usize,
+    ) -> io::Result<Self> {
+        let mut tree = Self::with_store(b, FileBlockStore::create(path, page_size, capacity)?);
+        tree.flush()?;
+        Ok(tree)
+    }
+    // blocks that only snapshots alive at the last checkpoint reached are
+    // freed here, as no snapshot outlives the tree that made it
+    pub fn open<P: AsRef<Path>>(path: P, capacity: usize) -> io::Result<Self> {
+        let bs = FileBlockStore::open(path, capacity)?;
+        let mut meta = bs.meta();
+        let header = (
+            usize::decode(&mut meta),
+            usize::decode(&mut meta),
+            usize::decode(&mut meta),
+        );
+        let snapshots = usize::decode(&mut meta).unwrap_or(0);
+        let mut tree = match header {
+            (Some(b), Some(n), Some(ri)) if b % 2 == 1 && ri < bs.pages() => Self {
+                b,
+                B: b / 2,
+                n,
+                ri,
+                bs,
+                refs: Default::default(),
+                snapshots: vec![],
+                t: std::marker::PhantomData,
+            },
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "not a btree file",
+                ))
+            }
+        };
+        if snapshots > 0 {
+            tree.sweep();
+            tree.flush()?;
+        }
+        Ok(tree)
+    }
+    // without a log the file only changes at a checkpoint: this flush, or one
+    // a commit forces when many pages are pending. a restart finds the tree as
+    // of the last one, unless the crash tore the checkpoint itself. with a log
+    // every finished add or remove survives
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.reclaim();
+        self.bs.set_meta(&self.meta())?;
+        self.bs.flush()
+    }
+    pub fn enable_wal(&mut self) -> io::Result<()> {
+        self.bs.set_meta(&self.meta())?;
+        self.bs.enable_wal()
+    }
+    pub fn disable_wal(&mut self) -> io::Result<()> {
+        self.bs.set_meta(&self.meta())?;
+        self.bs.disable_wal()
+    }
+}
+
+impl<T: Clone + PartialOrd, S: BlockDevice<Node<T>>> BTree<T, S> {
+    // bs must not hold any blocks yet
+    pub fn with_store(b: usize, bs: S) -> Self {
         let mut tree = Self {
             b: b | 1,
             B: b / 2,
-            bs: BlockStore::new(),
+            bs,
             ri: 0,
             n: 0,
+            refs: Default::default(),
+            snapshots: vec![],
+            t: std::marker::PhantomData,
         };
         tree.ri = Node::<T>::new(&mut tree).id;
         tree
     }
+    pub fn store(&self) -> &S {
+        &self.bs
+    }
+    pub fn stats(&self) -> IoStats {
+        self.bs.stats()
+    }
+    pub fn reset_stats(&mut self) {
+        self.bs.reset_stats()
+    }
+    fn meta(&self) -> Vec<u8> {
+        let mut meta = vec![];
+        self.b.encode(&mut meta);
+        self.n.encode(&mut meta);
+        self.ri.encode(&mut meta);
+        self.snapshots.len().encode(&mut meta);
+        meta
+    }
+    fn commit(&mut self) {
+        self.reclaim();
+        let meta = self.meta();
+        self.bs.commit(&meta);
+    }
+    // O(1): the live tree copies a shared node the first time it changes it
+    pub fn snapshot(&mut self) -> Snapshot {
+        self.share(self.ri);
+        let token = Arc::new(());
+        self.snapshots.push((token.clone(), self.ri));
+        Snapshot {
+            ri: self.ri,
+            n: self.n,
+            token,
+        }
+    }
+    // None for a snapshot of another tree
+    pub fn at(&self, s: &Snapshot) -> Option<Version<'_, T, S>> {
+        self.snapshots
+            .iter()
+            .any(|(token, _)| Arc::ptr_eq(token, &s.token))
+            .then_some(Version {
+                t: self,
+                ri: s.ri,
+                n: s.n,
+            })
+    }
+    // drops s and gives the blocks only it still used back to the block store
+    // now rather than at the next commit; a snapshot of another tree goes
+    // back to that tree instead
+    pub fn release(&mut self, s: Snapshot) {
+        drop(s);
+        self.commit();
+    }
+    // unshares the root of every snapshot that has been dropped
+    fn reclaim(&mut self) {
+        let mut i = 0;
+        while i < self.snapshots.len() {
+            if Arc::strong_count(&self.snapshots[i].0) == 1 {
+                let (_, ri) = self.snapshots.swap_remove(i);
+                self.unshare(ri);
+            } else {
+                i += 1;
+            }
+        }
+    }
+    // frees every block the root does not reach
+    fn sweep(&mut self) {
+        let mut seen = std::collections::BTreeSet::new();
+        let mut stack = vec![self.ri];
+        while let Some(ui) = stack.pop() {
+            if let Some(u) = self.bs.read_block(ui) {
+                if seen.insert(ui) && !u.is_leaf() {
+                    stack.extend(u.children[..(u.size() + 1)].iter().map(|&c| c as usize));
+                }
+            }
+        }
+        for i in 0..self.bs.bound() {
+            if !seen.contains(&i) && self.bs.read_block(i).is_some() {
+                self.bs.free_block(i);
+            }
+        }
+    }
+    pub fn validate(&self) -> Result<(), BTreeError> {
+        let mut seen = std::collections::BTreeSet::new();
+        let mut leaf_depth = None;
+        let found =
+            self.validate_node(self.ri, None, (None, None), 0, &mut leaf_depth, &mut seen)?;
+        if found != self.n {
+            return Err(BTreeError::Size { n: self.n, found });
+        }
+        // blocks only a snapshot reaches cannot be told apart from leaked ones
+        if self.refs.is_empty() {
+            let ids: Vec<usize> = (0..self.bs.bound())
+                .filter(|i| !seen.contains(i) && self.bs.read_block(*i).is_some())
+                .collect();
+            if !ids.is_empty() {
+                return Err(BTreeError::Leaked { ids });
+            }
+        }
+        Ok(())
+    }
+    // checks the subtree at ui, whose keys must lie strictly between lo and hi, and counts its keys
+    fn validate_node(
+        &self,
+        ui: usize,
+        parent: Option<usize>,
+        (lo, hi): (Option<&T>, Option<&T>),
+        depth: usize,
+        leaf_depth: &mut Option<usize>,
+        seen: &mut std::collections::BTreeSet<usize>,
+    ) -> Result<usize, BTreeError> {
+        let u = self
+            .bs
+            .read_block(ui)
+            .ok_or(BTreeError::Dangling { parent, id: ui })?;
+        if !seen.insert(ui) {
+            return Err(BTreeError::Revisited { id: ui });
+        }
+        if u.id != ui {
+            return Err(BTreeError::WrongId {
+                id: ui,
+                found: u.id,
+            });
+        }
+        let k = u.size();
+        if u.keys[..k].iter().any(|x| x.is_none()) || u.keys[k..].iter().any(|x| x.is_some()) {
+            return Err(BTreeError::Malformed { id: ui });
+        }
+        let keys: Vec<&T> = u.keys[..k].iter().flatten().collect();
+        let ordered = keys.windows(2).all(|w| w[0] < w[1])
+            && keys.first().is_none_or(|x| lo.is_none_or(|lo| lo < *x))
+            && keys.last().is_none_or(|x| hi.is_none_or(|hi| *x < hi));
+        if !ordered {
+            return Err(BTreeError::Unordered { id: ui });
+        }
+        let children = u.children.iter().filter(|&&c| c >= 0).count();
+        let leaf = u.is_leaf();
+        if (leaf && children > 0)
+            || (!leaf && (children != k + 1 || u.children[..=k].iter().any(|&c| c < 0)))
+        {
+            return Err(BTreeError::Children {
+                id: ui,
+                keys: k,
+                children,
+            });
+        }
+        let occupied = if ui == self.ri {
+            leaf || k > 0
+        } else {
+            k + 1 >= self.B && k < self.b
+        };
+        if !occupied {
+            return Err(BTreeError::Occupancy { id: ui, keys: k });
+        }
+        if leaf {
+            let expected = *leaf_depth.get_or_insert(depth);
+            if depth != expected {
+                return Err(BTreeError::Depth {
+                    id: ui,
+                    depth,
+                    expected,
+                });
+            }
+            return Ok(k);
+        }
+        let mut count = k;
+        for (i, &c) in u.children[..(k + 1)].iter().enumerate() {
+            let lo = if i > 0 { Some(keys[i - 1]) } else { lo };
+            let hi = if i < k { Some(keys[i]) } else { hi };
+            count +=
+                self.validate_node(c as usize, Some(ui), (lo, hi), depth + 1, leaf_depth, seen)?;
+        }
+        Ok(count)
+    }
+    fn is_shared(&self, i: usize) -> bool {
+        self.refs.contains_key(&i)
+    }
+    fn share(&mut self, i: usize) {
+        *self.refs.entry(i).or_insert(0) += 1;
+    }
+    // drops one reference to block i, freeing it and its subtree with the last one
+    fn unshare(&mut self, i: usize) {
+        match self.refs.get_mut(&i) {
+            Some(r) if *r > 1 => *r -= 1,
+            Some(_) => {
+                self.refs.remove(&i);
+            }
+            None => {
+                if let Some(u) = self.bs.read_block(i) {
+                    if !u.is_leaf() {
+                        for &c in u.children[..(u.size() + 1)].iter() {
+                            self.unshare(c as usize);
+                        }
+                    }
+                }
+                self.bs.free_block(i);
+            }
+        }
+    }
+    // a private copy of u, which then shares u's children
+    fn own(&mut self, mut u: Node<T>) -> Node<T> {
+        if !self.is_shared(u.id) {
+            return u;
+        }
+        if !u.is_leaf() {
+            for &c in u.children[..(u.size() + 1)].iter() {
+                self.share(c as usize);
+            }
+        }
+        self.unshare(u.id);
+        u.id = self.bs.place_block(u.clone());
+        self.bs.write_block(u.id, u.clone());
+        u
+    }
+    fn own_root(&mut self) {
+        if self.is_shared(self.ri) {
+            if let Some(r) = self.bs.read_block(self.ri) {
+                self.ri = self.own(r).id;
+            }
+        }
+    }
+    // the index of u's i-th child, first copied if shared; u must be private
+    fn own_child(&mut self, u: &mut Node<T>, i: usize) -> i32 {
+        let c = u.children[i];
+        if c < 0 || !self.is_shared(c as usize) {
+            return c;
+        }
+        match self.bs.read_block(c as usize) {
+            Some(w) => {
+                u.children[i] = self.own(w).id as i32;
+                self.bs.write_block(u.id, u.clone());
+                u.children[i]
+            }
+            None => c,
+        }
+    }
+    fn push_leftmost(&self, stack: &mut Vec<(Node<T>, usize)>, mut ui: i32) {
+        while ui >= 0 {
+            match self.bs.read_block(ui as usize) {
+                Some(u) => {
+                    ui = u.children[0];
+                    stack.push((u, 0));
+                }
+                None => break,
+            }
+        }
+    }
+    fn push_rightmost(&self, stack: &mut Vec<(Node<T>, usize)>, mut ui: i32) {
+        while ui >= 0 {
+            match self.bs.read_block(ui as usize) {
+                Some(u) => {
+                    let i = u.size();
+                    ui = u.children[i];
+                    stack.push((u, i));
+                }
+                None => break,
+            }
+        }
+    }
+    pub fn iter(&self) -> Iter<'_, T, S> {
+        self.iter_from(self.ri, self.n)
+    }
+    fn iter_from(&self, ri: usize, n: usize) -> Iter<'_, T, S> {
+        let mut it = Iter {
+            t: self,
+            front: vec![],
+            back: vec![],
+            n,
+        };
+        self.push_leftmost(&mut it.front, ri as i32);
+        self.push_rightmost(&mut it.back, ri as i32);
+        it
+    }
+    pub fn range(&self, r: std::ops::Range<T>) -> Range<'_, T, S> {
+        self.range_from(self.ri, r)
+    }
+    fn range_from(&self, ri: usize, r: std::ops::Range<T>) -> Range<'_, T, S> {
+        let mut it = Range {
+            t: self,
+            stack: vec![],
+            hi: r.end,
+        };
+        let mut ui = ri as i32;
+        while ui >= 0 {
+            match self.bs.read_block(ui as usize) {
+                Some(u) => {
+                    let i = match Self::find_it(&u.keys, &r.start) {
+                        i if i < 0 => (-(i + 1)) as usize,
+                        i => i as usize,
+                    };
+                    ui = u.children[i];
+                    it.stack.push((u, i));
+                }
+                None => break,
+            }
+        }
+        it
+    }
     fn find_it(a: &[Option<T>], x: &T) -> i32 {
         let mut lo = 0;
         let mut hi = a.len();
@@ -120,17 +771,45 @@ impl<T: Clone + PartialOrd> BTree<T> {
         }
         lo as i32
     }
-    fn add_recursive(&mut self, mut x: T, ui: usize) -> Result<Option<Node<T>>, ()> {
+    fn find_from(&self, ri: usize, x: &T) -> Option<T> {
+        let mut z = None;
+        let mut ui = ri as i32;
+        while ui >= 0 {
+            let u = self.bs.read_block(ui as usize)?;
+            let i = Self::find_it(&u.keys, x);
+            if i < 0 {
+                return u.keys[(-(i + 1)) as usize].clone();
+            }
+            if u.keys[i as usize].is_some() {
+                z = u.keys[i as usize].clone()
+            }
+            ui = u.children[i as usize];
+        }
+        z
+    }
+    // Err holds the key x took the place of when replacing
+    fn add_recursive(
+        &mut self,
+        mut x: T,
+        ui: usize,
+        replace: bool,
+    ) -> Result<Option<Node<T>>, Option<T>> {
         if let Some(mut u) = self.bs.read_block(ui) {
             let i = Self::find_it(&u.keys, &x);
             if i < 0 {
-                return Err(());
+                if !replace {
+                    return Err(None);
+                }
+                let y = u.keys[(-(i + 1)) as usize].replace(x);
+                self.bs.write_block(u.id, u);
+                return Err(y);
             }
             if u.children[i as usize] < 0 {
                 u.add(x, -1);
                 self.bs.write_block(u.id, u.clone());
             } else {
-                let w = self.add_recursive(x, u.children[i as usize] as usize)?;
+                let ci = self.own_child(&mut u, i as usize);
+                let w = self.add_recursive(x, ci as usize, replace)?;
                 if let Some(mut w) = w {
                     x = w.remove(0).unwrap();
                     u.add(x, w.id as i32);
@@ -144,9 +823,39 @@ impl<T: Clone + PartialOrd> BTree<T> {
                 Ok(None)
             }
         } else {
-            Err(())
+            Err(None)
+        }
+    }
+    // one descent for add and replace: Err holds what x replaced, if anything
+    fn put(&mut self, x: T, replace: bool) -> Result<(), Option<T>> {
+        self.own_root();
+        match self.add_recursive(x, self.ri, replace) {
+            Ok(w) => {
+                if let Some(mut w) = w {
+                    let mut newroot = Node::new(self);
+                    let x = w.remove(0);
+                    newroot.children[0] = self.ri as i32;
+                    newroot.keys[0] = x;
+                    newroot.children[1] = w.id as i32;
+                    self.bs.write_block(w.id, w);
+                    self.ri = newroot.id;
+                    self.bs.write_block(self.ri, newroot);
+                }
+                self.n += 1;
+                self.commit();
+                Ok(())
+            }
+            Err(Some(y)) => {
+                self.commit();
+                Err(Some(y))
+            }
+            Err(None) => Err(None),
         }
     }
+    // adds x, or puts it in the place of the element equal to it and returns that one
+    pub fn replace(&mut self, x: T) -> Option<T> {
+        self.put(x, true).err().flatten()
+    }
     fn merge(&mut self, u: &mut Node<T>, i: usize, v: &mut Node<T>, w: &mut Node<T>) {
         assert_eq!(v.id, u.children[i] as usize);
         assert_eq!(w.id, u.children[i + 1] as usize);
@@ -235,9 +944,11 @@ impl<T: Clone + PartialOrd> BTree<T> {
         self.bs.write_block(w.id, w.clone());
     }
     fn check_underflow_zero(&mut self, u: &mut Node<T>, i: usize) {
-        if let Some(ref mut w) = self.bs.read_block(u.children[i] as usize) {
+        let wi = self.own_child(u, i);
+        if let Some(ref mut w) = self.bs.read_block(wi as usize) {
             if w.size() < self.B - 1 {
-                if let Some(ref mut v) = self.bs.read_block(u.children[i + 1] as usize) {
+                let vi = self.own_child(u, i + 1);
+                if let Some(ref mut v) = self.bs.read_block(vi as usize) {
                     if v.size() > self.B {
                         self.shift_rl(u, i, v, w);
                     } else {
@@ -250,9 +961,11 @@ impl<T: Clone + PartialOrd> BTree<T> {
         }
     }
     fn check_underflow_nonzero(&mut self, u: &mut Node<T>, i: usize) {
-        if let Some(ref mut w) = self.bs.read_block(u.children[i] as usize) {
+        let wi = self.own_child(u, i);
+        if let Some(ref mut w) = self.bs.read_block(wi as usize) {
             if w.size() < self.B - 1 {
-                if let Some(ref mut v) = self.bs.read_block(u.children[i - 1] as usize) {
+                let vi = self.own_child(u, i - 1);
+                if let Some(ref mut v) = self.bs.read_block(vi as usize) {
                     if v.size() > self.B {
                         self.shift_lr(u, i - 1, v, w);
                     } else {
@@ -280,7 +993,8 @@ impl<T: Clone + PartialOrd> BTree<T> {
                 self.bs.write_block(u.id, u.clone());
                 y
             } else {
-                let y = self.remove_smallest(u.children[0]);
+                let ci = self.own_child(u, 0);
+                let y = self.remove_smallest(ci);
                 self.check_underflow(u, 0);
                 y
             }
@@ -303,7 +1017,8 @@ impl<T: Clone + PartialOrd> BTree<T> {
                         self.bs.write_block(u.id, u.clone());
                         y
                     } else {
-                        let x = self.remove_smallest(u.children[i as usize + 1]);
+                        let ci = self.own_child(u, i as usize + 1);
+                        let x = self.remove_smallest(ci);
                         let y = u.keys[i as usize].take();
                         u.keys[i as usize] = x;
                         self.bs.write_block(u.id, u.clone());
@@ -311,7 +1026,8 @@ impl<T: Clone + PartialOrd> BTree<T> {
                         y
                     }
                 } else {
-                    let y = self.remove_recursive(x, u.children[i as usize]);
+                    let ci = self.own_child(u, i as usize);
+                    let y = self.remove_recursive(x, ci);
                     if y.is_some() {
                         self.check_underflow(u, i as usize);
                         y
@@ -323,35 +1039,8 @@ impl<T: Clone + PartialOrd> BTree<T> {
             None => None,
         }
     }
-}
-
-impl<T> SSet<T> for BTree<T>
-where
-    T: Clone + PartialOrd,
-{
-    fn size(&self) -> usize {
-        self.n
-    }
-    fn add(&mut self, x: T) -> bool {
-        match self.add_recursive(x, self.ri) {
-            Ok(w) => {
-                if let Some(mut w) = w {
-                    let mut newroot = Node::new(self);
-                    let x = w.remove(0);
-                    newroot.children[0] = self.ri as i32;
-                    newroot.keys[0] = x;
-                    newroot.children[1] = w.id as i32;
-                    self.bs.write_block(w.id, w);
-                    self.ri = newroot.id;
-                    self.bs.write_block(self.ri, newroot);
-                }
-                self.n += 1;
-                true
-            }
-            Err(()) => false,
-        }
-    }
-    fn remove(&mut self, x: &T) -> Option<T> {
+    fn remove_one(&mut self, x: &T) -> Option<T> {
+        self.own_root();
         match self.remove_recursive(x, self.ri as i32) {
             Some(y) => {
                 self.n -= 1;
@@ -359,6 +1048,7 @@ where
                 if let Some(r) = r {
                     if r.size() == 0 && self.n > 0 {
                         self.ri = r.children[0] as usize;
+                        self.bs.free_block(r.id);
                     }
                 }
                 Some(y)
@@ -366,30 +1056,420 @@ where
             None => None,
         }
     }
+    // builds the tree bottom up from sorted input, dropping repeats; fill is the
+    // fraction of each node to use
+    pub fn bulk_load_with_store<I: IntoIterator<Item = T>>(
+        b: usize,
+        bs: S,
+        iter: I,
+        fill: f64,
+    ) -> Result<Self, Error> {
+        let mut keys: Vec<T> = vec![];
+        for (index, x) in iter.into_iter().enumerate() {
+            match keys.last() {
+                Some(last) if *last > x => return Err(Error::Unsorted { index }),
+                Some(last) if *last == x => continue,
+                _ => keys.push(x),
+            }
+        }
+        Ok(Self::build(b, bs, keys, fill))
+    }
+    // keys must be strictly increasing
+    fn build(b: usize, bs: S, mut keys: Vec<T>, fill: f64) -> Self {
+        let mut tree = Self::with_store(b, bs);
+        if keys.is_empty() {
+            tree.commit();
+            return tree;
+        }
+        tree.n = keys.len();
+        let ri = tree.ri;
+        tree.bs.free_block(ri);
+
+        // a node with k keys takes k + 1 slots out of a level's keys + 1
+        let lo = std::cmp::max(tree.B, 2);
+        let target = ((tree.b - 1) as f64 * fill.clamp(0.0, 1.0)).round() as usize + 1;
+        let target = std::cmp::min(std::cmp::max(target, lo), tree.b);
+        let mut children: Vec<i32> = vec![];
+        loop {
+            let x = keys.len() + 1;
+            let min_m = x.div_ceil(tree.b);
+            let max_m = std::cmp::max(x / lo, min_m);
+            let m = ((x as f64 / target as f64).round() as usize).clamp(min_m, max_m);
+            let m = std::cmp::max(m, 1);
+            let mut ks = keys.into_iter();
+            let mut cs = children.into_iter();
+            let leaves = cs.len() == 0;
+            keys = vec![];
+            children = vec![];
+            for k in 0..m {
+                let z = x / m + if k < x % m { 1 } else { 0 };
+                let mut u = Node::new(&mut tree);
+                for key in u.keys[..(z - 1)].iter_mut() {
+                    *key = ks.next();
+                }
+                if !leaves {
+                    for c in u.children[..z].iter_mut() {
+                        *c = cs.next().unwrap();
+                    }
+                }
+                children.push(u.id as i32);
+                tree.bs.write_block(u.id, u);
+                if k + 1 < m {
+                    keys.extend(ks.next());
+                }
+            }
+            if m == 1 {
+                tree.ri = children[0] as usize;
+                tree.commit();
+                return tree;
+            }
+        }
+    }
+    pub fn dump(&self) -> Vec<u8>
+    where
+        T: Codec,
+    {
+        snapshot::dump(self.iter())
+    }
+    // a snapshot is sorted, so it is bulk loaded
+    pub fn load_with_store(b: usize, bs: S, buf: &[u8], fill: f64) -> Result<Self, SnapshotError>
+    where
+        T: Codec,
+    {
+        Ok(Self::build(b, bs, snapshot::load(buf)?, fill))
+    }
+    // removes every x with r.start <= x < r.end and returns how many there were
+    pub fn remove_range(&mut self, r: std::ops::Range<T>) -> usize {
+        if r.start >= r.end {
+            return 0;
+        }
+        // whole subtrees inside the range go straight back to the block store
+        self.own_root();
+        let freed = self.cut_range(self.ri as i32, &r);
+        self.n -= freed;
+        // what is left in range sits on the two root-to-leaf paths bounding it
+        let rest: Vec<T> = self.range(r.clone()).collect();
+        for x in rest.iter() {
+            self.remove_one(x);
+        }
+        self.repair_range(&r);
+        self.commit();
+        freed + rest.len()
+    }
+    fn free_subtree(&mut self, ui: i32) -> usize {
+        let freed = self.count_subtree(ui);
+        self.unshare(ui as usize);
+        freed
+    }
+    fn count_subtree(&self, ui: i32) -> usize {
+        let mut count = 0;
+        if let Some(u) = self.bs.read_block(ui as usize) {
+            count += u.size();
+            if !u.is_leaf() {
+                for &c in u.children[..(u.size() + 1)].iter() {
+                    count += self.count_subtree(c);
+                }
+            }
+        }
+        count
+    }
+    fn cut_range(&mut self, ui: i32, r: &std::ops::Range<T>) -> usize {
+        let mut u = match self.bs.read_block(ui as usize) {
+            Some(u) if !u.is_leaf() => u,
+            _ => return 0,
+        };
+        let bound = |x: &T| match Self::find_it(&u.keys, x) {
+            i if i < 0 => (-(i + 1)) as usize,
+            i => i as usize,
+        };
+        let (i, j) = (bound(&r.start), bound(&r.end));
+        let mut freed = 0;
+        if j > i + 1 {
+            // keep keys[i] to separate children i and j, drop what lies between them
+            let d = j - i - 1;
+            for c in (i + 1)..j {
+                freed += self.free_subtree(u.children[c]) + 1;
+            }
+            let size = u.size();
+            for k in (i + 1)..size {
+                u.keys[k] = if k + d < size {
+                    u.keys[k + d].take()
+                } else {
+                    None
+                };
+            }
+            for k in (i + 1)..=size {
+                u.children[k] = if k + d <= size { u.children[k + d] } else { -1 };
+            }
+            self.bs.write_block(u.id, u.clone());
+        }
+        let ci = self.own_child(&mut u, i);
+        freed += self.cut_range(ci, r);
+        if j > i {
+            let ci = self.own_child(&mut u, i + 1);
+            freed += self.cut_range(ci, r);
+        }
+        freed
+    }
+    // restores the minimum occupancy of nodes left short by cut_range
+    fn repair_range(&mut self, r: &std::ops::Range<T>) {
+        loop {
+            self.own_root();
+            while let Some(root) = self.bs.read_block(self.ri) {
+                if root.size() > 0 || root.is_leaf() {
+                    break;
+                }
+                self.ri = root.children[0] as usize;
+                self.bs.free_block(root.id);
+                self.own_root();
+            }
+            let short = [&r.start, &r.end].iter().find_map(|x| self.find_short(x));
+            match short {
+                Some((mut u, i)) => self.repair(&mut u, i),
+                None => return,
+            }
+        }
+    }
+    // the first node on the search path for x whose child on that path is too small
+    fn find_short(&mut self, x: &T) -> Option<(Node<T>, usize)> {
+        let mut u = self.bs.read_block(self.ri)?;
+        while !u.is_leaf() {
+            let i = match Self::find_it(&u.keys, x) {
+                i if i < 0 => (-(i + 1)) as usize,
+                i => i as usize,
+            };
+            let ci = self.own_child(&mut u, i);
+            let w = self.bs.read_block(ci as usize)?;
+            if w.size() + 1 < self.B {
+                return Some((u, i));
+            }
+            u = w;
+        }
+        None
+    }
+    fn repair(&mut self, u: &mut Node<T>, i: usize) {
+        let (i, shift_left) = if i > 0 { (i - 1, true) } else { (i, false) };
+        let vi = self.own_child(u, i);
+        let wi = self.own_child(u, i + 1);
+        let v = self.bs.read_block(vi as usize);
+        let w = self.bs.read_block(wi as usize);
+        if let (Some(mut v), Some(mut w)) = (v, w) {
+            if v.size() + w.size() < self.b - 1 {
+                self.merge(u, i, &mut v, &mut w);
+            } else if shift_left {
+                self.shift_lr(u, i, &mut v, &mut w);
+            } else {
+                self.shift_rl(u, i, &mut w, &mut v);
+            }
+        }
+    }
+}
+
+impl<T, S> SSet<T> for BTree<T, S>
+where
+    T: Clone + PartialOrd,
+    S: BlockDevice<Node<T>>,
+{
+    fn size(&self) -> usize {
+        self.n
+    }
+    fn add(&mut self, x: T) -> bool {
+        self.put(x, false).is_ok()
+    }
+    fn remove(&mut self, x: &T) -> Option<T> {
+        let y = self.remove_one(x)?;
+        self.commit();
+        Some(y)
+    }
     fn find(&self, x: &T) -> Option<T> {
+        self.find_from(self.ri, x)
+    }
+    fn successor(&self, x: &T) -> Option<T> {
         let mut z = None;
         let mut ui = self.ri as i32;
         while ui >= 0 {
             let u = self.bs.read_block(ui as usize)?;
-            let i = Self::find_it(&u.keys, &x);
-            if i < 0 {
-                return u.keys[(-(i + 1)) as usize].clone();
+            let i = match Self::find_it(&u.keys, x) {
+                i if i < 0 => -i as usize,
+                i => i as usize,
+            };
+            if i < u.keys.len() && u.keys[i].is_some() {
+                z = u.keys[i].clone()
             }
-            if u.keys[i as usize].is_some() {
-                z = u.keys[i as usize].clone()
+            ui = u.children[i];
+        }
+        z
+    }
+    fn predecessor(&self, x: &T) -> Option<T> {
+        let mut z = None;
+        let mut ui = self.ri as i32;
+        while ui >= 0 {
+            let u = self.bs.read_block(ui as usize)?;
+            let i = match Self::find_it(&u.keys, x) {
+                i if i < 0 => (-(i + 1)) as usize,
+                i => i as usize,
+            };
+            if i > 0 {
+                z = u.keys[i - 1].clone()
             }
-            ui = u.children[i as usize];
+            ui = u.children[i];
         }
         z
     }
+    fn find_min(&self) -> Option<T> {
+        let mut u = self.bs.read_block(self.ri)?;
+        while !u.is_leaf() {
+            u = self.bs.read_block(u.children[0] as usize)?;
+        }
+        u.keys[0].clone()
+    }
+    fn find_max(&self) -> Option<T> {
+        let mut u = self.bs.read_block(self.ri)?;
+        while !u.is_leaf() {
+            u = self.bs.read_block(u.children[u.size()] as usize)?;
+        }
+        match u.size() {
+            0 => None,
+            s => u.keys[s - 1].clone(),
+        }
+    }
+    fn count_range(&self, r: std::ops::Range<T>) -> usize {
+        self.range(r).count()
+    }
+}
+
+impl<'a, T: Clone + PartialOrd, S: BlockDevice<Node<T>>> IntoIterator for &'a BTree<T, S> {
+    type Item = T;
+    type IntoIter = Iter<'a, T, S>;
+    fn into_iter(self) -> Iter<'a, T, S> {
+        self.iter()
+    }
+}
+
+impl<T: Clone + PartialOrd, S: BlockDevice<Node<T>>> IntoIterator for BTree<T, S> {
+    type Item = T;
+    type IntoIter = std::vec::IntoIter<T>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter().collect::<Vec<_>>().into_iter()
+    }
+}
+
+impl<T: Clone + PartialOrd, S: BlockDevice<Node<T>>> Extend<T> for BTree<T, S> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for x in iter {
+            self.add(x);
+        }
+    }
+}
+
+// serialized as b and the sorted elements, whatever store the tree lives in
+#[cfg(feature = "serde")]
+impl<T, S> serde::Serialize for BTree<T, S>
+where
+    T: Clone + PartialOrd + serde::Serialize,
+    S: BlockDevice<Node<T>>,
+{
+    fn serialize<Z: serde::Serializer>(&self, s: Z) -> Result<Z::Ok, Z::Error> {
+        serde::Serialize::serialize(&(self.b, self.iter().collect::<Vec<_>>()), s)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, T: Clone + PartialOrd + serde::Deserialize<'de>> serde::Deserialize<'de> for BTree<T> {
+    fn deserialize<D: serde::Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+        let (b, xs): (usize, Vec<T>) = serde::Deserialize::deserialize(d)?;
+        if b < 3 || b % 2 == 0 {
+            return Err(serde::de::Error::custom("b must be odd and at least 3"));
+        }
+        if xs.windows(2).all(|w| w[0] < w[1]) {
+            Ok(Self::build(b, BlockStore::new(), xs, 1.0))
+        } else {
+            let mut tree = Self::new(b);
+            tree.extend(xs);
+            Ok(tree)
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct BTreeMap<K, V>
+where
+    K: PartialOrd + Clone,
+    V: Clone,
+{
+    t: BTree<Pair<K, V>>,
+}
+
+impl<K, V> BTreeMap<K, V>
+where
+    K: PartialOrd + Clone,
+    V: Clone,
+{
+    pub fn new(b: usize) -> Self {
+        Self { t: BTree::new(b) }
+    }
+}
+
+impl<K, V> USetMap<K, V> for BTreeMap<K, V>
+where
+    K: PartialOrd + Clone,
+    V: Clone,
+{
+    type ValueMut<'a>
+        = &'a mut V
+    where
+        Self: 'a;
+    fn size(&self) -> usize {
+        self.t.size()
+    }
+    fn insert(&mut self, k: K, v: V) -> Option<V> {
+        self.t.replace(Pair::new(k, v)).and_then(|p| p.v)
+    }
+    fn get(&self, k: &K) -> Option<V> {
+        self.t
+            .find(&Pair::probe(k.clone()))
+            .filter(|p| p.k == *k)
+            .and_then(|p| p.v)
+    }
+    fn get_mut(&mut self, k: &K) -> Option<&mut V> {
+        self.t.find_mut(&Pair::probe(k.clone()))?.v.as_mut()
+    }
+    fn remove(&mut self, k: &K) -> Option<V> {
+        self.t.remove(&Pair::probe(k.clone())).and_then(|p| p.v)
+    }
+}
+
+impl<K, V> SSetMap<K, V> for BTreeMap<K, V>
+where
+    K: PartialOrd + Clone,
+    V: Clone,
+{
+    fn successor(&self, k: &K) -> Option<(K, V)> {
+        self.t
+            .successor(&Pair::probe(k.clone()))
+            .and_then(Pair::into_tuple)
+    }
+    fn predecessor(&self, k: &K) -> Option<(K, V)> {
+        self.t
+            .predecessor(&Pair::probe(k.clone()))
+            .and_then(Pair::into_tuple)
+    }
+    fn find_min(&self) -> Option<(K, V)> {
+        self.t.find_min().and_then(Pair::into_tuple)
+    }
+    fn find_max(&self) -> Option<(K, V)> {
+        self.t.find_max().and_then(Pair::into_tuple)
+    }
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
-    use chapter01::interface::SSet;
+    use chapter01::interface::{SSet, USetMap};
+    use chapter01::testing::check_ssetmap;
     use chapter09::redblacktree::RedBlackTree;
     use rand::{thread_rng, Rng};
+    use std::collections::BTreeSet;
     #[test]
     fn test_btree() {
         let mut rng = thread_rng();
@@ -424,6 +1504,627 @@ mod test {
                 let y2 = btree.find(&x);
                 assert_eq!(y1, y2);
             }
+            for _ in 0..n {
+                let x = rng.gen_range(0, 5 * n);
+                assert_eq!(redblacktree.successor(&x), btree.successor(&x));
+                assert_eq!(redblacktree.predecessor(&x), btree.predecessor(&x));
+                assert_eq!(redblacktree.floor(&x), btree.floor(&x));
+                assert_eq!(redblacktree.ceiling(&x), btree.ceiling(&x));
+            }
+            assert_eq!(redblacktree.find_min(), btree.find_min());
+            assert_eq!(redblacktree.find_max(), btree.find_max());
+            for _ in 0..n {
+                let x = rng.gen_range(0, 5 * n);
+                let y = x + rng.gen_range(0, n);
+                let r1 = redblacktree.range(x..y).collect::<Vec<_>>();
+                let r2 = btree.range(x..y).collect::<Vec<_>>();
+                assert_eq!(r1, r2);
+                assert_eq!(r1.len(), btree.count_range(x..y));
+            }
+            assert_eq!(
+                redblacktree.iter().collect::<Vec<_>>(),
+                btree.iter().collect::<Vec<_>>()
+            );
+            assert_eq!(
+                redblacktree.iter().rev().collect::<Vec<_>>(),
+                (&btree).into_iter().rev().collect::<Vec<_>>()
+            );
+        }
+        let mut btree = BTree::<i32>::new(3);
+        btree.extend((0..20).rev());
+        let mut iter = btree.iter();
+        assert_eq!(iter.next(), Some(0));
+        assert_eq!(iter.next_back(), Some(19));
+        assert_eq!(iter.size_hint(), (18, Some(18)));
+        assert_eq!(iter.collect::<Vec<_>>(), (1..19).collect::<Vec<_>>());
+        assert_eq!(
+            btree.into_iter().collect::<Vec<_>>(),
+            (0..20).collect::<Vec<_>>()
+        );
+        // an add that splits no node writes its leaf and nothing else,
+        // however deep the tree
+        let mut btree = BTree::<i32>::new(5);
+        btree.extend((0..1000).map(|x| 2 * x));
+        let mut plain = 0;
+        for x in 0..1000 {
+            let stats = btree.stats();
+            assert!(btree.add(2 * x + 1));
+            let io = btree.stats() - stats;
+            if io.allocs == 0 {
+                assert_eq!(io.writes, 1);
+                plain += 1;
+            }
+        }
+        assert!(plain > 500);
+    }
+
+    // checks node occupancy and leaf depth, returning the number of nodes
+    fn check<T: Clone + PartialOrd>(t: &BTree<T>) -> usize {
+        fn walk<T: Clone + PartialOrd>(
+            t: &BTree<T>,
+            ui: i32,
+            depth: usize,
+            leaf_depth: &mut Option<usize>,
+        ) -> usize {
+            let u = t.bs.read_block(ui as usize).unwrap();
+            if ui as usize != t.ri {
+                assert!(u.size() + 1 >= t.B && u.size() < t.b);
+            }
+            if u.is_leaf() {
+                assert_eq!(*leaf_depth.get_or_insert(depth), depth);
+                return 1;
+            }
+            assert!(u.children[u.size() + 1..].iter().all(|&c| c < 0));
+            u.children[..u.size() + 1]
+                .iter()
+                .map(|&c| walk(t, c, depth + 1, leaf_depth))
+                .sum::<usize>()
+                + 1
+        }
+        walk(t, t.ri as i32, 0, &mut None)
+    }
+
+    #[test]
+    fn test_btree_bulk_load() {
+        let mut rng = thread_rng();
+        for &b in [4, 5, 8, 11].iter() {
+            for &n in [0, 1, 2, 3, 10, 100, 1000].iter() {
+                for &fill in [1.0, 0.75, 0.5, 0.0].iter() {
+                    let mut btree =
+                        BTree::bulk_load(b, (0..n).flat_map(|x| vec![x, x]), fill).unwrap();
+                    assert_eq!(btree.size(), n as usize);
+                    assert_eq!(btree.iter().collect::<Vec<_>>(), (0..n).collect::<Vec<_>>());
+                    check(&btree);
+                    assert_eq!(btree.validate(), Ok(()));
+                    let mut redblacktree = RedBlackTree::<i32>::new();
+                    redblacktree.extend(0..n);
+                    for _ in 0..2 * n {
+                        let x = rng.gen_range(0, 2 * n + 1);
+                        if rng.gen_range(0, 2) == 0 {
+                            assert_eq!(redblacktree.add(x), btree.add(x));
+                        } else {
+                            assert_eq!(redblacktree.remove(&x), btree.remove(&x));
+                        }
+                    }
+                    assert_eq!(
+                        redblacktree.iter().collect::<Vec<_>>(),
+                        btree.iter().collect::<Vec<_>>()
+                    );
+                }
+            }
+        }
+        // fuller nodes mean fewer blocks
+        let full = check(&BTree::bulk_load(11, 0..10000, 1.0).unwrap());
+        let half = check(&BTree::bulk_load(11, 0..10000, 0.5).unwrap());
+        assert!(full < half);
+        assert!(full <= 10000 / 10 + 10000 / 100 + 10);
+        #[cfg(feature = "serde")]
+        {
+            let btree = BTree::bulk_load(5, 0..100, 0.5).unwrap();
+            let json = serde_json::to_string(&btree).unwrap();
+            assert!(json.starts_with("[5,[0,1,2,"));
+            let copy: BTree<i32> = serde_json::from_str(&json).unwrap();
+            check(&copy);
+            assert_eq!(copy.validate(), Ok(()));
+            assert!(copy.iter().eq(btree.iter()));
+            let copy: BTree<i32> = serde_json::from_str("[7,[3,1,2,3]]").unwrap();
+            assert_eq!(copy.validate(), Ok(()));
+            assert_eq!(copy.iter().collect::<Vec<_>>(), vec![1, 2, 3]);
+            assert!(serde_json::from_str::<BTree<i32>>("[4,[]]").is_err());
+        }
+        // snapshots are bulk loaded into any store, and damage is caught before anything is built
+        for &n in [0, 1, 100, 1000].iter() {
+            let btree = BTree::bulk_load(7, 0..n, 0.5).unwrap();
+            let buf = btree.dump();
+            let copy = BTree::<i32>::load(7, &buf, 1.0).unwrap();
+            check(&copy);
+            assert_eq!(copy.validate(), Ok(()));
+            assert!(copy.iter().eq(0..n));
+            let copy: BTree<i32> = BTree::load_with_store(5, BlockStore::new(), &buf, 0.5).unwrap();
+            assert_eq!(copy.validate(), Ok(()));
+            assert_eq!(copy.size(), n as usize);
+            for cut in [0, 5, 13, buf.len() / 2].iter() {
+                if *cut < buf.len() {
+                    assert!(BTree::<i32>::load(7, &buf[..*cut], 1.0).is_err());
+                }
+            }
+        }
+        let mut buf = BTree::bulk_load(7, 0..10, 1.0).unwrap().dump();
+        buf.swap(14, 18);
+        assert_eq!(
+            BTree::<i32>::load(7, &buf, 1.0).err(),
+            Some(SnapshotError::Unsorted { index: 1 })
+        );
+    }
+
+    #[test]
+    fn test_btree_bulk_load_unsorted() {
+        assert_eq!(
+            BTree::bulk_load(5, vec![1, 3, 3, 2], 1.0).err(),
+            Some(Error::Unsorted { index: 3 })
+        );
+        let btree = BTree::bulk_load(5, vec![1, 1, 2, 3, 3], 1.0).unwrap();
+        assert_eq!(btree.iter().collect::<Vec<_>>(), vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn test_btree_remove_range() {
+        let mut rng = thread_rng();
+        let n = 1000;
+        for &b in [4, 5, 8, 11].iter() {
+            let mut redblacktree = RedBlackTree::<i32>::new();
+            let mut btree = BTree::<i32>::new(b);
+            for _ in 0..n {
+                let x = rng.gen_range(0, 5 * n);
+                redblacktree.add(x);
+                btree.add(x);
+            }
+            for _ in 0..20 {
+                let x = rng.gen_range(0, 5 * n);
+                let y = x + rng.gen_range(0, n);
+                let expected = redblacktree.range(x..y).collect::<Vec<_>>();
+                for z in expected.iter() {
+                    redblacktree.remove(z);
+                }
+                assert_eq!(btree.remove_range(x..y), expected.len());
+                assert_eq!(btree.size(), redblacktree.size());
+                assert_eq!(btree.count_range(x..y), 0);
+                check(&btree);
+                assert_eq!(btree.validate(), Ok(()));
+                for _ in 0..n / 20 {
+                    let x = rng.gen_range(0, 5 * n);
+                    assert_eq!(redblacktree.add(x), btree.add(x));
+                }
+                assert_eq!(
+                    redblacktree.iter().collect::<Vec<_>>(),
+                    btree.iter().collect::<Vec<_>>()
+                );
+            }
+            assert_eq!(btree.remove_range(10..10), 0);
+            assert_eq!(btree.remove_range(-1..5 * n), redblacktree.size());
+            assert_eq!(btree.size(), 0);
+            assert_eq!(btree.iter().next(), None);
+            check(&btree);
+            btree.extend(0..100);
+            assert_eq!(
+                btree.iter().collect::<Vec<_>>(),
+                (0..100).collect::<Vec<_>>()
+            );
+        }
+    }
+
+    #[test]
+    fn test_btree_validate() {
+        let mut btree = BTree::<i32>::new(9);
+        assert_eq!(btree.validate(), Ok(()));
+        btree.extend(0..100);
+        assert_eq!(btree.validate(), Ok(()));
+        let root = btree.bs.read_block(btree.ri).unwrap();
+        let leaf = {
+            let mut u = root.clone();
+            while !u.is_leaf() {
+                u = btree.bs.read_block(u.children[1] as usize).unwrap();
+            }
+            u
+        };
+        let corrupt = |f: &dyn Fn(&mut BTree<i32>)| {
+            let mut t = btree.clone();
+            f(&mut t);
+            t.validate().unwrap_err()
+        };
+
+        let e = corrupt(&|t| t.n += 1);
+        assert_eq!(e, BTreeError::Size { n: 101, found: 100 });
+        assert_eq!(e.to_string(), "the tree says it has 101 keys but holds 100");
+        let e = corrupt(&|t| {
+            let mut u = leaf.clone();
+            u.keys.swap(0, 1);
+            t.bs.write_block(u.id, u);
+        });
+        assert_eq!(e, BTreeError::Unordered { id: leaf.id });
+        // a key that belongs in a sibling
+        let e = corrupt(&|t| {
+            let mut u = leaf.clone();
+            u.keys[0] = Some(-1);
+            t.bs.write_block(u.id, u);
+        });
+        assert_eq!(e, BTreeError::Unordered { id: leaf.id });
+        let e = corrupt(&|t| {
+            let mut u = leaf.clone();
+            for k in u.keys[1..].iter_mut() {
+                k.take();
+            }
+            t.bs.write_block(u.id, u);
+        });
+        assert_eq!(
+            e,
+            BTreeError::Occupancy {
+                id: leaf.id,
+                keys: 1
+            }
+        );
+        let e = corrupt(&|t| {
+            let mut u = leaf.clone();
+            u.keys[0] = None;
+            t.bs.write_block(u.id, u);
+        });
+        assert_eq!(e, BTreeError::Malformed { id: leaf.id });
+        let e = corrupt(&|t| {
+            let mut u = root.clone();
+            u.children[1] = u.children[0];
+            t.bs.write_block(u.id, u);
+        });
+        assert_eq!(
+            e.to_string(),
+            format!("node {} is reached twice", root.children[0])
+        );
+        let e = corrupt(&|t| {
+            let mut u = root.clone();
+            u.children[0] = 1000;
+            t.bs.write_block(u.id, u);
+        });
+        assert_eq!(
+            e,
+            BTreeError::Dangling {
+                parent: Some(root.id),
+                id: 1000
+            }
+        );
+        let e = corrupt(&|t| {
+            let mut u = root.clone();
+            u.children[root.size() + 1] = u.children[0];
+            t.bs.write_block(u.id, u);
+        });
+        assert!(matches!(e, BTreeError::Children { .. }));
+        let e = corrupt(&|t| {
+            let mut u = leaf.clone();
+            u.id = 1000;
+            t.bs.write_block(leaf.id, u);
+        });
+        assert_eq!(
+            e,
+            BTreeError::WrongId {
+                id: leaf.id,
+                found: 1000
+            }
+        );
+        // a subtree moved one level up
+        let e = corrupt(&|t| {
+            let mut u = root.clone();
+            let child = t.bs.read_block(u.children[0] as usize).unwrap();
+            u.children[0] = child.children[0];
+            t.bs.write_block(u.id, u);
+        });
+        assert!(matches!(
+            e,
+            BTreeError::Depth {
+                depth: 2,
+                expected: 1,
+                ..
+            }
+        ));
+        let e = corrupt(&|t| {
+            let i = t.bs.place_block(leaf.clone());
+            t.bs.place_block(leaf.clone());
+            t.bs.free_block(i);
+        });
+        assert_eq!(
+            e,
+            BTreeError::Leaked {
+                ids: vec![btree.bs.bound() + 1]
+            }
+        );
+        assert_eq!(
+            e.to_string(),
+            format!(
+                "blocks not reachable from the root: {}",
+                btree.bs.bound() + 1
+            )
+        );
+    }
+
+    #[test]
+    fn test_btree_snapshot() {
+        let mut rng = thread_rng();
+        let n = 500;
+        let live = |t: &BTree<i32>| t.stats().allocs - t.stats().frees;
+        for &b in [4, 5, 8].iter() {
+            let mut redblacktree = RedBlackTree::<i32>::new();
+            let mut btree = BTree::<i32>::new(b);
+            for _ in 0..n {
+                let x = rng.gen_range(0, 5 * n);
+                assert_eq!(redblacktree.add(x), btree.add(x));
+            }
+            assert_eq!(live(&btree), check(&btree));
+            let mut snapshots = vec![];
+            for k in 0..4 {
+                let s = btree.snapshot();
+                snapshots.push((s, redblacktree.iter().collect::<Vec<_>>()));
+                for _ in 0..n / 2 {
+                    let x = rng.gen_range(0, 5 * n);
+                    match rng.gen_range(0, 20) {
+                        0 => {
+                            let y = x + rng.gen_range(0, n / 10);
+                            let expected = redblacktree.range(x..y).collect::<Vec<_>>();
+                            for z in expected.iter() {
+                                redblacktree.remove(z);
+                            }
+                            assert_eq!(btree.remove_range(x..y), expected.len());
+                        }
+                        1..=9 => assert_eq!(redblacktree.add(x), btree.add(x)),
+                        _ => assert_eq!(redblacktree.remove(&x), btree.remove(&x)),
+                    }
+                }
+                check(&btree);
+                assert_eq!(btree.validate(), Ok(()));
+                assert_eq!(
+                    redblacktree.iter().collect::<Vec<_>>(),
+                    btree.iter().collect::<Vec<_>>()
+                );
+                // every older version still reads as it was
+                for (s, old) in snapshots.iter() {
+                    let v = btree.at(s).unwrap();
+                    assert_eq!(v.size(), old.len());
+                    assert_eq!(v.iter().collect::<Vec<_>>(), *old);
+                    assert_eq!(v.iter().rev().collect::<Vec<_>>().len(), old.len());
+                    for _ in 0..10 {
+                        let x = rng.gen_range(0, 5 * n);
+                        let y = x + rng.gen_range(0, n);
+                        let expected = old.iter().find(|&&z| z >= x).cloned();
+                        assert_eq!(v.find(&x), expected);
+                        assert_eq!(
+                            v.range(x..y).collect::<Vec<_>>(),
+                            old.iter()
+                                .filter(|&&z| x <= z && z < y)
+                                .cloned()
+                                .collect::<Vec<_>>()
+                        );
+                    }
+                }
+                if k % 2 == 1 {
+                    let (s, _) = snapshots.remove(0);
+                    btree.release(s);
+                }
+            }
+            for (s, _) in snapshots.drain(..) {
+                btree.release(s);
+            }
+            // with the snapshots gone every block left is reachable from the root
+            assert_eq!(live(&btree), check(&btree));
+            assert!(btree.refs.is_empty());
+            assert_eq!(btree.validate(), Ok(()));
+
+            // a dropped snapshot is reclaimed by the next update, and one
+            // released into another tree goes back to its own
+            let mut other = BTree::<i32>::new(b);
+            other.add(0);
+            let s = btree.snapshot();
+            let t = btree.snapshot();
+            btree.add(-1);
+            assert!(other.at(&s).is_none());
+            other.release(s);
+            assert_eq!(other.validate(), Ok(()));
+            assert_eq!(other.iter().collect::<Vec<_>>(), vec![0]);
+            drop(t);
+            btree.remove(&-1);
+            assert_eq!(live(&btree), check(&btree));
+            assert!(btree.refs.is_empty());
+            assert_eq!(btree.validate(), Ok(()));
+        }
+    }
+
+    #[test]
+    fn test_btree_file() {
+        let path = std::env::temp_dir().join(format!("ods-btree-{}.db", std::process::id()));
+        let mut rng = thread_rng();
+        let n = 500;
+        let mut redblacktree = RedBlackTree::<i64>::new();
+        // a pool much smaller than the tree forces blocks in and out of the file
+        let mut btree = BTree::<i64, _>::create(&path, 7, 256, 4).unwrap();
+        for _ in 0..n {
+            let x = rng.gen_range(0, 5 * n);
+            assert_eq!(redblacktree.add(x), btree.add(x));
+        }
+        for _ in 0..n / 2 {
+            let x = rng.gen_range(0, 5 * n);
+            assert_eq!(redblacktree.remove(&x), btree.remove(&x));
+        }
+        btree.flush().unwrap();
+        drop(btree);
+
+        let mut btree = BTree::<i64, _>::open(&path, 4).unwrap();
+        assert_eq!(btree.size(), redblacktree.size());
+        let flushed = redblacktree.iter().collect::<Vec<_>>();
+        assert_eq!(flushed, btree.iter().collect::<Vec<_>>());
+        let updates = (0..n)
+            .map(|_| (rng.gen_range(0, 5 * n), rng.gen_range(0, 3)))
+            .collect::<Vec<_>>();
+        // the reference state after each update, to tell which one a
+        // checkpoint captured
+        let mut reference = flushed.iter().copied().collect::<BTreeSet<_>>();
+        let mut states = vec![flushed];
+        for &(x, op) in updates.iter() {
+            match op {
+                0 => assert_eq!(btree.add(x), reference.insert(x)),
+                1 => assert_eq!(btree.remove(&x), reference.take(&x)),
+                _ => assert_eq!(btree.find(&x), reference.range(x..).next().copied()),
+            };
+            states.push(reference.iter().copied().collect());
+        }
+        // dropped without a flush, as in a crash: evicted blocks never
+        // reached the file, which holds the last checkpoint, taken by a
+        // commit once the pending pages outgrew the pool
+        drop(btree);
+        let mut btree = BTree::<i64, _>::open(&path, 4).unwrap();
+        assert_eq!(btree.validate(), Ok(()));
+        let found = btree.iter().collect::<Vec<_>>();
+        assert_eq!(btree.size(), found.len());
+        let k = states.iter().position(|xs| *xs == found).unwrap();
+        let mut redblacktree = found.iter().copied().collect::<RedBlackTree<_>>();
+        for &(x, op) in updates[k..].iter() {
+            match op {
+                0 => assert_eq!(redblacktree.add(x), btree.add(x)),
+                1 => assert_eq!(redblacktree.remove(&x), btree.remove(&x)),
+                _ => assert_eq!(redblacktree.find(&x), btree.find(&x)),
+            }
+        }
+        btree.flush().unwrap();
+        drop(btree);
+        let btree = BTree::<i64, _>::open(&path, 16).unwrap();
+        assert_eq!(
+            redblacktree.iter().collect::<Vec<_>>(),
+            btree.iter().collect::<Vec<_>>()
+        );
+        drop(btree);
+        // a snapshot alive at a checkpoint is gone after reopening, and so
+        // are the blocks only it reached
+        let mut btree = BTree::<i64, _>::open(&path, 4).unwrap();
+        let s = btree.snapshot();
+        for x in 0..n {
+            btree.add(5 * x);
+        }
+        btree.flush().unwrap();
+        assert_eq!(btree.at(&s).unwrap().size(), redblacktree.size());
+        drop(btree);
+        let btree = BTree::<i64, _>::open(&path, 4).unwrap();
+        assert!(btree.at(&s).is_none());
+        assert_eq!(btree.validate(), Ok(()));
+        drop(btree);
+        assert!(BTree::<i64, _>::open(std::env::temp_dir().join("ods-missing.db"), 4).is_err());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_btree_wal() {
+        let path = std::env::temp_dir().join(format!("ods-btree-wal-{}.db", std::process::id()));
+        let wal = std::env::temp_dir().join(format!("ods-btree-wal-{}.db.wal", std::process::id()));
+        let crashed = (path.with_extension("crash"), wal.with_extension("crash"));
+        let mut rng = thread_rng();
+        let n = 200;
+        // a pool larger than the tree, so no commit forces a checkpoint and
+        // the log keeps every update
+        let mut btree = BTree::<i64, _>::create(&path, 7, 256, 1024).unwrap();
+        btree.enable_wal().unwrap();
+        assert!(btree.store().has_wal());
+        for _ in 0..n {
+            btree.add(rng.gen_range(0, 5 * n));
+        }
+        btree.flush().unwrap();
+        assert_eq!(std::fs::metadata(&wal).unwrap().len(), 0);
+
+        // after each update: how long the log is and what the tree holds
+        let mut history = vec![(0, btree.iter().collect::<Vec<_>>())];
+        for _ in 0..n {
+            let x = rng.gen_range(0, 5 * n);
+            match rng.gen_range(0, 2) {
+                0 => btree.add(x),
+                _ => btree.remove(&x).is_some(),
+            };
+            let len = std::fs::metadata(&wal).unwrap().len();
+            history.push((len, btree.iter().collect::<Vec<_>>()));
+        }
+        // crash: nothing is flushed and the file still holds the checkpoint
+        drop(btree);
+        std::fs::copy(&path, &crashed.0).unwrap();
+        std::fs::copy(&wal, &crashed.1).unwrap();
+
+        // a crash tears the log at any byte; every update logged in full survives
+        let end = history[n as usize].0;
+        for k in 0..30 {
+            let len = if k == 0 { end } else { rng.gen_range(0, end) };
+            std::fs::copy(&crashed.0, &path).unwrap();
+            std::fs::copy(&crashed.1, &wal).unwrap();
+            std::fs::OpenOptions::new()
+                .write(true)
+                .open(&wal)
+                .unwrap()
+                .set_len(len)
+                .unwrap();
+            let btree = BTree::<i64, _>::open(&path, 4).unwrap();
+            let (_, expected) = history.iter().rev().find(|(l, _)| *l <= len).unwrap();
+            assert_eq!(btree.size(), expected.len());
+            assert_eq!(btree.iter().collect::<Vec<_>>(), *expected);
+            assert_eq!(std::fs::metadata(&wal).unwrap().len(), 0);
+        }
+
+        // a checkpoint cut short leaves pages half written, the log puts them right
+        std::fs::copy(&crashed.0, &path).unwrap();
+        std::fs::copy(&crashed.1, &wal).unwrap();
+        let mut file = std::fs::OpenOptions::new().write(true).open(&path).unwrap();
+        std::io::Write::write_all(&mut file, &[0u8; 100]).unwrap();
+        drop(file);
+        let mut btree = BTree::<i64, _>::open(&path, 4).unwrap();
+        assert!(btree.store().has_wal());
+        let mut redblacktree = RedBlackTree::<i64>::new();
+        for &x in history[n as usize].1.iter() {
+            redblacktree.add(x);
+        }
+        assert_eq!(btree.iter().collect::<Vec<_>>(), history[n as usize].1);
+        for _ in 0..n {
+            let x = rng.gen_range(0, 5 * n);
+            match rng.gen_range(0, 3) {
+                0 => assert_eq!(redblacktree.add(x), btree.add(x)),
+                1 => assert_eq!(redblacktree.remove(&x), btree.remove(&x)),
+                _ => assert_eq!(redblacktree.find(&x), btree.find(&x)),
+            }
+        }
+        assert_eq!(
+            btree.remove_range(100..400),
+            redblacktree.count_range(100..400)
+        );
+        for x in 100..400 {
+            redblacktree.remove(&x);
+        }
+        drop(btree);
+        let mut btree = BTree::<i64, _>::open(&path, 4).unwrap();
+        assert_eq!(
+            redblacktree.iter().collect::<Vec<_>>(),
+            btree.iter().collect::<Vec<_>>()
+        );
+        btree.disable_wal().unwrap();
+        assert!(!wal.exists());
+        drop(btree);
+        let btree = BTree::<i64, _>::open(&path, 4).unwrap();
+        assert!(!btree.store().has_wal());
+        assert_eq!(btree.size(), redblacktree.size());
+        drop(btree);
+        for p in [&path, &crashed.0, &crashed.1].iter() {
+            std::fs::remove_file(p).unwrap();
+        }
+    }
+    #[test]
+    fn test_btreemap() {
+        check_ssetmap(|| BTreeMap::<i32, usize>::new(5));
+        // get_mut changes the key's block where it lies
+        let mut btreemap = BTreeMap::<i32, usize>::new(5);
+        for k in 0..100 {
+            btreemap.insert(k, 0);
         }
+        let stats = btreemap.t.stats();
+        *btreemap.get_mut(&50).unwrap() += 1;
+        let io = btreemap.t.stats() - stats;
+        assert_eq!(io.writes, 1);
+        assert_eq!(io.allocs, 0);
+        assert_eq!(btreemap.get(&50), Some(1));
     }
 }
@@ -0,0 +1,176 @@
+#![allow(clippy::many_single_char_names,clippy::explicit_counter_loop)]
+use crate::blockstore::{BlockDevice, Counters, IoStats};
+use crate::pool::Pool;
+use std::cell::RefCell;
+
+// a write-back LRU cache of capacity blocks in front of another store,
+// standing in for the internal memory of the external memory model
+#[derive(Clone, Debug)]
+pub struct CachedBlockStore<T: Clone, S> {
+    inner: RefCell<S>,
+    capacity: usize,
+    pool: RefCell<Pool<T>>,
+    stats: Counters,
+}
+
+impl<T: Clone, S: BlockDevice<T>> CachedBlockStore<T, S> {
+    pub fn new(inner: S, capacity: usize) -> Self {
+        Self {
+            inner: RefCell::new(inner),
+            capacity,
+            pool: RefCell::new(Pool::new(capacity)),
+            stats: Counters::default(),
+        }
+    }
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+    // writes every dirty block back to the underlying store
+    pub fn flush(&mut self) {
+        let mut dirty = vec![];
+        for (&i, f) in self.pool.borrow_mut().frames.iter_mut() {
+            if f.dirty {
+                f.dirty = false;
+                dirty.push((i, f.block.clone()));
+            }
+        }
+        dirty.sort_by_key(|(i, _)| *i);
+        let mut inner = self.inner.borrow_mut();
+        for (i, block) in dirty {
+            inner.write_block(i, block);
+        }
+    }
+    pub fn into_inner(mut self) -> S {
+        self.flush();
+        self.inner.into_inner()
+    }
+    fn cache(&self, i: usize, block: T, dirty: bool) {
+        let evicted = self.pool.borrow_mut().put(i, block, dirty);
+        if let Some((j, f)) = evicted {
+            if f.dirty {
+                self.inner.borrow_mut().write_block(j, f.block);
+            }
+        }
+    }
+}
+
+impl<T: Clone, S: BlockDevice<T>> BlockDevice<T> for CachedBlockStore<T, S> {
+    fn place_block(&mut self, block: T) -> usize {
+        let i = self.inner.get_mut().place_block(block.clone());
+        self.cache(i, block, false);
+        i
+    }
+    fn free_block(&mut self, i: usize) {
+        self.pool.get_mut().remove(i);
+        self.inner.get_mut().free_block(i);
+    }
+    fn read_block(&self, i: usize) -> Option<T> {
+        if let Some(block) = self.pool.borrow_mut().get(i) {
+            self.stats.update(|s| s.hits += 1);
+            return Some(block);
+        }
+        self.stats.update(|s| s.misses += 1);
+        let block = self.inner.borrow().read_block(i)?;
+        self.cache(i, block.clone(), false);
+        Some(block)
+    }
+    fn write_block(&mut self, i: usize, block: T) {
+        self.cache(i, block, true);
+    }
+    fn bound(&self) -> usize {
+        self.inner.borrow().bound()
+    }
+    fn commit(&mut self, meta: &[u8]) {
+        self.flush();
+        self.inner.get_mut().commit(meta);
+    }
+    // transfers to and from the underlying store, with this cache's hits and misses
+    fn stats(&self) -> IoStats {
+        let mut s = self.inner.borrow().stats();
+        s.hits = self.stats.get().hits;
+        s.misses = self.stats.get().misses;
+        s
+    }
+    fn reset_stats(&mut self) {
+        self.stats.reset();
+        self.inner.get_mut().reset_stats();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::blockstore::BlockStore;
+    use crate::btree::BTree;
+    use chapter01::interface::SSet;
+    use rand::{thread_rng, Rng};
+    #[test]
+    fn test_cachedblockstore() {
+        let mut bs = CachedBlockStore::new(BlockStore::new(), 2);
+        for i in 0..4 {
+            assert_eq!(bs.place_block(i * 10), i as usize);
+        }
+        let placed = bs.stats();
+        assert_eq!(placed.allocs, 4);
+        assert_eq!(placed.transfers(), 0);
+        // blocks 2 and 3 are cached, 0 and 1 must come from below
+        assert_eq!(bs.read_block(3), Some(30));
+        assert_eq!(bs.read_block(0), Some(0));
+        let s = bs.stats() - placed;
+        assert_eq!((s.hits, s.misses, s.reads), (1, 1, 1));
+        // dirty blocks only reach the store when evicted or flushed
+        bs.write_block(0, 1);
+        assert_eq!(bs.stats().writes, 0);
+        bs.read_block(1);
+        bs.read_block(2);
+        assert_eq!(bs.stats().writes, 1);
+        bs.write_block(2, 21);
+        bs.flush();
+        assert_eq!(bs.stats().writes, 2);
+        bs.free_block(2);
+        assert_eq!(bs.stats().frees, 1);
+        bs.reset_stats();
+        assert_eq!(bs.stats(), IoStats::default());
+        let inner = bs.into_inner();
+        assert_eq!(inner.read_block(0), Some(1));
+        assert_eq!(inner.read_block(2), None);
+    }
+
+    #[test]
+    fn test_btree_costs() {
+        let mut rng = thread_rng();
+        let n = 2000;
+        let keys: Vec<i32> = (0..n).map(|_| rng.gen_range(0, 10 * n)).collect();
+        let mut costs = vec![];
+        for &b in [5, 17, 65].iter() {
+            // no cache at all: every node on a search path is a block transfer
+            let mut btree = BTree::with_store(b, CachedBlockStore::new(BlockStore::new(), 0));
+            btree.extend(keys.iter().cloned());
+            btree.reset_stats();
+            for x in keys.iter() {
+                assert!(btree.find(x).is_some());
+            }
+            let cold = btree.stats();
+            assert_eq!(cold.hits, 0);
+            assert_eq!(cold.reads, cold.misses);
+            assert_eq!(cold.writes, 0);
+            costs.push(cold.reads);
+
+            // a cache big enough for the whole tree answers everything after one pass
+            let mut btree =
+                BTree::with_store(b, CachedBlockStore::new(BlockStore::new(), n as usize));
+            btree.extend(keys.iter().cloned());
+            for x in keys.iter() {
+                btree.find(x);
+            }
+            btree.reset_stats();
+            for x in keys.iter() {
+                btree.find(x);
+            }
+            assert_eq!(btree.stats().transfers(), 0);
+            assert!(btree.stats().hits > 0);
+        }
+        // bigger blocks make shallower trees
+        assert!(costs[0] > costs[1] && costs[1] > costs[2]);
+    }
+}
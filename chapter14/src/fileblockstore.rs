@@ -0,0 +1,540 @@
+#![allow(clippy::many_single_char_names,clippy::explicit_counter_loop)]
+use crate::blockstore::{BlockDevice, Counters, IoStats};
+use crate::codec::Codec;
+use crate::pool::Pool;
+use crate::wal::{Record, Wal};
+use std::cell::RefCell;
+use std::collections::{BTreeSet, HashMap};
+use std::fs::{File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+pub const PAGE_SIZE: usize = 4096;
+
+const MAGIC: &[u8; 8] = b"ODSBLKS\0";
+const VERSION: u32 = 1;
+const HEADER_SIZE: usize = 36; // magic, version, page size, pages, free list head, meta length
+const FREE: u8 = 0;
+const USED: u8 = 1;
+
+#[derive(Debug)]
+pub struct FileBlockStore<T: Codec + Clone> {
+    file: RefCell<File>,
+    page_size: usize,
+    npages: usize,
+    free_head: Option<usize>,
+    meta: Vec<u8>,
+    pool: RefCell<Pool<T>>,
+    error: RefCell<Option<io::Error>>, // the first i/o error, reported by flush
+    stats: Counters,
+    wal_path: PathBuf,
+    wal: Option<Wal>,
+    // pages written since the last checkpoint wait here, evicted ones
+    // included, so the file keeps its checkpointed contents until a flush;
+    // a commit checkpoints once they outnumber the pool's frames
+    pending: RefCell<HashMap<usize, Vec<u8>>>,
+    txn: RefCell<BTreeSet<usize>>, // pages written since the last commit
+}
+
+fn wal_path(path: &Path) -> PathBuf {
+    let mut p = path.as_os_str().to_owned();
+    p.push(".wal");
+    PathBuf::from(p)
+}
+
+fn invalid(msg: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+impl<T: Codec + Clone> FileBlockStore<T> {
+    pub fn create<P: AsRef<Path>>(path: P, page_size: usize, capacity: usize) -> io::Result<Self> {
+        if page_size < HEADER_SIZE || page_size > u32::MAX as usize {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("page size {} is out of range", page_size),
+            ));
+        }
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(path.as_ref())?;
+        // a log left over from an earlier file must not be replayed into this one
+        let wal_path = wal_path(path.as_ref());
+        match std::fs::remove_file(&wal_path) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+            _ => (),
+        }
+        let mut s = Self {
+            file: RefCell::new(file),
+            page_size,
+            npages: 0,
+            free_head: None,
+            meta: vec![],
+            pool: RefCell::new(Pool::new(std::cmp::max(capacity, 1))),
+            error: RefCell::new(None),
+            stats: Counters::default(),
+            wal_path,
+            wal: None,
+            pending: RefCell::new(HashMap::new()),
+            txn: RefCell::new(BTreeSet::new()),
+        };
+        s.flush()?;
+        Ok(s)
+    }
+    // replays the log, if there is one, and keeps logging
+    pub fn open<P: AsRef<Path>>(path: P, capacity: usize) -> io::Result<Self> {
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(path.as_ref())?;
+        let wal_path = wal_path(path.as_ref());
+        let wal = match Wal::open(&wal_path) {
+            Ok(mut wal) => {
+                Self::recover(&mut file, &mut wal)?;
+                Some(wal)
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+            Err(e) => return Err(e),
+        };
+        file.seek(SeekFrom::Start(0))?;
+        let mut header = [0u8; HEADER_SIZE];
+        file.read_exact(&mut header)?;
+        let mut buf = &header[..];
+        let magic = buf.get(..8).unwrap_or(&[]);
+        if magic != MAGIC {
+            return Err(invalid("not a block store file".into()));
+        }
+        buf = &buf[8..];
+        let version = u32::decode(&mut buf).unwrap_or(0);
+        if version != VERSION {
+            return Err(invalid(format!(
+                "unsupported block store version {}",
+                version
+            )));
+        }
+        let page_size = u32::decode(&mut buf).unwrap_or(0) as usize;
+        let npages = u64::decode(&mut buf).unwrap_or(0) as usize;
+        let free_head = i64::decode(&mut buf).unwrap_or(-1);
+        let meta_len = u32::decode(&mut buf).unwrap_or(0) as usize;
+        if page_size < HEADER_SIZE || HEADER_SIZE + meta_len > page_size {
+            return Err(invalid("corrupt block store header".into()));
+        }
+        if free_head >= npages as i64 {
+            return Err(invalid("corrupt free list".into()));
+        }
+        let mut meta = vec![0u8; meta_len];
+        file.read_exact(&mut meta)?;
+        Ok(Self {
+            file: RefCell::new(file),
+            page_size,
+            npages,
+            free_head: if free_head < 0 {
+                None
+            } else {
+                Some(free_head as usize)
+            },
+            meta,
+            pool: RefCell::new(Pool::new(std::cmp::max(capacity, 1))),
+            error: RefCell::new(None),
+            stats: Counters::default(),
+            wal_path,
+            wal,
+            pending: RefCell::new(HashMap::new()),
+            txn: RefCell::new(BTreeSet::new()),
+        })
+    }
+    // copies every complete record into the file; applying one twice does no harm
+    fn recover(file: &mut File, wal: &mut Wal) -> io::Result<()> {
+        let records = wal.records()?;
+        for r in records.iter() {
+            for (i, page) in r.pages.iter() {
+                let offset = i.map_or(0, |i| (i as u64 + 1) * r.page_size as u64);
+                file.seek(SeekFrom::Start(offset))?;
+                file.write_all(page)?;
+            }
+        }
+        file.sync_all()?;
+        wal.reset()
+    }
+    pub fn page_size(&self) -> usize {
+        self.page_size
+    }
+    pub fn pages(&self) -> usize {
+        self.npages
+    }
+    // a few bytes of user data kept in the header page
+    pub fn meta(&self) -> &[u8] {
+        &self.meta
+    }
+    pub fn set_meta(&mut self, meta: &[u8]) -> io::Result<()> {
+        if HEADER_SIZE + meta.len() > self.page_size {
+            return Err(invalid(format!(
+                "{} bytes of metadata do not fit in the header",
+                meta.len()
+            )));
+        }
+        self.meta = meta.to_vec();
+        Ok(())
+    }
+    pub fn has_wal(&self) -> bool {
+        self.wal.is_some()
+    }
+    // from here on every commit is logged before the file changes
+    pub fn enable_wal(&mut self) -> io::Result<()> {
+        if self.wal.is_none() {
+            self.flush()?;
+            self.wal = Some(Wal::create(&self.wal_path)?);
+        }
+        Ok(())
+    }
+    pub fn disable_wal(&mut self) -> io::Result<()> {
+        if self.wal.is_some() {
+            self.flush()?;
+            self.wal = None;
+            std::fs::remove_file(&self.wal_path)?;
+        }
+        Ok(())
+    }
+    // a checkpoint: the file catches up and the log, if any, starts over.
+    // without a log a crash in the middle of it can leave the file torn
+    pub fn flush(&mut self) -> io::Result<()> {
+        if let Some(e) = self.error.borrow_mut().take() {
+            return Err(e);
+        }
+        self.write_dirty()?;
+        if self.wal.is_some() {
+            self.log()?;
+        }
+        let mut pending: Vec<_> = self.pending.get_mut().drain().collect();
+        pending.sort_by_key(|(i, _)| *i);
+        for (i, page) in pending {
+            self.put_page(Some(i), page)?;
+        }
+        self.put_page(None, self.header())?;
+        self.file.borrow_mut().sync_all()?;
+        match self.wal.as_mut() {
+            Some(wal) => wal.reset(),
+            None => Ok(()),
+        }
+    }
+    // ends an update: with a log its pages are made durable as one record.
+    // more pending pages than pool frames force a checkpoint here, between
+    // two updates, so they never exceed the pool by more than one update
+    pub fn commit(&mut self, meta: &[u8]) -> io::Result<()> {
+        self.set_meta(meta)?;
+        if self.wal.is_some() {
+            self.write_dirty()?;
+            if !self.txn.get_mut().is_empty() {
+                self.log()?;
+            }
+        }
+        if self.pending.get_mut().len() > self.pool.get_mut().capacity() {
+            self.flush()?;
+        }
+        Ok(())
+    }
+    fn log(&mut self) -> io::Result<()> {
+        let mut pages = vec![(None, self.header())];
+        let pending = self.pending.get_mut();
+        for i in std::mem::take(self.txn.get_mut()) {
+            pages.push((Some(i), pending[&i].clone()));
+        }
+        let record = Record {
+            page_size: self.page_size,
+            pages,
+        };
+        match self.wal.as_mut() {
+            Some(wal) => wal.append(&record),
+            None => Ok(()),
+        }
+    }
+    fn write_dirty(&mut self) -> io::Result<()> {
+        let mut dirty = vec![];
+        for (&i, f) in self.pool.borrow_mut().frames.iter_mut() {
+            if f.dirty {
+                f.dirty = false;
+                dirty.push((i, f.block.clone()));
+            }
+        }
+        dirty.sort_by_key(|(i, _)| *i);
+        for (i, block) in dirty.iter() {
+            self.store(*i, block)?;
+        }
+        Ok(())
+    }
+    fn header(&self) -> Vec<u8> {
+        let mut header = MAGIC.to_vec();
+        VERSION.encode(&mut header);
+        (self.page_size as u32).encode(&mut header);
+        (self.npages as u64).encode(&mut header);
+        self.free_head.map_or(-1, |i| i as i64).encode(&mut header);
+        (self.meta.len() as u32).encode(&mut header);
+        header.extend_from_slice(&self.meta);
+        header
+    }
+    fn offset(&self, i: Option<usize>) -> u64 {
+        // page 0 holds the header, block i lives in page i + 1
+        i.map_or(0, |i| (i as u64 + 1) * self.page_size as u64)
+    }
+    fn write_page(&self, i: usize, mut page: Vec<u8>) -> io::Result<()> {
+        self.stats.update(|s| s.writes += 1);
+        page.resize(self.page_size, 0);
+        self.pending.borrow_mut().insert(i, page);
+        if self.wal.is_some() {
+            self.txn.borrow_mut().insert(i);
+        }
+        Ok(())
+    }
+    fn put_page(&self, i: Option<usize>, mut page: Vec<u8>) -> io::Result<()> {
+        page.resize(self.page_size, 0);
+        let mut file = self.file.borrow_mut();
+        file.seek(SeekFrom::Start(self.offset(i)))?;
+        file.write_all(&page)
+    }
+    // a pending page is answered from memory; only the file counts as a read
+    fn read_page(&self, i: usize) -> io::Result<Vec<u8>> {
+        if let Some(page) = self.pending.borrow().get(&i) {
+            self.stats.update(|s| s.hits += 1);
+            return Ok(page.clone());
+        }
+        self.stats.update(|s| {
+            s.reads += 1;
+            s.misses += 1;
+        });
+        let mut page = vec![0u8; self.page_size];
+        let mut file = self.file.borrow_mut();
+        file.seek(SeekFrom::Start(self.offset(Some(i))))?;
+        file.read_exact(&mut page)?;
+        Ok(page)
+    }
+    fn store(&self, i: usize, block: &T) -> io::Result<()> {
+        let mut payload = vec![];
+        block.encode(&mut payload);
+        let mut page = vec![USED];
+        (payload.len() as u32).encode(&mut page);
+        page.extend_from_slice(&payload);
+        if page.len() > self.page_size {
+            return Err(invalid(format!(
+                "block {} needs {} bytes but pages hold {}",
+                i,
+                page.len(),
+                self.page_size
+            )));
+        }
+        self.write_page(i, page)
+    }
+    fn load(&self, i: usize) -> io::Result<Option<T>> {
+        let page = self.read_page(i)?;
+        let mut buf = &page[1..];
+        match page[0] {
+            FREE => Ok(None),
+            USED => {
+                let n = u32::decode(&mut buf).unwrap_or(u32::MAX) as usize;
+                let mut payload = buf
+                    .get(..n)
+                    .ok_or_else(|| invalid(format!("block {} is truncated", i)))?;
+                T::decode(&mut payload)
+                    .map(Some)
+                    .ok_or_else(|| invalid(format!("block {} is corrupt", i)))
+            }
+            _ => Err(invalid(format!("page {} has a bad tag", i))),
+        }
+    }
+    fn fail(&self, e: io::Error) {
+        let mut error = self.error.borrow_mut();
+        if error.is_none() {
+            *error = Some(e);
+        }
+    }
+    fn cache(&self, i: usize, block: T, dirty: bool) {
+        let evicted = self.pool.borrow_mut().put(i, block, dirty);
+        if let Some((j, f)) = evicted {
+            if f.dirty {
+                if let Err(e) = self.store(j, &f.block) {
+                    self.fail(e);
+                }
+            }
+        }
+    }
+}
+
+impl<T: Codec + Clone> BlockDevice<T> for FileBlockStore<T> {
+    fn place_block(&mut self, block: T) -> usize {
+        self.stats.update(|s| s.allocs += 1);
+        let i = match self.free_head {
+            Some(i) => {
+                let next = self.read_page(i).ok().and_then(|page| match page[0] {
+                    FREE => i64::decode(&mut &page[1..]),
+                    _ => None,
+                });
+                match next {
+                    Some(next) => {
+                        self.free_head = if next < 0 { None } else { Some(next as usize) };
+                        i
+                    }
+                    None => {
+                        self.fail(invalid(format!("free list is corrupt at page {}", i)));
+                        self.free_head = None;
+                        self.npages += 1;
+                        self.npages - 1
+                    }
+                }
+            }
+            None => {
+                self.npages += 1;
+                self.npages - 1
+            }
+        };
+        self.cache(i, block, true);
+        i
+    }
+    fn free_block(&mut self, i: usize) {
+        if i >= self.npages {
+            return;
+        }
+        self.stats.update(|s| s.frees += 1);
+        self.pool.borrow_mut().remove(i);
+        let mut page = vec![FREE];
+        self.free_head.map_or(-1, |j| j as i64).encode(&mut page);
+        match self.write_page(i, page) {
+            Ok(()) => self.free_head = Some(i),
+            Err(e) => self.fail(e),
+        }
+    }
+    fn read_block(&self, i: usize) -> Option<T> {
+        if i >= self.npages {
+            return None;
+        }
+        if let Some(block) = self.pool.borrow_mut().get(i) {
+            self.stats.update(|s| s.hits += 1);
+            return Some(block);
+        }
+        match self.load(i) {
+            Ok(Some(block)) => {
+                self.cache(i, block.clone(), false);
+                Some(block)
+            }
+            Ok(None) => None,
+            Err(e) => {
+                self.fail(e);
+                None
+            }
+        }
+    }
+    fn write_block(&mut self, i: usize, block: T) {
+        if i < self.npages {
+            self.cache(i, block, true);
+        }
+    }
+    fn bound(&self) -> usize {
+        self.npages
+    }
+    fn commit(&mut self, meta: &[u8]) {
+        if let Err(e) = FileBlockStore::commit(self, meta) {
+            self.fail(e);
+        }
+    }
+    fn stats(&self) -> IoStats {
+        self.stats.get()
+    }
+    fn reset_stats(&mut self) {
+        self.stats.reset();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("ods-{}-{}.db", name, std::process::id()))
+    }
+
+    #[test]
+    fn test_fileblockstore() {
+        let path = temp_path("fileblockstore");
+        let mut bs = FileBlockStore::<String>::create(&path, 64, 2).unwrap();
+        let mut reference = vec![];
+        for i in 0..10 {
+            let x = format!("block {}", i);
+            assert_eq!(bs.place_block(x.clone()), i);
+            reference.push(Some(x));
+        }
+        // more blocks than pool frames, so most reads go to the file
+        for (i, x) in reference.iter().enumerate() {
+            assert_eq!(bs.read_block(i), *x);
+        }
+        bs.write_block(3, "three".into());
+        reference[3] = Some("three".into());
+        bs.free_block(5);
+        bs.free_block(7);
+        reference[5] = None;
+        reference[7] = None;
+        assert_eq!(bs.read_block(5), None);
+        assert_eq!(bs.read_block(10), None);
+        bs.set_meta(b"hello").unwrap();
+        bs.flush().unwrap();
+        drop(bs);
+
+        let mut bs = FileBlockStore::<String>::open(&path, 3).unwrap();
+        assert_eq!(bs.page_size(), 64);
+        assert_eq!(bs.pages(), 10);
+        assert_eq!(bs.meta(), b"hello");
+        for (i, x) in reference.iter().enumerate() {
+            assert_eq!(bs.read_block(i), *x);
+        }
+        let stats = bs.stats();
+        assert_eq!(stats.hits + stats.misses, reference.len());
+        assert_eq!(stats.reads, stats.misses);
+        bs.read_block(9);
+        assert_eq!(bs.stats().hits, stats.hits + 1);
+        bs.reset_stats();
+        assert_eq!(bs.stats(), IoStats::default());
+        // freed pages are reused last-freed first
+        assert_eq!(bs.place_block("a".into()), 7);
+        assert_eq!(bs.place_block("b".into()), 5);
+        assert_eq!(bs.place_block("c".into()), 10);
+        assert_eq!(bs.read_block(5), Some("b".into()));
+
+        // a block larger than a page is reported by flush
+        bs.write_block(0, "x".repeat(100));
+        for i in 1..10 {
+            bs.read_block(i);
+        }
+        assert_eq!(bs.flush().unwrap_err().kind(), io::ErrorKind::InvalidData);
+        assert!(bs.set_meta(&[0u8; 64]).is_err());
+        drop(bs);
+
+        // pages evicted before a checkpoint are read back from memory, and a
+        // commit checkpoints once they outnumber the pool's frames
+        let mut bs = FileBlockStore::<String>::create(&path, 64, 2).unwrap();
+        for i in 0..5 {
+            bs.place_block(format!("block {}", i));
+        }
+        assert_eq!(bs.pending.borrow().len(), 3);
+        bs.reset_stats();
+        assert_eq!(bs.read_block(0), Some("block 0".into()));
+        assert_eq!(bs.stats().hits, 1);
+        assert_eq!((bs.stats().reads, bs.stats().misses), (0, 0));
+        BlockDevice::commit(&mut bs, b"");
+        assert_eq!(bs.pending.borrow().len(), 0);
+        for i in 5..8 {
+            bs.place_block(format!("block {}", i));
+        }
+        BlockDevice::commit(&mut bs, b"");
+        assert_eq!(bs.pending.borrow().len(), 1);
+        drop(bs);
+        let bs = FileBlockStore::<String>::open(&path, 2).unwrap();
+        assert_eq!(bs.pages(), 5);
+        assert_eq!(bs.read_block(4), Some("block 4".into()));
+        assert_eq!((bs.stats().reads, bs.stats().misses), (1, 1));
+        drop(bs);
+
+        std::fs::write(&path, b"garbage").unwrap();
+        assert!(FileBlockStore::<String>::open(&path, 3).is_err());
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -1,2 +1,9 @@
 pub mod blockstore;
+pub mod bplustree;
 pub mod btree;
+pub mod cachedblockstore;
+pub mod fileblockstore;
+mod pool;
+mod wal;
+
+pub use chapter01::codec;
@@ -0,0 +1,50 @@
+#![allow(clippy::many_single_char_names,clippy::explicit_counter_loop)]
+use std::collections::HashMap;
+
+#[derive(Clone, Debug)]
+pub(crate) struct Frame<T> {
+    pub(crate) block: T,
+    pub(crate) dirty: bool,
+    used: u64,
+}
+
+// an LRU buffer pool of decoded blocks
+#[derive(Clone, Debug)]
+pub(crate) struct Pool<T> {
+    capacity: usize,
+    clock: u64,
+    pub(crate) frames: HashMap<usize, Frame<T>>,
+}
+
+impl<T: Clone> Pool<T> {
+    pub(crate) fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            clock: 0,
+            frames: HashMap::new(),
+        }
+    }
+    pub(crate) fn capacity(&self) -> usize {
+        self.capacity
+    }
+    pub(crate) fn get(&mut self, i: usize) -> Option<T> {
+        self.clock += 1;
+        let f = self.frames.get_mut(&i)?;
+        f.used = self.clock;
+        Some(f.block.clone())
+    }
+    pub(crate) fn put(&mut self, i: usize, block: T, dirty: bool) -> Option<(usize, Frame<T>)> {
+        self.clock += 1;
+        let used = self.clock;
+        let dirty = dirty || self.frames.get(&i).is_some_and(|f| f.dirty);
+        self.frames.insert(i, Frame { block, dirty, used });
+        if self.frames.len() <= self.capacity {
+            return None;
+        }
+        let (&j, _) = self.frames.iter().min_by_key(|(_, f)| f.used)?;
+        self.frames.remove(&j).map(|f| (j, f))
+    }
+    pub(crate) fn remove(&mut self, i: usize) {
+        self.frames.remove(&i);
+    }
+}
@@ -0,0 +1,166 @@
+#![allow(clippy::many_single_char_names,clippy::explicit_counter_loop)]
+use crate::codec::Codec;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+// one committed update: the full images of the pages it changed, page None being the header
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub(crate) struct Record {
+    pub(crate) page_size: usize,
+    pub(crate) pages: Vec<(Option<usize>, Vec<u8>)>,
+}
+
+// a redo log of page images; a record is [length][checksum][payload] and
+// a record cut short by a crash fails its checksum and ends the log
+#[derive(Debug)]
+pub(crate) struct Wal {
+    file: File,
+}
+
+fn checksum(buf: &[u8]) -> u64 {
+    // FNV-1a
+    let mut h = 0xcbf2_9ce4_8422_2325u64;
+    for &c in buf.iter() {
+        h ^= c as u64;
+        h = h.wrapping_mul(0x0100_0000_01b3);
+    }
+    h
+}
+
+impl Record {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        let mut payload = vec![];
+        (self.page_size as u32).encode(&mut payload);
+        (self.pages.len() as u32).encode(&mut payload);
+        for (i, page) in self.pages.iter() {
+            i.map_or(-1, |i| i as i64).encode(&mut payload);
+            payload.extend_from_slice(page);
+            payload.resize(payload.len() + self.page_size - page.len(), 0);
+        }
+        (payload.len() as u32).encode(buf);
+        checksum(&payload).encode(buf);
+        buf.extend_from_slice(&payload);
+    }
+    fn decode(buf: &mut &[u8]) -> Option<Self> {
+        let n = u32::decode(buf)? as usize;
+        let sum = u64::decode(buf)?;
+        let mut payload = buf.get(..n)?;
+        if checksum(payload) != sum {
+            return None;
+        }
+        *buf = &buf[n..];
+        let page_size = u32::decode(&mut payload)? as usize;
+        let count = u32::decode(&mut payload)? as usize;
+        let mut pages = vec![];
+        for _ in 0..count {
+            let i = i64::decode(&mut payload)?;
+            let page = payload.get(..page_size)?.to_vec();
+            payload = &payload[page_size..];
+            pages.push((if i < 0 { None } else { Some(i as usize) }, page));
+        }
+        Some(Self { page_size, pages })
+    }
+}
+
+impl Wal {
+    pub(crate) fn create<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(path)?;
+        file.sync_all()?;
+        Ok(Self { file })
+    }
+    pub(crate) fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let file = OpenOptions::new().read(true).write(true).open(path)?;
+        Ok(Self { file })
+    }
+    // the update is durable once this returns
+    pub(crate) fn append(&mut self, record: &Record) -> io::Result<()> {
+        let mut buf = vec![];
+        record.encode(&mut buf);
+        self.file.seek(SeekFrom::End(0))?;
+        self.file.write_all(&buf)?;
+        self.file.sync_data()
+    }
+    // every complete record, oldest first; anything after the first bad one is dropped
+    pub(crate) fn records(&mut self) -> io::Result<Vec<Record>> {
+        let mut log = vec![];
+        self.file.seek(SeekFrom::Start(0))?;
+        self.file.read_to_end(&mut log)?;
+        let mut buf = &log[..];
+        let mut records = vec![];
+        while let Some(record) = Record::decode(&mut buf) {
+            records.push(record);
+        }
+        Ok(records)
+    }
+    pub(crate) fn reset(&mut self) -> io::Result<()> {
+        self.file.set_len(0)?;
+        self.file.sync_all()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rand::{thread_rng, Rng};
+    #[test]
+    fn test_wal() {
+        let path = std::env::temp_dir().join(format!("ods-wal-{}.wal", std::process::id()));
+        let mut rng = thread_rng();
+        let mut wal = Wal::create(&path).unwrap();
+        let mut reference = vec![];
+        let mut ends = vec![];
+        for _ in 0..10 {
+            let page_size = rng.gen_range(16, 64);
+            let pages = (0..rng.gen_range(1, 4))
+                .map(|_| {
+                    let i = rng.gen_range(-1, 8);
+                    let page: Vec<u8> = (0..rng.gen_range(0, page_size))
+                        .map(|_| rng.gen())
+                        .collect();
+                    (if i < 0 { None } else { Some(i as usize) }, page)
+                })
+                .collect();
+            let record = Record { page_size, pages };
+            wal.append(&record).unwrap();
+            ends.push(std::fs::metadata(&path).unwrap().len());
+            reference.push(record);
+        }
+        // pages come back padded to the page size
+        for r in reference.iter_mut() {
+            for (_, page) in r.pages.iter_mut() {
+                page.resize(r.page_size, 0);
+            }
+        }
+        assert_eq!(wal.records().unwrap(), reference);
+
+        // cut the log anywhere: exactly the records that were written in full survive
+        for _ in 0..50 {
+            let len = rng.gen_range(0, ends[9] + 1);
+            std::fs::copy(&path, path.with_extension("torn")).unwrap();
+            let torn = OpenOptions::new()
+                .write(true)
+                .open(path.with_extension("torn"))
+                .unwrap();
+            torn.set_len(len).unwrap();
+            let complete = ends.iter().filter(|&&e| e <= len).count();
+            let mut wal = Wal::open(path.with_extension("torn")).unwrap();
+            assert_eq!(wal.records().unwrap(), reference[..complete].to_vec());
+        }
+
+        // a flipped byte ends the log at the damaged record
+        let mut log = std::fs::read(&path).unwrap();
+        log[ends[4] as usize + 20] ^= 1;
+        std::fs::write(&path, &log).unwrap();
+        assert_eq!(wal.records().unwrap(), reference[..5].to_vec());
+        wal.reset().unwrap();
+        assert_eq!(wal.records().unwrap(), vec![]);
+        std::fs::remove_file(&path).unwrap();
+        std::fs::remove_file(path.with_extension("torn")).unwrap();
+    }
+}